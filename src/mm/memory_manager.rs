@@ -14,22 +14,32 @@
 use std::sync::atomic::Ordering;
 
 use crate::plan::mutator_context::{Mutator, MutatorContext};
-use crate::plan::Plan;
+#[cfg(feature = "global_alloc_bit")]
+use crate::scheduler::gc_works::{ProcessConservativeRoots, ProcessEdgesWork};
 use crate::scheduler::GCWorker;
 
 use crate::vm::Collection;
 
+use crate::util::header_byte;
 use crate::util::{Address, ObjectReference};
 
-use self::selected_plan::SelectedPlan;
-use crate::plan::selected_plan;
 use crate::util::alloc::allocators::AllocatorSelector;
 
+#[cfg(feature = "global_alloc_bit")]
+use crate::mmtk::MMAPPER;
 use crate::mmtk::MMTK;
 use crate::plan::AllocationSemantics;
+#[cfg(feature = "global_alloc_bit")]
+use crate::policy::space::object_contains;
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::heap::gc_trigger::GCTriggerPolicy;
 use crate::util::heap::layout::vm_layout_constants::HEAP_END;
 use crate::util::heap::layout::vm_layout_constants::HEAP_START;
+use crate::util::heap::snapshot;
+use crate::util::pin_bit;
+use crate::util::statistics::report::StatsReport;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 
@@ -48,7 +58,9 @@ pub fn start_control_collector<VM: VMBinding>(mmtk: &MMTK<VM>, tls: OpaquePointe
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance to initialize.
-/// * `heap_size`: The heap size for the MMTk instance in bytes.
+/// * `heap_size`: The initial heap size for the MMTk instance in bytes. With the `TimeRatio` GC
+///   trigger, the heap may be resized after each GC between the `min_heap` and `max_heap`
+///   options, which default to this size.
 pub fn gc_init<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, heap_size: usize) {
     match crate::util::logger::try_init() {
         Ok(_) => debug!("MMTk initialized the logger."),
@@ -57,6 +69,26 @@ pub fn gc_init<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, heap_size: usize) {
         ),
     }
     mmtk.plan.gc_init(heap_size, &mmtk.vm_map, &mmtk.scheduler);
+    if !mmtk.options.restore_heap.is_empty() {
+        let path = &mmtk.options.restore_heap;
+        if let Err(e) = snapshot::restore_heap(mmtk, path) {
+            panic!("Failed to restore the heap from {}: {}", path, e);
+        }
+    }
+}
+
+/// Set the policy that triggers GCs and sizes the heap, instead of the one selected by the
+/// `gc_trigger` option. A VM should call this method before `gc_init()`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `gc_trigger`: The GC trigger policy.
+pub fn set_gc_trigger<VM: VMBinding>(
+    mmtk: &'static mut MMTK<VM>,
+    gc_trigger: Box<dyn GCTriggerPolicy>,
+) {
+    // The plan is not initialized yet, so no other thread can be using the trigger.
+    unsafe { mmtk.plan.base().set_gc_trigger(gc_trigger) };
 }
 
 /// Request MMTk to create a mutator for the given thread. For performance reasons, A VM should
@@ -68,15 +100,15 @@ pub fn gc_init<VM: VMBinding>(mmtk: &'static mut MMTK<VM>, heap_size: usize) {
 pub fn bind_mutator<VM: VMBinding>(
     mmtk: &'static MMTK<VM>,
     tls: OpaquePointer,
-) -> Box<Mutator<SelectedPlan<VM>>> {
-    SelectedPlan::bind_mutator(&mmtk.plan, tls, mmtk)
+) -> Box<Mutator<VM>> {
+    mmtk.plan.bind_mutator(tls, mmtk)
 }
 
 /// Reclaim a mutator that is no longer needed.
 ///
 /// Arguments:
 /// * `mutator`: A reference to the mutator to be destroyed.
-pub fn destroy_mutator<VM: VMBinding>(mutator: Box<Mutator<SelectedPlan<VM>>>) {
+pub fn destroy_mutator<VM: VMBinding>(mutator: Box<Mutator<VM>>) {
     drop(mutator);
 }
 
@@ -84,7 +116,7 @@ pub fn destroy_mutator<VM: VMBinding>(mutator: Box<Mutator<SelectedPlan<VM>>>) {
 ///
 /// Arguments:
 /// * `mutator`: A reference to the mutator.
-pub fn flush_mutator<VM: VMBinding>(mutator: &mut Mutator<SelectedPlan<VM>>) {
+pub fn flush_mutator<VM: VMBinding>(mutator: &mut Mutator<VM>) {
     mutator.flush()
 }
 
@@ -98,7 +130,7 @@ pub fn flush_mutator<VM: VMBinding>(mutator: &mut Mutator<SelectedPlan<VM>>) {
 /// * `offset`: Offset associated with the alignment.
 /// * `semantics`: The allocation semantic required for the allocation.
 pub fn alloc<VM: VMBinding>(
-    mutator: &mut Mutator<SelectedPlan<VM>>,
+    mutator: &mut Mutator<VM>,
     size: usize,
     align: usize,
     offset: isize,
@@ -118,7 +150,7 @@ pub fn alloc<VM: VMBinding>(
 /// * `bytes`: The size of the space allocated for the object (in bytes).
 /// * `semantics`: The allocation semantics used for the allocation.
 pub fn post_alloc<VM: VMBinding>(
-    mutator: &mut Mutator<SelectedPlan<VM>>,
+    mutator: &mut Mutator<VM>,
     refer: ObjectReference,
     type_refer: ObjectReference,
     bytes: usize,
@@ -128,7 +160,9 @@ pub fn post_alloc<VM: VMBinding>(
 }
 
 /// Return an AllocatorSelector for the given allocation semantic. This method is provided
-/// so that VM compilers may call it to help generate allocation fast-path.
+/// so that VM compilers may call it to help generate allocation fast-path. Default allocations
+/// larger than the `max_non_los_default_alloc_bytes` constraint of the plan must use the
+/// allocator of `AllocationSemantics::Los` instead.
 ///
 /// Arguments:
 /// * `mmtk`: The reference to an MMTk instance.
@@ -140,6 +174,51 @@ pub fn get_allocator_mapping<VM: VMBinding>(
     mmtk.plan.get_allocator_mapping()[semantics]
 }
 
+/// The fast path of the object remembering write barrier, used by plans whose
+/// `needs_log_bit_in_header` constraint is set. Returns true if the object is unlogged, in which
+/// case the VM should call `record_modified_node()` after writing a reference into it.
+///
+/// A VM should inline this check into its compiled code: the object is unlogged if
+/// `header_byte::UNLOGGED_BIT` is set in its GC byte, which is at `ObjectModel::GC_BYTE_OFFSET`
+/// from the object reference, or in the side metadata described by `gc_byte::SIDE_GC_BYTE_SPEC`
+/// if the object model has no GC byte.
+///
+/// Arguments:
+/// * `object`: The object that is written to.
+#[inline(always)]
+pub fn is_unlogged<VM: VMBinding>(object: ObjectReference) -> bool {
+    header_byte::is_unlogged::<VM>(object)
+}
+
+/// The slow path of the write barrier: remember an object that is written to.
+///
+/// Arguments:
+/// * `mutator`: The mutator that wrote to the object.
+/// * `object`: The object that is written to.
+pub fn record_modified_node<VM: VMBinding>(mutator: &mut Mutator<VM>, object: ObjectReference) {
+    mutator.record_modified_node(object);
+}
+
+/// The slow path of the pre-write barrier: remember the references in an object that is about
+/// to be written to. This must be called before the write.
+///
+/// Arguments:
+/// * `mutator`: The mutator that writes to the object.
+/// * `object`: The object that is written to.
+pub fn record_overwritten_node<VM: VMBinding>(mutator: &mut Mutator<VM>, object: ObjectReference) {
+    mutator.record_overwritten_node(object);
+}
+
+/// The slow path of the pre-write barrier: remember the reference in a slot that is about to be
+/// overwritten. This must be called before the write.
+///
+/// Arguments:
+/// * `mutator`: The mutator that writes to the slot.
+/// * `slot`: The slot that is written to.
+pub fn record_overwritten_edge<VM: VMBinding>(mutator: &mut Mutator<VM>, slot: Address) {
+    mutator.record_overwritten_edge(slot);
+}
+
 /// Run the main loop of a GC worker. This method does not return.
 ///
 /// Arguments:
@@ -163,11 +242,15 @@ pub fn start_worker<VM: VMBinding>(
 /// * `tls`: The thread that wants to enable the collection.
 pub fn enable_collection<VM: VMBinding>(mmtk: &'static MMTK<VM>, tls: OpaquePointer) {
     mmtk.scheduler.initialize(mmtk.options.threads, mmtk, tls);
+    if !mmtk.options.work_trace.is_empty() {
+        mmtk.scheduler.enable_work_trace();
+    }
     VM::VMCollection::spawn_worker_thread(tls, None); // spawn controller thread
     mmtk.plan.base().initialized.store(true, Ordering::SeqCst);
 }
 
-/// Process MMTk run-time options.
+/// Process MMTk run-time options. Returns false if the value is invalid, or if the option cannot
+/// be changed once MMTk is created, like `plan`.
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
@@ -205,6 +288,44 @@ pub fn last_heap_address() -> Address {
     HEAP_END
 }
 
+/// Return the address range of the VM space, i.e. `[start, end)`. A boot image must be built for
+/// this range before it is mapped with `map_boot_image()`. With the 64-bit heap layout, the range
+/// is a whole space, otherwise its size is the `vm_space_size` option.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+#[cfg(feature = "vm_space")]
+pub fn vm_space_range<VM: VMBinding>(mmtk: &MMTK<VM>) -> (Address, Address) {
+    use crate::policy::space::Space;
+    let common = mmtk.plan.base().get_vm_space().common();
+    (common.start, common.start + common.extent)
+}
+
+/// Map a boot image file at the start of the VM space, and return the end of the image. The
+/// file is mapped with copy-on-write, so the VM can write to the objects in it without changing
+/// the file. The objects in the image are immortal: they are never moved or reclaimed, and the
+/// objects a GC reaches in the image are scanned like those of the immortal space. The
+/// references from the image that are not reachable from the roots, e.g. from the static
+/// fields in the image, have to be reported as roots by the VM. Objects in the image are not
+/// visited by `enumerate_objects()`.
+///
+/// The image must be built for the address range returned by `vm_space_range()`, with the GC
+/// byte of every object as `post_alloc()` would leave it before the first GC, i.e. with the mark
+/// bit clear. This must be called after `gc_init()` and before `enable_collection()`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `path`: The path of the image file.
+#[cfg(feature = "vm_space")]
+pub fn map_boot_image<VM: VMBinding>(mmtk: &MMTK<VM>, path: &str) -> std::io::Result<Address> {
+    assert!(
+        !mmtk.plan.is_initialized(),
+        "The boot image must be mapped before collection is enabled"
+    );
+    let file = std::fs::File::open(path)?;
+    mmtk.plan.base().get_vm_space().map_image(&file)
+}
+
 /// Return the total memory in bytes.
 ///
 /// Arguments:
@@ -253,6 +374,151 @@ pub fn is_mapped_address(address: Address) -> bool {
     address.is_mapped()
 }
 
+/// Pin an object, so that no GC moves it until it is unpinned, e.g. while native code accesses
+/// it through a raw pointer. Copy spaces and Immix spaces keep pinned objects in place, and
+/// spaces that never move objects are not affected. MarkCompact does not support pinning.
+///
+/// A copy space keeps the pages of its pinned objects when it is released, so pins should be
+/// short-lived. The objects that a GenCopy nursery keeps in place stay there until the next full
+/// heap collection, even if they are unpinned. Copy spaces can only keep pinned objects with the
+/// 64-bit heap layout, in which they are contiguous.
+///
+/// This must not be called during a GC. Pins do not nest: returns `false` if the object was
+/// already pinned, in which case it is unpinned by the first call to `unpin_object()`.
+///
+/// Arguments:
+/// * `object`: The object reference to pin. It must be allocated by MMTk.
+pub fn pin_object(object: ObjectReference) -> bool {
+    pin_bit::pin_object(object)
+}
+
+/// Unpin an object, so that GCs may move it again. Returns `false` if the object was not pinned.
+/// This must not be called during a GC.
+///
+/// Arguments:
+/// * `object`: The object reference to unpin.
+pub fn unpin_object(object: ObjectReference) -> bool {
+    pin_bit::unpin_object(object)
+}
+
+/// Is the object pinned?
+///
+/// Arguments:
+/// * `object`: The object reference to query.
+pub fn is_pinned(object: ObjectReference) -> bool {
+    pin_bit::is_pinned(object)
+}
+
+/// Is the address the reference of an object that MMTk allocated and has not reclaimed yet?
+/// This can be asked of any word, e.g. a word of a stack frame that may or may not hold a
+/// reference. The answer comes from the allocation bits that `post_alloc()` sets, so objects that
+/// MMTk did not allocate, e.g. those of a boot image, are not valid objects. This needs the
+/// `global_alloc_bit` feature.
+///
+/// Arguments:
+/// * `addr`: The address to query.
+#[cfg(feature = "global_alloc_bit")]
+pub fn is_valid_object(addr: Address) -> bool {
+    alloc_bit::is_alloced_object(&MMAPPER, addr)
+}
+
+/// Find the object that an internal pointer points into, i.e. the object whose memory, from
+/// `object_start_ref()` for `get_current_size()` bytes, contains the pointer. Returns `None` if
+/// there is no such object. The search walks the allocation bits backward from the pointer, so
+/// its cost grows with the distance to the object reference. A pointer into the part of an object
+/// below its reference is not resolved. This needs the `global_alloc_bit` feature.
+///
+/// Arguments:
+/// * `internal_ptr`: The address to resolve.
+/// * `max_search_bytes`: How far below `internal_ptr` to look for the object reference, e.g. the
+///   size of the largest object the VM expects internal pointers into.
+#[cfg(feature = "global_alloc_bit")]
+pub fn find_object_from_internal_pointer<VM: VMBinding>(
+    internal_ptr: Address,
+    max_search_bytes: usize,
+) -> Option<ObjectReference> {
+    alloc_bit::find_alloced_object_below(&MMAPPER, internal_ptr, max_search_bytes)
+        .filter(|&object| object_contains::<VM>(object, internal_ptr))
+}
+
+/// Find the object whose memory contains an address, e.g. a derived pointer into an array.
+/// Objects outside the large object space are only found within the size limit of the plan,
+/// or the chunk of the address. During a GC, this may only be called while the VM scans roots.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `addr`: The address to resolve.
+#[cfg(feature = "global_alloc_bit")]
+pub fn find_base_object<VM: VMBinding>(mmtk: &MMTK<VM>, addr: Address) -> Option<ObjectReference> {
+    let mut base = None;
+    mmtk.plan.for_each_space(&mut |space| {
+        if base.is_none() && space.address_in_space(addr) {
+            base = space.find_base_object(addr);
+        }
+    });
+    base
+}
+
+/// Report words that may or may not be references, e.g. of native frames, while the VM scans
+/// roots for a GC. The words that are valid objects are pinned for the GC and traced as roots.
+/// This panics for plans that cannot pin objects.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `words`: The ambiguous words.
+#[cfg(feature = "global_alloc_bit")]
+pub fn add_conservative_roots<E: ProcessEdgesWork>(mmtk: &'static MMTK<E::VM>, words: &[Address]) {
+    assert!(
+        mmtk.plan.constraints().supports_pinning,
+        "The plan cannot pin objects, so it does not support conservative roots"
+    );
+    let objects: Vec<ObjectReference> = words
+        .iter()
+        .filter(|&&word| is_valid_object(word))
+        .map(|word| unsafe { word.to_object_reference() })
+        .collect();
+    if objects.is_empty() {
+        return;
+    }
+    {
+        // Only unpin the objects at the end of the GC that the VM did not pin itself.
+        let mut pins = mmtk.plan.base().conservative_pins.lock().unwrap();
+        for &object in &objects {
+            if pin_bit::pin_object(object) {
+                pins.push(object);
+            }
+        }
+    }
+    mmtk.scheduler
+        .closure_stage
+        .add(ProcessConservativeRoots::<E>::new(objects));
+}
+
+/// Write the heap to a file, from which `gc_init()` restores it at the same addresses in another
+/// process if the `restore_heap` option names the file. This must be called at a safepoint after
+/// a full heap GC, and returns an error for the plans that do not support snapshots.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `path`: The path of the file to write.
+pub fn snapshot_heap<VM: VMBinding>(mmtk: &MMTK<VM>, path: &str) -> std::io::Result<()> {
+    debug_assert!(!mmtk.plan.base().gc_in_progress());
+    snapshot::snapshot_heap(mmtk, path)
+}
+
+/// Visit every object that MMTk allocated and has not reclaimed yet, including dead objects that
+/// no GC has swept. This must be called at a safepoint, and `f` must not allocate in the heap.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `f`: The closure to call with each object.
+#[cfg(feature = "global_alloc_bit")]
+pub fn enumerate_objects<VM: VMBinding>(mmtk: &MMTK<VM>, mut f: impl FnMut(ObjectReference)) {
+    debug_assert!(!mmtk.plan.base().gc_in_progress());
+    mmtk.plan
+        .for_each_space(&mut |space| space.enumerate_objects(&mut f));
+}
+
 /// Check that if a garbage collection is in progress and if the given
 /// object is not movable.  If it is movable error messages are
 /// logged and the system exits.
@@ -264,7 +530,9 @@ pub fn modify_check<VM: VMBinding>(mmtk: &MMTK<VM>, object: ObjectReference) {
     mmtk.plan.modify_check(object);
 }
 
-/// Add a reference to the list of weak references.
+/// Add a reference to the list of weak references. If the referent is not strongly reachable
+/// at the next collection, the collection clears the referent and passes the reference to
+/// `ReferenceGlue::enqueue_references()`.
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
@@ -309,6 +577,77 @@ pub fn add_phantom_candidate<VM: VMBinding>(
         .add_phantom_candidate::<VM>(reff, referent);
 }
 
+/// Add an ephemeron to the list of ephemerons. The value of an ephemeron is kept alive only
+/// while its key is reachable. If the key is not reachable at a collection, the collection
+/// clears the key and the value, and passes the ephemeron to
+/// `ReferenceGlue::enqueue_references()`. The VM must implement
+/// `ReferenceGlue::get_ephemeron_value()` and `set_ephemeron_value()`, and must not scan the key
+/// or the value of the ephemeron as strong references.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `ephemeron`: The ephemeron to add.
+/// * `key`: The key object of the ephemeron.
+/// * `value`: The value object of the ephemeron.
+pub fn add_ephemeron_candidate<VM: VMBinding>(
+    mmtk: &MMTK<VM>,
+    ephemeron: ObjectReference,
+    key: ObjectReference,
+    value: ObjectReference,
+) {
+    mmtk.reference_processors
+        .add_ephemeron_candidate::<VM>(ephemeron, key, value);
+}
+
+/// Register an object with a finalizer. When the object becomes unreachable, it is kept alive
+/// for one more collection, and returned by `get_finalizable_object()` so that the VM can run its
+/// finalizer.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `object`: The object that has a finalizer.
+pub fn add_finalizer<VM: VMBinding>(mmtk: &MMTK<VM>, object: ObjectReference) {
+    if mmtk.options.no_finalizer {
+        warn!("add_finalizer() is called when no_finalizer = true");
+    }
+
+    mmtk.finalizable_processor.lock().unwrap().add(object);
+}
+
+/// Get an object that is ready to be finalized, i.e. an object registered with
+/// `add_finalizer()` that was found unreachable. Returns `None` if there is no such object.
+/// The object is kept alive until it is returned by this function, and is not returned again.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn get_finalizable_object<VM: VMBinding>(mmtk: &MMTK<VM>) -> Option<ObjectReference> {
+    if mmtk.options.no_finalizer {
+        warn!("get_finalizable_object() is called when no_finalizer = true");
+    }
+
+    mmtk.finalizable_processor
+        .lock()
+        .unwrap()
+        .get_ready_object()
+}
+
+/// Get all the objects with finalizers, whether they are reachable or not, e.g. to run all
+/// the finalizers when the VM exits. The objects are deregistered, and will not be returned by
+/// `get_finalizable_object()`.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn get_all_finalizers<VM: VMBinding>(mmtk: &MMTK<VM>) -> Vec<ObjectReference> {
+    if mmtk.options.no_finalizer {
+        warn!("get_all_finalizers() is called when no_finalizer = true");
+    }
+
+    mmtk.finalizable_processor
+        .lock()
+        .unwrap()
+        .get_all_finalizers()
+}
+
 /// Generic hook to allow benchmarks to be harnessed. We do a full heap
 /// GC, and then start recording statistics for MMTk.
 ///
@@ -320,10 +659,30 @@ pub fn harness_begin<VM: VMBinding>(mmtk: &MMTK<VM>, tls: OpaquePointer) {
 }
 
 /// Generic hook to allow benchmarks to be harnessed. We stop collecting
-/// statistics, and print stats values.
+/// statistics, and write stats values in the `stats_format` format to `stats_output`
+/// (stdout by default).
 ///
 /// Arguments:
 /// * `mmtk`: A reference to an MMTk instance.
 pub fn harness_end<VM: VMBinding>(mmtk: &'static MMTK<VM>) {
     mmtk.harness_end();
 }
+
+/// Return the statistics gathered since `harness_begin()`: the value of each counter in
+/// each mutator and GC phase, and the statistics of the work packets.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+pub fn harness_stats<VM: VMBinding>(mmtk: &'static MMTK<VM>) -> StatsReport {
+    mmtk.plan.base().stats.report(mmtk)
+}
+
+/// Write the timeline of the work packets done so far as Chrome trace-event JSON. The timeline
+/// is only recorded if the `work_trace` option is set when collection is enabled.
+///
+/// Arguments:
+/// * `mmtk`: A reference to an MMTk instance.
+/// * `path`: The file to write to.
+pub fn write_work_trace<VM: VMBinding>(mmtk: &MMTK<VM>, path: &str) -> std::io::Result<()> {
+    mmtk.write_work_trace(path)
+}
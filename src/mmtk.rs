@@ -1,7 +1,8 @@
+use crate::plan::create_plan;
 use crate::plan::Plan;
-use crate::plan::SelectedPlan;
 use crate::policy::space::SFTMap;
 use crate::scheduler::Scheduler;
+use crate::util::finalizable_processor::FinalizableProcessor;
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::map::Map;
@@ -12,9 +13,10 @@ use crate::util::sanity::sanity_checker::SanityChecker;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 use std::default::Default;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-#[cfg(feature = "sanity")]
 use std::sync::Mutex;
 
 lazy_static! {
@@ -33,11 +35,12 @@ lazy_static! {
 /// An MMTk instance. MMTk allows mutiple instances to run independently, and each instance gives users a separate heap.
 /// *Note that multi-instances is not fully supported yet*
 pub struct MMTK<VM: VMBinding> {
-    pub plan: SelectedPlan<VM>,
+    pub plan: Box<dyn Plan<VM = VM>>,
     pub vm_map: &'static VMMap,
     pub mmapper: &'static Mmapper,
     pub sftmap: &'static SFTMap,
     pub reference_processors: ReferenceProcessors,
+    pub finalizable_processor: Mutex<FinalizableProcessor>,
     pub options: Arc<UnsafeOptionsWrapper>,
     pub scheduler: Arc<Scheduler<Self>>,
     #[cfg(feature = "sanity")]
@@ -52,7 +55,7 @@ impl<VM: VMBinding> MMTK<VM> {
     pub fn new() -> Self {
         let scheduler = Scheduler::new();
         let options = Arc::new(UnsafeOptionsWrapper::new(Options::default()));
-        let plan = SelectedPlan::new(&VM_MAP, &MMAPPER, options.clone(), unsafe {
+        let plan = create_plan(options.plan, &VM_MAP, &MMAPPER, options.clone(), unsafe {
             &*(scheduler.as_ref() as *const Scheduler<MMTK<VM>>)
         });
         MMTK {
@@ -61,6 +64,7 @@ impl<VM: VMBinding> MMTK<VM> {
             mmapper: &MMAPPER,
             sftmap: &SFT_MAP,
             reference_processors: ReferenceProcessors::new(),
+            finalizable_processor: Mutex::new(FinalizableProcessor::new()),
             options,
             scheduler,
             #[cfg(feature = "sanity")]
@@ -80,6 +84,17 @@ impl<VM: VMBinding> MMTK<VM> {
     pub fn harness_end(&'static self) {
         self.plan.base().stats.stop_all(self);
         self.inside_harness.store(false, Ordering::SeqCst);
+        if !self.options.work_trace.is_empty() {
+            if let Err(e) = self.write_work_trace(&self.options.work_trace) {
+                warn!("Failed to write the work trace: {}", e);
+            }
+        }
+    }
+
+    pub fn write_work_trace(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.scheduler.write_work_trace(&mut out)?;
+        out.flush()
     }
 }
 
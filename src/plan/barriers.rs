@@ -1,7 +1,14 @@
+use crate::plan::TransitiveClosure;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWork;
+use crate::util::card_table;
+use crate::util::header_byte;
 use crate::util::*;
+use crate::vm::{Scanning, VMBinding};
 use crate::MMTK;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 /// For field writes in HotSpot, we cannot always get the source object pointer and the field address
 pub enum WriteTarget {
@@ -11,6 +18,9 @@ pub enum WriteTarget {
 
 pub trait Barrier: 'static + Send + Sync {
     fn flush(&mut self);
+    /// Called before a reference is written to the target, while the old value is still there.
+    /// Only barriers that need the overwritten references do anything here.
+    fn pre_write_barrier(&mut self, _target: WriteTarget) {}
     fn post_write_barrier(&mut self, target: WriteTarget);
 }
 
@@ -27,6 +37,22 @@ pub struct ModBuffer {
     modified_edges: Vec<Address>,
 }
 
+impl ModBuffer {
+    /// Hand the modified objects and slots to the GC, to be scanned in the next closure.
+    fn flush<E: ProcessEdgesWork>(&mut self, mmtk: &'static MMTK<E::VM>) {
+        let modified_nodes = std::mem::take(&mut self.modified_nodes);
+        let modified_edges = std::mem::take(&mut self.modified_edges);
+        debug_assert!(
+            !mmtk.scheduler.final_stage.is_activated(),
+            "{:?}",
+            self as *const _
+        );
+        mmtk.scheduler
+            .closure_stage
+            .add(ProcessModBuf::<E>::new(modified_nodes, modified_edges));
+    }
+}
+
 pub struct FieldRememberingBarrier<E: ProcessEdgesWork, S: Space<E::VM>> {
     mmtk: &'static MMTK<E::VM>,
     nursery: &'static S,
@@ -60,19 +86,7 @@ impl<E: ProcessEdgesWork, S: Space<E::VM>> FieldRememberingBarrier<E, S> {
 
 impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for FieldRememberingBarrier<E, S> {
     fn flush(&mut self) {
-        let mut modified_nodes = vec![];
-        std::mem::swap(&mut modified_nodes, &mut self.mod_buffer.modified_nodes);
-        let mut modified_edges = vec![];
-        std::mem::swap(&mut modified_edges, &mut self.mod_buffer.modified_edges);
-        debug_assert!(
-            !self.mmtk.scheduler.final_stage.is_activated(),
-            "{:?}",
-            self as *const _
-        );
-        self.mmtk
-            .scheduler
-            .closure_stage
-            .add(ProcessModBuf::<E>::new(modified_nodes, modified_edges));
+        self.mod_buffer.flush::<E>(self.mmtk);
     }
     fn post_write_barrier(&mut self, target: WriteTarget) {
         match target {
@@ -89,3 +103,224 @@ impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for FieldRememberingBarrier<E
         }
     }
 }
+
+/// A barrier that remembers each modified object once per GC cycle, with the unlogged bit in the
+/// object header. Objects outside the nursery are created unlogged. The first write to an
+/// unlogged object clears the bit and remembers the object, so later writes to it only check the
+/// bit. The GC sets the bit again for the remembered objects and the promoted objects.
+///
+/// Writes with only a slot as the target cannot be logged by object, so they fall back to
+/// remembering the slot if it is outside the nursery.
+pub struct ObjectRememberingBarrier<E: ProcessEdgesWork, S: Space<E::VM>> {
+    mmtk: &'static MMTK<E::VM>,
+    nursery: &'static S,
+    mod_buffer: ModBuffer,
+}
+
+impl<E: ProcessEdgesWork, S: Space<E::VM>> ObjectRememberingBarrier<E, S> {
+    pub fn new(mmtk: &'static MMTK<E::VM>, nursery: &'static S) -> Self {
+        Self {
+            mmtk,
+            nursery,
+            mod_buffer: ModBuffer::default(),
+        }
+    }
+
+    /// The slow path: log the object, unless another mutator has just logged it.
+    #[inline(never)]
+    fn log_object(&mut self, obj: ObjectReference) {
+        if header_byte::attempt_log::<E::VM>(obj) {
+            self.mod_buffer.modified_nodes.push(obj);
+            if self.mod_buffer.modified_nodes.len() >= E::CAPACITY {
+                self.flush();
+            }
+        }
+    }
+
+    fn enqueue_edge(&mut self, slot: Address) {
+        self.mod_buffer.modified_edges.push(slot);
+        if self.mod_buffer.modified_edges.len() >= 512 {
+            self.flush();
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for ObjectRememberingBarrier<E, S> {
+    fn flush(&mut self) {
+        self.mod_buffer.flush::<E>(self.mmtk);
+    }
+    #[inline(always)]
+    fn post_write_barrier(&mut self, target: WriteTarget) {
+        match target {
+            WriteTarget::Object(obj) => {
+                if header_byte::is_unlogged::<E::VM>(obj) {
+                    self.log_object(obj);
+                }
+            }
+            WriteTarget::Slot(slot) => {
+                if !self.nursery.address_in_space(slot) {
+                    self.enqueue_edge(slot);
+                }
+            }
+        }
+    }
+}
+
+/// A barrier that remembers modified objects in the card table: each write dirties the card of
+/// the modified object, and nursery GCs scan the objects on the dirty cards of the mature spaces.
+/// The fast path is a single store with no check, which suits barriers compiled by a JIT.
+///
+/// Writes with only a slot as the target cannot be matched to the card of their object, so they
+/// fall back to remembering the slot if it is outside the nursery.
+pub struct CardMarkingBarrier<E: ProcessEdgesWork, S: Space<E::VM>> {
+    mmtk: &'static MMTK<E::VM>,
+    nursery: &'static S,
+    mod_buffer: ModBuffer,
+}
+
+impl<E: ProcessEdgesWork, S: Space<E::VM>> CardMarkingBarrier<E, S> {
+    pub fn new(mmtk: &'static MMTK<E::VM>, nursery: &'static S) -> Self {
+        Self {
+            mmtk,
+            nursery,
+            mod_buffer: ModBuffer::default(),
+        }
+    }
+
+    fn enqueue_edge(&mut self, slot: Address) {
+        self.mod_buffer.modified_edges.push(slot);
+        if self.mod_buffer.modified_edges.len() >= 512 {
+            self.flush();
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork, S: Space<E::VM>> Barrier for CardMarkingBarrier<E, S> {
+    fn flush(&mut self) {
+        self.mod_buffer.flush::<E>(self.mmtk);
+    }
+    #[inline(always)]
+    fn post_write_barrier(&mut self, target: WriteTarget) {
+        match target {
+            WriteTarget::Object(obj) => card_table::mark_card(obj),
+            WriteTarget::Slot(slot) => {
+                if !self.nursery.address_in_space(slot) {
+                    self.enqueue_edge(slot);
+                }
+            }
+        }
+    }
+}
+
+/// A snapshot-at-the-beginning deletion barrier for concurrent marking. While marking is in
+/// progress, each reference about to be overwritten is remembered and later marked, so every
+/// object reachable when marking started is marked, even if the mutators disconnect it from the
+/// graph before the marker gets there. New objects are allocated marked, so the barrier does
+/// not need to remember the references written.
+///
+/// Writes with only an object as the target remember all the references in the object, once per
+/// marking cycle: the barrier clears the unlogged bit of the object, and the GC sets it again at
+/// the end of marking for the objects in `logged`.
+pub struct SATBBarrier<VM: VMBinding, W: GCWork<VM>> {
+    mmtk: &'static MMTK<VM>,
+    /// Whether concurrent marking is in progress. The barrier does nothing otherwise.
+    marking: &'static AtomicBool,
+    /// The objects that the barriers of all mutators logged in this marking cycle.
+    logged: &'static Mutex<Vec<ObjectReference>>,
+    tls: OpaquePointer,
+    buffer: Vec<ObjectReference>,
+    logged_buffer: Vec<ObjectReference>,
+    /// Creates the work that marks the remembered objects.
+    new_work: fn(&'static MMTK<VM>, Vec<ObjectReference>) -> W,
+}
+
+impl<VM: VMBinding, W: GCWork<VM>> SATBBarrier<VM, W> {
+    pub fn new(
+        mmtk: &'static MMTK<VM>,
+        marking: &'static AtomicBool,
+        logged: &'static Mutex<Vec<ObjectReference>>,
+        tls: OpaquePointer,
+        new_work: fn(&'static MMTK<VM>, Vec<ObjectReference>) -> W,
+    ) -> Self {
+        Self {
+            mmtk,
+            marking,
+            logged,
+            tls,
+            buffer: vec![],
+            logged_buffer: vec![],
+            new_work,
+        }
+    }
+
+    fn enqueue(&mut self, object: ObjectReference) {
+        if object.is_null() {
+            return;
+        }
+        self.buffer.push(object);
+        if self.buffer.len() >= 4096 {
+            self.flush();
+        }
+    }
+
+    /// The slow path: remember the references in an object or slot that is about to be written.
+    /// An object is scanned before it is logged, as the writes to it after it is logged are not
+    /// remembered. Mutators that write to it at the same time may scan it as well.
+    #[inline(never)]
+    fn remember_old_values(&mut self, target: WriteTarget) {
+        match target {
+            WriteTarget::Object(obj) => {
+                let mut old_values = OldValues(vec![]);
+                VM::VMScanning::scan_object(&mut old_values, obj, self.tls);
+                for object in old_values.0 {
+                    self.enqueue(object);
+                }
+                if header_byte::attempt_log::<VM>(obj) {
+                    self.logged_buffer.push(obj);
+                }
+            }
+            WriteTarget::Slot(slot) => self.enqueue(unsafe { slot.load() }),
+        }
+    }
+}
+
+impl<VM: VMBinding, W: GCWork<VM>> Barrier for SATBBarrier<VM, W> {
+    fn flush(&mut self) {
+        if !self.logged_buffer.is_empty() {
+            let logged = std::mem::take(&mut self.logged_buffer);
+            self.logged.lock().unwrap().extend(logged);
+        }
+        if self.buffer.is_empty() {
+            return;
+        }
+        let objects = std::mem::take(&mut self.buffer);
+        // The marker may be stopped for a pause. The packet is then done in the pause.
+        let work = (self.new_work)(self.mmtk, objects);
+        self.mmtk.scheduler.unconstrained_works.add(work);
+    }
+    #[inline(always)]
+    fn pre_write_barrier(&mut self, target: WriteTarget) {
+        if !self.marking.load(Ordering::Relaxed) {
+            return;
+        }
+        if let WriteTarget::Object(obj) = target {
+            if !header_byte::is_unlogged::<VM>(obj) {
+                return;
+            }
+        }
+        self.remember_old_values(target);
+    }
+    fn post_write_barrier(&mut self, _target: WriteTarget) {}
+}
+
+/// Collects the references in an object for the `SATBBarrier`.
+struct OldValues(Vec<ObjectReference>);
+
+impl TransitiveClosure for OldValues {
+    fn process_edge(&mut self, slot: Address) {
+        self.0.push(unsafe { slot.load() });
+    }
+    fn process_node(&mut self, _object: ObjectReference) {
+        unreachable!()
+    }
+}
@@ -0,0 +1,10 @@
+use crate::plan::PlanConstraints;
+
+pub const CMS_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: false,
+    gc_header_bits: 0,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    needs_concurrent_workers: true,
+    ..PlanConstraints::default()
+};
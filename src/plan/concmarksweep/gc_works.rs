@@ -0,0 +1,217 @@
+use super::global::ConcMarkSweep;
+use crate::plan::global::GcStatus;
+use crate::plan::{Plan, TransitiveClosure};
+use crate::scheduler::gc_works::*;
+use crate::scheduler::{CoordinatorWork, GCWork, GCWorker};
+use crate::util::conversions::pages_to_bytes;
+use crate::util::{Address, ObjectReference};
+use crate::vm::{Collection, Scanning, VMBinding};
+use crate::MMTK;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
+
+/// The trace of the pauses that mark: the remark pause, and full stop-the-world collections.
+#[derive(Default)]
+pub struct CMSProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<CMSProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static ConcMarkSweep<VM>>,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> CMSProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static ConcMarkSweep<VM> {
+        self.plan.unwrap()
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for CMSProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<ConcMarkSweep<VM>>();
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        self.plan().trace_object(self, object)
+    }
+}
+
+impl<VM: VMBinding> Deref for CMSProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for CMSProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// The root edges of the initial-mark pause. The pause only collects the objects that the roots
+/// point to. They are marked by `ConcurrentMark` once the mutators have resumed.
+#[derive(Default)]
+pub struct CMSRootEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<CMSRootEdges<VM>>,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for CMSRootEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if !object.is_null() {
+            ProcessEdgesWork::process_node(self, object);
+        }
+        object
+    }
+    fn flush(&mut self) {
+        let plan = self.plan().downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+        let nodes = mem::take(&mut self.nodes);
+        plan.initial_roots.lock().unwrap().extend(nodes);
+    }
+}
+
+impl<VM: VMBinding> Deref for CMSRootEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for CMSRootEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Ends the initial-mark pause: resumes the mutators, and starts marking from the roots found
+/// in the pause.
+#[derive(Default)]
+pub struct StartConcurrentMarking;
+
+impl<VM: VMBinding> GCWork<VM> for StartConcurrentMarking {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let plan = mmtk.plan.downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+        // Unlike `EndOfGC`, this does not tell the GC trigger or the uncommitter that a GC has
+        // ended, as nothing is reclaimed until the remark pause.
+        plan.base().set_gc_status(GcStatus::NotInGC);
+        <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
+        plan.base()
+            .gc_finished(pages_to_bytes(plan.get_pages_used()));
+        let roots = mem::take(&mut *plan.initial_roots.lock().unwrap());
+        if roots.is_empty() {
+            plan.base().control_collector_context.request();
+            return;
+        }
+        for chunk in roots.chunks(ConcurrentMark::<VM>::CAPACITY) {
+            mmtk.scheduler
+                .unconstrained_works
+                .add(ConcurrentMark::new(mmtk, chunk.to_vec()));
+        }
+    }
+}
+
+impl<VM: VMBinding> CoordinatorWork<MMTK<VM>> for StartConcurrentMarking {}
+
+/// Marks the objects reachable from a set of objects, while the mutators run. The objects come
+/// from the roots found in the initial-mark pause, and from the references remembered by the
+/// `SATBBarrier`. When the last packet is done, the remark pause is requested.
+///
+/// Each packet marks and scans its objects itself, as the closure buckets are not open between
+/// pauses. Large packets are split to keep the workers busy.
+pub struct ConcurrentMark<VM: VMBinding> {
+    /// The objects to mark.
+    objects: Vec<ObjectReference>,
+    /// The objects that are marked, and not yet scanned.
+    grey: Vec<ObjectReference>,
+    plan: &'static ConcMarkSweep<VM>,
+}
+
+impl<VM: VMBinding> ConcurrentMark<VM> {
+    pub const CAPACITY: usize = 4096;
+
+    pub fn new(mmtk: &'static MMTK<VM>, objects: Vec<ObjectReference>) -> Self {
+        let plan = mmtk.plan.downcast_ref::<ConcMarkSweep<VM>>().unwrap();
+        plan.pending_marks.fetch_add(1, Ordering::SeqCst);
+        Self {
+            objects,
+            grey: vec![],
+            plan,
+        }
+    }
+
+    /// A packet that scans objects which are already marked, e.g. the half of the grey objects
+    /// that a packet splits off. Tracing them again would find them marked, and not scan them.
+    pub fn new_grey(mmtk: &'static MMTK<VM>, grey: Vec<ObjectReference>) -> Self {
+        let mut packet = Self::new(mmtk, vec![]);
+        packet.grey = grey;
+        packet
+    }
+}
+
+impl<VM: VMBinding> TransitiveClosure for ConcurrentMark<VM> {
+    fn process_edge(&mut self, slot: Address) {
+        let object = unsafe { slot.load::<ObjectReference>() };
+        if !object.is_null() {
+            self.plan.trace_object(self, object);
+        }
+    }
+    fn process_node(&mut self, object: ObjectReference) {
+        self.grey.push(object);
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for ConcurrentMark<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let plan = self.plan;
+        for object in mem::take(&mut self.objects) {
+            plan.trace_object(self, object);
+        }
+        while let Some(object) = self.grey.pop() {
+            <VM as VMBinding>::VMScanning::scan_object(self, object, worker.tls);
+            if self.grey.len() > Self::CAPACITY {
+                let half = self.grey.split_off(self.grey.len() / 2);
+                mmtk.scheduler
+                    .unconstrained_works
+                    .add(ConcurrentMark::new_grey(mmtk, half));
+            }
+        }
+        if plan.pending_marks.fetch_sub(1, Ordering::SeqCst) == 1
+            && plan.marking.load(Ordering::SeqCst)
+            && !plan.base().gc_in_progress()
+        {
+            // Marking has caught up with the mutators, so the remark pause should be short
+            plan.base().control_collector_context.request();
+        }
+    }
+}
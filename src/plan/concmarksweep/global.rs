@@ -0,0 +1,234 @@
+use super::gc_works::{CMSProcessEdges, CMSRootEdges, StartConcurrentMarking};
+use crate::mmtk::MMTK;
+use crate::plan::concmarksweep::constraints::CMS_CONSTRAINTS;
+use crate::plan::concmarksweep::mutator::create_cms_mutator;
+use crate::plan::concmarksweep::mutator::ALLOCATOR_MAPPING;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::plan::TransitiveClosure;
+use crate::policy::marksweepspace::MarkSweepSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::header_byte;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::VMRequest;
+use crate::util::options::UnsafeOptionsWrapper;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::{ObjectReference, OpaquePointer};
+use crate::vm::VMBinding;
+use std::cell::UnsafeCell;
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use enum_map::EnumMap;
+
+/// The percentage of the heap that is reserved when a concurrent collection starts.
+const CONCURRENT_TRIGGER_PERCENT: usize = 75;
+
+pub struct ConcMarkSweep<VM: VMBinding> {
+    pub ms: UnsafeCell<MarkSweepSpace<VM>>,
+    pub common: CommonPlan<VM>,
+    /// Whether a collection is marking, from the initial-mark pause to the end of the remark
+    /// pause. The SATB barrier is active while this is set.
+    pub marking: AtomicBool,
+    /// Whether the current pause is an initial-mark pause.
+    initial_mark: AtomicBool,
+    /// The number of `ConcurrentMark` packets that are created and not yet done.
+    pub(super) pending_marks: AtomicUsize,
+    /// The objects that the roots point to, found in the initial-mark pause.
+    pub(super) initial_roots: Mutex<Vec<ObjectReference>>,
+    /// The objects that the SATB barriers logged while marking, to be unlogged when it ends.
+    pub(super) logged_objects: Mutex<Vec<ObjectReference>>,
+}
+
+unsafe impl<VM: VMBinding> Sync for ConcMarkSweep<VM> {}
+
+impl<VM: VMBinding> Plan for ConcMarkSweep<VM> {
+    type VM = VM;
+
+    fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        _scheduler: &'static MMTkScheduler<Self::VM>,
+    ) -> Self {
+        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
+
+        ConcMarkSweep {
+            ms: UnsafeCell::new(MarkSweepSpace::new(
+                "marksweep",
+                true,
+                VMRequest::discontiguous(),
+                vm_map,
+                mmapper,
+                &mut heap,
+            )),
+            common: CommonPlan::new(vm_map, mmapper, options, heap),
+            marking: AtomicBool::new(false),
+            initial_mark: AtomicBool::new(false),
+            pending_marks: AtomicUsize::new(0),
+            initial_roots: Mutex::new(vec![]),
+            logged_objects: Mutex::new(vec![]),
+        }
+    }
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &CMS_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box NoCopy::new(mmtk)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        unsafe { &mut *self.ms.get() }.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Start marking concurrently, unless a collection is already marking, or the heap is
+        // too full for the mutators to keep running while it is marked.
+        let initial_mark = !self.marking.load(Ordering::SeqCst)
+            && !self
+                .base()
+                .user_triggered_collection
+                .load(Ordering::Relaxed)
+            && !self.is_emergency_collection()
+            && !self
+                .base()
+                .gc_trigger()
+                .is_heap_full(self.get_pages_reserved());
+        self.initial_mark.store(initial_mark, Ordering::SeqCst);
+        if initial_mark {
+            // Stop & scan mutators, and only collect the objects that the roots point to
+            scheduler
+                .unconstrained_works
+                .add(StopMutators::<CMSRootEdges<VM>>::new());
+            scheduler.prepare_stage.add(Prepare::new(self));
+            // Resume mutators, and mark while they run
+            scheduler.set_finalizer(Some(StartConcurrentMarking));
+            return;
+        }
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler
+            .unconstrained_works
+            .add(StopMutators::<CMSProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators. In a remark pause, this also flushes the barriers.
+        scheduler.prepare_stage.add(Prepare::new(self));
+        // Process the references after the closure. The referents that the mutators loaded
+        // during concurrent marking are not remembered by the barrier, so references are only
+        // processed in this pause.
+        scheduler.schedule_ref_processing::<CMSProcessEdges<VM>>();
+        // Release global/collectors/mutators
+        scheduler.release_stage.add(Release::new(self));
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.final_stage.add(ScheduleSanityGC);
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn concurrent_collection_required(&self) -> bool {
+        !self.marking.load(Ordering::Relaxed)
+            && !self.base().gc_in_progress()
+            && self.get_pages_reserved() * 100
+                >= self.get_total_pages() * CONCURRENT_TRIGGER_PERCENT
+    }
+
+    fn bind_mutator(
+        &'static self,
+        tls: OpaquePointer,
+        mmtk: &'static MMTK<Self::VM>,
+    ) -> Box<Mutator<VM>> {
+        Box::new(create_cms_mutator(tls, mmtk))
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        // A remark pause keeps the marks of the concurrent marking.
+        if !self.marking.load(Ordering::SeqCst) {
+            self.common.prepare(tls, true);
+            unsafe { &mut *self.ms.get() }.prepare();
+        }
+        if self.initial_mark.load(Ordering::SeqCst) {
+            self.marking.store(true, Ordering::SeqCst);
+            // The large objects allocated while marking are not traced, so they must be marked.
+            self.common.get_los().set_allocate_black(true);
+        }
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        // The barriers remember the objects again in the next marking cycle. Dead objects are
+        // unlogged as well, before they are freed.
+        for object in mem::take(&mut *self.logged_objects.lock().unwrap()) {
+            header_byte::mark_as_unlogged::<VM>(object);
+        }
+        self.common.release(tls, true);
+        // sweep the mark-sweep space
+        unsafe { &mut *self.ms.get() }.release();
+        self.marking.store(false, Ordering::SeqCst);
+        self.common.get_los().set_allocate_black(false);
+        self.reset_collection_trigger();
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.ms_space().reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(self.ms_space());
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+}
+
+impl<VM: VMBinding> ConcMarkSweep<VM> {
+    pub fn ms_space(&self) -> &'static MarkSweepSpace<VM> {
+        unsafe { &*self.ms.get() }
+    }
+
+    /// Mark an object, for both the pauses and the concurrent marking.
+    #[inline]
+    pub fn trace_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if self.ms_space().in_space(object) {
+            self.ms_space().trace_object(trace, object)
+        } else {
+            self.common.trace_object(trace, object)
+        }
+    }
+}
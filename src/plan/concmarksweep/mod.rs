@@ -0,0 +1,10 @@
+//! A concurrent mark-sweep plan. A collection marks the roots in a short initial-mark pause,
+//! then marks the heap with `unconstrained_works` packets while the mutators run, and finishes
+//! with a remark pause that marks what the barrier remembered and sweeps.
+
+mod constraints;
+mod gc_works;
+mod global;
+mod mutator;
+
+pub use self::global::ConcMarkSweep;
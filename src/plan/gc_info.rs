@@ -0,0 +1,45 @@
+//! The information about a GC that is passed to the GC hooks of
+//! [`Collection`](../../vm/trait.Collection.html).
+
+use std::time::Duration;
+
+/// Which part of the heap a GC collects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CollectionKind {
+    /// Only the nursery of a generational plan.
+    Nursery,
+    /// The whole heap.
+    Full,
+}
+
+/// The information about the current or the last GC.
+#[derive(Copy, Clone, Debug)]
+pub struct GCInfo {
+    /// The number of the GC, starting from 1.
+    pub gc: usize,
+    pub kind: CollectionKind,
+    /// Whether the GC was requested by the VM with `handle_user_collection_request()`.
+    pub user_triggered: bool,
+    /// Whether the GC is a last attempt to free memory after previous GCs failed to.
+    pub emergency: bool,
+    /// The bytes used by the heap when the GC started.
+    pub bytes_before: usize,
+    /// The bytes used by the heap when the GC finished. This is 0 until the GC finishes.
+    pub bytes_after: usize,
+    /// The time from the start to the end of the GC. This is 0 until the GC finishes.
+    pub duration: Duration,
+}
+
+impl Default for GCInfo {
+    fn default() -> Self {
+        GCInfo {
+            gc: 0,
+            kind: CollectionKind::Full,
+            user_triggered: false,
+            emergency: false,
+            bytes_before: 0,
+            bytes_after: 0,
+            duration: Duration::default(),
+        }
+    }
+}
@@ -1,6 +1,12 @@
-pub use crate::plan::plan_constraints::*;
+use crate::plan::PlanConstraints;
+use crate::util::header_byte;
 
-pub const MOVES_OBJECTS: bool = true;
-pub const GC_HEADER_BITS: usize = 2;
-pub const GC_HEADER_WORDS: usize = 0;
-pub const NUM_SPECIALIZED_SCANS: usize = 1;
+pub const GENCOPY_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    gc_header_bits: 3,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    needs_log_bit_in_header: true,
+    needs_log_bit_in_header_num: header_byte::UNLOGGED_BIT_NUMBER,
+    ..PlanConstraints::default()
+};
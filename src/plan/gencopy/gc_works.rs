@@ -1,10 +1,13 @@
 use super::global::GenCopy;
-use crate::plan::{CopyContext, Plan};
+use crate::plan::CopyContext;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
 use crate::scheduler::{GCWork, GCWorker};
 use crate::util::alloc::{Allocator, BumpAllocator};
+use crate::util::alloc_bit;
 use crate::util::forwarding_word;
+use crate::util::header_byte;
+use crate::util::pin_bit;
 use crate::util::{Address, ObjectReference, OpaquePointer};
 use crate::vm::*;
 use crate::MMTK;
@@ -20,8 +23,8 @@ impl<VM: VMBinding> CopyContext for GenCopyCopyContext<VM> {
     type VM = VM;
     fn new(mmtk: &'static MMTK<Self::VM>) -> Self {
         Self {
-            plan: unsafe { &*(&mmtk.plan as *const _ as *const GenCopy<VM>) },
-            ss: BumpAllocator::new(OpaquePointer::UNINITIALIZED, None, &mmtk.plan),
+            plan: mmtk.plan.downcast_ref::<GenCopy<VM>>().unwrap(),
+            ss: BumpAllocator::new(OpaquePointer::UNINITIALIZED, None, &*mmtk.plan),
         }
     }
     fn init(&mut self, tls: OpaquePointer) {
@@ -54,15 +57,28 @@ impl<VM: VMBinding> CopyContext for GenCopyCopyContext<VM> {
         _semantics: crate::AllocationSemantics,
     ) {
         forwarding_word::clear_forwarding_bits::<VM>(obj);
+        // Objects are only copied to the mature space, where their writes are remembered.
+        header_byte::mark_as_unlogged::<VM>(obj);
+        alloc_bit::set_alloc_bit(obj);
     }
 }
 
 #[derive(Default)]
 pub struct GenCopyNurseryProcessEdges<VM: VMBinding> {
     base: ProcessEdgesBase<GenCopyNurseryProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static GenCopy<VM>>,
     phantom: PhantomData<VM>,
 }
 
+impl<VM: VMBinding> GenCopyNurseryProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static GenCopy<VM> {
+        self.plan.unwrap()
+    }
+}
+
 impl<VM: VMBinding> ProcessEdgesWork for GenCopyNurseryProcessEdges<VM> {
     type VM = VM;
     fn new(edges: Vec<Address>, _roots: bool) -> Self {
@@ -72,6 +88,12 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyNurseryProcessEdges<VM> {
         }
     }
     #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<GenCopy<VM>>();
+    }
+    #[inline]
     fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
         if object.is_null() {
             return object;
@@ -82,19 +104,34 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyNurseryProcessEdges<VM> {
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<GenCopyCopyContext<VM>>(),
             );
         }
-        debug_assert!(!self.plan().fromspace().in_space(object));
-        debug_assert!(self.plan().tospace().in_space(object));
+        // New large objects are in the nursery of the large object space.
+        if self.plan().common.get_los().in_space(object) {
+            return self.plan().common.get_los().trace_object(self, object);
+        }
+        // Only the pinned objects that the last full heap collection kept are in the from-space.
+        debug_assert!(
+            !self.plan().fromspace().in_space(object)
+                || self.plan().fromspace().in_kept_pages(object.to_address())
+        );
         object
     }
     #[inline]
     fn process_edge(&mut self, slot: Address) {
-        debug_assert!(!self.plan().fromspace().address_in_space(slot));
+        debug_assert!(
+            !self.plan().fromspace().address_in_space(slot)
+                || self.plan().fromspace().in_kept_pages(slot)
+        );
         let object = unsafe { slot.load::<ObjectReference>() };
         let new_object = self.trace_object(object);
-        debug_assert!(!self.plan().nursery.in_space(new_object));
+        // Only the objects that the nursery keeps in place stay in it.
+        debug_assert!(
+            !self.plan().nursery.in_space(new_object)
+                || self.plan().nursery.in_kept_pages(new_object.to_address())
+                || pin_bit::is_pinned(new_object)
+        );
         unsafe { slot.store(new_object) };
     }
 }
@@ -115,9 +152,19 @@ impl<VM: VMBinding> DerefMut for GenCopyNurseryProcessEdges<VM> {
 #[derive(Default)]
 pub struct GenCopyMatureProcessEdges<VM: VMBinding> {
     base: ProcessEdgesBase<GenCopyMatureProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static GenCopy<VM>>,
     phantom: PhantomData<VM>,
 }
 
+impl<VM: VMBinding> GenCopyMatureProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static GenCopy<VM> {
+        self.plan.unwrap()
+    }
+}
+
 impl<VM: VMBinding> ProcessEdgesWork for GenCopyMatureProcessEdges<VM> {
     type VM = VM;
     fn new(edges: Vec<Address>, _roots: bool) -> Self {
@@ -127,6 +174,12 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyMatureProcessEdges<VM> {
         }
     }
     #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<GenCopy<VM>>();
+    }
+    #[inline]
     fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
         if object.is_null() {
             return object;
@@ -137,7 +190,7 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyMatureProcessEdges<VM> {
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<GenCopyCopyContext<VM>>(),
             );
         }
         // Evacuate mature objects
@@ -146,7 +199,7 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyMatureProcessEdges<VM> {
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<GenCopyCopyContext<VM>>(),
             );
         }
         if self.plan().fromspace().in_space(object) {
@@ -154,7 +207,7 @@ impl<VM: VMBinding> ProcessEdgesWork for GenCopyMatureProcessEdges<VM> {
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<GenCopyCopyContext<VM>>(),
             );
         }
         self.plan().common.trace_object(self, object)
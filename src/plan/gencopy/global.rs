@@ -2,15 +2,19 @@ use super::gc_works::{GenCopyCopyContext, GenCopyMatureProcessEdges, GenCopyNurs
 use super::mutator::create_gencopy_mutator;
 use super::mutator::ALLOCATOR_MAPPING;
 use crate::mmtk::MMTK;
+use crate::plan::gencopy::constraints::GENCOPY_CONSTRAINTS;
 use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::GcStatus;
 use crate::plan::mutator_context::Mutator;
 use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
 use crate::plan::Plan;
+use crate::plan::PlanConstraints;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::LOG_BYTES_IN_PAGE;
@@ -19,7 +23,7 @@ use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
-use crate::util::options::UnsafeOptionsWrapper;
+use crate::util::options::{NurseryZeroingOptions, RememberedSet, UnsafeOptionsWrapper};
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::*;
 use crate::util::OpaquePointer;
@@ -28,8 +32,6 @@ use enum_map::EnumMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-pub type SelectedPlan<VM> = GenCopy<VM>;
-
 pub const ALLOC_SS: AllocationSemantics = AllocationSemantics::Default;
 pub const NURSERY_SIZE: usize = 16 * 1024 * 1024;
 
@@ -39,7 +41,6 @@ pub struct GenCopy<VM: VMBinding> {
     pub copyspace0: CopySpace<VM>,
     pub copyspace1: CopySpace<VM>,
     pub common: CommonPlan<VM>,
-    in_nursery: AtomicBool,
     pub scheduler: &'static MMTkScheduler<VM>,
 }
 
@@ -47,16 +48,19 @@ unsafe impl<VM: VMBinding> Sync for GenCopy<VM> {}
 
 impl<VM: VMBinding> Plan for GenCopy<VM> {
     type VM = VM;
-    type Mutator = Mutator<Self>;
-    type CopyContext = GenCopyCopyContext<VM>;
 
     fn collection_required(&self, space_full: bool, _space: &dyn Space<Self::VM>) -> bool
     where
         Self: Sized,
     {
-        let nursery_full = self.nursery.reserved_pages() >= (NURSERY_SIZE >> LOG_BYTES_IN_PAGE);
-        let heap_full = self.get_pages_reserved() > self.get_total_pages();
-        space_full || nursery_full || heap_full
+        // The pages kept for pinned objects are not allocated into again until they are freed.
+        let nursery_full = self.nursery.reserved_pages() - self.nursery.kept_pages()
+            >= (NURSERY_SIZE >> LOG_BYTES_IN_PAGE);
+        nursery_full
+            || self
+                .base()
+                .gc_trigger()
+                .is_gc_required(space_full, self.get_pages_reserved())
     }
 
     fn new(
@@ -97,11 +101,18 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
                 &mut heap,
             ),
             common: CommonPlan::new(vm_map, mmapper, options, heap),
-            in_nursery: AtomicBool::default(),
             scheduler,
         }
     }
 
+    fn constraints(&self) -> &'static PlanConstraints {
+        &GENCOPY_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box GenCopyCopyContext::new(mmtk)
+    }
+
     fn gc_init(
         &mut self,
         heap_size: usize,
@@ -110,25 +121,59 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     ) {
         self.common.gc_init(heap_size, vm_map, scheduler);
         self.nursery.init(&vm_map);
+        let (nontemporal, concurrent) = match self.base().options.nursery_zeroing {
+            NurseryZeroingOptions::Temporal => (false, false),
+            NurseryZeroingOptions::Nontemporal => (true, false),
+            NurseryZeroingOptions::Concurrent | NurseryZeroingOptions::Adaptive => (false, true),
+        };
+        self.nursery
+            .get_page_resource()
+            .update_zeroing_approach(nontemporal, concurrent);
         self.copyspace0.init(&vm_map);
         self.copyspace1.init(&vm_map);
     }
 
     fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
         let in_nursery = !self.request_full_heap_collection();
-        self.in_nursery.store(in_nursery, Ordering::SeqCst);
+        self.base()
+            .nursery_collection
+            .store(in_nursery, Ordering::SeqCst);
         self.base().set_collection_kind();
         self.base().set_gc_status(GcStatus::GcPrepare);
 
-        // Stop & scan mutators (mutator scanning can happen before STW)
+        // Stop & scan mutators (mutator scanning can happen before STW), and process the
+        // references after the closure. Nursery collections only process new references.
         if in_nursery {
             scheduler
                 .unconstrained_works
                 .add(StopMutators::<GenCopyNurseryProcessEdges<VM>>::new());
+            scheduler.schedule_ref_processing::<GenCopyNurseryProcessEdges<VM>>();
         } else {
             scheduler
                 .unconstrained_works
                 .add(StopMutators::<GenCopyMatureProcessEdges<VM>>::new());
+            scheduler.schedule_ref_processing::<GenCopyMatureProcessEdges<VM>>();
+        }
+        // Scan the dirty cards of the mature spaces in nursery collections, including the pinned
+        // objects that the from-space keeps. Full heap collections trace all the objects, so they
+        // only clean the cards. The copy spaces have their cards cleaned when they are released.
+        if self.base().options.remembered_set == RememberedSet::CardTable {
+            let (los, immortal) = (self.common.get_los(), self.common.get_immortal());
+            if in_nursery {
+                scheduler
+                    .closure_stage
+                    .add(ScanCardTable::<GenCopyNurseryProcessEdges<VM>>::new(
+                        vec![self.tospace(), self.fromspace(), los, immortal],
+                        true,
+                    ));
+            } else {
+                scheduler
+                    .closure_stage
+                    .add(ScanCardTable::<GenCopyMatureProcessEdges<VM>>::new(
+                        vec![los, immortal],
+                        false,
+                    ));
+            }
         }
         // Prepare global/collectors/mutators
         scheduler.prepare_stage.add(Prepare::new(self));
@@ -144,7 +189,7 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
         &'static self,
         tls: OpaquePointer,
         mmtk: &'static MMTK<Self::VM>,
-    ) -> Box<Mutator<Self>> {
+    ) -> Box<Mutator<VM>> {
         Box::new(create_gencopy_mutator(tls, mmtk))
     }
 
@@ -153,20 +198,34 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     }
 
     fn prepare(&self, tls: OpaquePointer) {
-        self.common.prepare(tls, true);
+        // Nursery collections only trace the new objects of the large object space.
+        self.common.prepare(tls, !self.in_nursery());
         self.nursery.prepare(true);
+        // The writes to the objects in the nursery are not remembered, so the objects that the
+        // nursery keeps in place stay there, and are scanned, until the next full heap collection.
+        if self.in_nursery() {
+            let kept = self.nursery.retain_kept_objects();
+            if !kept.is_empty() {
+                self.scheduler.closure_stage.add(
+                    ScanObjects::<GenCopyNurseryProcessEdges<VM>>::new(kept, false),
+                );
+            }
+        }
+        // The mature from-space may keep pinned objects, which are live until the next full heap
+        // collection.
         if !self.in_nursery() {
             self.hi
                 .store(!self.hi.load(Ordering::SeqCst), Ordering::SeqCst); // flip the semi-spaces
+            let hi = self.hi.load(Ordering::SeqCst);
+            self.copyspace0.prepare(hi);
+            self.copyspace1.prepare(!hi);
         }
-        let hi = self.hi.load(Ordering::SeqCst);
-        self.copyspace0.prepare(hi);
-        self.copyspace1.prepare(!hi);
     }
 
     fn release(&self, tls: OpaquePointer) {
-        self.common.release(tls, true);
+        self.common.release(tls, !self.in_nursery());
         self.nursery.release();
+        self.switch_nursery_zeroing_approach();
         if !self.in_nursery() {
             self.fromspace().release();
         }
@@ -179,9 +238,25 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     fn get_pages_used(&self) -> usize {
         self.nursery.reserved_pages()
             + self.tospace().reserved_pages()
+            + self.fromspace().reserved_pages()
             + self.common.get_pages_used()
     }
 
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.nursery);
+        f(&self.copyspace0);
+        f(&self.copyspace1);
+        self.common.for_each_space(f);
+    }
+
+    fn snapshot_state(&self) -> Vec<usize> {
+        vec![self.hi.load(Ordering::SeqCst) as usize]
+    }
+
+    fn restore_state(&self, state: &[usize]) {
+        self.hi.store(state[0] != 0, Ordering::SeqCst);
+    }
+
     fn base(&self) -> &BasePlan<VM> {
         &self.common.base
     }
@@ -189,15 +264,34 @@ impl<VM: VMBinding> Plan for GenCopy<VM> {
     fn common(&self) -> &CommonPlan<VM> {
         &self.common
     }
-
-    fn in_nursery(&self) -> bool {
-        self.in_nursery.load(Ordering::SeqCst)
-    }
 }
 
 impl<VM: VMBinding> GenCopy<VM> {
     fn request_full_heap_collection(&self) -> bool {
         self.get_total_pages() <= self.get_pages_reserved()
+            || (self
+                .base()
+                .user_triggered_collection
+                .load(Ordering::Relaxed)
+                && self.base().options.full_heap_system_gc)
+    }
+
+    /// Choose how the nursery released by this GC is zeroed. With concurrent zeroing, the nursery
+    /// is zeroed by a zeroing thread while the mutators run. The adaptive approach falls back to
+    /// zeroing at acquire when the mutators allocate faster than the zeroing thread zeroes.
+    fn switch_nursery_zeroing_approach(&self) {
+        let pr = self.nursery.get_page_resource();
+        match self.base().options.nursery_zeroing {
+            NurseryZeroingOptions::Concurrent => pr.trigger_concurrent_zeroing(),
+            NurseryZeroingOptions::Adaptive => {
+                if pr.concurrent_zeroing_keeps_up() {
+                    pr.trigger_concurrent_zeroing()
+                } else {
+                    pr.skip_concurrent_zeroing()
+                }
+            }
+            NurseryZeroingOptions::Temporal | NurseryZeroingOptions::Nontemporal => {}
+        }
     }
 
     pub fn tospace(&self) -> &CopySpace<VM> {
@@ -1,8 +1,6 @@
-pub mod constraints;
+mod constraints;
 mod gc_works;
 mod global;
 mod mutator;
 
-pub use self::constraints as SelectedConstraints;
 pub use self::global::GenCopy;
-pub use self::global::SelectedPlan;
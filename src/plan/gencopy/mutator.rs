@@ -4,26 +4,22 @@ use crate::plan::barriers::*;
 use crate::plan::mutator_context::Mutator;
 use crate::plan::mutator_context::MutatorConfig;
 use crate::plan::AllocationSemantics as AllocationType;
+use crate::plan::Plan;
 use crate::policy::copyspace::CopySpace;
 use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
 use crate::util::alloc::BumpAllocator;
+use crate::util::options::RememberedSet;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 use crate::MMTK;
 use enum_map::enum_map;
 use enum_map::EnumMap;
 
-pub fn gencopy_mutator_prepare<VM: VMBinding>(
-    _mutator: &mut Mutator<GenCopy<VM>>,
-    _tls: OpaquePointer,
-) {
+pub fn gencopy_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
     // Do nothing
 }
 
-pub fn gencopy_mutator_release<VM: VMBinding>(
-    mutator: &mut Mutator<GenCopy<VM>>,
-    _tls: OpaquePointer,
-) {
+pub fn gencopy_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
     // rebind the allocation bump pointer to the nursery space
     let bump_allocator = unsafe {
         mutator
@@ -32,7 +28,8 @@ pub fn gencopy_mutator_release<VM: VMBinding>(
     }
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
-    bump_allocator.rebind(Some(&mutator.plan.nursery));
+    let plan = mutator.plan.downcast_ref::<GenCopy<VM>>().unwrap();
+    bump_allocator.rebind(Some(&plan.nursery));
 }
 
 lazy_static! {
@@ -47,26 +44,34 @@ lazy_static! {
 pub fn create_gencopy_mutator<VM: VMBinding>(
     mutator_tls: OpaquePointer,
     mmtk: &'static MMTK<VM>,
-) -> Mutator<GenCopy<VM>> {
+) -> Mutator<VM> {
+    let plan = mmtk.plan.downcast_ref::<GenCopy<VM>>().unwrap();
     let config = MutatorConfig {
         allocator_mapping: &*ALLOCATOR_MAPPING,
         space_mapping: box vec![
-            (AllocatorSelector::BumpPointer(0), &mmtk.plan.nursery),
-            (AllocatorSelector::BumpPointer(1), mmtk.plan.fromspace()),
-            (AllocatorSelector::BumpPointer(2), mmtk.plan.tospace()),
+            (AllocatorSelector::BumpPointer(0), &plan.nursery),
+            (AllocatorSelector::BumpPointer(1), plan.fromspace()),
+            (AllocatorSelector::BumpPointer(2), plan.tospace()),
+            (AllocatorSelector::LargeObject(0), plan.common.get_los()),
         ],
         prepare_func: &gencopy_mutator_prepare,
         release_func: &gencopy_mutator_release,
     };
 
     Mutator {
-        allocators: Allocators::<VM>::new(mutator_tls, &mmtk.plan, &config.space_mapping),
-        barrier: box FieldRememberingBarrier::<GenCopyNurseryProcessEdges<VM>, CopySpace<VM>>::new(
-            mmtk,
-            &mmtk.plan.nursery,
-        ),
+        allocators: Allocators::<VM>::new(mutator_tls, plan, &config.space_mapping),
+        barrier: match plan.base().options.remembered_set {
+            RememberedSet::ModBuffer => box ObjectRememberingBarrier::<
+                GenCopyNurseryProcessEdges<VM>,
+                CopySpace<VM>,
+            >::new(mmtk, &plan.nursery),
+            RememberedSet::CardTable => box CardMarkingBarrier::<
+                GenCopyNurseryProcessEdges<VM>,
+                CopySpace<VM>,
+            >::new(mmtk, &plan.nursery),
+        },
         mutator_tls,
         config,
-        plan: &mmtk.plan,
+        plan,
     }
 }
@@ -1,5 +1,15 @@
+use super::concmarksweep::ConcMarkSweep;
 use super::controller_collector_context::ControllerCollectorContext;
-use super::MutatorContext;
+use super::gc_info::{CollectionKind, GCInfo};
+use super::gencopy::GenCopy;
+use super::immix::Immix;
+use super::markcompact::MarkCompact;
+use super::marksweep::MarkSweep;
+use super::mygc::MyGC;
+use super::nogc::NoGC;
+use super::semispace::SemiSpace;
+use super::Mutator;
+use super::PlanConstraints;
 use crate::mmtk::MMTK;
 use crate::plan::transitive_closure::TransitiveClosure;
 use crate::policy::immortalspace::ImmortalSpace;
@@ -11,23 +21,27 @@ use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::constants::*;
 use crate::util::conversions::bytes_to_pages;
+use crate::util::heap::gc_trigger::{create_gc_trigger, GCTriggerPolicy};
 use crate::util::heap::layout::heap_layout::Mmapper;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::map::Map;
+use crate::util::heap::uncommit::Uncommitter;
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
-use crate::util::options::{Options, UnsafeOptionsWrapper};
+use crate::util::options::{Options, PlanSelector, UnsafeOptionsWrapper};
 #[cfg(feature = "sanity")]
 use crate::util::sanity::sanity_checker::*;
 use crate::util::statistics::stats::Stats;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
 use crate::vm::*;
+use downcast_rs::Downcast;
 use enum_map::EnumMap;
 use std::cell::UnsafeCell;
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// A GC worker's context for copying GCs.
 /// Each GC plan should provide their implementation of a CopyContext.
@@ -102,17 +116,23 @@ impl<VM: VMBinding> CopyContext for NoCopy<VM> {
 ///
 /// The global instance defines and manages static resources
 /// (such as memory and virtual memory resources).
-pub trait Plan: Sized + 'static + Sync + Send {
+///
+/// Plans are selected at run time (see `create_plan()`), so the rest of MMTk only sees a plan
+/// as a `dyn Plan`. Plan-specific code can downcast it to the concrete plan type.
+pub trait Plan: 'static + Sync + Send + Downcast {
     type VM: VMBinding;
-    type Mutator: MutatorContext<Self::VM>;
-    type CopyContext: CopyContext;
 
     fn new(
         vm_map: &'static VMMap,
         mmapper: &'static Mmapper,
         options: Arc<UnsafeOptionsWrapper>,
         scheduler: &'static MMTkScheduler<Self::VM>,
-    ) -> Self;
+    ) -> Self
+    where
+        Self: Sized;
+    fn constraints(&self) -> &'static PlanConstraints;
+    /// Create the worker-local data (i.e. the copy context) for a GC worker.
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr;
     fn base(&self) -> &BasePlan<Self::VM>;
     fn schedule_collection(&'static self, _scheduler: &MMTkScheduler<Self::VM>);
     #[cfg(feature = "sanity")]
@@ -154,12 +174,12 @@ pub trait Plan: Sized + 'static + Sync + Send {
         &'static self,
         tls: OpaquePointer,
         mmtk: &'static MMTK<Self::VM>,
-    ) -> Box<Self::Mutator>;
+    ) -> Box<Mutator<Self::VM>>;
 
     fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector>;
 
     fn in_nursery(&self) -> bool {
-        false
+        self.base().nursery_collection.load(Ordering::SeqCst)
     }
 
     #[cfg(feature = "sanity")]
@@ -201,22 +221,22 @@ pub trait Plan: Sized + 'static + Sync + Send {
             return true;
         }
 
-        // FIXME
-        /*if self.concurrent_collection_required() {
-            // FIXME
-            /*if space == self.common().meta_data_space {
-                self.log_poll(space, "Triggering async concurrent collection");
-                Self::trigger_internal_collection_request();
-                return false;
-            } else {*/
+        if self.concurrent_collection_required() {
+            // The mutator does not wait for a concurrent collection. It is stopped by the
+            // collection, like any other mutator.
             self.log_poll(space, "Triggering concurrent collection");
-            Self::trigger_internal_collection_request();
-            return true;
-        }*/
+            self.base().control_collector_context.request();
+        }
 
         false
     }
 
+    /// Should a concurrent collection be started? Plans that collect concurrently start a
+    /// collection before the heap is full, so that it finishes before the heap runs out.
+    fn concurrent_collection_required(&self) -> bool {
+        false
+    }
+
     fn log_poll(&self, space: &dyn Space<Self::VM>, message: &'static str) {
         info!("  [POLL] {}: {}", space.get_name(), message);
     }
@@ -229,19 +249,18 @@ pub trait Plan: Sized + 'static + Sync + Send {
      * @param space TODO
      * @return <code>true</code> if a collection is requested by the plan.
      */
-    fn collection_required(&self, space_full: bool, _space: &dyn Space<Self::VM>) -> bool
-    where
-        Self: Sized,
-    {
+    fn collection_required(&self, space_full: bool, _space: &dyn Space<Self::VM>) -> bool {
         let stress_force_gc = self.stress_test_gc_required();
         debug!(
             "self.get_pages_reserved()={}, self.get_total_pages()={}",
             self.get_pages_reserved(),
             self.get_total_pages()
         );
-        let heap_full = self.get_pages_reserved() > self.get_total_pages();
-
-        space_full || stress_force_gc || heap_full
+        stress_force_gc
+            || self
+                .base()
+                .gc_trigger()
+                .is_gc_required(space_full, self.get_pages_reserved())
     }
 
     fn get_pages_reserved(&self) -> usize {
@@ -249,7 +268,7 @@ pub trait Plan: Sized + 'static + Sync + Send {
     }
 
     fn get_total_pages(&self) -> usize {
-        self.base().heap.get_total_pages()
+        self.base().gc_trigger().get_heap_size_in_pages()
     }
 
     fn get_pages_avail(&self) -> usize {
@@ -262,6 +281,17 @@ pub trait Plan: Sized + 'static + Sync + Send {
 
     fn get_pages_used(&self) -> usize;
 
+    /// Call `f` with each space of this plan.
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<Self::VM>));
+
+    /// The state of this plan that a heap snapshot keeps, other than the state of its spaces.
+    fn snapshot_state(&self) -> Vec<usize> {
+        vec![]
+    }
+
+    /// Restore the state saved by `snapshot_state()`.
+    fn restore_state(&self, _state: &[usize]) {}
+
     fn is_emergency_collection(&self) -> bool {
         self.base().emergency_collection.load(Ordering::Relaxed)
     }
@@ -315,6 +345,28 @@ pub trait Plan: Sized + 'static + Sync + Send {
     }
 }
 
+impl_downcast!(Plan assoc VM where VM: VMBinding);
+
+/// Create the plan selected by the `plan` option.
+pub fn create_plan<VM: VMBinding>(
+    plan: PlanSelector,
+    vm_map: &'static VMMap,
+    mmapper: &'static Mmapper,
+    options: Arc<UnsafeOptionsWrapper>,
+    scheduler: &'static MMTkScheduler<VM>,
+) -> Box<dyn Plan<VM = VM>> {
+    match plan {
+        PlanSelector::NoGC => box NoGC::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::SemiSpace => box SemiSpace::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::GenCopy => box GenCopy::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::MarkSweep => box MarkSweep::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::ConcMarkSweep => box ConcMarkSweep::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::Immix => box Immix::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::MarkCompact => box MarkCompact::new(vm_map, mmapper, options, scheduler),
+        PlanSelector::MyGC => box MyGC::new(vm_map, mmapper, options, scheduler),
+    }
+}
+
 #[derive(PartialEq)]
 pub enum GcStatus {
     NotInGC,
@@ -333,6 +385,11 @@ pub struct BasePlan<VM: VMBinding> {
     pub stacks_prepared: AtomicBool,
     pub emergency_collection: AtomicBool,
     pub user_triggered_collection: AtomicBool,
+    // Whether the current GC only collects the nursery
+    pub nursery_collection: AtomicBool,
+    // The current or the last GC, for the GC hooks of the VM
+    gc_info: Mutex<GCInfo>,
+    gc_start: Mutex<Option<Instant>>,
     // Has an allocation succeeded since the emergency collection?
     pub allocation_success: AtomicBool,
     // Maximum number of failed attempts by a single thread
@@ -347,6 +404,8 @@ pub struct BasePlan<VM: VMBinding> {
     pub vm_map: &'static VMMap,
     pub options: Arc<UnsafeOptionsWrapper>,
     pub heap: HeapMeta,
+    gc_trigger: UnsafeCell<Option<Box<dyn GCTriggerPolicy>>>,
+    pub uncommitter: Uncommitter,
     #[cfg(feature = "base_spaces")]
     pub unsync: UnsafeCell<BaseUnsync<VM>>,
     #[cfg(feature = "sanity")]
@@ -354,6 +413,9 @@ pub struct BasePlan<VM: VMBinding> {
     // A counter for per-mutator stack scanning
     pub scanned_stacks: AtomicUsize,
     pub mutator_iterator_lock: Mutex<()>,
+    // The objects that the conservative roots of the current GC pinned, which are unpinned when
+    // the GC ends
+    pub conservative_pins: Mutex<Vec<ObjectReference>>,
 }
 
 #[cfg(feature = "base_spaces")]
@@ -428,6 +490,9 @@ impl<VM: VMBinding> BasePlan<VM> {
             stacks_prepared: AtomicBool::new(false),
             emergency_collection: AtomicBool::new(false),
             user_triggered_collection: AtomicBool::new(false),
+            nursery_collection: AtomicBool::new(false),
+            gc_info: Mutex::new(GCInfo::default()),
+            gc_start: Mutex::new(None),
             allocation_success: AtomicBool::new(false),
             max_collection_attempts: AtomicUsize::new(0),
             cur_collection_attempts: AtomicUsize::new(0),
@@ -436,12 +501,15 @@ impl<VM: VMBinding> BasePlan<VM> {
             stats: Stats::new(),
             mmapper,
             heap,
+            gc_trigger: UnsafeCell::new(None),
+            uncommitter: Uncommitter::new(options.clone()),
             vm_map,
             options,
             #[cfg(feature = "sanity")]
             inside_sanity: AtomicBool::new(false),
             scanned_stacks: AtomicUsize::new(0),
             mutator_iterator_lock: Mutex::new(()),
+            conservative_pins: Mutex::new(vec![]),
         }
     }
 
@@ -456,9 +524,10 @@ impl<VM: VMBinding> BasePlan<VM> {
             self.heap.get_discontig_start(),
             self.heap.get_discontig_end(),
         );
-        self.heap
-            .total_pages
-            .store(bytes_to_pages(heap_size), Ordering::Relaxed);
+        let gc_trigger = unsafe { &mut *self.gc_trigger.get() };
+        if gc_trigger.is_none() {
+            *gc_trigger = Some(create_gc_trigger(&self.options, bytes_to_pages(heap_size)));
+        }
         self.control_collector_context.init(scheduler);
 
         #[cfg(feature = "base_spaces")]
@@ -469,13 +538,32 @@ impl<VM: VMBinding> BasePlan<VM> {
             #[cfg(feature = "ro_space")]
             unsync.ro_space.init(vm_map);
             #[cfg(feature = "vm_space")]
-            {
-                unsync.vm_space.init(vm_map);
-                unsync.vm_space.ensure_mapped();
-            }
+            // The VM space is only marked as mapped when a boot image is mapped into it, and
+            // only as far as the image goes.
+            unsync.vm_space.init(vm_map);
         }
     }
 
+    #[cfg(feature = "vm_space")]
+    pub fn get_vm_space(&self) -> &'static ImmortalSpace<VM> {
+        let unsync = unsafe { &*self.unsync.get() };
+        &unsync.vm_space
+    }
+
+    /// Use a GC trigger policy other than the one selected by the options.
+    ///
+    /// # Safety
+    /// This method is not thread safe. It can only be called by the init thread before `gc_init()`.
+    pub unsafe fn set_gc_trigger(&self, gc_trigger: Box<dyn GCTriggerPolicy>) {
+        *self.gc_trigger.get() = Some(gc_trigger);
+    }
+
+    pub fn gc_trigger(&self) -> &dyn GCTriggerPolicy {
+        unsafe { &*self.gc_trigger.get() }
+            .as_deref()
+            .expect("GC trigger is not created before gc_init()")
+    }
+
     #[cfg(feature = "base_spaces")]
     pub fn get_pages_used(&self) -> usize {
         let mut pages = 0;
@@ -504,6 +592,17 @@ impl<VM: VMBinding> BasePlan<VM> {
         0
     }
 
+    pub fn for_each_space(&self, _f: &mut dyn FnMut(&dyn Space<VM>)) {
+        #[cfg(feature = "base_spaces")]
+        let unsync = unsafe { &*self.unsync.get() };
+        #[cfg(feature = "code_space")]
+        _f(&unsync.code_space);
+        #[cfg(feature = "ro_space")]
+        _f(&unsync.ro_space);
+        #[cfg(feature = "vm_space")]
+        _f(&unsync.vm_space);
+    }
+
     pub fn trace_object<T: TransitiveClosure>(
         &self,
         _trace: &mut T,
@@ -585,6 +684,7 @@ impl<VM: VMBinding> BasePlan<VM> {
 
     pub fn set_gc_status(&self, s: GcStatus) {
         let mut gc_status = self.gc_status.lock().unwrap();
+        let started = *gc_status == GcStatus::NotInGC && s != GcStatus::NotInGC;
         if *gc_status == GcStatus::NotInGC {
             self.stacks_prepared.store(false, Ordering::SeqCst);
             // FIXME stats
@@ -597,6 +697,52 @@ impl<VM: VMBinding> BasePlan<VM> {
                 self.stats.end_gc();
             }
         }
+        // The hook may ask whether a GC is in progress.
+        drop(gc_status);
+        if started {
+            let info = self.start_gc_info();
+            VM::VMCollection::gc_started(&info);
+        }
+    }
+
+    /// Record the bytes used by the heap before a GC starts, for the GC hooks.
+    pub fn set_bytes_before_gc(&self, bytes: usize) {
+        self.gc_info.lock().unwrap().bytes_before = bytes;
+    }
+
+    /// The information about the current GC, or the last one if no GC is in progress.
+    pub fn gc_info(&self) -> GCInfo {
+        *self.gc_info.lock().unwrap()
+    }
+
+    fn start_gc_info(&self) -> GCInfo {
+        let mut info = self.gc_info.lock().unwrap();
+        info.gc += 1;
+        info.kind = if self.nursery_collection.load(Ordering::SeqCst) {
+            CollectionKind::Nursery
+        } else {
+            CollectionKind::Full
+        };
+        info.user_triggered = self.is_user_triggered_collection();
+        info.emergency = self.emergency_collection.load(Ordering::Relaxed);
+        info.bytes_after = 0;
+        info.duration = Duration::default();
+        *self.gc_start.lock().unwrap() = Some(Instant::now());
+        *info
+    }
+
+    /// Record the bytes used by the heap after a GC, and call the `gc_finished` hook of the VM.
+    /// This is called once the mutators are resumed.
+    pub fn gc_finished(&self, bytes_after: usize) {
+        let info = {
+            let mut info = self.gc_info.lock().unwrap();
+            info.bytes_after = bytes_after;
+            if let Some(start) = *self.gc_start.lock().unwrap() {
+                info.duration = start.elapsed();
+            }
+            *info
+        };
+        VM::VMCollection::gc_finished(&info);
     }
 
     pub fn stacks_prepared(&self) -> bool {
@@ -698,6 +844,13 @@ impl<VM: VMBinding> CommonPlan<VM> {
         unsync.immortal.reserved_pages() + unsync.los.reserved_pages() + self.base.get_pages_used()
     }
 
+    pub fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        let unsync = unsafe { &*self.unsync.get() };
+        f(&unsync.immortal);
+        f(&unsync.los);
+        self.base.for_each_space(f);
+    }
+
     pub fn trace_object<T: TransitiveClosure>(
         &self,
         trace: &mut T,
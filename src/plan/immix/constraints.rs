@@ -0,0 +1,12 @@
+use crate::plan::PlanConstraints;
+use crate::policy::immixspace::MAX_IMMIX_OBJECT_SIZE;
+
+pub const IMMIX_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    // Two bits for forwarding, and one mark bit
+    gc_header_bits: 3,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    max_non_los_default_alloc_bytes: MAX_IMMIX_OBJECT_SIZE,
+    ..PlanConstraints::default()
+};
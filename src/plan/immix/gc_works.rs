@@ -0,0 +1,120 @@
+use super::global::Immix;
+use crate::plan::CopyContext;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorker;
+use crate::util::alloc::{Allocator, BumpAllocator};
+use crate::util::{Address, ObjectReference, OpaquePointer};
+use crate::vm::VMBinding;
+use crate::MMTK;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+pub struct ImmixCopyContext<VM: VMBinding> {
+    plan: &'static Immix<VM>,
+    immix: BumpAllocator<VM>,
+}
+
+impl<VM: VMBinding> CopyContext for ImmixCopyContext<VM> {
+    type VM = VM;
+    fn new(mmtk: &'static MMTK<Self::VM>) -> Self {
+        Self {
+            plan: mmtk.plan.downcast_ref::<Immix<VM>>().unwrap(),
+            immix: BumpAllocator::new(OpaquePointer::UNINITIALIZED, None, &*mmtk.plan),
+        }
+    }
+    fn init(&mut self, tls: OpaquePointer) {
+        self.immix.tls = tls;
+    }
+    fn prepare(&mut self) {
+        self.immix.rebind(Some(self.plan.immix_space()));
+    }
+    fn release(&mut self) {
+        // The rest of the current block becomes holes after the sweep, so do not keep allocating into it.
+        self.immix.rebind(Some(self.plan.immix_space()));
+    }
+    #[inline(always)]
+    fn alloc_copy(
+        &mut self,
+        _original: ObjectReference,
+        bytes: usize,
+        align: usize,
+        offset: isize,
+        _semantics: crate::AllocationSemantics,
+    ) -> Address {
+        self.immix.alloc(bytes, align, offset)
+    }
+    #[inline(always)]
+    fn post_copy(
+        &mut self,
+        obj: ObjectReference,
+        _tib: Address,
+        _bytes: usize,
+        _semantics: crate::AllocationSemantics,
+    ) {
+        self.plan.immix_space().post_copy(obj);
+    }
+}
+
+#[derive(Default)]
+pub struct ImmixProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<ImmixProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static Immix<VM>>,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> ImmixProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static Immix<VM> {
+        self.plan.unwrap()
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for ImmixProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<Immix<VM>>();
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan().immix_space().in_space(object) {
+            self.plan().immix_space().trace_object(
+                self,
+                object,
+                super::global::ALLOC_IMMIX,
+                self.worker().copy_context::<ImmixCopyContext<VM>>(),
+            )
+        } else {
+            self.plan().common.trace_object(self, object)
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for ImmixProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for ImmixProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
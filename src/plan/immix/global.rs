@@ -0,0 +1,154 @@
+use super::gc_works::{ImmixCopyContext, ImmixProcessEdges};
+use crate::mmtk::MMTK;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::immix::constraints::IMMIX_CONSTRAINTS;
+use crate::plan::immix::mutator::create_immix_mutator;
+use crate::plan::immix::mutator::ALLOCATOR_MAPPING;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::immixspace::ImmixSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::VMRequest;
+use crate::util::options::UnsafeOptionsWrapper;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+
+use enum_map::EnumMap;
+
+pub const ALLOC_IMMIX: AllocationSemantics = AllocationSemantics::Default;
+
+pub struct Immix<VM: VMBinding> {
+    pub immix: UnsafeCell<ImmixSpace<VM>>,
+    pub common: CommonPlan<VM>,
+}
+
+unsafe impl<VM: VMBinding> Sync for Immix<VM> {}
+
+impl<VM: VMBinding> Plan for Immix<VM> {
+    type VM = VM;
+
+    fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        _scheduler: &'static MMTkScheduler<Self::VM>,
+    ) -> Self {
+        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
+
+        Immix {
+            immix: UnsafeCell::new(ImmixSpace::new(
+                "immix",
+                true,
+                VMRequest::discontiguous(),
+                vm_map,
+                mmapper,
+                &mut heap,
+            )),
+            common: CommonPlan::new(vm_map, mmapper, options, heap),
+        }
+    }
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &IMMIX_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box ImmixCopyContext::new(mmtk)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        unsafe { &mut *self.immix.get() }.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler
+            .unconstrained_works
+            .add(StopMutators::<ImmixProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.prepare_stage.add(Prepare::new(self));
+        // Process the references after the closure
+        scheduler.schedule_ref_processing::<ImmixProcessEdges<VM>>();
+        // Release global/collectors/mutators
+        scheduler.release_stage.add(Release::new(self));
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.final_stage.add(ScheduleSanityGC);
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn bind_mutator(
+        &'static self,
+        tls: OpaquePointer,
+        _mmtk: &'static MMTK<Self::VM>,
+    ) -> Box<Mutator<VM>> {
+        Box::new(create_immix_mutator(tls, self))
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+        unsafe { &mut *self.immix.get() }.prepare(self.get_collection_reserve());
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+        // sweep the immix space
+        unsafe { &mut *self.immix.get() }.release();
+    }
+
+    fn get_collection_reserve(&self) -> usize {
+        ImmixSpace::<VM>::defrag_headroom_pages(self.get_total_pages())
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.immix_space().reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(self.immix_space());
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+}
+
+impl<VM: VMBinding> Immix<VM> {
+    pub fn immix_space(&self) -> &'static ImmixSpace<VM> {
+        unsafe { &*self.immix.get() }
+    }
+}
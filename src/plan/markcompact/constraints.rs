@@ -0,0 +1,12 @@
+use crate::plan::PlanConstraints;
+
+pub const MC_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    // One mark bit. Forwarding addresses are kept on the side.
+    gc_header_bits: 1,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    // The compaction slides every live object.
+    supports_pinning: false,
+    ..PlanConstraints::default()
+};
@@ -0,0 +1,279 @@
+use super::global::{MarkCompact, MarkingBuffers};
+use crate::policy::markcompactspace::CompactionGroup;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::{GCWork, GCWorker};
+use crate::util::{Address, ObjectReference};
+use crate::vm::{ObjectModel, Scanning, VMBinding};
+use crate::MMTK;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// The number of objects or root slots handled by each packet when updating references.
+const UPDATE_REFERENCES_CHUNK: usize = 4096;
+
+/// Marks objects. Besides the usual closure, this records every root slot and every live
+/// object, so that their references can be updated after forwarding addresses are known.
+#[derive(Default)]
+pub struct MCMarkProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<MCMarkProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static MarkCompact<VM>>,
+    roots: bool,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> MCMarkProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static MarkCompact<VM> {
+        self.plan.unwrap()
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for MCMarkProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            roots,
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>();
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan().mc_space().in_space(object) {
+            self.plan().mc_space().trace_mark_object(self, object)
+        } else {
+            self.plan().common.trace_object(self, object)
+        }
+    }
+    #[inline]
+    fn process_edges(&mut self) {
+        if self.roots {
+            self.plan()
+                .marking_buffers(self.worker())
+                .root_slots
+                .extend(&self.edges);
+        }
+        for i in 0..self.edges.len() {
+            self.process_edge(self.edges[i])
+        }
+    }
+    #[cold]
+    fn flush(&mut self) {
+        let nodes = mem::replace(&mut self.nodes, vec![]);
+        self.plan()
+            .marking_buffers(self.worker())
+            .live_objects
+            .extend(&nodes);
+        self.worker()
+            .do_work(ScanObjects::<Self>::new(nodes, false));
+    }
+}
+
+impl<VM: VMBinding> Deref for MCMarkProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for MCMarkProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Updates slots to the references their objects will have after compaction. Objects are
+/// not traced: every live object is scanned exactly once, by `UpdateReferences`.
+#[derive(Default)]
+pub struct MCForwardingProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<MCForwardingProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static MarkCompact<VM>>,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> MCForwardingProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static MarkCompact<VM> {
+        self.plan.unwrap()
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for MCForwardingProcessEdges<VM> {
+    type VM = VM;
+    fn new(edges: Vec<Address>, _roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>();
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if !object.is_null() && self.plan().mc_space().in_space(object) {
+            self.plan().mc_space().trace_forward_object(object)
+        } else {
+            object
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for MCForwardingProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for MCForwardingProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// Sort the live objects of the mark-compact space and split them into compaction groups,
+/// then calculate the forwarding addresses of each group in parallel.
+#[derive(Default)]
+pub struct CalculateForwardingAddress<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> CalculateForwardingAddress<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for CalculateForwardingAddress<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        trace!("CalculateForwardingAddress");
+        let plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+        let space = plan.mc_space();
+        let mut objects: Vec<ObjectReference> = vec![];
+        for buffers in &plan.marking_buffers {
+            let buffers = buffers.lock().unwrap();
+            objects.extend(
+                buffers
+                    .live_objects
+                    .iter()
+                    .filter(|&&object| space.in_space(object)),
+            );
+        }
+        objects.sort_unstable_by_key(|&object| VM::VMObjectModel::object_start_ref(object));
+        for group in space.create_groups(objects) {
+            worker
+                .scheduler()
+                .calculate_forwarding_stage
+                .add(CalculateGroupForwarding::<VM>(Some(group), PhantomData));
+        }
+    }
+}
+
+pub struct CalculateGroupForwarding<VM: VMBinding>(Option<CompactionGroup>, PhantomData<VM>);
+
+impl<VM: VMBinding> GCWork<VM> for CalculateGroupForwarding<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let mut group = self.0.take().unwrap();
+        group.calculate_forwarding::<VM>();
+        let plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+        plan.mc_space().add_group(group);
+    }
+}
+
+/// Update the references in all root slots and live objects, in chunks.
+#[derive(Default)]
+pub struct UpdateReferences<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> UpdateReferences<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for UpdateReferences<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        trace!("UpdateReferences");
+        let plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+        let MarkingBuffers {
+            mut root_slots,
+            live_objects,
+        } = plan.take_marking_buffers();
+        // A slot reported twice must only be updated once.
+        root_slots.sort_unstable();
+        root_slots.dedup();
+        for slots in root_slots.chunks(UPDATE_REFERENCES_CHUNK) {
+            worker
+                .scheduler()
+                .ref_forwarding_stage
+                .add(MCForwardingProcessEdges::<VM>::new(slots.to_vec(), true));
+        }
+        for objects in live_objects.chunks(UPDATE_REFERENCES_CHUNK) {
+            worker
+                .scheduler()
+                .ref_forwarding_stage
+                .add(UpdateObjectReferences::<VM>(objects.to_vec(), PhantomData));
+        }
+    }
+}
+
+pub struct UpdateObjectReferences<VM: VMBinding>(Vec<ObjectReference>, PhantomData<VM>);
+
+impl<VM: VMBinding> GCWork<VM> for UpdateObjectReferences<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, _mmtk: &'static MMTK<VM>) {
+        <VM as VMBinding>::VMScanning::scan_objects::<MCForwardingProcessEdges<VM>>(&self.0);
+    }
+}
+
+/// Slide the objects of each compaction group, in parallel.
+#[derive(Default)]
+pub struct Compact<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> Compact<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for Compact<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        trace!("Compact");
+        let plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+        for start in plan.mc_space().group_starts() {
+            worker
+                .scheduler()
+                .compact_stage
+                .add(CompactGroup::<VM>(start, PhantomData));
+        }
+    }
+}
+
+pub struct CompactGroup<VM: VMBinding>(Address, PhantomData<VM>);
+
+impl<VM: VMBinding> GCWork<VM> for CompactGroup<VM> {
+    fn do_work(&mut self, _worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        let plan = mmtk.plan.downcast_ref::<MarkCompact<VM>>().unwrap();
+        plan.mc_space().compact_group(self.0);
+    }
+}
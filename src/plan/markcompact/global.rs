@@ -0,0 +1,201 @@
+use super::gc_works::*;
+use crate::mmtk::MMTK;
+use crate::plan::global::BasePlan;
+use crate::plan::global::CommonPlan;
+use crate::plan::global::GcStatus;
+use crate::plan::global::NoCopy;
+use crate::plan::markcompact::constraints::MC_CONSTRAINTS;
+use crate::plan::markcompact::mutator::create_mc_mutator;
+use crate::plan::markcompact::mutator::ALLOCATOR_MAPPING;
+use crate::plan::mutator_context::Mutator;
+use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
+use crate::plan::Plan;
+use crate::plan::PlanConstraints;
+use crate::policy::markcompactspace::MarkCompactSpace;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
+use crate::scheduler::*;
+use crate::util::alloc::allocators::AllocatorSelector;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::VMRequest;
+use crate::util::options::UnsafeOptionsWrapper;
+#[cfg(feature = "sanity")]
+use crate::util::sanity::sanity_checker::*;
+use crate::util::{Address, ObjectReference, OpaquePointer};
+use crate::vm::VMBinding;
+use std::cell::UnsafeCell;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use enum_map::EnumMap;
+
+/// What a GC worker records while marking, so that the references can be updated after the
+/// forwarding addresses are known.
+#[derive(Default)]
+pub struct MarkingBuffers {
+    /// The slots of the roots that the worker processed.
+    pub root_slots: Vec<Address>,
+    /// The objects (in any space) that the worker marked.
+    pub live_objects: Vec<ObjectReference>,
+}
+
+pub struct MarkCompact<VM: VMBinding> {
+    pub mc: UnsafeCell<MarkCompactSpace<VM>>,
+    pub common: CommonPlan<VM>,
+    /// The marking buffers of each GC worker, and those of the coordinator last. Each worker
+    /// only locks its own buffers while marking.
+    pub marking_buffers: Vec<Mutex<MarkingBuffers>>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MarkCompact<VM> {}
+
+impl<VM: VMBinding> Plan for MarkCompact<VM> {
+    type VM = VM;
+
+    fn new(
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        options: Arc<UnsafeOptionsWrapper>,
+        _scheduler: &'static MMTkScheduler<Self::VM>,
+    ) -> Self {
+        let mut heap = HeapMeta::new(HEAP_START, HEAP_END);
+        let marking_buffers = (0..=options.threads)
+            .map(|_| Mutex::new(MarkingBuffers::default()))
+            .collect();
+
+        MarkCompact {
+            mc: UnsafeCell::new(MarkCompactSpace::new(
+                "markcompact",
+                true,
+                VMRequest::discontiguous(),
+                vm_map,
+                mmapper,
+                &mut heap,
+            )),
+            common: CommonPlan::new(vm_map, mmapper, options, heap),
+            marking_buffers,
+        }
+    }
+
+    fn constraints(&self) -> &'static PlanConstraints {
+        &MC_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box NoCopy::new(mmtk)
+    }
+
+    fn gc_init(
+        &mut self,
+        heap_size: usize,
+        vm_map: &'static VMMap,
+        scheduler: &Arc<MMTkScheduler<VM>>,
+    ) {
+        self.common.gc_init(heap_size, vm_map, scheduler);
+        unsafe { &mut *self.mc.get() }.init(&vm_map);
+    }
+
+    fn schedule_collection(&'static self, scheduler: &MMTkScheduler<VM>) {
+        self.base().set_collection_kind();
+        self.base().set_gc_status(GcStatus::GcPrepare);
+        // Stop & scan mutators (mutator scanning can happen before STW)
+        scheduler
+            .unconstrained_works
+            .add(StopMutators::<MCMarkProcessEdges<VM>>::new());
+        // Prepare global/collectors/mutators
+        scheduler.prepare_stage.add(Prepare::new(self));
+        // Process the references after the marking closure, and forward them with the objects
+        scheduler.schedule_ref_processing::<MCMarkProcessEdges<VM>>();
+        scheduler
+            .weak_ref_forwarding_stage
+            .add(ForwardRefs::<MCForwardingProcessEdges<VM>>::new());
+        scheduler
+            .weak_ref_forwarding_stage
+            .add(ForwardFinalization::<MCForwardingProcessEdges<VM>>::new());
+        // Compute forwarding addresses, update references, and move objects
+        scheduler
+            .calculate_forwarding_stage
+            .add(CalculateForwardingAddress::<VM>::new());
+        scheduler
+            .ref_forwarding_stage
+            .add(UpdateReferences::<VM>::new());
+        scheduler.compact_stage.add(Compact::<VM>::new());
+        // Release global/collectors/mutators
+        scheduler.release_stage.add(Release::new(self));
+        // Resume mutators
+        #[cfg(feature = "sanity")]
+        scheduler.final_stage.add(ScheduleSanityGC);
+        scheduler.set_finalizer(Some(EndOfGC));
+    }
+
+    fn bind_mutator(
+        &'static self,
+        tls: OpaquePointer,
+        _mmtk: &'static MMTK<Self::VM>,
+    ) -> Box<Mutator<VM>> {
+        Box::new(create_mc_mutator(tls, self))
+    }
+
+    fn get_allocator_mapping(&self) -> &'static EnumMap<AllocationSemantics, AllocatorSelector> {
+        &*ALLOCATOR_MAPPING
+    }
+
+    fn prepare(&self, tls: OpaquePointer) {
+        self.common.prepare(tls, true);
+        unsafe { &mut *self.mc.get() }.prepare();
+    }
+
+    fn release(&self, tls: OpaquePointer) {
+        self.common.release(tls, true);
+        // release the regions emptied by compaction
+        unsafe { &mut *self.mc.get() }.release();
+    }
+
+    fn get_pages_used(&self) -> usize {
+        self.mc_space().reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(self.mc_space());
+        self.common.for_each_space(f);
+    }
+
+    fn base(&self) -> &BasePlan<VM> {
+        &self.common.base
+    }
+
+    fn common(&self) -> &CommonPlan<VM> {
+        &self.common
+    }
+}
+
+impl<VM: VMBinding> MarkCompact<VM> {
+    pub fn mc_space(&self) -> &'static MarkCompactSpace<VM> {
+        unsafe { &*self.mc.get() }
+    }
+
+    /// The marking buffers of a GC worker.
+    pub fn marking_buffers(&self, worker: &GCWorker<VM>) -> MutexGuard<MarkingBuffers> {
+        let index = if worker.is_coordinator() {
+            self.marking_buffers.len() - 1
+        } else {
+            worker.ordinal
+        };
+        self.marking_buffers[index].lock().unwrap()
+    }
+
+    /// Take what all workers recorded while marking.
+    pub fn take_marking_buffers(&self) -> MarkingBuffers {
+        let mut all = MarkingBuffers::default();
+        for buffers in &self.marking_buffers {
+            let mut buffers = buffers.lock().unwrap();
+            all.root_slots.append(&mut buffers.root_slots);
+            all.live_objects.append(&mut buffers.live_objects);
+        }
+        all
+    }
+}
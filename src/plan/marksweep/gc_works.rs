@@ -0,0 +1,68 @@
+use super::global::MarkSweep;
+use crate::policy::space::Space;
+use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorker;
+use crate::util::{Address, ObjectReference};
+use crate::vm::VMBinding;
+use crate::MMTK;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+#[derive(Default)]
+pub struct MSProcessEdges<VM: VMBinding> {
+    base: ProcessEdgesBase<MSProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static MarkSweep<VM>>,
+    phantom: PhantomData<VM>,
+}
+
+impl<VM: VMBinding> MSProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static MarkSweep<VM> {
+        self.plan.unwrap()
+    }
+}
+
+impl<VM: VMBinding> ProcessEdgesWork for MSProcessEdges<VM> {
+    type VM = VM;
+    const OVERWRITE_REFERENCE: bool = false;
+    fn new(edges: Vec<Address>, _roots: bool) -> Self {
+        Self {
+            base: ProcessEdgesBase::new(edges),
+            ..Default::default()
+        }
+    }
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<MarkSweep<VM>>();
+    }
+    #[inline]
+    fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
+        if object.is_null() {
+            return object;
+        }
+        if self.plan().ms_space().in_space(object) {
+            self.plan().ms_space().trace_object(self, object)
+        } else {
+            self.plan().common.trace_object(self, object)
+        }
+    }
+}
+
+impl<VM: VMBinding> Deref for MSProcessEdges<VM> {
+    type Target = ProcessEdgesBase<Self>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<VM: VMBinding> DerefMut for MSProcessEdges<VM> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
@@ -0,0 +1,6 @@
+mod constraints;
+mod gc_works;
+mod global;
+mod mutator;
+
+pub use self::global::MarkSweep;
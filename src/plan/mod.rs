@@ -1,11 +1,14 @@
 pub mod barriers;
 pub mod controller_collector_context;
+pub mod gc_info;
 pub mod global;
 pub mod mutator_context;
 pub mod plan_constraints;
 mod trace;
 pub mod tracelocal;
 pub mod transitive_closure;
+pub use self::gc_info::{CollectionKind, GCInfo};
+pub use self::global::create_plan;
 pub use self::global::AllocationSemantics;
 pub use self::global::CopyContext;
 pub use self::global::Plan;
@@ -14,25 +17,13 @@ pub use self::mutator_context::MutatorContext;
 pub use self::tracelocal::TraceLocal;
 pub use self::transitive_closure::TransitiveClosure;
 
-#[cfg(feature = "mygc")]
-pub mod mygc;
-#[cfg(feature = "mygc")]
-pub use self::mygc as selected_plan;
+pub use self::plan_constraints::PlanConstraints;
 
-#[cfg(feature = "nogc")]
+pub mod concmarksweep;
+pub mod gencopy;
+pub mod immix;
+pub mod markcompact;
+pub mod marksweep;
+pub mod mygc;
 pub mod nogc;
-#[cfg(feature = "nogc")]
-pub use self::nogc as selected_plan;
-
-#[cfg(feature = "semispace")]
 pub mod semispace;
-#[cfg(feature = "semispace")]
-pub use self::semispace as selected_plan;
-
-#[cfg(feature = "gencopy")]
-pub mod gencopy;
-#[cfg(feature = "gencopy")]
-pub use self::gencopy as selected_plan;
-
-pub use self::selected_plan::SelectedConstraints;
-pub use self::selected_plan::SelectedPlan;
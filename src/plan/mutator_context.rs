@@ -3,6 +3,8 @@ use crate::plan::global::Plan;
 use crate::plan::AllocationSemantics as AllocationType;
 use crate::policy::space::Space;
 use crate::util::alloc::allocators::{AllocatorSelector, Allocators};
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
 use crate::vm::VMBinding;
@@ -14,20 +16,20 @@ type SpaceMapping<VM> = Vec<(AllocatorSelector, &'static dyn Space<VM>)>;
 // This struct is part of the Mutator struct.
 // We are trying to make it fixed-sized so that VM bindings can easily define a Mutator type to have the exact same layout as our Mutator struct.
 #[repr(C)]
-pub struct MutatorConfig<P: Plan> {
+pub struct MutatorConfig<VM: VMBinding> {
     // Mapping between allocation semantics and allocator selector
     pub allocator_mapping: &'static EnumMap<AllocationType, AllocatorSelector>,
     // Mapping between allocator selector and spaces. Each pair represents a mapping.
     // Put this behind a box, so it is a pointer-sized field.
     #[allow(clippy::box_vec)]
-    pub space_mapping: Box<SpaceMapping<P::VM>>,
+    pub space_mapping: Box<SpaceMapping<VM>>,
     // Plan-specific code for mutator prepare/release
-    pub prepare_func: &'static dyn Fn(&mut Mutator<P>, OpaquePointer),
-    pub release_func: &'static dyn Fn(&mut Mutator<P>, OpaquePointer),
+    pub prepare_func: &'static dyn Fn(&mut Mutator<VM>, OpaquePointer),
+    pub release_func: &'static dyn Fn(&mut Mutator<VM>, OpaquePointer),
 }
 
-unsafe impl<P: Plan> Send for MutatorConfig<P> {}
-unsafe impl<P: Plan> Sync for MutatorConfig<P> {}
+unsafe impl<VM: VMBinding> Send for MutatorConfig<VM> {}
+unsafe impl<VM: VMBinding> Sync for MutatorConfig<VM> {}
 
 /// A mutator is a per-thread data structure that manages allocations and barriers. It is usually highly coupled with the language VM.
 /// It is recommended for MMTk users 1) to have a mutator struct of the same layout in the thread local storage that can be accessed efficiently,
@@ -38,15 +40,15 @@ unsafe impl<P: Plan> Sync for MutatorConfig<P> {}
 // - Allocators are fixed-length arrays of allocators.
 // - MutatorConfig only has pointers/refs (including fat pointers), and is fixed sized.
 #[repr(C)]
-pub struct Mutator<P: Plan> {
-    pub allocators: Allocators<P::VM>,
+pub struct Mutator<VM: VMBinding> {
+    pub allocators: Allocators<VM>,
     pub barrier: Box<dyn Barrier>,
     pub mutator_tls: OpaquePointer,
-    pub plan: &'static P,
-    pub config: MutatorConfig<P>,
+    pub plan: &'static dyn Plan<VM = VM>,
+    pub config: MutatorConfig<VM>,
 }
 
-impl<P: Plan<Mutator = Self>> MutatorContext<P::VM> for Mutator<P> {
+impl<VM: VMBinding> MutatorContext<VM> for Mutator<VM> {
     fn prepare(&mut self, tls: OpaquePointer) {
         (*self.config.prepare_func)(self, tls)
     }
@@ -62,6 +64,7 @@ impl<P: Plan<Mutator = Self>> MutatorContext<P::VM> for Mutator<P> {
         offset: isize,
         allocator: AllocationType,
     ) -> Address {
+        let allocator = self.allocation_type_for(size, allocator);
         unsafe {
             self.allocators
                 .get_allocator_mut(self.config.allocator_mapping[allocator])
@@ -75,16 +78,19 @@ impl<P: Plan<Mutator = Self>> MutatorContext<P::VM> for Mutator<P> {
         &mut self,
         refer: ObjectReference,
         _type_refer: ObjectReference,
-        _bytes: usize,
+        bytes: usize,
         allocator: AllocationType,
     ) {
+        let allocator = self.allocation_type_for(bytes, allocator);
         unsafe {
             self.allocators
                 .get_allocator_mut(self.config.allocator_mapping[allocator])
         }
         .get_space()
         .unwrap()
-        .initialize_header(refer, true)
+        .initialize_header(refer, true);
+        #[cfg(feature = "global_alloc_bit")]
+        alloc_bit::set_alloc_bit(refer);
     }
 
     fn get_tls(&self) -> OpaquePointer {
@@ -96,6 +102,22 @@ impl<P: Plan<Mutator = Self>> MutatorContext<P::VM> for Mutator<P> {
     }
 }
 
+impl<VM: VMBinding> Mutator<VM> {
+    /// The allocation semantics that an allocation of `size` bytes is actually served with.
+    /// Default allocations that are too large for the default space of the plan are allocated
+    /// in the large object space.
+    #[inline(always)]
+    fn allocation_type_for(&self, size: usize, allocator: AllocationType) -> AllocationType {
+        if matches!(allocator, AllocationType::Default)
+            && size > self.plan.constraints().max_non_los_default_alloc_bytes
+        {
+            AllocationType::Los
+        } else {
+            allocator
+        }
+    }
+}
+
 /// Each GC plan should provide their implementation of a MutatorContext. *Note that this trait is no longer needed as we removed
 /// per-plan mutator implementation and we will remove this trait as well in the future.*
 
@@ -127,6 +149,12 @@ pub trait MutatorContext<VM: VMBinding>: Send + Sync + 'static {
     fn get_tls(&self) -> OpaquePointer;
     fn barrier(&mut self) -> &mut dyn Barrier;
 
+    fn record_overwritten_node(&mut self, obj: ObjectReference) {
+        self.barrier().pre_write_barrier(WriteTarget::Object(obj));
+    }
+    fn record_overwritten_edge(&mut self, slot: Address) {
+        self.barrier().pre_write_barrier(WriteTarget::Slot(slot));
+    }
     fn record_modified_node(&mut self, obj: ObjectReference) {
         self.barrier().post_write_barrier(WriteTarget::Object(obj));
     }
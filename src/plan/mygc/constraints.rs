@@ -1,7 +1,10 @@
-pub use crate::plan::plan_constraints::*;
+use crate::plan::PlanConstraints;
 
-// It's a copying collector, so it moves objects
-pub const MOVES_OBJECTS: bool = true;
-pub const GC_HEADER_BITS: usize = 2;
-pub const GC_HEADER_WORDS: usize = 0;
-pub const NUM_SPECIALIZED_SCANS: usize = 1;
\ No newline at end of file
+pub const MYGC_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    // It's a copying collector, so it moves objects
+    moves_objects: true,
+    gc_header_bits: 3,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    ..PlanConstraints::default()
+};
@@ -2,7 +2,10 @@ use super::global::MyGC;
 use crate::plan::CopyContext;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorker;
 use crate::util::alloc::{Allocator, BumpAllocator};
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
 use crate::util::forwarding_word;
 use crate::util::{Address, ObjectReference, OpaquePointer};
 use crate::vm::VMBinding;
@@ -19,8 +22,8 @@ impl<VM: VMBinding> CopyContext for SSCopyContext<VM> {
     type VM = VM;
     fn new(mmtk: &'static MMTK<Self::VM>) -> Self {
         Self {
-            plan: &mmtk.plan,
-            ss: BumpAllocator::new(OpaquePointer::UNINITIALIZED, None, &mmtk.plan),
+            plan: mmtk.plan.downcast_ref::<MyGC<VM>>().unwrap(),
+            ss: BumpAllocator::new(OpaquePointer::UNINITIALIZED, None, &*mmtk.plan),
         }
     }
     fn init(&mut self, tls: OpaquePointer) {
@@ -52,15 +55,27 @@ impl<VM: VMBinding> CopyContext for SSCopyContext<VM> {
         _semantics: crate::AllocationSemantics,
     ) {
         forwarding_word::clear_forwarding_bits::<VM>(obj);
+        #[cfg(feature = "global_alloc_bit")]
+        alloc_bit::set_alloc_bit(obj);
     }
 }
 
 #[derive(Default)]
 pub struct SSProcessEdges<VM: VMBinding> {
     base: ProcessEdgesBase<SSProcessEdges<VM>>,
+    /// The plan, which is resolved in `set_context()`.
+    plan: Option<&'static MyGC<VM>>,
     phantom: PhantomData<VM>,
 }
 
+impl<VM: VMBinding> SSProcessEdges<VM> {
+    /// The plan, as the concrete plan type.
+    #[inline]
+    fn plan(&self) -> &'static MyGC<VM> {
+        self.plan.unwrap()
+    }
+}
+
 impl<VM: VMBinding> ProcessEdgesWork for SSProcessEdges<VM> {
     type VM = VM;
     fn new(edges: Vec<Address>, _roots: bool) -> Self {
@@ -70,6 +85,12 @@ impl<VM: VMBinding> ProcessEdgesWork for SSProcessEdges<VM> {
         }
     }
     #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<VM>, worker: &mut GCWorker<VM>) {
+        self.base.mmtk = Some(mmtk);
+        self.base.set_worker(worker);
+        self.plan = mmtk.plan.downcast_ref::<MyGC<VM>>();
+    }
+    #[inline]
     fn trace_object(&mut self, object: ObjectReference) -> ObjectReference {
         if object.is_null() {
             return object;
@@ -79,14 +100,14 @@ impl<VM: VMBinding> ProcessEdgesWork for SSProcessEdges<VM> {
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<SSCopyContext<VM>>(),
             )
         } else if self.plan().fromspace().in_space(object) {
             self.plan().fromspace().trace_object(
                 self,
                 object,
                 super::global::ALLOC_SS,
-                self.worker().local(),
+                self.worker().copy_context::<SSCopyContext<VM>>(),
             )
         } else {
             self.plan().common.trace_object(self, object)
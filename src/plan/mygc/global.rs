@@ -1,16 +1,20 @@
 use super::gc_works::{SSCopyContext, SSProcessEdges};
 use crate::mmtk::MMTK;
-use crate::plan::global::{BasePlan, NoCopy};
+use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::GcStatus;
 use crate::plan::mutator_context::Mutator;
+use crate::plan::mygc::constraints::MYGC_CONSTRAINTS;
 use crate::plan::mygc::mutator::create_ss_mutator;
 use crate::plan::mygc::mutator::ALLOCATOR_MAPPING;
 use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
 use crate::plan::Plan;
+use crate::plan::PlanConstraints;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::heap::layout::heap_layout::Mmapper;
@@ -28,8 +32,6 @@ use enum_map::EnumMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-pub type SelectedPlan<VM> = MyGC<VM>;
-
 // Says that for the semispace algorithm we should use the default allocation
 // method i.e. to the copyspaces. This is as opposed to putting stuff in
 // the large object space, immortal space, readonly etc
@@ -80,15 +82,13 @@ unsafe impl<VM: VMBinding> Sync for MyGC<VM> {}
 // initialised, prepared, manually collected, statistically analyised etc
 impl<VM: VMBinding> Plan for MyGC<VM> {
     type VM = VM;
-    type Mutator = Mutator<Self>; // Mutator: per-thread data structure that manages allocations etc
-    type CopyContext = SSCopyContext<VM>;
 
     // Creates a new collector
     fn new(
         vm_map: &'static VMMap,
         mmapper: &'static Mmapper,
         options: Arc<UnsafeOptionsWrapper>,
-        scheduler: &'static MMTkScheduler<Self::VM>
+        _scheduler: &'static MMTkScheduler<Self::VM>
     ) -> Self {
         // This HeapMeta structure allows us to reserve blocks of memory
         // either at the start or the end of the heap
@@ -118,6 +118,16 @@ impl<VM: VMBinding> Plan for MyGC<VM> {
         }
     }
 
+    // The constraints that MyGC puts on the rest of MMTk, e.g. it moves objects
+    fn constraints(&self) -> &'static PlanConstraints {
+        &MYGC_CONSTRAINTS
+    }
+
+    // Each GC worker gets its own copy context, to copy objects to the tospace
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box SSCopyContext::new(mmtk)
+    }
+
     // After calling new(), we can initialise the GC
     fn gc_init(
         &mut self,
@@ -144,7 +154,7 @@ impl<VM: VMBinding> Plan for MyGC<VM> {
         &'static self,
         tls: OpaquePointer, // equivalent to a C *void pointer
         _mmtk: &'static MMTK<Self::VM>, // current MMTk instance
-    ) -> Box<Mutator<Self>> {
+    ) -> Box<Mutator<VM>> {
         Box::new(create_ss_mutator(tls, self))
     }
 
@@ -183,6 +193,8 @@ impl<VM: VMBinding> Plan for MyGC<VM> {
             .add(StopMutators::<SSProcessEdges<VM>>::new());
         // Prepare global/collectors/mutators
         scheduler.prepare_stage.add(Prepare::new(self));
+        // Process the references after the closure
+        scheduler.schedule_ref_processing::<SSProcessEdges<VM>>();
         // Release global/collectors/mutators
         scheduler.release_stage.add(Release::new(self));
         // Resume mutators
@@ -198,7 +210,22 @@ impl<VM: VMBinding> Plan for MyGC<VM> {
 
     // Mumber of reserved pages, but including immortal/LOS
     fn get_pages_used(&self) -> usize {
-        self.tospace().reserved_pages() + self.common.get_pages_used()
+        // The fromspace only has the pages kept for pinned objects
+        self.tospace().reserved_pages() + self.fromspace().reserved_pages() + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.copyspace0);
+        f(&self.copyspace1);
+        self.common.for_each_space(f);
+    }
+
+    fn snapshot_state(&self) -> Vec<usize> {
+        vec![self.hi.load(Ordering::SeqCst) as usize]
+    }
+
+    fn restore_state(&self, state: &[usize]) {
+        self.hi.store(state[0] != 0, Ordering::SeqCst);
     }
 }
 
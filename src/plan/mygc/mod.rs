@@ -1,9 +1,6 @@
-pub mod constraints;
+mod constraints;
 mod gc_works;
 mod global;
 mod mutator;
 
 pub use self::global::MyGC;
-
-pub use self::constraints as SelectedConstraints;
-pub use self::global::SelectedPlan;
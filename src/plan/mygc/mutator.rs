@@ -26,7 +26,7 @@ pub fn create_ss_mutator<VM: VMBinding>(
     // to be storage, it only needs to be a unique pointer which MMTk can
     // use to identify the mutator
     plan: &'static MyGC<VM>,
-) -> Mutator<MyGC<VM>> {
+) -> Mutator<VM> {
     let config = MutatorConfig {
         allocator_mapping: &*ALLOCATOR_MAPPING, // This maps allocation types to allocation selectors...
         space_mapping: box vec![ // This maps allocation selectors to object spaces
@@ -51,14 +51,14 @@ pub fn create_ss_mutator<VM: VMBinding>(
 }
 
 pub fn ss_mutator_prepare<VM: VMBinding>(
-    _mutator: &mut Mutator<MyGC<VM>>,
+    _mutator: &mut Mutator<VM>,
     _tls: OpaquePointer,
 ) {
     // Do nothing
 }
 
 pub fn ss_mutator_release<VM: VMBinding>(
-    mutator: &mut Mutator<MyGC<VM>>,
+    mutator: &mut Mutator<VM>,
     _tls: OpaquePointer,
 ) {
     // rebind the allocation bump pointer to the appropriate semispace
@@ -69,5 +69,6 @@ pub fn ss_mutator_release<VM: VMBinding>(
     }
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
-    bump_allocator.rebind(Some(mutator.plan.tospace()));
+    let plan = mutator.plan.downcast_ref::<MyGC<VM>>().unwrap();
+    bump_allocator.rebind(Some(plan.tospace()));
 }
@@ -1,4 +1,7 @@
-pub use crate::plan::plan_constraints::*;
+use crate::plan::PlanConstraints;
 
-pub const GC_HEADER_BITS: usize = 0;
-pub const GC_HEADER_WORDS: usize = 0;
+pub const NOGC_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    gc_header_bits: 0,
+    gc_header_words: 0,
+    ..PlanConstraints::default()
+};
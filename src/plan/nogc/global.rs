@@ -1,11 +1,15 @@
 use crate::mmtk::MMTK;
 use crate::plan::global::{BasePlan, NoCopy};
 use crate::plan::mutator_context::Mutator;
+use crate::plan::nogc::constraints::NOGC_CONSTRAINTS;
 use crate::plan::nogc::mutator::create_nogc_mutator;
 use crate::plan::nogc::mutator::ALLOCATOR_MAPPING;
 use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
 use crate::plan::Plan;
+use crate::plan::PlanConstraints;
 use crate::policy::space::Space;
+use crate::scheduler::GCWorkerLocalPtr;
 use crate::scheduler::MMTkScheduler;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::heap::layout::heap_layout::Mmapper;
@@ -25,8 +29,6 @@ use crate::policy::immortalspace::ImmortalSpace as NoGCImmortalSpace;
 #[cfg(feature = "nogc_lock_free")]
 use crate::policy::lockfreeimmortalspace::LockFreeImmortalSpace as NoGCImmortalSpace;
 
-pub type SelectedPlan<VM> = NoGC<VM>;
-
 pub struct NoGC<VM: VMBinding> {
     pub base: BasePlan<VM>,
     pub nogc_space: NoGCImmortalSpace<VM>,
@@ -36,8 +38,6 @@ unsafe impl<VM: VMBinding> Sync for NoGC<VM> {}
 
 impl<VM: VMBinding> Plan for NoGC<VM> {
     type VM = VM;
-    type Mutator = Mutator<Self>;
-    type CopyContext = NoCopy<VM>;
 
     fn new(
         vm_map: &'static VMMap,
@@ -69,6 +69,14 @@ impl<VM: VMBinding> Plan for NoGC<VM> {
         }
     }
 
+    fn constraints(&self) -> &'static PlanConstraints {
+        &NOGC_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box NoCopy::new(mmtk)
+    }
+
     fn gc_init(
         &mut self,
         heap_size: usize,
@@ -89,7 +97,7 @@ impl<VM: VMBinding> Plan for NoGC<VM> {
         &'static self,
         tls: OpaquePointer,
         _mmtk: &'static MMTK<Self::VM>,
-    ) -> Box<Mutator<Self>> {
+    ) -> Box<Mutator<VM>> {
         Box::new(create_nogc_mutator(tls, self))
     }
 
@@ -113,6 +121,11 @@ impl<VM: VMBinding> Plan for NoGC<VM> {
         self.nogc_space.reserved_pages()
     }
 
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.nogc_space);
+        self.base.for_each_space(f);
+    }
+
     fn handle_user_collection_request(&self, _tls: OpaquePointer, _force: bool) {
         println!("Warning: User attempted a collection request, but it is not supported in NoGC. The request is ignored.");
     }
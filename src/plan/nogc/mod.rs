@@ -1,8 +1,5 @@
-pub mod constraints;
+mod constraints;
 mod global;
 mod mutator;
 
 pub use self::global::NoGC;
-
-pub use self::constraints as SelectedConstraints;
-pub use self::global::SelectedPlan;
@@ -15,14 +15,14 @@ lazy_static! {
     };
 }
 
-pub fn nogc_mutator_noop<VM: VMBinding>(_mutator: &mut Mutator<NoGC<VM>>, _tls: OpaquePointer) {
+pub fn nogc_mutator_noop<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
     unreachable!();
 }
 
 pub fn create_nogc_mutator<VM: VMBinding>(
     mutator_tls: OpaquePointer,
     plan: &'static NoGC<VM>,
-) -> Mutator<NoGC<VM>> {
+) -> Mutator<VM> {
     let config = MutatorConfig {
         allocator_mapping: &*ALLOCATOR_MAPPING,
         space_mapping: box vec![(AllocatorSelector::BumpPointer(0), &plan.nogc_space)],
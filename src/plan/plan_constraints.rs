@@ -1,20 +1,42 @@
-// Specific plans may 'pub use' the constraints here, and overwrites some. In that case,
-// the constants that get overwritten are unused.
-#![allow(unused)]
-
 use crate::util::constants::*;
 
-pub const MOVES_OBJECTS: bool = false;
-pub const NUM_SPECIALIZED_SCANS: usize = 0;
-// The two consts below need to be consistent
-pub const NEEDS_LOG_BIT_IN_HEADER: bool = false;
-pub const NEEDS_LOG_BIT_IN_HEADER_NUM: usize = 0;
-
-pub const NEEDS_LINEAR_SCAN: bool = SUPPORT_CARD_SCANNING || LAZY_SWEEP;
-pub const NEEDS_CONCURRENT_WORKERS: bool = false;
-
-pub const GENERATE_GC_TRACE: bool = false;
-
-pub const MAX_NON_LOS_COPY_BYTES: usize = MAX_INT;
+/// The constraints a plan puts on the rest of MMTk and on the VM. Each plan declares a
+/// constant of this struct, which can be queried at run time through `Plan::constraints()`.
+pub struct PlanConstraints {
+    pub moves_objects: bool,
+    pub gc_header_bits: usize,
+    pub gc_header_words: usize,
+    pub num_specialized_scans: usize,
+    // The two fields below need to be consistent
+    pub needs_log_bit_in_header: bool,
+    pub needs_log_bit_in_header_num: usize,
+    pub needs_linear_scan: bool,
+    pub needs_concurrent_workers: bool,
+    pub generate_gc_trace: bool,
+    pub max_non_los_copy_bytes: usize,
+    /// Default allocations larger than this are allocated in the large object space.
+    pub max_non_los_default_alloc_bytes: usize,
+    pub needs_forward_after_liveness: bool,
+    /// Whether the plan keeps pinned objects in place, which conservative roots rely on.
+    pub supports_pinning: bool,
+}
 
-pub const NEEDS_FORWARD_AFTER_LIVENESS: bool = false;
+impl PlanConstraints {
+    pub const fn default() -> Self {
+        PlanConstraints {
+            moves_objects: false,
+            gc_header_bits: 0,
+            gc_header_words: 0,
+            num_specialized_scans: 0,
+            needs_log_bit_in_header: false,
+            needs_log_bit_in_header_num: 0,
+            needs_linear_scan: SUPPORT_CARD_SCANNING || LAZY_SWEEP,
+            needs_concurrent_workers: false,
+            generate_gc_trace: false,
+            max_non_los_copy_bytes: MAX_INT,
+            max_non_los_default_alloc_bytes: MAX_INT,
+            needs_forward_after_liveness: false,
+            supports_pinning: true,
+        }
+    }
+}
@@ -1,7 +1,9 @@
-//pub use ::plan::plan_constraints::{NEEDS_LOG_BIT_IN_HEADER, NEEDS_LOG_BIT_IN_HEADER_NUM};
-pub use crate::plan::plan_constraints::*;
+use crate::plan::PlanConstraints;
 
-pub const MOVES_OBJECTS: bool = true;
-pub const GC_HEADER_BITS: usize = 2;
-pub const GC_HEADER_WORDS: usize = 0;
-pub const NUM_SPECIALIZED_SCANS: usize = 1;
+pub const SS_CONSTRAINTS: PlanConstraints = PlanConstraints {
+    moves_objects: true,
+    gc_header_bits: 3,
+    gc_header_words: 0,
+    num_specialized_scans: 1,
+    ..PlanConstraints::default()
+};
@@ -4,13 +4,17 @@ use crate::plan::global::BasePlan;
 use crate::plan::global::CommonPlan;
 use crate::plan::global::GcStatus;
 use crate::plan::mutator_context::Mutator;
+use crate::plan::semispace::constraints::SS_CONSTRAINTS;
 use crate::plan::semispace::mutator::create_ss_mutator;
 use crate::plan::semispace::mutator::ALLOCATOR_MAPPING;
 use crate::plan::AllocationSemantics;
+use crate::plan::CopyContext;
 use crate::plan::Plan;
+use crate::plan::PlanConstraints;
 use crate::policy::copyspace::CopySpace;
 use crate::policy::space::Space;
 use crate::scheduler::gc_works::*;
+use crate::scheduler::GCWorkerLocalPtr;
 use crate::scheduler::*;
 use crate::util::alloc::allocators::AllocatorSelector;
 use crate::util::heap::layout::heap_layout::Mmapper;
@@ -28,8 +32,6 @@ use std::sync::Arc;
 
 use enum_map::EnumMap;
 
-pub type SelectedPlan<VM> = SemiSpace<VM>;
-
 pub const ALLOC_SS: AllocationSemantics = AllocationSemantics::Default;
 
 pub struct SemiSpace<VM: VMBinding> {
@@ -43,8 +45,6 @@ unsafe impl<VM: VMBinding> Sync for SemiSpace<VM> {}
 
 impl<VM: VMBinding> Plan for SemiSpace<VM> {
     type VM = VM;
-    type Mutator = Mutator<Self>;
-    type CopyContext = SSCopyContext<VM>;
 
     fn new(
         vm_map: &'static VMMap,
@@ -78,6 +78,14 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
         }
     }
 
+    fn constraints(&self) -> &'static PlanConstraints {
+        &SS_CONSTRAINTS
+    }
+
+    fn create_worker_local(&self, mmtk: &'static MMTK<Self::VM>) -> GCWorkerLocalPtr {
+        box SSCopyContext::new(mmtk)
+    }
+
     fn gc_init(
         &mut self,
         heap_size: usize,
@@ -99,6 +107,8 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
             .add(StopMutators::<SSProcessEdges<VM>>::new());
         // Prepare global/collectors/mutators
         scheduler.prepare_stage.add(Prepare::new(self));
+        // Process the references after the closure
+        scheduler.schedule_ref_processing::<SSProcessEdges<VM>>();
         // Release global/collectors/mutators
         scheduler.release_stage.add(Release::new(self));
         // Resume mutators
@@ -111,7 +121,7 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
         &'static self,
         tls: OpaquePointer,
         _mmtk: &'static MMTK<Self::VM>,
-    ) -> Box<Mutator<Self>> {
+    ) -> Box<Mutator<VM>> {
         Box::new(create_ss_mutator(tls, self))
     }
 
@@ -141,7 +151,24 @@ impl<VM: VMBinding> Plan for SemiSpace<VM> {
     }
 
     fn get_pages_used(&self) -> usize {
-        self.tospace().reserved_pages() + self.common.get_pages_used()
+        // The from-space only has the pages kept for pinned objects.
+        self.tospace().reserved_pages()
+            + self.fromspace().reserved_pages()
+            + self.common.get_pages_used()
+    }
+
+    fn for_each_space(&self, f: &mut dyn FnMut(&dyn Space<VM>)) {
+        f(&self.copyspace0);
+        f(&self.copyspace1);
+        self.common.for_each_space(f);
+    }
+
+    fn snapshot_state(&self) -> Vec<usize> {
+        vec![self.hi.load(Ordering::SeqCst) as usize]
+    }
+
+    fn restore_state(&self, state: &[usize]) {
+        self.hi.store(state[0] != 0, Ordering::SeqCst);
     }
 
     fn base(&self) -> &BasePlan<VM> {
@@ -10,17 +10,11 @@ use crate::vm::VMBinding;
 use enum_map::enum_map;
 use enum_map::EnumMap;
 
-pub fn ss_mutator_prepare<VM: VMBinding>(
-    _mutator: &mut Mutator<SemiSpace<VM>>,
-    _tls: OpaquePointer,
-) {
+pub fn ss_mutator_prepare<VM: VMBinding>(_mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
     // Do nothing
 }
 
-pub fn ss_mutator_release<VM: VMBinding>(
-    mutator: &mut Mutator<SemiSpace<VM>>,
-    _tls: OpaquePointer,
-) {
+pub fn ss_mutator_release<VM: VMBinding>(mutator: &mut Mutator<VM>, _tls: OpaquePointer) {
     // rebind the allocation bump pointer to the appropriate semispace
     let bump_allocator = unsafe {
         mutator
@@ -29,7 +23,8 @@ pub fn ss_mutator_release<VM: VMBinding>(
     }
     .downcast_mut::<BumpAllocator<VM>>()
     .unwrap();
-    bump_allocator.rebind(Some(mutator.plan.tospace()));
+    let plan = mutator.plan.downcast_ref::<SemiSpace<VM>>().unwrap();
+    bump_allocator.rebind(Some(plan.tospace()));
 }
 
 lazy_static! {
@@ -43,7 +38,7 @@ lazy_static! {
 pub fn create_ss_mutator<VM: VMBinding>(
     mutator_tls: OpaquePointer,
     plan: &'static SemiSpace<VM>,
-) -> Mutator<SemiSpace<VM>> {
+) -> Mutator<VM> {
     let config = MutatorConfig {
         allocator_mapping: &*ALLOCATOR_MAPPING,
         space_mapping: box vec![
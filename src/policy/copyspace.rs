@@ -2,26 +2,53 @@ use crate::plan::TransitiveClosure;
 use crate::plan::{AllocationSemantics, CopyContext};
 use crate::policy::space::SpaceOptions;
 use crate::policy::space::{CommonSpace, Space, SFT};
-use crate::util::constants::CARD_META_PAGES_PER_REGION;
+use crate::util::alloc_bit;
+use crate::util::constants::{BYTES_IN_PAGE, CARD_META_PAGES_PER_REGION};
+use crate::util::conversions::bytes_to_pages;
 use crate::util::forwarding_word as ForwardingWord;
+use crate::util::gc_byte;
 use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::snapshot::{next_value, SpaceSnapshot};
 use crate::util::heap::HeapMeta;
 use crate::util::heap::VMRequest;
 use crate::util::heap::{MonotonePageResource, PageResource};
+use crate::util::pin_bit;
+use crate::util::side_metadata;
 use crate::util::{Address, ObjectReference};
 use crate::vm::*;
 use libc::{mprotect, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
 use std::cell::UnsafeCell;
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::slice::Iter;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
 
 unsafe impl<VM: VMBinding> Sync for CopySpace<VM> {}
 
 const META_DATA_PAGES_PER_REGION: usize = CARD_META_PAGES_PER_REGION;
 
+/// The mark of the objects that are kept in place rather than copied. It is above the forwarding
+/// bits and below the unlogged bit.
+const MARK_BIT: u8 = 0b100;
+
 pub struct CopySpace<VM: VMBinding> {
     common: UnsafeCell<CommonSpace<VM>>,
     pr: MonotonePageResource<VM>,
     from_space: AtomicBool,
+    /// The objects that the last release kept in place, and the pages they are on.
+    kept: RwLock<KeptObjects>,
+    /// The objects that this collection keeps in place.
+    pinned: Mutex<Vec<ObjectReference>>,
+}
+
+#[derive(Default)]
+struct KeptObjects {
+    /// The objects, in address order.
+    objects: Vec<ObjectReference>,
+    /// The page aligned ranges of memory of the objects, in address order.
+    pages: VecDeque<(Address, Address)>,
 }
 
 impl<VM: VMBinding> SFT for CopySpace<VM> {
@@ -29,7 +56,7 @@ impl<VM: VMBinding> SFT for CopySpace<VM> {
         self.get_name()
     }
     fn is_live(&self, object: ObjectReference) -> bool {
-        !self.from_space() || ForwardingWord::is_forwarded::<VM>(object)
+        !self.from_space() || ForwardingWord::is_forwarded::<VM>(object) || Self::is_marked(object)
     }
     fn is_movable(&self) -> bool {
         true
@@ -68,6 +95,46 @@ impl<VM: VMBinding> Space<VM> for CopySpace<VM> {
     fn release_multiple_pages(&mut self, _start: Address) {
         panic!("copyspace only releases pages enmasse")
     }
+
+    fn snapshot(&self) -> io::Result<SpaceSnapshot> {
+        let mut snapshot = SpaceSnapshot::default();
+        self.pr.snapshot(&mut snapshot)?;
+        snapshot.state.push(self.from_space() as usize);
+        let kept = self.kept.read().unwrap();
+        snapshot.state.push(kept.objects.len());
+        for object in &kept.objects {
+            snapshot.state.push(object.to_address().as_usize());
+        }
+        Ok(snapshot)
+    }
+
+    fn restore(&self, state: &mut Iter<usize>) -> io::Result<()> {
+        self.pr.restore(state)?;
+        self.from_space
+            .store(next_value(state) != 0, Ordering::SeqCst);
+        let objects = (0..next_value(state))
+            .map(|_| unsafe { Address::from_usize(next_value(state)).to_object_reference() })
+            .collect();
+        *self.kept.write().unwrap() = KeptObjects::new::<VM>(objects);
+        Ok(())
+    }
+}
+
+impl KeptObjects {
+    fn new<VM: VMBinding>(mut objects: Vec<ObjectReference>) -> Self {
+        objects.sort_by_key(|object| object.to_address());
+        let mut pages: VecDeque<(Address, Address)> = VecDeque::new();
+        for &object in &objects {
+            let start = VM::VMObjectModel::object_start_ref(object);
+            let end = (start + VM::VMObjectModel::get_current_size(object)).align_up(BYTES_IN_PAGE);
+            let start = start.align_down(BYTES_IN_PAGE);
+            match pages.back_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => pages.push_back((start, end)),
+            }
+        }
+        KeptObjects { objects, pages }
+    }
 }
 
 impl<VM: VMBinding> CopySpace<VM> {
@@ -87,6 +154,7 @@ impl<VM: VMBinding> CopySpace<VM> {
                 immortal: false,
                 zeroed,
                 vmrequest,
+                local_side_metadata_specs: vec![],
             },
             vm_map,
             mmapper,
@@ -105,20 +173,119 @@ impl<VM: VMBinding> CopySpace<VM> {
             },
             common: UnsafeCell::new(common),
             from_space: AtomicBool::new(from_space),
+            kept: RwLock::new(KeptObjects::default()),
+            pinned: Mutex::new(vec![]),
         }
     }
 
     pub fn prepare(&self, from_space: bool) {
+        // The kept objects are still marked by the collection that kept them.
+        for &object in &self.kept.read().unwrap().objects {
+            let old_value = gc_byte::read_gc_byte::<VM>(object);
+            gc_byte::write_gc_byte::<VM>(object, old_value & !MARK_BIT);
+        }
         self.from_space.store(from_space, Ordering::SeqCst);
     }
 
+    /// Keep the objects that the last release kept in place again, whether they are still
+    /// pinned or not, e.g. because a nursery collection does not find all the references to
+    /// them. Returns the objects, which the collection has to scan. This is called after
+    /// `prepare()`.
+    pub fn retain_kept_objects(&self) -> Vec<ObjectReference> {
+        let objects = self.kept.read().unwrap().objects.clone();
+        for &object in &objects {
+            let old_value = gc_byte::read_gc_byte::<VM>(object);
+            gc_byte::write_gc_byte::<VM>(object, old_value | MARK_BIT);
+        }
+        self.pinned.lock().unwrap().extend_from_slice(&objects);
+        objects
+    }
+
+    /// Release the space, except for the pages of the objects that were kept in place. The other
+    /// objects on those pages are dead, and are no longer allocated.
     pub fn release(&self) {
-        unsafe {
-            self.pr.reset();
+        let pinned = mem::replace(&mut *self.pinned.lock().unwrap(), vec![]);
+        let mut kept = self.kept.write().unwrap();
+        if pinned.is_empty() {
+            *kept = KeptObjects::default();
+            unsafe {
+                self.pr.reset();
+            }
+        } else {
+            assert!(
+                self.common().contiguous,
+                "{} cannot keep pinned objects, as it is discontiguous",
+                self.get_name()
+            );
+            *kept = KeptObjects::new::<VM>(pinned);
+            let alloced: Vec<bool> = kept
+                .objects
+                .iter()
+                .map(|&object| alloc_bit::is_alloced(object))
+                .collect();
+            for &(start, end) in &kept.pages {
+                side_metadata::bzero_metadata(alloc_bit::ALLOC_BIT_SPEC, start, end - start);
+            }
+            for (&object, &alloced) in kept.objects.iter().zip(&alloced) {
+                if alloced {
+                    alloc_bit::set_alloc_bit(object);
+                }
+            }
+            unsafe {
+                self.pr.reset_keeping(kept.pages.clone());
+            }
         }
         self.from_space.store(false, Ordering::SeqCst);
     }
 
+    /// Whether `addr` is on the pages that the last release kept for pinned objects.
+    pub fn in_kept_pages(&self, addr: Address) -> bool {
+        self.kept
+            .read()
+            .unwrap()
+            .pages
+            .iter()
+            .any(|&(start, end)| addr >= start && addr < end)
+    }
+
+    /// The number of pages that the last release kept for pinned objects.
+    pub fn kept_pages(&self) -> usize {
+        self.kept
+            .read()
+            .unwrap()
+            .pages
+            .iter()
+            .map(|&(start, end)| bytes_to_pages(end - start))
+            .sum()
+    }
+
+    /// Whether the last release kept `object` in place.
+    fn is_kept(&self, object: ObjectReference) -> bool {
+        self.kept
+            .read()
+            .unwrap()
+            .objects
+            .binary_search_by_key(&object.to_address(), |o| o.to_address())
+            .is_ok()
+    }
+
+    fn is_marked(object: ObjectReference) -> bool {
+        gc_byte::read_gc_byte::<VM>(object) & MARK_BIT != 0
+    }
+
+    /// Mark an object that is kept in place. Returns `true` if it was not marked yet.
+    fn test_and_mark(object: ObjectReference) -> bool {
+        loop {
+            let old_value = gc_byte::read_gc_byte::<VM>(object);
+            if old_value & MARK_BIT != 0 {
+                return false;
+            }
+            if gc_byte::compare_exchange_gc_byte::<VM>(object, old_value, old_value | MARK_BIT) {
+                return true;
+            }
+        }
+    }
+
     fn from_space(&self) -> bool {
         self.from_space.load(Ordering::SeqCst)
     }
@@ -133,6 +300,11 @@ impl<VM: VMBinding> CopySpace<VM> {
     ) -> ObjectReference {
         trace!("copyspace.trace_object(, {:?}, {:?})", object, semantics,);
         if !self.from_space() {
+            // The objects kept by the last release are only reachable through the trace, which
+            // has to scan them, as they may refer to objects that move.
+            if self.is_kept(object) && Self::test_and_mark(object) {
+                trace.process_node(object);
+            }
             return object;
         }
         trace!("attempting to forward");
@@ -140,10 +312,28 @@ impl<VM: VMBinding> CopySpace<VM> {
         trace!("checking if object is being forwarded");
         if ForwardingWord::state_is_forwarded_or_being_forwarded(forwarding_status) {
             trace!("... yes it is");
-            let new_object =
-                ForwardingWord::spin_and_get_forwarded_object::<VM>(object, forwarding_status);
+            // The object is either copied, or marked in place, by another worker.
+            let mut status = forwarding_status;
+            while ForwardingWord::state_is_being_forwarded(status) {
+                status = gc_byte::read_gc_byte::<VM>(object);
+            }
+            if !ForwardingWord::state_is_forwarded_or_being_forwarded(status) {
+                return object;
+            }
+            let new_object = ForwardingWord::spin_and_get_forwarded_object::<VM>(object, status);
             trace!("Returning");
             new_object
+        } else if forwarding_status & MARK_BIT != 0 {
+            // Already marked in place by another worker.
+            gc_byte::write_gc_byte::<VM>(object, forwarding_status);
+            object
+        } else if pin_bit::is_pinned(object) {
+            trace!("... no it isn't, but it is pinned. Marking in place");
+            // Marking the object also releases the forwarding claim.
+            gc_byte::write_gc_byte::<VM>(object, forwarding_status | MARK_BIT);
+            self.pinned.lock().unwrap().push(object);
+            trace.process_node(object);
+            object
         } else {
             trace!("... no it isn't. Copying");
             let new_object =
@@ -0,0 +1,474 @@
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+
+use crate::plan::TransitiveClosure;
+use crate::plan::{AllocationSemantics, CopyContext};
+use crate::policy::space::SpaceOptions;
+use crate::policy::space::{CommonSpace, Space, SFT};
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
+use crate::util::constants::LOG_BYTES_IN_PAGE;
+use crate::util::forwarding_word as ForwardingWord;
+use crate::util::gc_byte;
+use crate::util::header_byte;
+use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::{FreeListPageResource, PageResource, VMRequest};
+use crate::util::pin_bit;
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
+use crate::util::OpaquePointer;
+use crate::util::{Address, ObjectReference};
+use crate::vm::*;
+
+pub const LOG_BYTES_IN_LINE: usize = 8;
+pub const BYTES_IN_LINE: usize = 1 << LOG_BYTES_IN_LINE;
+pub const LOG_BYTES_IN_BLOCK: usize = 15;
+pub const BYTES_IN_BLOCK: usize = 1 << LOG_BYTES_IN_BLOCK;
+pub const PAGES_IN_BLOCK: usize = 1 << (LOG_BYTES_IN_BLOCK - LOG_BYTES_IN_PAGE as usize);
+pub const LINES_IN_BLOCK: usize = 1 << (LOG_BYTES_IN_BLOCK - LOG_BYTES_IN_LINE);
+
+/// The largest object that can be allocated in an Immix space. Larger objects are allocated in
+/// the large object space. An object of this size still fits in a clean block with any
+/// alignment padding.
+pub const MAX_IMMIX_OBJECT_SIZE: usize = BYTES_IN_BLOCK >> 1;
+/// Objects larger than this are always marked in place, so that the space wasted at the end
+/// of each evacuation target block is bounded.
+const MAX_EVACUATED_OBJECT_SIZE: usize = BYTES_IN_BLOCK >> 2;
+/// A block is a candidate for evacuation if no more than this many lines were live when it
+/// was last swept.
+const DEFRAG_LINE_THRESHOLD: usize = LINES_IN_BLOCK >> 2;
+/// The percentage of the heap that is kept free as the target of evacuation.
+pub const DEFRAG_HEADROOM_PERCENT: usize = 2;
+
+/// The mark bit in the GC byte. The two low bits are used by the forwarding word.
+const MARK_BIT: u8 = 0b100;
+
+const LINE_MARKED: u8 = 1;
+
+/// The line marks: a byte of local side metadata for each line.
+pub const LINE_MARK_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Local,
+    offset: 0,
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_BYTES_IN_LINE,
+};
+
+/// A bit of local side metadata for each block, which is set if the objects in the block are
+/// evacuated during the current collection.
+pub const DEFRAG_SOURCE_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Local,
+    offset: LINE_MARK_SPEC.next_offset(),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_BLOCK,
+};
+
+/// What the space keeps of a block between collections. The metadata that is used while
+/// tracing is kept on the side, so tracing never looks up the table of blocks.
+struct Block {
+    /// The number of live lines at the last sweep, or `None` if the block has not been swept
+    /// since it was acquired.
+    marked_lines: Option<usize>,
+}
+
+#[inline(always)]
+fn line_is_marked(block: Address, line: usize) -> bool {
+    side_metadata::load_atomic(LINE_MARK_SPEC, block + (line << LOG_BYTES_IN_LINE)) == LINE_MARKED
+}
+
+#[inline(always)]
+fn block_align_down(addr: Address) -> Address {
+    addr.align_down(BYTES_IN_BLOCK)
+}
+
+/// A mark-region space. Memory is managed in blocks, and blocks are divided into lines.
+/// A collection marks objects and the lines they occupy; the unmarked lines of a block form
+/// holes that the allocator can reuse. Objects in sparsely occupied blocks are evacuated
+/// opportunistically into free blocks, as long as there is headroom to copy them to.
+pub struct ImmixSpace<VM: VMBinding> {
+    common: UnsafeCell<CommonSpace<VM>>,
+    pr: FreeListPageResource<VM>,
+    /// All blocks in this space, keyed by their (block-aligned) start address.
+    blocks: RwLock<HashMap<Address, Block>>,
+    /// Blocks with free lines that are not owned by any allocator.
+    recyclable_blocks: Mutex<Vec<Address>>,
+    mark_state: AtomicU8,
+    in_collection: AtomicBool,
+    /// The number of bytes that can still be evacuated in the current collection.
+    evacuation_budget: AtomicUsize,
+}
+
+unsafe impl<VM: VMBinding> Sync for ImmixSpace<VM> {}
+
+impl<VM: VMBinding> SFT for ImmixSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        self.is_marked(object) || ForwardingWord::is_forwarded::<VM>(object)
+    }
+    fn is_movable(&self) -> bool {
+        true
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        let old_value = gc_byte::read_gc_byte::<VM>(object);
+        let mut new_value = (old_value & !MARK_BIT) | self.mark_state.load(Ordering::Relaxed);
+        if header_byte::NEEDS_UNLOGGED_BIT {
+            new_value |= header_byte::UNLOGGED_BIT;
+        }
+        gc_byte::write_gc_byte::<VM>(object, new_value);
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for ImmixSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        let me = unsafe { &*(self as *const Self) };
+        self.pr.bind_space(me);
+        self.common().init(self.as_sft());
+    }
+
+    fn common(&self) -> &CommonSpace<VM> {
+        unsafe { &*self.common.get() }
+    }
+
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        &mut *self.common.get()
+    }
+
+    fn release_multiple_pages(&mut self, start: Address) {
+        self.pr.release_pages(start);
+    }
+}
+
+impl<VM: VMBinding> ImmixSpace<VM> {
+    pub fn new(
+        name: &'static str,
+        zeroed: bool,
+        vmrequest: VMRequest,
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        heap: &mut HeapMeta,
+    ) -> Self {
+        let common = CommonSpace::new(
+            SpaceOptions {
+                name,
+                movable: true,
+                immortal: false,
+                zeroed,
+                vmrequest,
+                local_side_metadata_specs: vec![LINE_MARK_SPEC, DEFRAG_SOURCE_SPEC],
+            },
+            vm_map,
+            mmapper,
+            heap,
+        );
+        ImmixSpace {
+            pr: if vmrequest.is_discontiguous() {
+                FreeListPageResource::new_discontiguous(0, vm_map)
+            } else {
+                FreeListPageResource::new_contiguous(common.start, common.extent, 0, vm_map)
+            },
+            common: UnsafeCell::new(common),
+            blocks: RwLock::new(HashMap::new()),
+            recyclable_blocks: Mutex::new(vec![]),
+            mark_state: AtomicU8::new(0),
+            in_collection: AtomicBool::new(false),
+            evacuation_budget: AtomicUsize::new(0),
+        }
+    }
+
+    /// Is a collection in progress? All allocation into this space during a collection is
+    /// evacuation, which must only use clean blocks.
+    pub fn in_collection(&self) -> bool {
+        self.in_collection.load(Ordering::SeqCst)
+    }
+
+    /// Acquire a completely free block. Returns zero if no memory is available (a GC may
+    /// have been triggered).
+    pub fn get_clean_block(&self, tls: OpaquePointer) -> Address {
+        let start = self.acquire(tls, PAGES_IN_BLOCK);
+        if start.is_zero() {
+            return start;
+        }
+        // The page resource only ever hands out runs of PAGES_IN_BLOCK pages, so blocks stay aligned.
+        debug_assert!(start.is_aligned_to(BYTES_IN_BLOCK));
+        self.blocks
+            .write()
+            .unwrap()
+            .insert(start, Block { marked_lines: None });
+        start
+    }
+
+    /// Take a block that has free lines from the last collection. The block is owned by the
+    /// caller until the next collection.
+    pub fn get_recyclable_block(&self) -> Option<Address> {
+        self.recyclable_blocks.lock().unwrap().pop()
+    }
+
+    /// Find the next hole (a run of lines that were free at the last collection) in `block`,
+    /// starting at line `start_line`. Returns the start and end of the hole, and the line to
+    /// continue searching from.
+    pub fn get_next_hole(
+        &self,
+        block: Address,
+        start_line: usize,
+    ) -> Option<(Address, Address, usize)> {
+        let start = (start_line..LINES_IN_BLOCK).find(|&line| !line_is_marked(block, line))?;
+        let end = (start..LINES_IN_BLOCK)
+            .find(|&line| line_is_marked(block, line))
+            .unwrap_or(LINES_IN_BLOCK);
+        Some((
+            block + (start << LOG_BYTES_IN_LINE),
+            block + (end << LOG_BYTES_IN_LINE),
+            end,
+        ))
+    }
+
+    fn is_marked(&self, object: ObjectReference) -> bool {
+        gc_byte::read_gc_byte::<VM>(object) & MARK_BIT == self.mark_state.load(Ordering::Relaxed)
+    }
+
+    fn test_and_mark(&self, object: ObjectReference) -> bool {
+        let mark_state = self.mark_state.load(Ordering::Relaxed);
+        let mut old_value = gc_byte::read_gc_byte::<VM>(object);
+        loop {
+            if old_value & MARK_BIT == mark_state {
+                return false;
+            }
+            if gc_byte::compare_exchange_gc_byte::<VM>(
+                object,
+                old_value,
+                (old_value & !MARK_BIT) | mark_state,
+            ) {
+                return true;
+            }
+            old_value = gc_byte::read_gc_byte::<VM>(object);
+        }
+    }
+
+    /// Mark the lines occupied by an object.
+    #[inline]
+    fn mark_lines(&self, object: ObjectReference) {
+        let start = VM::VMObjectModel::object_start_ref(object);
+        let end = start + VM::VMObjectModel::get_current_size(object);
+        let mut line = start.align_down(BYTES_IN_LINE);
+        while line < end {
+            side_metadata::store_atomic(LINE_MARK_SPEC, line, LINE_MARKED);
+            line += BYTES_IN_LINE;
+        }
+    }
+
+    #[inline]
+    fn is_defrag_source(&self, object: ObjectReference) -> bool {
+        let block = block_align_down(VM::VMObjectModel::ref_to_address(object));
+        side_metadata::load_atomic(DEFRAG_SOURCE_SPEC, block) == 1
+    }
+
+    /// Take `bytes` from the evacuation budget. Returns false if there is not enough left.
+    fn reserve_evacuation(&self, bytes: usize) -> bool {
+        if bytes > MAX_EVACUATED_OBJECT_SIZE {
+            return false;
+        }
+        let mut budget = self.evacuation_budget.load(Ordering::Relaxed);
+        loop {
+            if budget < bytes {
+                return false;
+            }
+            match self.evacuation_budget.compare_exchange_weak(
+                budget,
+                budget - bytes,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => budget = current,
+            }
+        }
+    }
+
+    /// Mark a newly evacuated object and its lines. This is called by the copy context after
+    /// copying an object into this space.
+    pub fn post_copy(&self, object: ObjectReference) {
+        let old_value = gc_byte::read_gc_byte::<VM>(object);
+        gc_byte::write_gc_byte::<VM>(
+            object,
+            (old_value & !(MARK_BIT | ForwardingWord::FORWARDING_MASK))
+                | self.mark_state.load(Ordering::Relaxed),
+        );
+        self.mark_lines(object);
+        #[cfg(feature = "global_alloc_bit")]
+        alloc_bit::set_alloc_bit(object);
+    }
+
+    #[inline]
+    pub fn trace_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+        semantics: AllocationSemantics,
+        copy_context: &mut impl CopyContext,
+    ) -> ObjectReference {
+        if self.is_defrag_source(object) {
+            self.trace_object_with_opportunistic_copy(trace, object, semantics, copy_context)
+        } else {
+            if self.test_and_mark(object) {
+                self.mark_lines(object);
+                trace.process_node(object);
+            }
+            object
+        }
+    }
+
+    fn trace_object_with_opportunistic_copy<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+        semantics: AllocationSemantics,
+        copy_context: &mut impl CopyContext,
+    ) -> ObjectReference {
+        let forwarding_status = ForwardingWord::attempt_to_forward::<VM>(object);
+        if ForwardingWord::state_is_forwarded_or_being_forwarded(forwarding_status) {
+            // Another worker got there first. It either copies the object, or marks it in place.
+            let mut status = forwarding_status;
+            while ForwardingWord::state_is_being_forwarded(status) {
+                status = gc_byte::read_gc_byte::<VM>(object);
+            }
+            return if ForwardingWord::state_is_forwarded_or_being_forwarded(status) {
+                ForwardingWord::spin_and_get_forwarded_object::<VM>(object, status)
+            } else {
+                object
+            };
+        }
+        let mark_state = self.mark_state.load(Ordering::Relaxed);
+        if forwarding_status & MARK_BIT == mark_state {
+            // Already marked in place by another worker.
+            gc_byte::write_gc_byte::<VM>(object, forwarding_status);
+            return object;
+        }
+        if !pin_bit::is_pinned(object)
+            && self.reserve_evacuation(VM::VMObjectModel::get_current_size(object))
+        {
+            let new_object =
+                ForwardingWord::forward_object::<VM, _>(object, semantics, copy_context);
+            trace!("Evacuating [{:?} -> {:?}]", object, new_object);
+            trace.process_node(new_object);
+            new_object
+        } else {
+            // Pinned, or out of headroom: mark the object in place, which also releases the
+            // forwarding claim.
+            gc_byte::write_gc_byte::<VM>(
+                object,
+                (forwarding_status & !(MARK_BIT | ForwardingWord::FORWARDING_MASK)) | mark_state,
+            );
+            self.mark_lines(object);
+            trace.process_node(object);
+            object
+        }
+    }
+
+    /// Prepare for a collection: flip the mark state, clear line marks, and select the
+    /// blocks to evacuate. `headroom_pages` is the number of free pages that evacuation
+    /// may copy into.
+    pub fn prepare(&mut self, headroom_pages: usize) {
+        let mark_state = self.mark_state.load(Ordering::Relaxed);
+        self.mark_state
+            .store(MARK_BIT - mark_state, Ordering::Relaxed);
+        self.in_collection.store(true, Ordering::SeqCst);
+
+        let headroom_blocks = headroom_pages / PAGES_IN_BLOCK;
+        // Each target block may waste up to MAX_EVACUATED_OBJECT_SIZE at its end.
+        let budget = headroom_blocks * (BYTES_IN_BLOCK - MAX_EVACUATED_OBJECT_SIZE);
+        self.evacuation_budget.store(budget, Ordering::Relaxed);
+
+        let mut evacuated_bytes = 0;
+        for (&start, block) in self.blocks.get_mut().unwrap().iter() {
+            side_metadata::bzero_metadata(LINE_MARK_SPEC, start, BYTES_IN_BLOCK);
+            // Pick sparsely occupied blocks while their estimated live data fits in the budget.
+            let defrag_source = match block.marked_lines {
+                Some(lines) if lines <= DEFRAG_LINE_THRESHOLD => {
+                    let bytes = lines << LOG_BYTES_IN_LINE;
+                    if evacuated_bytes + bytes <= budget {
+                        evacuated_bytes += bytes;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                _ => false,
+            };
+            side_metadata::store_atomic(DEFRAG_SOURCE_SPEC, start, defrag_source as u8);
+        }
+    }
+
+    /// Sweep all blocks. Blocks with no marked lines are returned to the page resource, and
+    /// blocks with some free lines become available to allocators.
+    pub fn release(&mut self) {
+        let mut recyclable = vec![];
+        let mut free_blocks = vec![];
+        for (&start, block) in self.blocks.get_mut().unwrap().iter_mut() {
+            let marked = (0..LINES_IN_BLOCK)
+                .filter(|&line| line_is_marked(start, line))
+                .count();
+            block.marked_lines = Some(marked);
+            side_metadata::store_atomic(DEFRAG_SOURCE_SPEC, start, 0);
+            if marked == 0 {
+                free_blocks.push(start);
+                continue;
+            }
+            // A live object marks the line it starts on, so the objects that start on free lines
+            // are dead, or are the old copies of evacuated objects. The dead objects on marked
+            // lines are found from their marks.
+            #[cfg(feature = "global_alloc_bit")]
+            for line in 0..LINES_IN_BLOCK {
+                let line_start = start + (line << LOG_BYTES_IN_LINE);
+                if line_is_marked(start, line) {
+                    let mark_state = self.mark_state.load(Ordering::Relaxed);
+                    alloc_bit::scan_objects::<VM, _>(
+                        line_start,
+                        line_start + BYTES_IN_LINE,
+                        &mut alloc_bit::ObjectVisitor(&mut |object| {
+                            if gc_byte::read_gc_byte::<VM>(object) & MARK_BIT != mark_state {
+                                alloc_bit::unset_alloc_bit(object);
+                            }
+                        }),
+                    );
+                } else {
+                    side_metadata::bzero_metadata(
+                        alloc_bit::ALLOC_BIT_SPEC,
+                        line_start,
+                        BYTES_IN_LINE,
+                    );
+                }
+            }
+            if marked < LINES_IN_BLOCK {
+                recyclable.push(start);
+            }
+        }
+        for start in free_blocks {
+            self.blocks.get_mut().unwrap().remove(&start);
+            self.pr.release_pages(start);
+        }
+        *self.recyclable_blocks.get_mut().unwrap() = recyclable;
+        self.in_collection.store(false, Ordering::SeqCst);
+    }
+
+    /// The number of pages to keep free for evacuation, given the heap size in pages.
+    pub fn defrag_headroom_pages(total_pages: usize) -> usize {
+        let pages = total_pages * DEFRAG_HEADROOM_PERCENT / 100;
+        pages - pages % PAGES_IN_BLOCK
+    }
+}
@@ -6,14 +6,23 @@ use crate::util::constants::CARD_META_PAGES_PER_REGION;
 use crate::util::ObjectReference;
 
 use crate::plan::TransitiveClosure;
+use crate::util::alloc_bit;
 use crate::util::header_byte;
 
 use crate::policy::space::SpaceOptions;
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::conversions::raw_align_up;
 use crate::util::gc_byte;
 use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::heap::snapshot::{next_value, SpaceSnapshot};
 use crate::util::heap::HeapMeta;
+use crate::util::memory;
 use crate::vm::VMBinding;
 use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io;
+use std::slice::Iter;
 
 pub struct ImmortalSpace<VM: VMBinding> {
     mark_state: u8,
@@ -47,6 +56,8 @@ impl<VM: VMBinding> SFT for ImmortalSpace<VM> {
             new_value |= header_byte::UNLOGGED_BIT;
         }
         gc_byte::write_gc_byte::<VM>(object, new_value);
+        // The objects on dirty cards are found from their allocation bits.
+        alloc_bit::set_alloc_bit(object);
     }
 }
 
@@ -76,6 +87,37 @@ impl<VM: VMBinding> Space<VM> for ImmortalSpace<VM> {
     fn release_multiple_pages(&mut self, _start: Address) {
         panic!("immortalspace only releases pages enmasse")
     }
+    /// A contiguous immortal space acquires its chunks from its start upwards, and a boot image is
+    /// mapped at the start of the VM space, so only the mapped prefix of the space is walked. The
+    /// VM space may be much larger than the image in it.
+    fn mapped_chunks(&self) -> Vec<Address> {
+        use crate::util::heap::layout::Mmapper;
+        let common = self.common();
+        if !common.contiguous {
+            return common.mapped_discontiguous_chunks();
+        }
+        let mut chunks = vec![];
+        let mut chunk = common.start;
+        while chunk < common.start + common.extent && common.mmapper.is_mapped_address(chunk) {
+            chunks.push(chunk);
+            chunk += BYTES_IN_CHUNK;
+        }
+        chunks
+    }
+    fn snapshot(&self) -> io::Result<SpaceSnapshot> {
+        let mut snapshot = SpaceSnapshot::default();
+        self.pr.snapshot(&mut snapshot)?;
+        snapshot.state.push(self.mark_state as usize);
+        Ok(snapshot)
+    }
+    fn restore(&self, state: &mut Iter<usize>) -> io::Result<()> {
+        self.pr.restore(state)?;
+        // The mark state is only changed by `prepare()`, with `&mut self`.
+        #[allow(clippy::cast_ref_to_mut)]
+        let self_mut: &mut Self = unsafe { &mut *(self as *const _ as *mut _) };
+        self_mut.mark_state = next_value(state) as u8;
+        Ok(())
+    }
 }
 
 impl<VM: VMBinding> ImmortalSpace<VM> {
@@ -94,6 +136,7 @@ impl<VM: VMBinding> ImmortalSpace<VM> {
                 immortal: true,
                 zeroed,
                 vmrequest,
+                local_side_metadata_specs: vec![],
             },
             vm_map,
             mmapper,
@@ -135,6 +178,42 @@ impl<VM: VMBinding> ImmortalSpace<VM> {
         true
     }
 
+    /// Map the contents of `file` at the start of this space with copy-on-write, so the space
+    /// holds the objects of a pre-built heap image. The space must be contiguous, and the image
+    /// must have been built for its address range. Returns the end of the image.
+    pub fn map_image(&self, file: &File) -> io::Result<Address> {
+        use crate::util::heap::layout::Mmapper;
+        let common = self.common();
+        assert!(common.contiguous, "{} is not contiguous", common.name);
+        let len = file.metadata()?.len() as usize;
+        if len > common.extent {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the image ({} bytes) does not fit in {} ({} bytes)",
+                    len, common.name, common.extent
+                ),
+            ));
+        }
+        // The rest of the page after the end of the file reads as zero.
+        let image_bytes = raw_align_up(len, BYTES_IN_PAGE);
+        if image_bytes > 0 {
+            memory::mmap_file(common.start, image_bytes, file, 0)?;
+        }
+        // Only the chunks of the image are marked as mapped, and get side metadata, so that the
+        // walks of the mapped chunks of this space stop at the end of the image. The rest of the
+        // last chunk is zeroed memory.
+        let mapped_bytes = raw_align_up(image_bytes, BYTES_IN_CHUNK);
+        if mapped_bytes > image_bytes {
+            memory::dzmmap(common.start + image_bytes, mapped_bytes - image_bytes)?;
+        }
+        common.mmapper.mark_as_mapped(common.start, mapped_bytes);
+        common
+            .metadata
+            .ensure_mapped(common.mmapper, common.start, mapped_bytes);
+        Ok(common.start + len)
+    }
+
     pub fn prepare(&mut self) {
         self.mark_state = GC_MARK_BIT_MASK - self.mark_state;
     }
@@ -1,14 +1,21 @@
 use std::cell::UnsafeCell;
+use std::io;
+use std::slice::Iter;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::plan::TransitiveClosure;
 use crate::policy::space::SpaceOptions;
-use crate::policy::space::{CommonSpace, Space, SFT};
-use crate::util::constants::{BYTES_IN_PAGE, LOG_BYTES_IN_WORD};
+use crate::policy::space::{object_contains, CommonSpace, Space, SFT};
+use crate::util::alloc_bit;
+use crate::util::constants::{BYTES_IN_PAGE, BYTES_IN_WORD, LOG_BYTES_IN_PAGE, LOG_BYTES_IN_WORD};
 use crate::util::gc_byte;
 use crate::util::header_byte;
 use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+use crate::util::heap::snapshot::{next_value, SpaceSnapshot};
 use crate::util::heap::HeapMeta;
 use crate::util::heap::{FreeListPageResource, PageResource, VMRequest};
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
 use crate::util::treadmill::TreadMill;
 use crate::util::OpaquePointer;
 use crate::util::{Address, ObjectReference};
@@ -25,11 +32,22 @@ const USE_PRECEEDING_GC_HEADER: bool = true;
 const PRECEEDING_GC_HEADER_WORDS: usize = 1;
 const PRECEEDING_GC_HEADER_BYTES: usize = PRECEEDING_GC_HEADER_WORDS << LOG_BYTES_IN_WORD;
 
+/// A bit of local side metadata for each page, which is set for the first page of each object.
+/// The pages are released with their metadata, which clears the bit.
+pub const FIRST_PAGE_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Local,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_PAGE as usize,
+};
+
 pub struct LargeObjectSpace<VM: VMBinding> {
     common: UnsafeCell<CommonSpace<VM>>,
     pr: FreeListPageResource<VM>,
     mark_state: u8,
     in_nursery_gc: bool,
+    /// Whether new objects are allocated marked, for a collection that marks concurrently.
+    allocate_black: AtomicBool,
     treadmill: TreadMill,
 }
 
@@ -52,17 +70,16 @@ impl<VM: VMBinding> SFT for LargeObjectSpace<VM> {
     fn initialize_header(&self, object: ObjectReference, alloc: bool) {
         let old_value = gc_byte::read_gc_byte::<VM>(object);
         let mut new_value = (old_value & (!LOS_BIT_MASK)) | self.mark_state;
-        if alloc {
+        // An object allocated black is already marked for the current collection, so it goes to
+        // the to-space, which the collection keeps.
+        let nursery = alloc && !self.allocate_black.load(Ordering::SeqCst);
+        if nursery {
             new_value |= NURSERY_BIT;
         }
         gc_byte::write_gc_byte::<VM>(object, new_value);
-        let cell = VM::VMObjectModel::object_start_ref(object)
-            - if USE_PRECEEDING_GC_HEADER {
-                PRECEEDING_GC_HEADER_BYTES
-            } else {
-                0
-            };
-        self.treadmill.add_to_treadmill(cell, alloc);
+        self.treadmill.add_to_treadmill(object, nursery);
+        // The objects on dirty cards are found from their allocation bits.
+        alloc_bit::set_alloc_bit(object);
         if header_byte::NEEDS_UNLOGGED_BIT {
             gc_byte::write_gc_byte::<VM>(
                 object,
@@ -98,6 +115,77 @@ impl<VM: VMBinding> Space<VM> for LargeObjectSpace<VM> {
     fn release_multiple_pages(&mut self, start: Address) {
         self.pr.release_pages(start);
     }
+
+    /// Walk the treadmill rather than the allocation bits, which are sparse in this space.
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        for object in self.treadmill.objects() {
+            f(object);
+        }
+    }
+
+    /// Search the first pages of the objects backward from `addr`, which takes one bit for each
+    /// page rather than one for each word like the allocation bits. The object is then found from
+    /// its allocation bit on its first page.
+    fn find_base_object(&self, addr: Address) -> Option<ObjectReference> {
+        use crate::util::heap::layout::Mmapper;
+        let common = self.common();
+        let mut page = addr.align_down(BYTES_IN_PAGE);
+        let mut new_chunk = true;
+        loop {
+            // The chunks that the space has never used have no metadata, and the chunks before
+            // those of a discontiguous space may be in other spaces.
+            if new_chunk
+                && (!self.address_in_space(page)
+                    || !common
+                        .mmapper
+                        .is_mapped_address(side_metadata::address_to_meta_address(
+                            FIRST_PAGE_SPEC,
+                            page,
+                        )))
+            {
+                return None;
+            }
+            if side_metadata::load_atomic(FIRST_PAGE_SPEC, page) == 1 {
+                break;
+            }
+            if page <= common.start {
+                return None;
+            }
+            new_chunk = page.is_aligned_to(BYTES_IN_CHUNK);
+            page -= BYTES_IN_PAGE;
+        }
+        (0..BYTES_IN_PAGE)
+            .step_by(BYTES_IN_WORD)
+            .map(|offset| unsafe { (page + offset).to_object_reference() })
+            .find(|&object| alloc_bit::is_alloced(object))
+            .filter(|&object| object_contains::<VM>(object, addr))
+    }
+
+    fn snapshot(&self) -> io::Result<SpaceSnapshot> {
+        let mut snapshot = SpaceSnapshot::default();
+        self.pr.snapshot(&mut snapshot)?;
+        let objects = self.treadmill.objects();
+        snapshot.state.push(self.mark_state as usize);
+        snapshot.state.push(objects.len());
+        snapshot
+            .state
+            .extend(objects.iter().map(|object| object.to_address().as_usize()));
+        Ok(snapshot)
+    }
+
+    /// The objects are restored as mature objects, which only a full heap GC reclaims.
+    fn restore(&self, state: &mut Iter<usize>) -> io::Result<()> {
+        self.pr.restore(state)?;
+        // The mark state is only changed by `prepare()`, with `&mut self`.
+        #[allow(clippy::cast_ref_to_mut)]
+        let self_mut: &mut Self = unsafe { &mut *(self as *const _ as *mut _) };
+        self_mut.mark_state = next_value(state) as u8;
+        for _ in 0..next_value(state) {
+            let object = unsafe { Address::from_usize(next_value(state)).to_object_reference() };
+            self.treadmill.add_to_treadmill(object, false);
+        }
+        Ok(())
+    }
 }
 
 impl<VM: VMBinding> LargeObjectSpace<VM> {
@@ -116,6 +204,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
                 immortal: false,
                 zeroed,
                 vmrequest,
+                local_side_metadata_specs: vec![FIRST_PAGE_SPEC],
             },
             vm_map,
             mmapper,
@@ -130,6 +219,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
             common: UnsafeCell::new(common),
             mark_state: 0,
             in_nursery_gc: false,
+            allocate_black: AtomicBool::new(false),
             treadmill: TreadMill::new(),
         }
     }
@@ -143,6 +233,13 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         self.in_nursery_gc = !full_heap;
     }
 
+    /// Allocate the new objects marked, so that they survive the collection that is marking
+    /// while the mutators run. This is set from the `prepare()` of that collection until its
+    /// `release()`.
+    pub fn set_allocate_black(&self, black: bool) {
+        self.allocate_black.store(black, Ordering::SeqCst);
+    }
+
     pub fn release(&mut self, full_heap: bool) {
         self.sweep_large_pages(true);
         debug_assert!(self.treadmill.nursery_empty());
@@ -162,13 +259,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         if !self.in_nursery_gc || nursery_object {
             // Note that test_and_mark() has side effects
             if self.test_and_mark(object, self.mark_state) {
-                let cell = VM::VMObjectModel::object_start_ref(object)
-                    - if USE_PRECEEDING_GC_HEADER {
-                        PRECEEDING_GC_HEADER_BYTES
-                    } else {
-                        0
-                    };
-                self.treadmill.copy(cell, nursery_object);
+                self.treadmill.copy(object, nursery_object);
                 trace.process_node(object);
             }
         }
@@ -179,16 +270,14 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         // FIXME: borrow checker fighting
         // didn't call self.release_multiple_pages
         // so the compiler knows I'm borrowing two different fields
-        if sweep_nursery {
-            for cell in self.treadmill.collect_nursery() {
-                // println!("- cn {}", cell);
-                self.pr.release_pages(get_super_page(cell));
-            }
+        let dead = if sweep_nursery {
+            self.treadmill.collect_nursery()
         } else {
-            for cell in self.treadmill.collect() {
-                // println!("- ts {}", cell);
-                self.pr.release_pages(get_super_page(cell));
-            }
+            self.treadmill.collect()
+        };
+        for object in dead {
+            // println!("- {}", object);
+            self.pr.release_pages(get_super_page(Self::cell(object)));
         }
     }
 
@@ -197,6 +286,7 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         if start.is_zero() {
             return start;
         }
+        side_metadata::store_atomic(FIRST_PAGE_SPEC, start, 1);
         if USE_PRECEEDING_GC_HEADER {
             start + PRECEEDING_GC_HEADER_BYTES
         } else {
@@ -204,6 +294,16 @@ impl<VM: VMBinding> LargeObjectSpace<VM> {
         }
     }
 
+    /// The start of the pages allocated for `object`, including its preceding header.
+    fn cell(object: ObjectReference) -> Address {
+        VM::VMObjectModel::object_start_ref(object)
+            - if USE_PRECEEDING_GC_HEADER {
+                PRECEEDING_GC_HEADER_BYTES
+            } else {
+                0
+            }
+    }
+
     fn test_and_mark(&self, object: ObjectReference, value: u8) -> bool {
         let mask = if self.in_nursery_gc {
             LOS_BIT_MASK
@@ -6,7 +6,6 @@ use crate::util::heap::PageResource;
 
 use crate::util::ObjectReference;
 
-use crate::plan::Plan;
 use crate::util::conversions;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::vm_layout_constants::{
@@ -76,11 +75,7 @@ impl<VM: VMBinding> Space<VM> for LockFreeImmortalSpace<VM> {
     }
 
     fn init(&mut self, _vm_map: &'static VMMap) {
-        let total_pages = VM::VMActivePlan::global()
-            .base()
-            .heap
-            .total_pages
-            .load(Ordering::SeqCst);
+        let total_pages = VM::VMActivePlan::global().get_total_pages();
         let total_bytes = conversions::pages_to_bytes(total_pages);
         assert!(total_pages > 0);
         assert!(
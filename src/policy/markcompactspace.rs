@@ -0,0 +1,411 @@
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, RwLock};
+
+use crate::plan::TransitiveClosure;
+use crate::policy::space::SpaceOptions;
+use crate::policy::space::{CommonSpace, Space, SFT};
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
+use crate::util::conversions;
+use crate::util::gc_byte;
+use crate::util::header_byte;
+use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::{FreeListPageResource, PageResource, VMRequest};
+use crate::util::memory;
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::side_metadata;
+use crate::util::OpaquePointer;
+use crate::util::{Address, ObjectReference};
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+
+const GC_MARK_BIT_MASK: u8 = 1;
+
+/// Regions are grouped until a group spans at least this many bytes. Each group is compacted
+/// into itself, so groups are the unit of parallelism when computing forwarding addresses
+/// and when moving objects.
+const BYTES_IN_COMPACTION_GROUP: usize = 4 << 20;
+
+/// A run of regions that is compacted independently of all other groups. Live objects slide
+/// towards the start of the first region, in address order, so an object is never moved to a
+/// higher address.
+pub struct CompactionGroup {
+    /// The regions of this group as `(start, bytes)`, in address order.
+    regions: Vec<(Address, usize)>,
+    /// The live objects of this group, in address order.
+    objects: Vec<ObjectReference>,
+    /// The start of the storage each object will be moved to. Only valid after
+    /// `calculate_forwarding()`.
+    targets: Vec<Address>,
+    /// The index of the region holding the end of the compacted objects, and the end itself.
+    end: (usize, Address),
+    /// The ends of the regions that an object did not fit in, as `(start, limit)`. Only valid
+    /// after `calculate_forwarding()`.
+    skipped_ends: Vec<(Address, Address)>,
+}
+
+impl CompactionGroup {
+    fn new(regions: Vec<(Address, usize)>) -> Self {
+        let first = regions[0].0;
+        CompactionGroup {
+            regions,
+            objects: vec![],
+            targets: vec![],
+            end: (0, first),
+            skipped_ends: vec![],
+        }
+    }
+
+    fn start(&self) -> Address {
+        self.regions[0].0
+    }
+
+    fn limit(&self) -> Address {
+        let (start, bytes) = self.regions[self.regions.len() - 1];
+        start + bytes
+    }
+
+    /// Assign each live object the address it slides to. Objects keep their offset
+    /// from `MAX_ALIGNMENT`, so any alignment they were allocated with is preserved.
+    pub fn calculate_forwarding<VM: VMBinding>(&mut self) {
+        let mut region = 0;
+        let mut cursor = self.start();
+        self.targets = Vec::with_capacity(self.objects.len());
+        self.skipped_ends.clear();
+        for &object in &self.objects {
+            let from = VM::VMObjectModel::object_start_ref(object);
+            let size = VM::VMObjectModel::get_current_size(object);
+            let target = loop {
+                let (start, bytes) = self.regions[region];
+                let padding =
+                    (from.as_usize().wrapping_sub(cursor.as_usize())) & (VM::MAX_ALIGNMENT - 1);
+                let target = cursor + padding;
+                if target + size <= start + bytes {
+                    break target;
+                }
+                // An object never spans regions. It fits in its own region, so it will
+                // fit in this one or in a region before it.
+                if cursor < start + bytes {
+                    self.skipped_ends.push((cursor, start + bytes));
+                }
+                region += 1;
+                cursor = self.regions[region].0;
+            };
+            debug_assert!(target <= from);
+            cursor = target + size;
+            self.targets.push(target);
+        }
+        self.end = (region, cursor);
+    }
+
+    /// Move all live objects to their targets. Returns the regions that no longer hold any
+    /// objects, and the unused ends of the regions that still do, as `(start, limit)`.
+    pub fn compact<VM: VMBinding>(&self) -> (Vec<Address>, Vec<(Address, Address)>) {
+        // The allocation bits are set again for the objects at their new addresses.
+        #[cfg(feature = "global_alloc_bit")]
+        for &(start, bytes) in &self.regions {
+            side_metadata::bzero_metadata(alloc_bit::ALLOC_BIT_SPEC, start, bytes);
+        }
+        for (&object, &target) in self.objects.iter().zip(self.targets.iter()) {
+            if VM::VMObjectModel::object_start_ref(object) == target {
+                #[cfg(feature = "global_alloc_bit")]
+                alloc_bit::set_alloc_bit(object);
+                continue;
+            }
+            let new_object = VM::VMObjectModel::get_reference_when_copied_to(object, target);
+            VM::VMObjectModel::copy_to(object, new_object, target);
+            if !VM::VMObjectModel::HAS_GC_BYTE {
+                // A side GC byte does not move with its object. Objects move to lower addresses
+                // in address order, so no object that is yet to move has its byte overwritten.
+                let value = gc_byte::read_gc_byte::<VM>(object);
+                gc_byte::write_gc_byte::<VM>(object, 0);
+                gc_byte::write_gc_byte::<VM>(new_object, value);
+            }
+            #[cfg(feature = "global_alloc_bit")]
+            alloc_bit::set_alloc_bit(new_object);
+        }
+        let (region, end) = self.end;
+        let (start, bytes) = self.regions[region];
+        let mut free_ends = self.skipped_ends.clone();
+        if end < start + bytes {
+            free_ends.push((end, start + bytes));
+        }
+        // The free ends still have stale copies of moved objects.
+        for &(start, limit) in &free_ends {
+            memory::zero(start, limit - start);
+        }
+        let dead_regions = self.regions[region + 1..]
+            .iter()
+            .map(|&(start, _)| start)
+            .collect();
+        (dead_regions, free_ends)
+    }
+}
+
+/// A space that is collected with sliding (Lisp-2) mark-compact. Mutators bump-allocate in
+/// regions acquired from a free-list page resource. A collection marks live objects in place,
+/// computes the address each one slides to, updates all references, and then moves the
+/// objects, so objects keep their allocation order and the space is left without fragmentation.
+pub struct MarkCompactSpace<VM: VMBinding> {
+    common: UnsafeCell<CommonSpace<VM>>,
+    pr: FreeListPageResource<VM>,
+    mark_state: u8,
+    /// All regions in this space, keyed by their start address, with their size in bytes.
+    regions: Mutex<BTreeMap<Address, usize>>,
+    /// The unused ends of regions left by the last compaction, as `(start, limit)`.
+    free_regions: Mutex<Vec<(Address, Address)>>,
+    /// The compaction groups of the current collection, keyed by their start address.
+    groups: RwLock<BTreeMap<Address, CompactionGroup>>,
+    /// Regions emptied by compaction, to be released at the end of the collection.
+    dead_regions: Mutex<Vec<Address>>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MarkCompactSpace<VM> {}
+
+impl<VM: VMBinding> SFT for MarkCompactSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        gc_byte::read_gc_byte::<VM>(object) & GC_MARK_BIT_MASK == self.mark_state
+    }
+    fn is_movable(&self) -> bool {
+        true
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        let old_value = gc_byte::read_gc_byte::<VM>(object);
+        let mut new_value = (old_value & !GC_MARK_BIT_MASK) | self.mark_state;
+        if header_byte::NEEDS_UNLOGGED_BIT {
+            new_value |= header_byte::UNLOGGED_BIT;
+        }
+        gc_byte::write_gc_byte::<VM>(object, new_value);
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for MarkCompactSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        let me = unsafe { &*(self as *const Self) };
+        self.pr.bind_space(me);
+        self.common().init(self.as_sft());
+    }
+
+    fn common(&self) -> &CommonSpace<VM> {
+        unsafe { &*self.common.get() }
+    }
+
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        &mut *self.common.get()
+    }
+
+    fn release_multiple_pages(&mut self, start: Address) {
+        self.pr.release_pages(start);
+    }
+}
+
+impl<VM: VMBinding> MarkCompactSpace<VM> {
+    pub fn new(
+        name: &'static str,
+        zeroed: bool,
+        vmrequest: VMRequest,
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        heap: &mut HeapMeta,
+    ) -> Self {
+        let common = CommonSpace::new(
+            SpaceOptions {
+                name,
+                movable: true,
+                immortal: false,
+                zeroed,
+                vmrequest,
+                local_side_metadata_specs: vec![],
+            },
+            vm_map,
+            mmapper,
+            heap,
+        );
+        MarkCompactSpace {
+            pr: if vmrequest.is_discontiguous() {
+                FreeListPageResource::new_discontiguous(0, vm_map)
+            } else {
+                FreeListPageResource::new_contiguous(common.start, common.extent, 0, vm_map)
+            },
+            common: UnsafeCell::new(common),
+            mark_state: 0,
+            regions: Mutex::new(BTreeMap::new()),
+            free_regions: Mutex::new(vec![]),
+            groups: RwLock::new(BTreeMap::new()),
+            dead_regions: Mutex::new(vec![]),
+        }
+    }
+
+    /// Acquire a new region of `pages` pages for bump allocation. Returns zero if no memory
+    /// is available (a GC may have been triggered).
+    pub fn acquire_region(&self, tls: OpaquePointer, pages: usize) -> Address {
+        let start = self.acquire(tls, pages);
+        if !start.is_zero() {
+            self.regions
+                .lock()
+                .unwrap()
+                .insert(start, conversions::pages_to_bytes(pages));
+        }
+        start
+    }
+
+    /// Take the unused end of a compacted region with room for at least `bytes` bytes,
+    /// as `(start, limit)`.
+    pub fn get_free_region(&self, bytes: usize) -> Option<(Address, Address)> {
+        let mut free_regions = self.free_regions.lock().unwrap();
+        let index = free_regions
+            .iter()
+            .position(|&(start, limit)| limit - start >= bytes)?;
+        Some(free_regions.swap_remove(index))
+    }
+
+    /// Give back the unused part `[start, limit)` of a region that an allocator took, so that
+    /// other allocations can use it until the next collection.
+    pub fn put_free_region(&self, start: Address, limit: Address) {
+        debug_assert!(start < limit);
+        self.free_regions.lock().unwrap().push((start, limit));
+    }
+
+    fn test_and_mark(object: ObjectReference, value: u8) -> bool {
+        let mut old_value = gc_byte::read_gc_byte::<VM>(object);
+        if old_value & GC_MARK_BIT_MASK == value {
+            return false;
+        }
+        while !gc_byte::compare_exchange_gc_byte::<VM>(
+            object,
+            old_value,
+            old_value ^ GC_MARK_BIT_MASK,
+        ) {
+            old_value = gc_byte::read_gc_byte::<VM>(object);
+            if old_value & GC_MARK_BIT_MASK == value {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Mark an object during the closure. Objects are not moved until all references are updated.
+    pub fn trace_mark_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if Self::test_and_mark(object, self.mark_state) {
+            trace.process_node(object);
+        }
+        object
+    }
+
+    /// Get the reference a live object will have once it is moved. Only valid after the
+    /// forwarding addresses are calculated, and until the objects are moved. An object that
+    /// was not marked is dead and has no forwarding address, so its reference is returned
+    /// unchanged. Only stale slots, e.g. the referents of cleared references, still hold it.
+    pub fn trace_forward_object(&self, object: ObjectReference) -> ObjectReference {
+        let start = VM::VMObjectModel::object_start_ref(object);
+        let groups = self.groups.read().unwrap();
+        let group = match groups.range(..=start).next_back() {
+            Some((_, group)) if start < group.limit() => group,
+            _ => return object,
+        };
+        match group
+            .objects
+            .binary_search_by_key(&start, |&o| VM::VMObjectModel::object_start_ref(o))
+        {
+            Ok(index) => {
+                VM::VMObjectModel::get_reference_when_copied_to(object, group.targets[index])
+            }
+            Err(_) => {
+                debug_assert!(
+                    !self.is_live(object),
+                    "{} is marked but not forwarded",
+                    object
+                );
+                object
+            }
+        }
+    }
+
+    /// Split the regions of this space into compaction groups, and hand each group the
+    /// live objects (in address order) that lie in it.
+    pub fn create_groups(&self, objects: Vec<ObjectReference>) -> Vec<CompactionGroup> {
+        let mut groups = vec![];
+        let mut regions = vec![];
+        let mut bytes_in_group = 0;
+        for (&start, &bytes) in self.regions.lock().unwrap().iter() {
+            regions.push((start, bytes));
+            bytes_in_group += bytes;
+            if bytes_in_group >= BYTES_IN_COMPACTION_GROUP {
+                groups.push(CompactionGroup::new(regions));
+                regions = vec![];
+                bytes_in_group = 0;
+            }
+        }
+        if !regions.is_empty() {
+            groups.push(CompactionGroup::new(regions));
+        }
+        let mut group = 0;
+        for object in objects {
+            let start = VM::VMObjectModel::object_start_ref(object);
+            while start >= groups[group].limit() {
+                group += 1;
+            }
+            debug_assert!(start >= groups[group].start());
+            groups[group].objects.push(object);
+        }
+        groups
+    }
+
+    /// Publish a group once its forwarding addresses are calculated.
+    pub fn add_group(&self, group: CompactionGroup) {
+        self.groups.write().unwrap().insert(group.start(), group);
+    }
+
+    /// The start addresses of all compaction groups of the current collection.
+    pub fn group_starts(&self) -> Vec<Address> {
+        self.groups.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Move the objects of the group starting at `start`.
+    pub fn compact_group(&self, start: Address) {
+        let (dead_regions, free_ends) = self.groups.read().unwrap()[&start].compact::<VM>();
+        self.dead_regions.lock().unwrap().extend(dead_regions);
+        for &(start, limit) in &free_ends {
+            self.common().metadata.bzero(start, limit - start);
+        }
+        self.free_regions.lock().unwrap().extend(free_ends);
+    }
+
+    pub fn prepare(&mut self) {
+        self.mark_state = GC_MARK_BIT_MASK - self.mark_state;
+        // Compaction decides again which parts of the regions are free.
+        self.free_regions.get_mut().unwrap().clear();
+    }
+
+    /// Return the regions emptied by compaction to the page resource.
+    pub fn release(&mut self) {
+        self.groups.get_mut().unwrap().clear();
+        let dead_regions = std::mem::replace(self.dead_regions.get_mut().unwrap(), vec![]);
+        for start in dead_regions {
+            self.regions.get_mut().unwrap().remove(&start);
+            self.pr.release_pages(start);
+        }
+    }
+}
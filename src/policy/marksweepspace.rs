@@ -0,0 +1,325 @@
+use std::cell::UnsafeCell;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, RwLock};
+
+use crate::plan::TransitiveClosure;
+use crate::policy::space::SpaceOptions;
+use crate::policy::space::{CommonSpace, Space, SFT};
+#[cfg(feature = "global_alloc_bit")]
+use crate::util::alloc_bit;
+use crate::util::constants::{BYTES_IN_PAGE, BYTES_IN_WORD, LOG_BYTES_IN_WORD};
+use crate::util::conversions;
+use crate::util::gc_byte;
+use crate::util::header_byte;
+use crate::util::heap::layout::heap_layout::{Mmapper, VMMap};
+use crate::util::heap::HeapMeta;
+use crate::util::heap::{FreeListPageResource, PageResource, VMRequest};
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
+use crate::util::OpaquePointer;
+use crate::util::{Address, ObjectReference};
+use crate::vm::ObjectModel;
+use crate::vm::VMBinding;
+
+/// Pages in a block. Every size class carves its cells out of blocks of this size.
+pub const PAGES_IN_BLOCK: usize = 16;
+pub const BYTES_IN_BLOCK: usize = PAGES_IN_BLOCK * BYTES_IN_PAGE;
+
+/// Cell sizes for each size class (in bytes). Cells are a multiple of the word size so
+/// that every free cell can hold the link to the next free cell.
+pub const SIZE_CLASSES: [usize; 36] = [
+    8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 640,
+    768, 896, 1024, 1280, 1536, 1792, 2048, 2560, 3072, 3584, 4096, 5120, 6144, 7168, 8192,
+];
+pub const NUM_SIZE_CLASSES: usize = SIZE_CLASSES.len();
+/// The largest cell size served from a size class. Larger requests get a block of their own.
+pub const MAX_CELL_SIZE: usize = SIZE_CLASSES[NUM_SIZE_CLASSES - 1];
+/// The pseudo size class for blocks holding a single cell larger than `MAX_CELL_SIZE`.
+const LARGE_CELL_CLASS: usize = NUM_SIZE_CLASSES;
+
+const UNMARKED: u8 = 0;
+const MARKED: u8 = 1;
+
+/// The mark bits: one bit of local side metadata for each word, which is set at the start of the
+/// marked objects. An object may start past the start of its cell to be aligned, but by less
+/// than `VM::MAX_ALIGNMENT`, so the bits of different cells do not overlap.
+pub const MARK_BIT_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Local,
+    offset: 0,
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// Returns the smallest size class whose cells can hold `bytes`, or `None` if the
+/// request is larger than `MAX_CELL_SIZE`.
+#[inline(always)]
+pub fn size_class_for(bytes: usize) -> Option<usize> {
+    if bytes > MAX_CELL_SIZE {
+        return None;
+    }
+    let class = SIZE_CLASSES
+        .iter()
+        .position(|&cell_size| cell_size >= bytes);
+    debug_assert!(class.is_some());
+    class
+}
+
+/// A contiguous run of pages divided into equally sized cells.
+struct Block {
+    bytes: usize,
+    cell_size: usize,
+    size_class: usize,
+    /// Head of the free cells of this block. Only valid after the block is swept or created,
+    /// and until the block is handed to an allocator.
+    free_list: Address,
+}
+
+impl Block {
+    fn cells(&self) -> usize {
+        self.bytes / self.cell_size
+    }
+}
+
+/// A non-moving space managed with segregated free lists. Memory is acquired in blocks
+/// from a free-list page resource, and each block serves cells of one size class.
+/// Liveness is recorded per cell in the mark bits, so a collection marks objects in place
+/// and sweeping rebuilds the free lists from the unmarked cells. The table of blocks is only
+/// used when blocks are acquired and swept.
+pub struct MarkSweepSpace<VM: VMBinding> {
+    common: UnsafeCell<CommonSpace<VM>>,
+    pr: FreeListPageResource<VM>,
+    /// All blocks in this space, keyed by their start address.
+    blocks: RwLock<BTreeMap<Address, Block>>,
+    /// Blocks with free cells that are not owned by any allocator, per size class.
+    available: Mutex<Vec<Vec<Address>>>,
+}
+
+unsafe impl<VM: VMBinding> Sync for MarkSweepSpace<VM> {}
+
+impl<VM: VMBinding> SFT for MarkSweepSpace<VM> {
+    fn name(&self) -> &str {
+        self.get_name()
+    }
+    fn is_live(&self, object: ObjectReference) -> bool {
+        self.is_marked(object)
+    }
+    fn is_movable(&self) -> bool {
+        false
+    }
+    #[cfg(feature = "sanity")]
+    fn is_sane(&self) -> bool {
+        true
+    }
+    fn initialize_header(&self, object: ObjectReference, _alloc: bool) {
+        // The allocator has already marked the object (see `mark_new_object`).
+        if header_byte::NEEDS_UNLOGGED_BIT {
+            gc_byte::write_gc_byte::<VM>(
+                object,
+                gc_byte::read_gc_byte::<VM>(object) | header_byte::UNLOGGED_BIT,
+            );
+        }
+    }
+}
+
+impl<VM: VMBinding> Space<VM> for MarkSweepSpace<VM> {
+    fn as_space(&self) -> &dyn Space<VM> {
+        self
+    }
+    fn as_sft(&self) -> &(dyn SFT + Sync + 'static) {
+        self
+    }
+    fn get_page_resource(&self) -> &dyn PageResource<VM> {
+        &self.pr
+    }
+    fn init(&mut self, _vm_map: &'static VMMap) {
+        let me = unsafe { &*(self as *const Self) };
+        self.pr.bind_space(me);
+        self.common().init(self.as_sft());
+    }
+
+    fn common(&self) -> &CommonSpace<VM> {
+        unsafe { &*self.common.get() }
+    }
+
+    unsafe fn unsafe_common_mut(&self) -> &mut CommonSpace<VM> {
+        &mut *self.common.get()
+    }
+
+    fn release_multiple_pages(&mut self, start: Address) {
+        self.pr.release_pages(start);
+    }
+}
+
+impl<VM: VMBinding> MarkSweepSpace<VM> {
+    pub fn new(
+        name: &'static str,
+        zeroed: bool,
+        vmrequest: VMRequest,
+        vm_map: &'static VMMap,
+        mmapper: &'static Mmapper,
+        heap: &mut HeapMeta,
+    ) -> Self {
+        let common = CommonSpace::new(
+            SpaceOptions {
+                name,
+                movable: false,
+                immortal: false,
+                zeroed,
+                vmrequest,
+                local_side_metadata_specs: vec![MARK_BIT_SPEC],
+            },
+            vm_map,
+            mmapper,
+            heap,
+        );
+        MarkSweepSpace {
+            pr: if vmrequest.is_discontiguous() {
+                FreeListPageResource::new_discontiguous(0, vm_map)
+            } else {
+                FreeListPageResource::new_contiguous(common.start, common.extent, 0, vm_map)
+            },
+            common: UnsafeCell::new(common),
+            blocks: RwLock::new(BTreeMap::new()),
+            available: Mutex::new(vec![vec![]; NUM_SIZE_CLASSES]),
+        }
+    }
+
+    /// Get a block with free cells for the given size class, and return the head of its
+    /// free list. The block is owned by the caller until the next collection. Returns zero
+    /// if no memory is available (a GC may have been triggered).
+    pub fn acquire_block(&self, tls: OpaquePointer, size_class: usize) -> Address {
+        debug_assert!(size_class < NUM_SIZE_CLASSES);
+        let reused = self.available.lock().unwrap()[size_class].pop();
+        if let Some(start) = reused {
+            let mut blocks = self.blocks.write().unwrap();
+            let block = blocks.get_mut(&start).unwrap();
+            let free_list = block.free_list;
+            block.free_list = unsafe { Address::zero() };
+            debug_assert!(!free_list.is_zero());
+            return free_list;
+        }
+        let start = self.acquire(tls, PAGES_IN_BLOCK);
+        if start.is_zero() {
+            return start;
+        }
+        let cell_size = SIZE_CLASSES[size_class];
+        let cells = BYTES_IN_BLOCK / cell_size;
+        // Thread a free list through all the cells of the fresh block.
+        for i in 0..cells {
+            let cell = start + i * cell_size;
+            let next = if i + 1 == cells {
+                unsafe { Address::zero() }
+            } else {
+                cell + cell_size
+            };
+            unsafe { cell.store(next) };
+        }
+        self.add_block(start, BYTES_IN_BLOCK, cell_size, size_class);
+        start
+    }
+
+    /// Get a block that holds a single cell of at least `bytes` bytes, for requests that are
+    /// larger than any size class. Returns zero if no memory is available.
+    pub fn acquire_large_cell(&self, tls: OpaquePointer, bytes: usize) -> Address {
+        let pages = conversions::bytes_to_pages_up(bytes);
+        let start = self.acquire(tls, pages);
+        if start.is_zero() {
+            return start;
+        }
+        let cell_size = conversions::pages_to_bytes(pages);
+        self.add_block(start, cell_size, cell_size, LARGE_CELL_CLASS);
+        start
+    }
+
+    fn add_block(&self, start: Address, bytes: usize, cell_size: usize, size_class: usize) {
+        let block = Block {
+            bytes,
+            cell_size,
+            size_class,
+            free_list: unsafe { Address::zero() },
+        };
+        self.blocks.write().unwrap().insert(start, block);
+    }
+
+    /// Mark an object that is just allocated at `start`. Objects are allocated marked, so that
+    /// objects allocated while a collection is marking concurrently survive it. Marks are cleared
+    /// at the start of each collection, so this otherwise only makes new objects report as live.
+    #[inline]
+    pub fn mark_new_object(&self, start: Address) {
+        side_metadata::store_atomic(MARK_BIT_SPEC, start, MARKED);
+    }
+
+    #[inline]
+    fn is_marked(&self, object: ObjectReference) -> bool {
+        let addr = VM::VMObjectModel::object_start_ref(object);
+        side_metadata::load_atomic(MARK_BIT_SPEC, addr) == MARKED
+    }
+
+    #[inline]
+    fn test_and_mark(&self, object: ObjectReference) -> bool {
+        let addr = VM::VMObjectModel::object_start_ref(object);
+        side_metadata::compare_exchange_atomic(MARK_BIT_SPEC, addr, UNMARKED, MARKED)
+    }
+
+    pub fn trace_object<T: TransitiveClosure>(
+        &self,
+        trace: &mut T,
+        object: ObjectReference,
+    ) -> ObjectReference {
+        if self.test_and_mark(object) {
+            trace.process_node(object);
+        }
+        object
+    }
+
+    pub fn prepare(&mut self) {
+        for (&start, block) in self.blocks.get_mut().unwrap().iter() {
+            side_metadata::bzero_metadata(MARK_BIT_SPEC, start, block.bytes);
+        }
+    }
+
+    /// Whether the object in a cell is marked. Its mark bit is at its start, which is padded past
+    /// the start of the cell if the object needs a larger alignment than the cell has.
+    #[inline]
+    fn is_cell_marked(cell: Address, cell_size: usize) -> bool {
+        (0..cell_size.min(VM::MAX_ALIGNMENT))
+            .step_by(BYTES_IN_WORD)
+            .any(|offset| side_metadata::load_atomic(MARK_BIT_SPEC, cell + offset) == MARKED)
+    }
+
+    /// Sweep all blocks. Unmarked cells are threaded onto the block's free list, blocks with
+    /// no live cells are returned to the page resource, and the remaining blocks with free
+    /// cells become available to allocators.
+    pub fn release(&mut self) {
+        let mut available = vec![vec![]; NUM_SIZE_CLASSES];
+        let mut empty_blocks = vec![];
+        for (&start, block) in self.blocks.get_mut().unwrap().iter_mut() {
+            let mut free_list = unsafe { Address::zero() };
+            let mut live_cells = 0;
+            // Build the list backwards so that allocation proceeds in address order.
+            for i in (0..block.cells()).rev() {
+                let cell = start + i * block.cell_size;
+                if Self::is_cell_marked(cell, block.cell_size) {
+                    live_cells += 1;
+                } else {
+                    unsafe { cell.store(free_list) };
+                    free_list = cell;
+                    #[cfg(feature = "global_alloc_bit")]
+                    side_metadata::bzero_metadata(alloc_bit::ALLOC_BIT_SPEC, cell, block.cell_size);
+                }
+            }
+            if live_cells == 0 {
+                empty_blocks.push(start);
+            } else {
+                block.free_list = free_list;
+                if !free_list.is_zero() && block.size_class != LARGE_CELL_CLASS {
+                    available[block.size_class].push(start);
+                }
+            }
+        }
+        for start in empty_blocks {
+            self.blocks.get_mut().unwrap().remove(&start);
+            self.pr.release_pages(start);
+        }
+        *self.available.get_mut().unwrap() = available;
+    }
+}
@@ -13,14 +13,17 @@
 /// memory).
 pub mod space;
 
-#[cfg(feature = "immortalspace")]
 pub mod immortalspace;
 
-#[cfg(feature = "copyspace")]
 pub mod copyspace;
 
-#[cfg(feature = "largeobjectspace")]
 pub mod largeobjectspace;
 
+pub mod marksweepspace;
+
+pub mod immixspace;
+
+pub mod markcompactspace;
+
 #[cfg(feature = "lockfreeimmortalspace")]
 pub mod lockfreeimmortalspace;
@@ -1,3 +1,4 @@
+use crate::util::alloc_bit;
 use crate::util::conversions::*;
 use crate::util::Address;
 use crate::util::ObjectReference;
@@ -7,9 +8,7 @@ use crate::util::heap::layout::vm_layout_constants::{AVAILABLE_END, AVAILABLE_ST
 use crate::util::heap::{PageResource, VMRequest};
 use crate::vm::{ActivePlan, Collection, ObjectModel};
 
-use crate::plan::Plan;
-
-use crate::util::constants::LOG_BYTES_IN_MBYTE;
+use crate::util::constants::{LOG_BYTES_IN_MBYTE, MAX_INT};
 use crate::util::conversions;
 use crate::util::OpaquePointer;
 
@@ -19,11 +18,15 @@ use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::map::Map;
 use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
 use crate::util::heap::layout::vm_layout_constants::MAX_CHUNKS;
+use crate::util::heap::snapshot::{unsupported, SpaceSnapshot};
 use crate::util::heap::space_descriptor::SpaceDescriptor;
 use crate::util::heap::HeapMeta;
+use crate::util::side_metadata::{SideMetadataContext, SideMetadataSpec};
 
 use crate::vm::VMBinding;
+use std::io;
 use std::marker::PhantomData;
+use std::slice::Iter;
 
 use downcast_rs::Downcast;
 
@@ -240,6 +243,10 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         let pr = self.get_page_resource();
         let pages_reserved = pr.reserve_pages(pages);
         trace!("Pages reserved");
+        VM::VMActivePlan::global()
+            .base()
+            .gc_trigger()
+            .on_alloc(pages_reserved);
 
         trace!("Polling ..");
 
@@ -340,12 +347,84 @@ pub trait Space<VM: VMBinding>: 'static + SFT + Sync + Downcast {
         self.common()
             .mmapper
             .mark_as_mapped(self.common().start, self.common().extent);
+        self.common().metadata.ensure_mapped(
+            self.common().mmapper,
+            self.common().start,
+            self.common().extent,
+        );
     }
 
     fn reserved_pages(&self) -> usize {
         self.get_page_resource().reserved_pages()
     }
 
+    /// The chunks of this space that are mapped, in address order. The side metadata of these
+    /// chunks is mapped too.
+    fn mapped_chunks(&self) -> Vec<Address> {
+        use crate::util::heap::layout::Mmapper;
+        let common = self.common();
+        let mut chunks = vec![];
+        if common.contiguous {
+            // A free-list page resource does not hand out the chunks of a contiguous space in
+            // address order, so every chunk of the extent is checked.
+            for offset in (0..common.extent).step_by(BYTES_IN_CHUNK) {
+                if common.mmapper.is_mapped_address(common.start + offset) {
+                    chunks.push(common.start + offset);
+                }
+            }
+        } else {
+            chunks = common.mapped_discontiguous_chunks();
+        }
+        chunks
+    }
+
+    /// Visit the objects allocated in this space that have not been reclaimed yet, i.e. live
+    /// objects and the dead objects that the last GC did not sweep. By default this walks the
+    /// allocation bits of the mapped chunks, which every space keeps from `post_alloc()` and
+    /// `post_copy()` until the object is reclaimed if the `global_alloc_bit` feature is enabled.
+    /// No GC may be in progress.
+    fn enumerate_objects(&self, f: &mut dyn FnMut(ObjectReference)) {
+        let mut visitor = alloc_bit::ObjectVisitor(f);
+        for chunk in self.mapped_chunks() {
+            alloc_bit::scan_objects::<VM, _>(chunk, chunk + BYTES_IN_CHUNK, &mut visitor);
+        }
+    }
+
+    /// Find the object whose memory contains `addr`, an address in this space. By default the
+    /// allocation bits are searched backward from `addr` for the nearest object, which is the
+    /// object if `addr` is within its size. The search takes time in proportion to the distance
+    /// from that object's reference, so it goes back no further than the largest object that the
+    /// plan allocates outside the large object space, or if the plan does not limit their size,
+    /// than the start of the chunk of `addr`. Spaces of large objects should override this.
+    fn find_base_object(&self, addr: Address) -> Option<ObjectReference> {
+        let common = self.common();
+        let max_object_bytes = VM::VMActivePlan::global()
+            .constraints()
+            .max_non_los_default_alloc_bytes;
+        let mut max_search_bytes = if max_object_bytes < MAX_INT {
+            max_object_bytes
+        } else {
+            addr - chunk_align_down(addr)
+        };
+        if common.contiguous {
+            max_search_bytes = max_search_bytes.min(addr - common.start);
+        }
+        alloc_bit::find_alloced_object_below(common.mmapper, addr, max_search_bytes)
+            .filter(|&object| object_contains::<VM>(object, addr))
+    }
+
+    /// What a heap snapshot keeps of this space, or an error if the space cannot be restored
+    /// from a snapshot. Spaces that keep state outside of their memory and their page resource,
+    /// e.g. a table of blocks, have to save it here.
+    fn snapshot(&self) -> io::Result<SpaceSnapshot> {
+        Err(unsupported(self.get_name()))
+    }
+
+    /// Restore the state saved by `snapshot()`, after the memory of the snapshot is mapped.
+    fn restore(&self, _state: &mut Iter<usize>) -> io::Result<()> {
+        Err(unsupported(self.get_name()))
+    }
+
     fn get_name(&self) -> &'static str {
         self.common().name
     }
@@ -444,6 +523,9 @@ pub struct CommonSpace<VM: VMBinding> {
     pub vm_map: &'static VMMap,
     pub mmapper: &'static Mmapper,
 
+    /// The side metadata of this space, which is mapped along with the memory of the space.
+    pub metadata: SideMetadataContext,
+
     p: PhantomData<VM>,
 }
 
@@ -453,6 +535,8 @@ pub struct SpaceOptions {
     pub immortal: bool,
     pub zeroed: bool,
     pub vmrequest: VMRequest,
+    /// The side metadata that only this space keeps.
+    pub local_side_metadata_specs: Vec<SideMetadataSpec>,
 }
 
 /// Print debug info for SFT. Should be false when committed.
@@ -480,6 +564,7 @@ impl<VM: VMBinding> CommonSpace<VM> {
             head_discontiguous_region: unsafe { Address::zero() },
             vm_map,
             mmapper,
+            metadata: SideMetadataContext::new::<VM>(opt.local_side_metadata_specs),
             p: PhantomData,
         };
 
@@ -556,6 +641,31 @@ impl<VM: VMBinding> CommonSpace<VM> {
     pub fn vm_map(&self) -> &'static VMMap {
         self.vm_map
     }
+
+    /// The mapped chunks of the contiguous regions of a discontiguous space, in the order of the
+    /// regions.
+    pub fn mapped_discontiguous_chunks(&self) -> Vec<Address> {
+        use crate::util::heap::layout::Mmapper;
+        let mut chunks = vec![];
+        let mut region = self.head_discontiguous_region;
+        while !region.is_zero() {
+            let size = self.vm_map().get_contiguous_region_size(region);
+            for offset in (0..size).step_by(BYTES_IN_CHUNK) {
+                if self.mmapper.is_mapped_address(region + offset) {
+                    chunks.push(region + offset);
+                }
+            }
+            region = self.vm_map().get_next_contiguous_region(region);
+        }
+        chunks
+    }
+}
+
+/// Whether `addr` is in the memory of `object`, i.e. between `object_start_ref()` and the end of
+/// the object's current size.
+pub fn object_contains<VM: VMBinding>(object: ObjectReference, addr: Address) -> bool {
+    let start = VM::VMObjectModel::object_start_ref(object);
+    addr >= start && addr < start + VM::VMObjectModel::get_current_size(object)
 }
 
 fn get_frac_available(frac: f32) -> usize {
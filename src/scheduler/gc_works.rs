@@ -1,5 +1,8 @@
 use super::*;
 use crate::plan::global::GcStatus;
+use crate::policy::space::Space;
+use crate::util::alloc::linear_scan::LinearScan;
+use crate::util::conversions::pages_to_bytes;
 use crate::util::*;
 use crate::vm::*;
 use crate::*;
@@ -14,6 +17,10 @@ unsafe impl Sync for ScheduleCollection {}
 
 impl<VM: VMBinding> GCWork<VM> for ScheduleCollection {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        mmtk.plan.base().gc_trigger().on_gc_start();
+        mmtk.plan
+            .base()
+            .set_bytes_before_gc(pages_to_bytes(mmtk.plan.get_pages_used()));
         mmtk.plan.schedule_collection(worker.scheduler());
     }
 }
@@ -43,7 +50,7 @@ impl<P: Plan> GCWork<P::VM> for Prepare<P> {
                 .add(PrepareMutator::<P::VM>::new(mutator));
         }
         for w in &mmtk.scheduler.worker_group().workers {
-            w.local_works.add(PrepareCollector::default());
+            w.add_local_work(PrepareCollector::default());
         }
     }
 }
@@ -52,13 +59,13 @@ impl<P: Plan> GCWork<P::VM> for Prepare<P> {
 pub struct PrepareMutator<VM: VMBinding> {
     // The mutator reference has static lifetime.
     // It is safe because the actual lifetime of this work-packet will not exceed the lifetime of a GC.
-    pub mutator: &'static mut Mutator<SelectedPlan<VM>>,
+    pub mutator: &'static mut Mutator<VM>,
 }
 
 unsafe impl<VM: VMBinding> Sync for PrepareMutator<VM> {}
 
 impl<VM: VMBinding> PrepareMutator<VM> {
-    pub fn new(mutator: &'static mut Mutator<SelectedPlan<VM>>) -> Self {
+    pub fn new(mutator: &'static mut Mutator<VM>) -> Self {
         Self { mutator }
     }
 }
@@ -95,6 +102,7 @@ impl<P: Plan> Release<P> {
 impl<P: Plan> GCWork<P::VM> for Release<P> {
     fn do_work(&mut self, worker: &mut GCWorker<P::VM>, mmtk: &'static MMTK<P::VM>) {
         trace!("Release Global");
+        <P::VM as VMBinding>::VMCollection::closure_done(&self.plan.base().gc_info());
         self.plan.release(worker.tls);
         for mutator in <P::VM as VMBinding>::VMActivePlan::mutators() {
             mmtk.scheduler
@@ -102,23 +110,21 @@ impl<P: Plan> GCWork<P::VM> for Release<P> {
                 .add(ReleaseMutator::<P::VM>::new(mutator));
         }
         for w in &mmtk.scheduler.worker_group().workers {
-            w.local_works.add(ReleaseCollector::default());
+            w.add_local_work(ReleaseCollector::default());
         }
-        // TODO: Process weak references properly
-        mmtk.reference_processors.clear();
     }
 }
 
 pub struct ReleaseMutator<VM: VMBinding> {
     // The mutator reference has static lifetime.
     // It is safe because the actual lifetime of this work-packet will not exceed the lifetime of a GC.
-    pub mutator: &'static mut Mutator<SelectedPlan<VM>>,
+    pub mutator: &'static mut Mutator<VM>,
 }
 
 unsafe impl<VM: VMBinding> Sync for ReleaseMutator<VM> {}
 
 impl<VM: VMBinding> ReleaseMutator<VM> {
-    pub fn new(mutator: &'static mut Mutator<SelectedPlan<VM>>) -> Self {
+    pub fn new(mutator: &'static mut Mutator<VM>) -> Self {
         Self { mutator }
     }
 }
@@ -162,6 +168,7 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for StopMutators<E> {
             <E::VM as VMBinding>::VMCollection::stop_all_mutators::<E>(worker.tls);
             trace!("stop_all_mutators end");
             mmtk.scheduler.notify_mutators_paused(mmtk);
+            <E::VM as VMBinding>::VMCollection::mutators_stopped(&mmtk.plan.base().gc_info());
             if <E::VM as VMBinding>::VMScanning::SCAN_MUTATORS_IN_SAFEPOINT {
                 // Prepare mutators if necessary
                 // FIXME: This test is probably redundant. JikesRVM requires to call `prepare_mutator` once after mutators are paused
@@ -201,8 +208,20 @@ pub struct EndOfGC;
 
 impl<VM: VMBinding> GCWork<VM> for EndOfGC {
     fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        mmtk.plan
+            .base()
+            .gc_trigger()
+            .on_gc_end(mmtk.plan.get_pages_reserved());
+        mmtk.plan.base().uncommitter.on_gc_end();
         mmtk.plan.common().base.set_gc_status(GcStatus::NotInGC);
+        // The objects of the conservative roots may only be pinned while the mutators are stopped.
+        for object in mmtk.plan.base().conservative_pins.lock().unwrap().drain(..) {
+            pin_bit::unpin_object(object);
+        }
         <VM as VMBinding>::VMCollection::resume_mutators(worker.tls);
+        mmtk.plan
+            .base()
+            .gc_finished(pages_to_bytes(mmtk.plan.get_pages_used()));
     }
 }
 
@@ -221,14 +240,16 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanStackRoots<E> {
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
         trace!("ScanStackRoots");
         <E::VM as VMBinding>::VMScanning::scan_thread_roots::<E>();
+        // Hand the remembered sets of the mutators to this GC, as `ScanStackRoot` does.
+        for mutator in <E::VM as VMBinding>::VMActivePlan::mutators() {
+            mutator.flush();
+        }
         <E::VM as VMBinding>::VMScanning::notify_initial_thread_scan_complete(false, worker.tls);
         mmtk.plan.common().base.set_gc_status(GcStatus::GcProper);
     }
 }
 
-pub struct ScanStackRoot<Edges: ProcessEdgesWork>(
-    pub &'static mut Mutator<SelectedPlan<Edges::VM>>,
-);
+pub struct ScanStackRoot<Edges: ProcessEdgesWork>(pub &'static mut Mutator<Edges::VM>);
 
 impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanStackRoot<E> {
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
@@ -269,6 +290,38 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanVMSpecificRoots<E> {
     }
 }
 
+/// Trace the objects that conservative roots refer to. The objects are pinned when the roots are
+/// reported, before the closure starts, so that the trace keeps them in place and the VM does not
+/// need to update the roots. The words that are not valid objects are dropped then, so internal
+/// pointers are not roots: the VM has to resolve them with `find_base_object()` first.
+pub struct ProcessConservativeRoots<E: ProcessEdgesWork> {
+    objects: Vec<ObjectReference>,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ProcessConservativeRoots<E> {
+    pub fn new(objects: Vec<ObjectReference>) -> Self {
+        Self {
+            objects,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessConservativeRoots<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessConservativeRoots");
+        // The roots are not slots of the VM, and the objects do not move, so the buffer of the
+        // objects serves as the slots.
+        let edges = self.objects.iter().map(Address::from_ref).collect();
+        GCWork::do_work(&mut E::new(edges, true), worker, mmtk);
+        debug_assert!(self
+            .objects
+            .iter()
+            .all(|&object| pin_bit::is_pinned(object)));
+    }
+}
+
 pub struct ProcessEdgesBase<E: ProcessEdgesWork> {
     pub edges: Vec<Address>,
     pub nodes: Vec<ObjectReference>,
@@ -311,8 +364,8 @@ impl<E: ProcessEdgesWork> ProcessEdgesBase<E> {
         self.mmtk.unwrap()
     }
     #[inline]
-    pub fn plan(&self) -> &'static SelectedPlan<E::VM> {
-        &self.mmtk.unwrap().plan
+    pub fn plan(&self) -> &'static dyn Plan<VM = E::VM> {
+        &*self.mmtk.unwrap().plan
     }
 }
 
@@ -327,6 +380,15 @@ pub trait ProcessEdgesWork:
     fn new(edges: Vec<Address>, roots: bool) -> Self;
     fn trace_object(&mut self, object: ObjectReference) -> ObjectReference;
 
+    /// Set the MMTk instance and the worker that run this packet, before any edge is processed.
+    /// Plans override this to resolve their concrete plan type once per packet, instead of once
+    /// per edge.
+    #[inline]
+    fn set_context(&mut self, mmtk: &'static MMTK<Self::VM>, worker: &mut GCWorker<Self::VM>) {
+        self.mmtk = Some(mmtk);
+        self.set_worker(worker);
+    }
+
     #[inline]
     fn process_node(&mut self, object: ObjectReference) {
         if self.nodes.is_empty() {
@@ -381,8 +443,7 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for E {
     #[inline]
     default fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
         trace!("ProcessEdgesWork");
-        self.mmtk = Some(mmtk);
-        self.set_worker(worker);
+        self.set_context(mmtk, worker);
         self.process_edges();
         if !self.nodes.is_empty() {
             self.flush();
@@ -417,6 +478,205 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanObjects<E> {
     }
 }
 
+/// Run `process` with a trace of type `E`, and scan the objects that the trace reaches. Their
+/// closure is computed in `closure_stage`, which is drained before the next reference
+/// processing stage opens.
+fn trace_refs<E: ProcessEdgesWork, F: FnOnce(&mut E)>(
+    worker: &mut GCWorker<E::VM>,
+    mmtk: &'static MMTK<E::VM>,
+    process: F,
+) {
+    let mut trace = E::new(vec![], false);
+    trace.set_context(mmtk, worker);
+    process(&mut trace);
+    if !trace.nodes.is_empty() {
+        trace.flush();
+    }
+}
+
+/// Trace the values of the ephemerons whose keys are found reachable. This is the sentinel of
+/// `closure_stage`, and sets itself as the sentinel again until no more ephemerons are resolved.
+#[derive(Default)]
+pub struct ProcessEphemerons<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ProcessEphemerons<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessEphemerons<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessEphemerons");
+        let mut resolved_any = false;
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            resolved_any = mmtk.reference_processors.process_ephemerons(trace)
+        });
+        if resolved_any {
+            mmtk.scheduler
+                .closure_stage
+                .set_sentinel(ProcessEphemerons::<E>::new());
+        }
+    }
+}
+
+/// Keep the referents of reachable soft references alive. Emergency collections skip this,
+/// so that all softly reachable objects are reclaimed.
+#[derive(Default)]
+pub struct RetainSoftRefs<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> RetainSoftRefs<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for RetainSoftRefs<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("RetainSoftRefs");
+        if mmtk.plan.is_emergency_collection() {
+            return;
+        }
+        let nursery = mmtk.plan.in_nursery();
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.reference_processors.retain_soft_refs(trace, nursery)
+        });
+        // The retained referents may reach more ephemeron keys.
+        mmtk.scheduler
+            .closure_stage
+            .set_sentinel(ProcessEphemerons::<E>::new());
+    }
+}
+
+/// Clear the soft and weak references whose referents are not strongly reachable, and the
+/// ephemerons whose keys are not strongly reachable.
+#[derive(Default)]
+pub struct ProcessWeakRefs<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ProcessWeakRefs<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessWeakRefs<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessWeakRefs");
+        let nursery = mmtk.plan.in_nursery();
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.reference_processors.scan_soft_refs(trace, nursery);
+            mmtk.reference_processors.scan_weak_refs(trace, nursery);
+            mmtk.reference_processors.clear_ephemerons(trace);
+        });
+    }
+}
+
+/// Retain the finalizable objects that are unreachable, and queue them for finalization.
+#[derive(Default)]
+pub struct Finalization<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> Finalization<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for Finalization<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("Finalization");
+        if mmtk.options.no_finalizer {
+            return;
+        }
+        let nursery = mmtk.plan.in_nursery();
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.finalizable_processor
+                .lock()
+                .unwrap()
+                .scan(trace, nursery)
+        });
+    }
+}
+
+/// Update the finalizable objects to their new addresses, for plans that move objects after
+/// the closure.
+#[derive(Default)]
+pub struct ForwardFinalization<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ForwardFinalization<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ForwardFinalization<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ForwardFinalization");
+        if mmtk.options.no_finalizer {
+            return;
+        }
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.finalizable_processor.lock().unwrap().forward(trace)
+        });
+    }
+}
+
+/// Clear the phantom references whose referents are unreachable.
+#[derive(Default)]
+pub struct ProcessPhantomRefs<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ProcessPhantomRefs<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessPhantomRefs<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ProcessPhantomRefs");
+        let nursery = mmtk.plan.in_nursery();
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.reference_processors.scan_phantom_refs(trace, nursery)
+        });
+    }
+}
+
+/// Update the references and their referents to the new addresses of their objects, for
+/// plans that move objects after the closure. `E` maps each object to its new address.
+#[derive(Default)]
+pub struct ForwardRefs<E: ProcessEdgesWork>(PhantomData<E>);
+
+impl<E: ProcessEdgesWork> ForwardRefs<E> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ForwardRefs<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        trace!("ForwardRefs");
+        trace_refs::<E, _>(worker, mmtk, |trace| {
+            mmtk.reference_processors.forward_refs(trace)
+        });
+    }
+}
+
+/// Hand the references cleared by this collection to the VM.
+#[derive(Default)]
+pub struct EnqueueRefs<VM: VMBinding>(PhantomData<VM>);
+
+impl<VM: VMBinding> EnqueueRefs<VM> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<VM: VMBinding> GCWork<VM> for EnqueueRefs<VM> {
+    fn do_work(&mut self, worker: &mut GCWorker<VM>, mmtk: &'static MMTK<VM>) {
+        trace!("EnqueueRefs");
+        mmtk.reference_processors.enqueue_refs::<VM>(worker.tls);
+    }
+}
+
 #[derive(Default)]
 pub struct ProcessModBuf<E: ProcessEdgesWork> {
     modified_nodes: Vec<ObjectReference>,
@@ -437,6 +697,16 @@ impl<E: ProcessEdgesWork> ProcessModBuf<E> {
 impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessModBuf<E> {
     #[inline]
     fn do_work(&mut self, worker: &mut GCWorker<E::VM>, mmtk: &'static MMTK<E::VM>) {
+        if mmtk.plan.constraints().needs_log_bit_in_header {
+            // Unlog the remembered objects again, so that their next writes are remembered. In
+            // a full heap GC, the objects that move are unlogged when they are copied instead,
+            // as their old headers may be overwritten with forwarding pointers.
+            for object in &self.modified_nodes {
+                if mmtk.plan.in_nursery() || !object.is_movable() {
+                    header_byte::mark_as_unlogged::<E::VM>(*object);
+                }
+            }
+        }
         if mmtk.plan.in_nursery() {
             let mut modified_nodes = vec![];
             ::std::mem::swap(&mut modified_nodes, &mut self.modified_nodes);
@@ -456,3 +726,93 @@ impl<E: ProcessEdgesWork> GCWork<E::VM> for ProcessModBuf<E> {
         }
     }
 }
+
+/// Scan the objects on the dirty cards of the given spaces, and clean the cards. Without
+/// `scan`, the cards are only cleaned, e.g. by full heap GCs, which trace the whole heap anyway.
+/// One `ScanDirtyCards` packet is created for each mapped chunk of the spaces.
+pub struct ScanCardTable<E: ProcessEdgesWork> {
+    spaces: Vec<&'static dyn Space<E::VM>>,
+    scan: bool,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanCardTable<E> {
+    pub fn new(spaces: Vec<&'static dyn Space<E::VM>>, scan: bool) -> Self {
+        Self {
+            spaces,
+            scan,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanCardTable<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, _mmtk: &'static MMTK<E::VM>) {
+        trace!("ScanCardTable");
+        use crate::util::heap::layout::vm_layout_constants::BYTES_IN_CHUNK;
+        for space in &self.spaces {
+            for chunk in space.mapped_chunks() {
+                worker
+                    .scheduler()
+                    .closure_stage
+                    .add(ScanDirtyCards::<E>::new(
+                        chunk,
+                        chunk + BYTES_IN_CHUNK,
+                        self.scan,
+                    ));
+            }
+        }
+    }
+}
+
+/// Clean the dirty cards in `[start, end)`, and scan the objects on them if `scan` is set.
+pub struct ScanDirtyCards<E: ProcessEdgesWork> {
+    start: Address,
+    end: Address,
+    scan: bool,
+    phantom: PhantomData<E>,
+}
+
+impl<E: ProcessEdgesWork> ScanDirtyCards<E> {
+    pub fn new(start: Address, end: Address, scan: bool) -> Self {
+        Self {
+            start,
+            end,
+            scan,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// Collects the objects found by a linear scan.
+struct CollectObjects(Vec<ObjectReference>);
+
+impl LinearScan for CollectObjects {
+    fn scan<VM: VMBinding>(&mut self, object: ObjectReference) {
+        self.0.push(object);
+    }
+}
+
+impl<E: ProcessEdgesWork> GCWork<E::VM> for ScanDirtyCards<E> {
+    fn do_work(&mut self, worker: &mut GCWorker<E::VM>, _mmtk: &'static MMTK<E::VM>) {
+        trace!("ScanDirtyCards");
+        let mut objects = CollectObjects(vec![]);
+        let mut card = self.start;
+        while card < self.end {
+            if card_table::attempt_clean(card) && self.scan {
+                alloc_bit::scan_objects::<E::VM, _>(
+                    card,
+                    card + card_table::BYTES_IN_CARD,
+                    &mut objects,
+                );
+            }
+            card += card_table::BYTES_IN_CARD;
+        }
+        if !objects.0.is_empty() {
+            worker
+                .scheduler()
+                .closure_stage
+                .add(ScanObjects::<E>::new(objects.0, false));
+        }
+    }
+}
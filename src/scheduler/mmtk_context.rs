@@ -1,24 +1,55 @@
 use super::*;
 use crate::util::OpaquePointer;
 use crate::vm::{Collection, VMBinding};
-use crate::{CopyContext, Plan, SelectedPlan, MMTK};
+use crate::{CopyContext, MMTK};
+use downcast_rs::Downcast;
 
-trait GCWorkerLocal<VM: VMBinding> = WorkerLocal<MMTK<VM>>;
+/// The worker-local data of a GC worker, i.e. the copy context of the plan.
+/// The plan is only known at run time, so workers hold their copy context as a trait object,
+/// which plan-specific work packets downcast to the plan's `CopyContext`.
+pub trait GCWorkerLocal: Downcast + Send + Sync {
+    fn init(&mut self, tls: OpaquePointer);
+    fn prepare(&mut self);
+    fn release(&mut self);
+}
+
+impl_downcast!(GCWorkerLocal);
+
+impl<C: CopyContext> GCWorkerLocal for C {
+    fn init(&mut self, tls: OpaquePointer) {
+        CopyContext::init(self, tls);
+    }
+    fn prepare(&mut self) {
+        CopyContext::prepare(self);
+    }
+    fn release(&mut self) {
+        CopyContext::release(self);
+    }
+}
+
+pub type GCWorkerLocalPtr = Box<dyn GCWorkerLocal>;
 
 /// The global context for mmtk is `MMTK<VM>`.
 impl<VM: VMBinding> Context for MMTK<VM> {
-    type WorkerLocal = <SelectedPlan<VM> as Plan>::CopyContext;
+    type WorkerLocal = GCWorkerLocalPtr;
     fn spawn_worker(worker: &GCWorker<VM>, tls: OpaquePointer, _context: &'static Self) {
         VM::VMCollection::spawn_worker_thread(tls, Some(worker));
     }
 }
 
-/// Each GC should define their own Worker-local data in `CopyContext`.
-impl<VM: VMBinding> WorkerLocal<MMTK<VM>> for <SelectedPlan<VM> as Plan>::CopyContext {
+/// Each GC should define their own Worker-local data in `CopyContext`, and create it in `Plan::create_worker_local()`.
+impl<VM: VMBinding> WorkerLocal<MMTK<VM>> for GCWorkerLocalPtr {
     fn new(mmtk: &'static MMTK<VM>) -> Self {
-        <<SelectedPlan<VM> as Plan>::CopyContext as CopyContext>::new(mmtk)
+        mmtk.plan.create_worker_local(mmtk)
     }
     fn init(&mut self, tls: OpaquePointer) {
-        CopyContext::init(self, tls);
+        GCWorkerLocal::init(&mut **self, tls);
+    }
+}
+
+impl<VM: VMBinding> GCWorker<VM> {
+    /// Get the copy context of this worker as the concrete type used by the current plan.
+    pub fn copy_context<C: CopyContext<VM = VM>>(&mut self) -> &mut C {
+        self.local().downcast_mut::<C>().unwrap()
     }
 }
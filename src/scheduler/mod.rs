@@ -7,6 +7,7 @@ mod scheduler;
 pub mod stat;
 mod work;
 mod work_bucket;
+mod work_trace;
 mod worker;
 
 pub use context::*;
@@ -1,16 +1,22 @@
+use super::gc_works::*;
 use super::stat::SchedulerStat;
 use super::work::Work;
 use super::work_bucket::*;
-use super::worker::{Worker, WorkerGroup};
+use super::work_trace::{write_chrome_trace, ThreadTrace};
+use super::worker::{Worker, WorkerGroup, WorkerMonitor};
 use super::*;
 use crate::mmtk::MMTK;
-use crate::plan::Plan;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
-use std::collections::HashMap;
-use std::sync::atomic::Ordering;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
-use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+/// The number of stop-the-world stages, i.e. the buckets that open in order
+const NUM_STAGES: usize = 12;
 
 pub enum CoordinatorMessage<C: Context> {
     Work(Box<dyn CoordinatorWork<C>>),
@@ -24,6 +30,20 @@ pub struct Scheduler<C: Context> {
     /// Works that are scheduable within Stop-the-world
     pub prepare_stage: WorkBucket<C>,
     pub closure_stage: WorkBucket<C>,
+    /// Works for reference processing, which run in order after the closure:
+    /// retaining soft referents, clearing soft and weak references, retaining finalizable
+    /// objects, and then clearing phantom references
+    pub soft_ref_closure_stage: WorkBucket<C>,
+    pub weak_ref_stage: WorkBucket<C>,
+    pub final_ref_stage: WorkBucket<C>,
+    pub phantom_ref_stage: WorkBucket<C>,
+    /// Works for compacting plans, which run in order after the closure:
+    /// computing forwarding addresses, forwarding the weakly held references,
+    /// updating the other references, and moving objects
+    pub calculate_forwarding_stage: WorkBucket<C>,
+    pub weak_ref_forwarding_stage: WorkBucket<C>,
+    pub ref_forwarding_stage: WorkBucket<C>,
+    pub compact_stage: WorkBucket<C>,
     pub release_stage: WorkBucket<C>,
     pub final_stage: WorkBucket<C>,
     /// Works for the coordinator thread
@@ -31,7 +51,7 @@ pub struct Scheduler<C: Context> {
     /// workers
     worker_group: Option<Arc<WorkerGroup<C>>>,
     /// Condition Variable for worker synchronization
-    pub worker_monitor: Arc<(Mutex<()>, Condvar)>,
+    pub worker_monitor: Arc<WorkerMonitor>,
     context: Option<&'static C>,
     coordinator_worker: Option<RwLock<Worker<C>>>,
     /// A message channel to send new coordinator works and other actions to the coordinator thread
@@ -41,6 +61,10 @@ pub struct Scheduler<C: Context> {
     ),
     startup: Mutex<Option<Box<dyn CoordinatorWork<C>>>>,
     finalizer: Mutex<Option<Box<dyn CoordinatorWork<C>>>>,
+    /// The number of GCs started so far
+    gc_count: AtomicUsize,
+    /// The start of the times in the work trace
+    epoch: Instant,
 }
 
 unsafe impl<C: Context> Send for Scheduler<C> {}
@@ -48,14 +72,34 @@ unsafe impl<C: Context> Sync for Scheduler<C> {}
 
 impl<C: Context> Scheduler<C> {
     pub fn new() -> Arc<Self> {
-        let worker_monitor: Arc<(Mutex<()>, Condvar)> = Default::default();
+        let worker_monitor: Arc<WorkerMonitor> = Default::default();
         Arc::new(Self {
-            unconstrained_works: WorkBucket::new(true, worker_monitor.clone()), // `default_bucket` is always activated
-            prepare_stage: WorkBucket::new(false, worker_monitor.clone()),
-            closure_stage: WorkBucket::new(false, worker_monitor.clone()),
-            release_stage: WorkBucket::new(false, worker_monitor.clone()),
-            final_stage: WorkBucket::new(false, worker_monitor.clone()),
-            coordinator_works: WorkBucket::new(true, worker_monitor.clone()),
+            unconstrained_works: WorkBucket::new("unconstrained", true, worker_monitor.clone()), // `default_bucket` is always activated
+            prepare_stage: WorkBucket::new("prepare", false, worker_monitor.clone()),
+            closure_stage: WorkBucket::new("closure", false, worker_monitor.clone()),
+            soft_ref_closure_stage: WorkBucket::new(
+                "soft_ref_closure",
+                false,
+                worker_monitor.clone(),
+            ),
+            weak_ref_stage: WorkBucket::new("weak_ref", false, worker_monitor.clone()),
+            final_ref_stage: WorkBucket::new("final_ref", false, worker_monitor.clone()),
+            phantom_ref_stage: WorkBucket::new("phantom_ref", false, worker_monitor.clone()),
+            calculate_forwarding_stage: WorkBucket::new(
+                "calculate_forwarding",
+                false,
+                worker_monitor.clone(),
+            ),
+            weak_ref_forwarding_stage: WorkBucket::new(
+                "weak_ref_forwarding",
+                false,
+                worker_monitor.clone(),
+            ),
+            ref_forwarding_stage: WorkBucket::new("ref_forwarding", false, worker_monitor.clone()),
+            compact_stage: WorkBucket::new("compact", false, worker_monitor.clone()),
+            release_stage: WorkBucket::new("release", false, worker_monitor.clone()),
+            final_stage: WorkBucket::new("final", false, worker_monitor.clone()),
+            coordinator_works: WorkBucket::new("coordinator", true, worker_monitor.clone()),
             worker_group: None,
             worker_monitor,
             context: None,
@@ -63,6 +107,8 @@ impl<C: Context> Scheduler<C> {
             channel: channel(),
             startup: Mutex::new(None),
             finalizer: Mutex::new(None),
+            gc_count: AtomicUsize::new(0),
+            epoch: Instant::now(),
         })
     }
 
@@ -95,24 +141,18 @@ impl<C: Context> Scheduler<C> {
             .unwrap()
             .spawn_workers(tls, context);
 
-        self_mut.closure_stage.set_open_condition(move || {
-            self.unconstrained_works.is_drained()
-                && self.prepare_stage.is_drained()
-                && self.worker_group().all_parked()
-        });
-        self_mut.release_stage.set_open_condition(move || {
-            self.unconstrained_works.is_drained()
-                && self.prepare_stage.is_drained()
-                && self.closure_stage.is_drained()
-                && self.worker_group().all_parked()
-        });
-        self_mut.final_stage.set_open_condition(move || {
-            self.unconstrained_works.is_drained()
-                && self.prepare_stage.is_drained()
-                && self.closure_stage.is_drained()
-                && self.release_stage.is_drained()
-                && self.worker_group().all_parked()
-        });
+        for bucket in self_mut.buckets_mut() {
+            bucket.init_deques(num_workers);
+        }
+
+        // Each stage opens once all the stages before it are drained.
+        for i in 1..NUM_STAGES {
+            self_mut.stages_mut()[i].set_open_condition(move || {
+                self.unconstrained_works.is_drained()
+                    && self.stages()[..i].iter().all(|stage| stage.is_drained())
+                    && self.worker_group().all_parked()
+            });
+        }
     }
 
     pub fn initialize_worker(self: &Arc<Self>, tls: OpaquePointer) {
@@ -132,37 +172,97 @@ impl<C: Context> Scheduler<C> {
         self.worker_group.as_ref().unwrap().clone()
     }
 
+    /// The stop-the-world stages, in the order they are opened
+    fn stages(&self) -> [&WorkBucket<C>; NUM_STAGES] {
+        [
+            &self.prepare_stage,
+            &self.closure_stage,
+            &self.soft_ref_closure_stage,
+            &self.weak_ref_stage,
+            &self.final_ref_stage,
+            &self.phantom_ref_stage,
+            &self.calculate_forwarding_stage,
+            &self.weak_ref_forwarding_stage,
+            &self.ref_forwarding_stage,
+            &self.compact_stage,
+            &self.release_stage,
+            &self.final_stage,
+        ]
+    }
+
+    fn stages_mut(&mut self) -> [&mut WorkBucket<C>; NUM_STAGES] {
+        [
+            &mut self.prepare_stage,
+            &mut self.closure_stage,
+            &mut self.soft_ref_closure_stage,
+            &mut self.weak_ref_stage,
+            &mut self.final_ref_stage,
+            &mut self.phantom_ref_stage,
+            &mut self.calculate_forwarding_stage,
+            &mut self.weak_ref_forwarding_stage,
+            &mut self.ref_forwarding_stage,
+            &mut self.compact_stage,
+            &mut self.release_stage,
+            &mut self.final_stage,
+        ]
+    }
+
+    fn buckets_mut(&mut self) -> Vec<&mut WorkBucket<C>> {
+        vec![
+            &mut self.unconstrained_works,
+            &mut self.prepare_stage,
+            &mut self.closure_stage,
+            &mut self.soft_ref_closure_stage,
+            &mut self.weak_ref_stage,
+            &mut self.final_ref_stage,
+            &mut self.phantom_ref_stage,
+            &mut self.calculate_forwarding_stage,
+            &mut self.weak_ref_forwarding_stage,
+            &mut self.ref_forwarding_stage,
+            &mut self.compact_stage,
+            &mut self.release_stage,
+            &mut self.final_stage,
+            &mut self.coordinator_works,
+        ]
+    }
+
     fn all_buckets_empty(&self) -> bool {
-        self.unconstrained_works.is_empty()
-            && self.prepare_stage.is_empty()
-            && self.closure_stage.is_empty()
-            && self.release_stage.is_empty()
-            && self.final_stage.is_empty()
+        self.unconstrained_works.is_empty() && self.stages().iter().all(|stage| stage.is_empty())
     }
 
     /// Open buckets if their conditions are met
     fn update_buckets(&self) {
         let mut buckets_updated = false;
-        buckets_updated |= self.prepare_stage.update();
-        buckets_updated |= self.closure_stage.update();
-        buckets_updated |= self.release_stage.update();
-        buckets_updated |= self.final_stage.update();
+        for stage in self.stages().iter() {
+            buckets_updated |= stage.update();
+            // The sentinel of the closure may add more closure works,
+            // so it must be scheduled before the later buckets are opened.
+            if std::ptr::eq(*stage, &self.closure_stage)
+                && self.closure_stage.is_drained()
+                && self.worker_group().all_parked()
+            {
+                buckets_updated |= self.closure_stage.schedule_sentinel();
+            }
+        }
         if buckets_updated {
             // Notify the workers for new works
-            let _guard = self.worker_monitor.0.lock().unwrap();
-            self.worker_monitor.1.notify_all();
+            self.worker_monitor.notify_all();
         }
     }
 
     /// Execute coordinator works, in the controller thread
     fn process_coordinator_work(&self, mut work: Box<dyn CoordinatorWork<C>>) {
         let mut coordinator_worker = self.coordinator_worker.as_ref().unwrap().write().unwrap();
+        coordinator_worker
+            .stat
+            .set_work_context("coordinator", self.gc_count());
         let context = self.context.unwrap();
         work.do_work_with_stat(&mut coordinator_worker, context);
     }
 
     /// Drain the message queue and execute coordinator works
     pub fn wait_for_completion(&self) {
+        self.gc_count.fetch_add(1, Ordering::SeqCst);
         // At the start of a GC, we probably already have received a `ScheduleCollection` work. Run it now.
         if let Some(initializer) = self.startup.lock().unwrap().take() {
             self.process_coordinator_work(initializer);
@@ -177,7 +277,7 @@ impl<C: Context> Scheduler<C> {
                     self.update_buckets();
                 }
             }
-            let _guard = self.worker_monitor.0.lock().unwrap();
+            let _guard = self.worker_monitor.lock.lock().unwrap();
             if self.worker_group().all_parked() && self.all_buckets_empty() {
                 break;
             }
@@ -196,24 +296,20 @@ impl<C: Context> Scheduler<C> {
         if let Some(finalizer) = self.finalizer.lock().unwrap().take() {
             self.process_coordinator_work(finalizer);
         }
-        debug_assert!(!self.prepare_stage.is_activated());
-        debug_assert!(!self.closure_stage.is_activated());
-        debug_assert!(!self.final_stage.is_activated());
-        debug_assert!(!self.release_stage.is_activated());
+        debug_assert!(self.stages().iter().all(|stage| !stage.is_activated()));
     }
 
     pub fn deactivate_all(&self) {
-        self.prepare_stage.deactivate();
-        self.closure_stage.deactivate();
-        self.release_stage.deactivate();
-        self.final_stage.deactivate();
+        for stage in self.stages().iter() {
+            stage.deactivate();
+        }
     }
 
     pub fn reset_state(&self) {
-        // self.prepare_stage.deactivate();
-        self.closure_stage.deactivate();
-        self.release_stage.deactivate();
-        self.final_stage.deactivate();
+        // The prepare stage is left open.
+        for stage in self.stages()[1..].iter() {
+            stage.deactivate();
+        }
     }
 
     pub fn add_coordinator_work(&self, work: impl CoordinatorWork<C>, worker: &Worker<C>) {
@@ -223,40 +319,38 @@ impl<C: Context> Scheduler<C> {
             .unwrap();
     }
 
+    /// Pop a work packet from the first bucket that has one. Returns the packet, the name of
+    /// its bucket, and whether the bucket looks drained afterwards.
     #[inline]
-    fn pop_scheduable_work(&self, worker: &Worker<C>) -> Option<(Box<dyn Work<C>>, bool)> {
-        if let Some(work) = worker.local_works.poll() {
-            return Some((work, worker.local_works.is_empty()));
-        }
-        if let Some(work) = self.unconstrained_works.poll() {
-            return Some((work, self.unconstrained_works.is_empty()));
-        }
-        if let Some(work) = self.prepare_stage.poll() {
-            return Some((work, self.prepare_stage.is_empty()));
-        }
-        if let Some(work) = self.closure_stage.poll() {
-            return Some((work, self.closure_stage.is_empty()));
+    fn pop_scheduable_work(
+        &self,
+        worker: &Worker<C>,
+    ) -> Option<(Box<dyn Work<C>>, &'static str, bool)> {
+        if let Some(work) = worker.local_works.steal().success() {
+            return Some((work, "local", false));
         }
-        if let Some(work) = self.release_stage.poll() {
-            return Some((work, self.release_stage.is_empty()));
-        }
-        if let Some(work) = self.final_stage.poll() {
-            return Some((work, self.final_stage.is_empty()));
+        let stages = self.stages();
+        for bucket in std::iter::once(&&self.unconstrained_works).chain(stages.iter()) {
+            if let Some(work) = bucket.poll(worker) {
+                // Only look at the other deques once this worker has run out of packets
+                let drained = bucket.is_locally_empty(worker) && bucket.is_empty();
+                return Some((work, bucket.name(), drained));
+            }
         }
         None
     }
 
     /// Get a scheduable work. Called by workers
     #[inline]
-    pub fn poll(&self, worker: &Worker<C>) -> Box<dyn Work<C>> {
-        let work = if let Some((work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
+    pub fn poll(&self, worker: &Worker<C>) -> (Box<dyn Work<C>>, &'static str) {
+        let work = if let Some((work, bucket, bucket_is_empty)) = self.pop_scheduable_work(worker) {
             if bucket_is_empty {
                 worker
                     .sender
                     .send(CoordinatorMessage::BucketDrained)
                     .unwrap();
             }
-            work
+            (work, bucket)
         } else {
             self.poll_slow(worker)
         };
@@ -264,19 +358,22 @@ impl<C: Context> Scheduler<C> {
     }
 
     #[cold]
-    fn poll_slow(&self, worker: &Worker<C>) -> Box<dyn Work<C>> {
+    fn poll_slow(&self, worker: &Worker<C>) -> (Box<dyn Work<C>>, &'static str) {
         debug_assert!(!worker.is_parked());
-        let mut guard = self.worker_monitor.0.lock().unwrap();
+        let monitor = &self.worker_monitor;
+        let mut guard = monitor.lock.lock().unwrap();
         loop {
             debug_assert!(!worker.is_parked());
-            if let Some((work, bucket_is_empty)) = self.pop_scheduable_work(worker) {
+            monitor.will_sleep();
+            if let Some((work, bucket, bucket_is_empty)) = self.pop_scheduable_work(worker) {
+                monitor.woke_up();
                 if bucket_is_empty {
                     worker
                         .sender
                         .send(CoordinatorMessage::BucketDrained)
                         .unwrap();
                 }
-                return work;
+                return (work, bucket);
             }
             // Park this worker
             worker.parked.store(true, Ordering::SeqCst);
@@ -287,12 +384,51 @@ impl<C: Context> Scheduler<C> {
                     .unwrap();
             }
             // Wait
-            guard = self.worker_monitor.1.wait(guard).unwrap();
+            guard = monitor.cond.wait(guard).unwrap();
             // Unpark this worker
             worker.parked.store(false, Ordering::SeqCst);
+            monitor.woke_up();
         }
     }
 
+    /// Record the packets done by each worker, to be written by `write_work_trace`.
+    pub fn enable_work_trace(&self) {
+        for worker in &self.worker_group().workers {
+            worker.stat.enable_tracing();
+        }
+        let coordinator_worker = self.coordinator_worker.as_ref().unwrap().read().unwrap();
+        coordinator_worker.stat.enable_tracing();
+    }
+
+    /// Write the packets done so far as Chrome trace-event JSON.
+    pub fn write_work_trace<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let coordinator_worker = self.coordinator_worker.as_ref().unwrap().read().unwrap();
+        let worker_group = self.worker_group();
+        let traces: Vec<_> = std::iter::once(&*coordinator_worker)
+            .chain(&worker_group.workers)
+            .map(|w| w.stat.trace.lock().unwrap())
+            .collect();
+        let threads: Vec<_> = traces
+            .iter()
+            .enumerate()
+            .map(|(tid, events)| ThreadTrace {
+                tid,
+                name: if tid == 0 {
+                    "coordinator".to_owned()
+                } else {
+                    format!("worker {}", tid - 1)
+                },
+                events,
+            })
+            .collect();
+        write_chrome_trace(out, self.epoch, &threads)
+    }
+
+    /// The number of GCs started so far.
+    pub fn gc_count(&self) -> usize {
+        self.gc_count.load(Ordering::SeqCst)
+    }
+
     pub fn enable_stat(&self) {
         for worker in &self.worker_group().workers {
             worker.stat.enable();
@@ -301,7 +437,7 @@ impl<C: Context> Scheduler<C> {
         coordinator_worker.stat.enable();
     }
 
-    pub fn statistics(&self) -> HashMap<String, String> {
+    pub fn statistics(&self) -> BTreeMap<String, String> {
         let mut summary = SchedulerStat::default();
         for worker in &self.worker_group().workers {
             summary.merge(&worker.stat);
@@ -319,7 +455,19 @@ impl<VM: VMBinding> MMTkScheduler<VM> {
         mmtk.plan.base().control_collector_context.clear_request();
         debug_assert!(!self.prepare_stage.is_activated());
         self.prepare_stage.activate();
-        let _guard = self.worker_monitor.0.lock().unwrap();
-        self.worker_monitor.1.notify_all();
+        self.worker_monitor.notify_all();
+    }
+
+    /// Schedule the reference processing and finalization works of a collection, using `E` to
+    /// trace the referents and finalizable objects that are kept alive. `E` must be the trace
+    /// used for the closure.
+    pub fn schedule_ref_processing<E: ProcessEdgesWork<VM = VM>>(&self) {
+        self.closure_stage
+            .set_sentinel(ProcessEphemerons::<E>::new());
+        self.soft_ref_closure_stage.add(RetainSoftRefs::<E>::new());
+        self.weak_ref_stage.add(ProcessWeakRefs::<E>::new());
+        self.final_ref_stage.add(Finalization::<E>::new());
+        self.phantom_ref_stage.add(ProcessPhantomRefs::<E>::new());
+        self.release_stage.add(EnqueueRefs::<VM>::new());
     }
 }
@@ -1,7 +1,20 @@
+use super::work_trace::WorkEvent;
 use std::any::TypeId;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, SystemTime};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Extract the work-packet name from the full type name.
+/// i.e. simplifies `crate::scheduler::gc_works::SomeWorkPacket<Semispace>` to `SomeWorkPacket`.
+pub(super) fn work_name(name: &str) -> String {
+    let end_index = name.find('<').unwrap_or_else(|| name.len());
+    let name = name[..end_index].to_owned();
+    match name.rfind(':') {
+        Some(start_index) => name[(start_index + 1)..end_index].to_owned(),
+        _ => name,
+    }
+}
 
 #[derive(Default)]
 pub struct SchedulerStat {
@@ -11,17 +24,6 @@ pub struct SchedulerStat {
 }
 
 impl SchedulerStat {
-    /// Extract the work-packet name from the full type name.
-    /// i.e. simplifies `crate::scheduler::gc_works::SomeWorkPacket<Semispace>` to `SomeWorkPacket`.
-    fn work_name(&self, name: &str) -> String {
-        let end_index = name.find('<').unwrap_or_else(|| name.len());
-        let name = name[..end_index].to_owned();
-        match name.rfind(':') {
-            Some(start_index) => name[(start_index + 1)..end_index].to_owned(),
-            _ => name,
-        }
-    }
-
     fn geomean(&self, values: &[f64]) -> f64 {
         // Geomean(xs, N=xs.len()) = (PI(xs))^(1/N) = e^{log{PI(xs)^(1/N)}} = e^{ (1/N) * sum_{x \in xs}{ log(x) } }
         let logs = values.iter().map(|v| v.ln());
@@ -47,17 +49,15 @@ impl SchedulerStat {
         max
     }
 
-    pub fn harness_stat(&self) -> HashMap<String, String> {
-        let mut stat = HashMap::new();
+    /// The statistics of the work packets, ordered by name.
+    pub fn harness_stat(&self) -> BTreeMap<String, String> {
+        let mut stat = BTreeMap::new();
         // Work counts
         let mut total_count = 0;
         for (t, c) in &self.work_counts {
             total_count += c;
             let n = self.work_id_name_map[t];
-            stat.insert(
-                format!("works.{}.count", self.work_name(n)),
-                format!("{}", c),
-            );
+            stat.insert(format!("works.{}.count", work_name(n)), format!("{}", c));
         }
         stat.insert("total-works.count".to_owned(), format!("{}", total_count));
         // Work execution times
@@ -74,7 +74,7 @@ impl SchedulerStat {
                     .collect::<Vec<_>>(),
             );
             stat.insert(
-                format!("works.{}.time.geomean", self.work_name(n)),
+                format!("works.{}.time.geomean", work_name(n)),
                 format!("{:.2}", geomean),
             );
         }
@@ -127,12 +127,22 @@ impl SchedulerStat {
 pub struct WorkStat {
     type_id: TypeId,
     type_name: &'static str,
-    start_time: SystemTime,
+    start_time: Instant,
 }
 
 impl WorkStat {
     #[inline(always)]
     pub fn end_of_work(&self, worker_stat: &mut WorkerLocalStat) {
+        if worker_stat.is_tracing() {
+            let event = WorkEvent {
+                type_name: self.type_name,
+                bucket: worker_stat.bucket,
+                gc: worker_stat.gc,
+                start: self.start_time,
+                end: Instant::now(),
+            };
+            worker_stat.trace.lock().unwrap().push(event);
+        }
         if !worker_stat.is_enabled() {
             return;
         };
@@ -140,7 +150,7 @@ impl WorkStat {
             .work_id_name_map
             .insert(self.type_id, self.type_name);
         *worker_stat.work_counts.entry(self.type_id).or_insert(0) += 1;
-        let duration = self.start_time.elapsed().unwrap();
+        let duration = self.start_time.elapsed();
         worker_stat
             .work_durations
             .entry(self.type_id)
@@ -155,6 +165,14 @@ pub struct WorkerLocalStat {
     work_counts: HashMap<TypeId, usize>,
     work_durations: HashMap<TypeId, Vec<Duration>>,
     enabled: AtomicBool,
+    /// Whether the packets are recorded in `trace`.
+    tracing: AtomicBool,
+    /// The timeline of the packets done by this worker.
+    pub(super) trace: Mutex<Vec<WorkEvent>>,
+    /// The bucket of the packet that is being done.
+    bucket: &'static str,
+    /// The number of the GC that the packet that is being done belongs to.
+    gc: usize,
 }
 
 impl WorkerLocalStat {
@@ -167,11 +185,25 @@ impl WorkerLocalStat {
         self.enabled.store(true, Ordering::SeqCst);
     }
     #[inline]
+    pub fn is_tracing(&self) -> bool {
+        self.tracing.load(Ordering::SeqCst)
+    }
+    #[inline]
+    pub fn enable_tracing(&self) {
+        self.tracing.store(true, Ordering::SeqCst);
+    }
+    /// Set the bucket and the GC number of the next packets, for the trace.
+    #[inline]
+    pub fn set_work_context(&mut self, bucket: &'static str, gc: usize) {
+        self.bucket = bucket;
+        self.gc = gc;
+    }
+    #[inline]
     pub fn measure_work(&mut self, work_id: TypeId, work_name: &'static str) -> WorkStat {
         WorkStat {
             type_id: work_id,
             type_name: work_name,
-            start_time: SystemTime::now(),
+            start_time: Instant::now(),
         }
     }
 }
@@ -1,10 +1,14 @@
 use super::work::Work;
+use super::worker::{current_worker_ordinal, WorkerMonitor};
 use super::*;
-use spin::RwLock;
+use crossbeam_deque::{Injector, Steal, Stealer, Worker as Deque};
 use std::cmp;
 use std::collections::BinaryHeap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The priority of work packets that are added without one.
+pub const DEFAULT_PRIORITY: usize = 1000;
 
 /// A unique work-packet id for each instance of work-packet
 #[derive(Eq, PartialEq, Clone, Copy)]
@@ -53,33 +57,66 @@ impl<C: Context> PartialOrd for PrioritizedWork<C> {
     }
 }
 
+/// A bucket of work packets.
+///
+/// Most packets have the default priority, and are kept in Chase-Lev deques: one for each GC
+/// worker, and a global injector for the packets added by other threads. A worker adds packets
+/// to its own deque and takes packets from it in LIFO order. When its deque is empty, it takes a
+/// batch of packets from the injector, or steals them from the other workers. None of these
+/// takes a lock.
+///
+/// Packets with any other priority are kept in a locked priority queue. Those with a higher
+/// priority than the default are done before the packets in the deques, and those with a lower
+/// priority after them.
 pub struct WorkBucket<C: Context> {
+    /// The name of the bucket, for the work trace
+    name: &'static str,
     active: AtomicBool,
-    /// A priority queue
-    queue: RwLock<BinaryHeap<PrioritizedWork<C>>>,
-    monitor: Arc<(Mutex<()>, Condvar)>,
+    injector: Injector<Box<dyn Work<C>>>,
+    /// The deque of each worker, by its ordinal. Only the worker uses its deque.
+    deques: Vec<Deque<Box<dyn Work<C>>>>,
+    stealers: Vec<Stealer<Box<dyn Work<C>>>>,
+    /// A priority queue, for the packets that do not have the default priority
+    prioritized: Mutex<BinaryHeap<PrioritizedWork<C>>>,
+    /// The number of packets in `prioritized`, so polling does not take its lock when it is empty
+    num_prioritized: AtomicUsize,
+    monitor: Arc<WorkerMonitor>,
     can_open: Option<Box<dyn Fn() -> bool>>,
+    /// A work packet that is scheduled when the bucket is drained. It may add more works to
+    /// the bucket and set a new sentinel, e.g. to iterate a computation to a fixpoint.
+    sentinel: Mutex<Option<Box<dyn Work<C>>>>,
 }
 
 unsafe impl<C: Context> Send for WorkBucket<C> {}
 unsafe impl<C: Context> Sync for WorkBucket<C> {}
 
 impl<C: Context> WorkBucket<C> {
-    pub fn new(active: bool, monitor: Arc<(Mutex<()>, Condvar)>) -> Self {
+    pub fn new(name: &'static str, active: bool, monitor: Arc<WorkerMonitor>) -> Self {
         Self {
+            name,
             active: AtomicBool::new(active),
-            queue: Default::default(),
+            injector: Injector::new(),
+            deques: vec![],
+            stealers: vec![],
+            prioritized: Default::default(),
+            num_prioritized: AtomicUsize::new(0),
             monitor,
             can_open: None,
+            sentinel: Mutex::new(None),
         }
     }
-    fn notify_one_worker(&self) {
-        let _guard = self.monitor.0.lock().unwrap();
-        self.monitor.1.notify_one()
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    /// Create the deques of the workers. Until then, all packets go to the injector.
+    pub fn init_deques(&mut self, num_workers: usize) {
+        self.deques = (0..num_workers).map(|_| Deque::new_lifo()).collect();
+        self.stealers = self.deques.iter().map(|d| d.stealer()).collect();
     }
-    fn notify_all_workers(&self) {
-        let _guard = self.monitor.0.lock().unwrap();
-        self.monitor.1.notify_all()
+    /// The deque of the worker on this thread, if this is a GC worker thread.
+    #[inline]
+    fn current_deque(&self) -> Option<&Deque<Box<dyn Work<C>>>> {
+        current_worker_ordinal().and_then(|ordinal| self.deques.get(ordinal))
     }
     pub fn is_activated(&self) -> bool {
         self.active.load(Ordering::SeqCst)
@@ -90,49 +127,136 @@ impl<C: Context> WorkBucket<C> {
     }
     /// Test if the bucket is drained
     pub fn is_empty(&self) -> bool {
-        self.queue.read().len() == 0
+        self.num_prioritized.load(Ordering::SeqCst) == 0
+            && self.injector.is_empty()
+            && self.stealers.iter().all(|s| s.is_empty())
     }
     pub fn is_drained(&self) -> bool {
         self.is_activated() && self.is_empty()
     }
     /// Disable the bucket
     pub fn deactivate(&self) {
+        debug_assert!(self.is_empty(), "Bucket not drained before close");
         debug_assert!(
-            self.queue.read().is_empty(),
-            "Bucket not drained before close"
+            self.sentinel.lock().unwrap().is_none(),
+            "Sentinel not scheduled before close"
         );
         self.active.store(false, Ordering::SeqCst);
     }
+    fn push(&self, priority: usize, work: Box<dyn Work<C>>) {
+        if priority != DEFAULT_PRIORITY {
+            self.prioritized
+                .lock()
+                .unwrap()
+                .push(PrioritizedWork::new(priority, work));
+            self.num_prioritized.fetch_add(1, Ordering::SeqCst);
+        } else if let Some(deque) = self.current_deque() {
+            deque.push(work);
+        } else {
+            self.injector.push(work);
+        }
+    }
     /// Add a work packet to this bucket, with a given priority
     pub fn add_with_priority<W: Work<C>>(&self, priority: usize, work: W) {
-        self.queue
-            .write()
-            .push(PrioritizedWork::new(priority, box work));
-        self.notify_one_worker(); // FIXME: Performance
+        self.push(priority, box work);
+        // Workers are woken when the bucket is activated
+        if self.is_activated() {
+            self.monitor.notify_one();
+        }
     }
     /// Add a work packet to this bucket, with a default priority (1000)
     pub fn add<W: Work<C>>(&self, work: W) {
-        self.add_with_priority(1000, work);
+        self.add_with_priority(DEFAULT_PRIORITY, work);
     }
     pub fn bulk_add(&self, priority: usize, works: Vec<Box<dyn Work<C>>>) {
-        {
-            let mut queue = self.queue.write();
-            for w in works {
-                queue.push(PrioritizedWork::new(priority, w));
+        for w in works {
+            self.push(priority, w);
+        }
+        if self.is_activated() {
+            self.monitor.notify_all();
+        }
+    }
+    /// Pop a packet from the priority queue, if its priority is higher than the default (or
+    /// lower, if `high` is false).
+    fn poll_prioritized(&self, high: bool) -> Option<Box<dyn Work<C>>> {
+        if self.num_prioritized.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        let mut queue = self.prioritized.lock().unwrap();
+        match queue.peek() {
+            Some(w) if (w.priority > DEFAULT_PRIORITY) == high => {
+                self.num_prioritized.fetch_sub(1, Ordering::SeqCst);
+                queue.pop().map(|w| w.work)
+            }
+            _ => None,
+        }
+    }
+    /// Take packets from the injector, or steal them from the other workers.
+    fn steal(&self, ordinal: usize) -> Option<Box<dyn Work<C>>> {
+        let deque = match self.deques.get(ordinal) {
+            Some(deque) => deque,
+            None => return self.injector.steal().success(),
+        };
+        loop {
+            let mut retry = false;
+            match self.injector.steal_batch_and_pop(deque) {
+                Steal::Success(work) => return Some(work),
+                Steal::Retry => retry = true,
+                Steal::Empty => {}
+            }
+            let n = self.stealers.len();
+            for i in 1..n {
+                match self.stealers[(ordinal + i) % n].steal_batch_and_pop(deque) {
+                    Steal::Success(work) => return Some(work),
+                    Steal::Retry => retry = true,
+                    Steal::Empty => {}
+                }
+            }
+            if !retry {
+                return None;
             }
         }
-        self.notify_all_workers(); // FIXME: Performance
     }
-    /// Get a work packet (with the greatest priority) from this bucket
-    pub fn poll(&self) -> Option<Box<dyn Work<C>>> {
+    /// Get a work packet for a worker: the packet with the greatest priority, or one of the
+    /// default priority from the worker's own deque, the injector or the other workers.
+    pub fn poll(&self, worker: &Worker<C>) -> Option<Box<dyn Work<C>>> {
         if !self.active.load(Ordering::SeqCst) {
             return None;
         }
-        self.queue.write().pop().map(|v| v.work)
+        if let Some(work) = self.poll_prioritized(true) {
+            return Some(work);
+        }
+        if let Some(work) = self.deques.get(worker.ordinal).and_then(|d| d.pop()) {
+            return Some(work);
+        }
+        if let Some(work) = self.steal(worker.ordinal) {
+            return Some(work);
+        }
+        self.poll_prioritized(false)
+    }
+    /// Test if the worker's own deque is empty. This is cheaper than `is_empty`.
+    pub fn is_locally_empty(&self, worker: &Worker<C>) -> bool {
+        self.deques
+            .get(worker.ordinal)
+            .map_or(true, |d| d.is_empty())
     }
     pub fn set_open_condition(&mut self, pred: impl Fn() -> bool + 'static) {
         self.can_open = Some(box pred);
     }
+    /// Set the work packet to schedule when the bucket is drained.
+    pub fn set_sentinel<W: Work<C>>(&self, work: W) {
+        *self.sentinel.lock().unwrap() = Some(box work);
+    }
+    /// Add the sentinel to the bucket, if there is one. Returns true if the sentinel is added.
+    pub fn schedule_sentinel(&self) -> bool {
+        let sentinel = self.sentinel.lock().unwrap().take();
+        if let Some(work) = sentinel {
+            self.injector.push(work);
+            true
+        } else {
+            false
+        }
+    }
     pub fn update(&self) -> bool {
         if let Some(can_open) = self.can_open.as_ref() {
             if !self.is_activated() && can_open() {
@@ -143,3 +267,47 @@ impl<C: Context> WorkBucket<C> {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    static DONE: AtomicUsize = AtomicUsize::new(0);
+
+    /// Records its priority when it is done.
+    struct Record(usize);
+
+    impl Work<()> for Record {
+        fn do_work(&mut self, _worker: &mut Worker<()>, _context: &'static ()) {
+            DONE.store(self.0, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn priorities() {
+        let scheduler = Scheduler::<()>::new();
+        let mut worker = Worker::new(0, Arc::downgrade(&scheduler), false);
+        let mut bucket = WorkBucket::new("test", true, scheduler.worker_monitor.clone());
+        bucket.init_deques(2);
+        bucket.add_with_priority(DEFAULT_PRIORITY - 1, Record(DEFAULT_PRIORITY - 1));
+        bucket.add(Record(DEFAULT_PRIORITY));
+        bucket.add(Record(DEFAULT_PRIORITY));
+        bucket.add_with_priority(DEFAULT_PRIORITY + 1, Record(DEFAULT_PRIORITY + 1));
+        let mut order = vec![];
+        while let Some(mut work) = bucket.poll(&worker) {
+            work.do_work(&mut worker, &());
+            order.push(DONE.load(Ordering::SeqCst));
+        }
+        assert_eq!(
+            order,
+            vec![
+                DEFAULT_PRIORITY + 1,
+                DEFAULT_PRIORITY,
+                DEFAULT_PRIORITY,
+                DEFAULT_PRIORITY - 1
+            ]
+        );
+        assert!(bucket.is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+//! A timeline of the work packets done by each worker, in the Chrome trace-event format, which
+//! can be loaded in Perfetto or `chrome://tracing`. Each packet is a complete (`"X"`) event on
+//! the track of its worker, with the bucket as its category and the GC number as an argument.
+
+use super::stat::work_name;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// A work packet done by a worker.
+pub struct WorkEvent {
+    pub type_name: &'static str,
+    pub bucket: &'static str,
+    pub gc: usize,
+    pub start: Instant,
+    pub end: Instant,
+}
+
+/// The events done by one thread.
+pub struct ThreadTrace<'a> {
+    pub tid: usize,
+    pub name: String,
+    pub events: &'a [WorkEvent],
+}
+
+/// Escape a string for a JSON string literal.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Write the events of the threads as a trace-event JSON object. Times are in microseconds
+/// since `epoch`.
+pub fn write_chrome_trace<W: Write>(
+    out: &mut W,
+    epoch: Instant,
+    threads: &[ThreadTrace],
+) -> io::Result<()> {
+    let micros = |t: Instant| t.saturating_duration_since(epoch).as_nanos() as f64 / 1000.0;
+    writeln!(out, "{{\"traceEvents\":[")?;
+    let mut first = true;
+    for thread in threads {
+        if !first {
+            writeln!(out, ",")?;
+        }
+        first = false;
+        write!(
+            out,
+            "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+            thread.tid,
+            escape(&thread.name)
+        )?;
+        for e in thread.events {
+            write!(
+                out,
+                ",\n{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":0,\"tid\":{},\"args\":{{\"gc\":{},\"type\":\"{}\"}}}}",
+                escape(&work_name(e.type_name)),
+                e.bucket,
+                micros(e.start),
+                micros(e.end) - micros(e.start),
+                thread.tid,
+                e.gc,
+                escape(e.type_name)
+            )?;
+        }
+    }
+    writeln!(out, "\n],\"displayTimeUnit\":\"ns\"}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn complete_events() {
+        let epoch = Instant::now();
+        let events = [WorkEvent {
+            type_name: "mmtk::plan::Prepare<Foo>",
+            bucket: "prepare",
+            gc: 1,
+            start: epoch + Duration::from_micros(10),
+            end: epoch + Duration::from_micros(25),
+        }];
+        let threads = [ThreadTrace {
+            tid: 1,
+            name: "worker 0".to_string(),
+            events: &events,
+        }];
+        let mut out = vec![];
+        write_chrome_trace(&mut out, epoch, &threads).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(json.contains("\"ph\":\"M\",\"pid\":0,\"tid\":1,\"args\":{\"name\":\"worker 0\"}"));
+        assert!(json.contains("\"cat\":\"prepare\",\"ph\":\"X\",\"ts\":10.000,\"dur\":15.000"));
+        assert!(json.trim_end().ends_with("\"displayTimeUnit\":\"ns\"}"));
+    }
+}
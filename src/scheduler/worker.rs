@@ -1,11 +1,61 @@
 use super::stat::WorkerLocalStat;
-use super::work_bucket::*;
+use super::work::Work;
 use super::*;
 use crate::mmtk::MMTK;
 use crate::util::OpaquePointer;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crossbeam_deque::Injector;
+use std::cell::Cell;
+use std::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::Sender;
-use std::sync::{Arc, Weak};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+
+thread_local! {
+    /// The ordinal of the GC worker that runs on this thread, if any.
+    static WORKER_ORDINAL: Cell<Option<usize>> = Cell::new(None);
+}
+
+/// The ordinal of the GC worker that runs on this thread, or `None` if this is not a GC worker
+/// thread. The work packets that a worker adds go to its own deques.
+#[inline]
+pub(crate) fn current_worker_ordinal() -> Option<usize> {
+    WORKER_ORDINAL.with(|ordinal| ordinal.get())
+}
+
+/// The monitor that idle workers wait on for new work packets.
+#[derive(Default)]
+pub struct WorkerMonitor {
+    pub lock: Mutex<()>,
+    pub cond: Condvar,
+    /// The number of workers that are waiting or about to wait. Adding a packet only takes the
+    /// lock to wake a worker if this is not zero.
+    sleepers: AtomicUsize,
+}
+
+impl WorkerMonitor {
+    /// Wake a waiting worker, if there is one.
+    pub fn notify_one(&self) {
+        // Pairs with the fence in `will_sleep`: either the worker sees the new packet before
+        // it waits, or we see that it waits.
+        fence(Ordering::SeqCst);
+        if self.sleepers.load(Ordering::SeqCst) != 0 {
+            let _guard = self.lock.lock().unwrap();
+            self.cond.notify_one();
+        }
+    }
+    pub fn notify_all(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.cond.notify_all();
+    }
+    /// Called with the lock held, before a worker looks for work for the last time and waits.
+    pub fn will_sleep(&self) {
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+    }
+    /// Called with the lock held, when a worker has found work or woken up.
+    pub fn woke_up(&self) {
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
 
 pub struct Worker<C: Context> {
     pub tls: OpaquePointer,
@@ -13,7 +63,9 @@ pub struct Worker<C: Context> {
     pub parked: AtomicBool,
     scheduler: Arc<Scheduler<C>>,
     local: Option<C::WorkerLocal>,
-    pub local_works: WorkBucket<C>,
+    /// Work packets that must be done by this worker, e.g. to prepare its local state. They are
+    /// done before any other packet, and are never stolen.
+    pub local_works: Injector<Box<dyn Work<C>>>,
     pub sender: Sender<CoordinatorMessage<C>>,
     pub stat: WorkerLocalStat,
     context: Option<&'static C>,
@@ -33,7 +85,7 @@ impl<C: Context> Worker<C> {
             ordinal,
             parked: AtomicBool::new(true),
             local: None,
-            local_works: WorkBucket::new(true, scheduler.worker_monitor.clone()),
+            local_works: Injector::new(),
             sender: scheduler.channel.0.clone(),
             scheduler,
             stat: Default::default(),
@@ -63,6 +115,13 @@ impl<C: Context> Worker<C> {
         self.tls = tls;
     }
 
+    /// Add a work packet that must be done by this worker.
+    pub fn add_local_work(&self, work: impl Work<C>) {
+        self.local_works.push(box work);
+        // Wake this worker, whichever worker is woken by `notify_one`
+        self.scheduler.worker_monitor.notify_all();
+    }
+
     pub fn do_work(&'static mut self, mut work: impl Work<C>) {
         work.do_work(self, self.context.unwrap());
     }
@@ -72,10 +131,13 @@ impl<C: Context> Worker<C> {
         self.local = Some(C::WorkerLocal::new(context));
         let tls = self.tls;
         self.local().init(tls);
+        WORKER_ORDINAL.with(|ordinal| ordinal.set(Some(self.ordinal)));
         self.parked.store(false, Ordering::SeqCst);
         loop {
-            let mut work = self.scheduler().poll(self);
+            let (mut work, bucket) = self.scheduler().poll(self);
             debug_assert!(!self.is_parked());
+            let gc = self.scheduler().gc_count();
+            self.stat.set_work_context(bucket, gc);
             work.do_work_with_stat(self, context);
         }
     }
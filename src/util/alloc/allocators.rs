@@ -1,14 +1,17 @@
 use std::mem::MaybeUninit;
 
-use crate::plan::selected_plan::SelectedPlan;
+use crate::plan::Plan;
 use crate::policy::largeobjectspace::LargeObjectSpace;
+use crate::policy::marksweepspace::MarkSweepSpace;
 use crate::policy::space::Space;
+use crate::util::alloc::FreeListAllocator;
 use crate::util::alloc::{Allocator, BumpAllocator, LargeObjectAllocator};
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 
 const MAX_BUMP_ALLOCATORS: usize = 5;
 const MAX_LARGE_OBJECT_ALLOCATORS: usize = 1;
+const MAX_FREE_LIST_ALLOCATORS: usize = 1;
 
 // The allocators set owned by each mutator. We provide a fixed number of allocators for each allocator type in the mutator,
 // and each plan will select part of the allocators to use.
@@ -18,6 +21,7 @@ const MAX_LARGE_OBJECT_ALLOCATORS: usize = 1;
 pub struct Allocators<VM: VMBinding> {
     pub bump_pointer: [MaybeUninit<BumpAllocator<VM>>; MAX_BUMP_ALLOCATORS],
     pub large_object: [MaybeUninit<LargeObjectAllocator<VM>>; MAX_LARGE_OBJECT_ALLOCATORS],
+    pub free_list: [MaybeUninit<FreeListAllocator<VM>>; MAX_FREE_LIST_ALLOCATORS],
 }
 
 impl<VM: VMBinding> Allocators<VM> {
@@ -27,6 +31,7 @@ impl<VM: VMBinding> Allocators<VM> {
         match selector {
             AllocatorSelector::BumpPointer(index) => self.bump_pointer[index as usize].get_ref(),
             AllocatorSelector::LargeObject(index) => self.large_object[index as usize].get_ref(),
+            AllocatorSelector::FreeList(index) => self.free_list[index as usize].get_ref(),
         }
     }
 
@@ -39,17 +44,19 @@ impl<VM: VMBinding> Allocators<VM> {
         match selector {
             AllocatorSelector::BumpPointer(index) => self.bump_pointer[index as usize].get_mut(),
             AllocatorSelector::LargeObject(index) => self.large_object[index as usize].get_mut(),
+            AllocatorSelector::FreeList(index) => self.free_list[index as usize].get_mut(),
         }
     }
 
     pub fn new(
         mutator_tls: OpaquePointer,
-        plan: &'static SelectedPlan<VM>,
+        plan: &'static dyn Plan<VM = VM>,
         space_mapping: &[(AllocatorSelector, &'static dyn Space<VM>)],
     ) -> Self {
         let mut ret = Allocators {
             bump_pointer: unsafe { MaybeUninit::uninit().assume_init() },
             large_object: unsafe { MaybeUninit::uninit().assume_init() },
+            free_list: unsafe { MaybeUninit::uninit().assume_init() },
         };
 
         for &(selector, space) in space_mapping.iter() {
@@ -68,6 +75,13 @@ impl<VM: VMBinding> Allocators<VM> {
                         plan,
                     ));
                 }
+                AllocatorSelector::FreeList(index) => {
+                    ret.free_list[index as usize].write(FreeListAllocator::new(
+                        mutator_tls,
+                        Some(space.downcast_ref::<MarkSweepSpace<VM>>().unwrap()),
+                        plan,
+                    ));
+                }
             }
         }
 
@@ -86,10 +100,12 @@ impl<VM: VMBinding> Allocators<VM> {
 // enum AllocatorSelectorTag {
 //   BumpPointer,
 //   LargeObject,
+//   FreeList,
 // }
 #[repr(C, u8)]
 #[derive(Copy, Clone)]
 pub enum AllocatorSelector {
     BumpPointer(u8),
     LargeObject(u8),
+    FreeList(u8),
 }
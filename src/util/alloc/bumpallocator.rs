@@ -3,9 +3,12 @@ use crate::util::Address;
 
 use crate::util::alloc::Allocator;
 
-use crate::plan::selected_plan::SelectedPlan;
+use crate::plan::Plan;
+use crate::policy::immixspace::{self, ImmixSpace};
+use crate::policy::markcompactspace::MarkCompactSpace;
 use crate::policy::space::Space;
 use crate::util::conversions::bytes_to_pages;
+use crate::util::memory;
 use crate::util::OpaquePointer;
 use crate::vm::VMBinding;
 
@@ -19,7 +22,11 @@ pub struct BumpAllocator<VM: VMBinding> {
     cursor: Address,
     limit: Address,
     space: Option<&'static dyn Space<VM>>,
-    plan: &'static SelectedPlan<VM>,
+    plan: &'static dyn Plan<VM = VM>,
+    /// The recyclable block we are allocating into, when bound to an Immix space.
+    recyclable_block: Address,
+    /// The line to start searching for the next hole in `recyclable_block`.
+    line: usize,
 }
 
 impl<VM: VMBinding> BumpAllocator<VM> {
@@ -31,6 +38,8 @@ impl<VM: VMBinding> BumpAllocator<VM> {
     fn reset(&mut self) {
         self.cursor = unsafe { Address::zero() };
         self.limit = unsafe { Address::zero() };
+        self.recyclable_block = unsafe { Address::zero() };
+        self.line = 0;
     }
 
     pub fn rebind(&mut self, space: Option<&'static dyn Space<VM>>) {
@@ -43,7 +52,7 @@ impl<VM: VMBinding> Allocator<VM> for BumpAllocator<VM> {
     fn get_space(&self) -> Option<&'static dyn Space<VM>> {
         self.space
     }
-    fn get_plan(&self) -> &'static SelectedPlan<VM> {
+    fn get_plan(&self) -> &'static dyn Plan<VM = VM> {
         self.plan
     }
 
@@ -71,6 +80,12 @@ impl<VM: VMBinding> Allocator<VM> for BumpAllocator<VM> {
 
     fn alloc_slow_once(&mut self, size: usize, align: usize, offset: isize) -> Address {
         trace!("alloc_slow");
+        if let Some(immix) = self.space.unwrap().downcast_ref::<ImmixSpace<VM>>() {
+            return self.alloc_slow_once_immix(immix, size, align, offset);
+        }
+        if let Some(mc) = self.space.unwrap().downcast_ref::<MarkCompactSpace<VM>>() {
+            return self.alloc_slow_once_mc(mc, size, align, offset);
+        }
         // TODO: internalLimit etc.
         let block_size = (size + BLOCK_MASK) & (!BLOCK_MASK);
         let acquired_start: Address = self
@@ -100,7 +115,7 @@ impl<VM: VMBinding> BumpAllocator<VM> {
     pub fn new(
         tls: OpaquePointer,
         space: Option<&'static dyn Space<VM>>,
-        plan: &'static SelectedPlan<VM>,
+        plan: &'static dyn Plan<VM = VM>,
     ) -> Self {
         BumpAllocator {
             tls,
@@ -108,6 +123,106 @@ impl<VM: VMBinding> BumpAllocator<VM> {
             limit: unsafe { Address::zero() },
             space,
             plan,
+            recyclable_block: unsafe { Address::zero() },
+            line: 0,
+        }
+    }
+
+    /// Does an object of `size` bytes fit between the cursor and the limit?
+    fn fits(&self, size: usize, align: usize, offset: isize) -> bool {
+        !self.cursor.is_zero()
+            && align_allocation_no_fill::<VM>(self.cursor, align, offset) + size <= self.limit
+    }
+
+    /// The slow path for Immix spaces. Mutators first look for holes in recyclable blocks,
+    /// and fall back to clean blocks. During a collection, evacuation only uses clean blocks.
+    fn alloc_slow_once_immix(
+        &mut self,
+        space: &'static ImmixSpace<VM>,
+        size: usize,
+        align: usize,
+        offset: isize,
+    ) -> Address {
+        if !space.in_collection() {
+            loop {
+                if self.recyclable_block.is_zero() {
+                    match space.get_recyclable_block() {
+                        Some(block) => {
+                            self.recyclable_block = block;
+                            self.line = 0;
+                        }
+                        None => break,
+                    }
+                }
+                while let Some((start, end, next_line)) =
+                    space.get_next_hole(self.recyclable_block, self.line)
+                {
+                    self.line = next_line;
+                    // Holes still contain dead objects from before the last collection.
+                    memory::zero(start, end - start);
+                    space.common().metadata.bzero(start, end - start);
+                    self.set_limit(start, end);
+                    if self.fits(size, align, offset) {
+                        trace!("Found a hole [{}, {}) in a recyclable block", start, end);
+                        return self.alloc(size, align, offset);
+                    }
+                }
+                self.recyclable_block = unsafe { Address::zero() };
+            }
+        }
+        let block = space.get_clean_block(self.tls);
+        if block.is_zero() {
+            trace!("Failed to acquire a clean block");
+            return block;
+        }
+        trace!("Acquired a clean block {}", block);
+        self.set_limit(block, block + immixspace::BYTES_IN_BLOCK);
+        if !self.fits(size, align, offset) {
+            // Only objects larger than MAX_IMMIX_OBJECT_SIZE, which mutators allocate in the
+            // large object space, do not fit. Trying again would take another block, and so on.
+            warn!(
+                "An object of {} bytes does not fit in an Immix block with its alignment",
+                size
+            );
+            return unsafe { Address::zero() };
         }
+        self.alloc(size, align, offset)
+    }
+
+    /// The slow path for mark-compact spaces. Mutators first reuse the free ends of compacted
+    /// regions, and otherwise acquire a new region that the space keeps track of.
+    fn alloc_slow_once_mc(
+        &mut self,
+        space: &'static MarkCompactSpace<VM>,
+        size: usize,
+        align: usize,
+        offset: isize,
+    ) -> Address {
+        // The rest of the current region can still serve smaller objects.
+        if self.limit - self.cursor >= BYTES_IN_PAGE {
+            space.put_free_region(self.cursor, self.limit);
+        }
+        self.reset();
+        // Any alignment padding is smaller than `align`.
+        if let Some((start, limit)) = space.get_free_region(size + align) {
+            trace!("Reusing the free region [{}, {})", start, limit);
+            self.set_limit(start, limit);
+            debug_assert!(self.fits(size, align, offset));
+            return self.alloc(size, align, offset);
+        }
+        let block_size = (size + align + BLOCK_MASK) & (!BLOCK_MASK);
+        let acquired_start = space.acquire_region(self.tls, bytes_to_pages(block_size));
+        if acquired_start.is_zero() {
+            trace!("Failed to acquire a new region");
+            return acquired_start;
+        }
+        trace!(
+            "Acquired a new region of size {} with start address {}",
+            block_size,
+            acquired_start
+        );
+        self.set_limit(acquired_start, acquired_start + block_size);
+        debug_assert!(self.fits(size, align, offset));
+        self.alloc(size, align, offset)
     }
 }
@@ -7,7 +7,7 @@ use crate::vm::VMBinding;
 pub struct DumpLinearScan {}
 
 impl LinearScan for DumpLinearScan {
-    fn scan<VM: VMBinding>(&self, object: ObjectReference) {
+    fn scan<VM: VMBinding>(&mut self, object: ObjectReference) {
         println!(
             "[{}], SIZE = {}",
             object.to_address(),
@@ -0,0 +1,118 @@
+use crate::plan::Plan;
+use crate::policy::marksweepspace::{
+    size_class_for, MarkSweepSpace, NUM_SIZE_CLASSES, SIZE_CLASSES,
+};
+use crate::policy::space::Space;
+use crate::util::alloc::{allocator, Allocator};
+use crate::util::conversions::raw_align_up;
+use crate::util::memory;
+use crate::util::Address;
+use crate::util::OpaquePointer;
+use crate::vm::VMBinding;
+
+/// A segregated free-list allocator. The allocator keeps one free list per size class,
+/// and refills an exhausted list with a block from the `MarkSweepSpace`.
+#[repr(C)]
+pub struct FreeListAllocator<VM: VMBinding> {
+    pub tls: OpaquePointer,
+    space: Option<&'static MarkSweepSpace<VM>>,
+    plan: &'static dyn Plan<VM = VM>,
+    free_lists: [Address; NUM_SIZE_CLASSES],
+}
+
+impl<VM: VMBinding> FreeListAllocator<VM> {
+    pub fn new(
+        tls: OpaquePointer,
+        space: Option<&'static MarkSweepSpace<VM>>,
+        plan: &'static dyn Plan<VM = VM>,
+    ) -> Self {
+        FreeListAllocator {
+            tls,
+            space,
+            plan,
+            free_lists: unsafe { [Address::zero(); NUM_SIZE_CLASSES] },
+        }
+    }
+
+    /// Forget all the cells in the free lists. This must be called after each collection,
+    /// as sweeping rebuilds the free lists of all blocks, including the ones owned by this allocator.
+    pub fn reset(&mut self) {
+        self.free_lists = unsafe { [Address::zero(); NUM_SIZE_CLASSES] };
+    }
+
+    #[inline(always)]
+    fn cell_bytes(size: usize, align: usize) -> usize {
+        allocator::get_maximum_aligned_size::<VM>(
+            raw_align_up(size, VM::MIN_ALIGNMENT),
+            align,
+            VM::MIN_ALIGNMENT,
+        )
+    }
+}
+
+impl<VM: VMBinding> Allocator<VM> for FreeListAllocator<VM> {
+    fn get_tls(&self) -> OpaquePointer {
+        self.tls
+    }
+    fn get_plan(&self) -> &'static dyn Plan<VM = VM> {
+        self.plan
+    }
+
+    fn get_space(&self) -> Option<&'static dyn Space<VM>> {
+        self.space.map(|s| s as &'static dyn Space<VM>)
+    }
+
+    fn alloc(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        trace!("alloc");
+        let size_class = match size_class_for(Self::cell_bytes(size, align)) {
+            Some(size_class) => size_class,
+            None => return self.alloc_slow(size, align, offset),
+        };
+        let cell = self.free_lists[size_class];
+        if cell.is_zero() {
+            trace!(
+                "Free list for size class {} is empty, go to alloc slow path",
+                size_class
+            );
+            return self.alloc_slow(size, align, offset);
+        }
+        self.free_lists[size_class] = unsafe { cell.load::<Address>() };
+        memory::zero(cell, SIZE_CLASSES[size_class]);
+        let space = self.space.unwrap();
+        space
+            .common()
+            .metadata
+            .bzero(cell, SIZE_CLASSES[size_class]);
+        let start = allocator::align_allocation::<VM>(cell, align, offset, VM::MIN_ALIGNMENT, true);
+        space.mark_new_object(start);
+        start
+    }
+
+    fn alloc_slow_once(&mut self, size: usize, align: usize, offset: isize) -> Address {
+        trace!("alloc_slow");
+        let bytes = Self::cell_bytes(size, align);
+        let space = self.space.unwrap();
+        match size_class_for(bytes) {
+            Some(size_class) => {
+                let free_list = space.acquire_block(self.tls, size_class);
+                if free_list.is_zero() {
+                    trace!("Failed to acquire a new block");
+                    return free_list;
+                }
+                self.free_lists[size_class] = free_list;
+                self.alloc(size, align, offset)
+            }
+            None => {
+                // Cells larger than any size class sit in fresh pages of their own, and are never reused.
+                let cell = space.acquire_large_cell(self.tls, bytes);
+                if cell.is_zero() {
+                    return cell;
+                }
+                let start =
+                    allocator::align_allocation::<VM>(cell, align, offset, VM::MIN_ALIGNMENT, true);
+                space.mark_new_object(start);
+                start
+            }
+        }
+    }
+}
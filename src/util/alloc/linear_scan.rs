@@ -2,5 +2,5 @@ use crate::util::ObjectReference;
 use crate::vm::VMBinding;
 
 pub trait LinearScan {
-    fn scan<VM: VMBinding>(&self, object: ObjectReference);
+    fn scan<VM: VMBinding>(&mut self, object: ObjectReference);
 }
@@ -3,9 +3,11 @@ pub mod allocators;
 mod bumpallocator;
 pub mod dump_linear_scan;
 pub mod embedded_meta_data;
+pub mod free_list_allocator;
 pub mod large_object_allocator;
 pub mod linear_scan;
 
 pub use self::allocator::Allocator;
 pub use self::bumpallocator::BumpAllocator;
+pub use self::free_list_allocator::FreeListAllocator;
 pub use self::large_object_allocator::LargeObjectAllocator;
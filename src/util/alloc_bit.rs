@@ -0,0 +1,176 @@
+//! The allocation bitmap: one bit of global side metadata for each word of the heap, which is set
+//! for the words where an object reference points to a live or not yet reclaimed object.
+//!
+//! The bits allow walking the objects in a range of memory without parsing the heap, e.g. to scan
+//! the objects on a dirty card.
+
+use crate::util::alloc::linear_scan::LinearScan;
+use crate::util::constants::{BYTES_IN_WORD, LOG_BYTES_IN_WORD};
+use crate::util::gc_byte::SIDE_GC_BYTE_SPEC;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START, MMAP_CHUNK_BYTES};
+use crate::util::heap::layout::Mmapper as _;
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
+use crate::util::{Address, ObjectReference};
+use crate::vm::VMBinding;
+
+/// The allocation bits, laid out after the side GC byte. The side GC byte is reserved even when
+/// the VM keeps the GC byte in its object headers, so the offset does not depend on the VM.
+pub const ALLOC_BIT_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: SIDE_GC_BYTE_SPEC.next_offset(),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// Record that `object` is allocated.
+#[inline]
+pub fn set_alloc_bit(object: ObjectReference) {
+    side_metadata::store_atomic(ALLOC_BIT_SPEC, object.to_address(), 1);
+}
+
+/// Record that `object` is reclaimed.
+#[inline]
+pub fn unset_alloc_bit(object: ObjectReference) {
+    side_metadata::store_atomic(ALLOC_BIT_SPEC, object.to_address(), 0);
+}
+
+/// Whether an object is allocated at `object`.
+#[inline]
+pub fn is_alloced(object: ObjectReference) -> bool {
+    side_metadata::load_atomic(ALLOC_BIT_SPEC, object.to_address()) == 1
+}
+
+/// Whether `addr` is the reference of an allocated object. Unlike `is_alloced()`, this can be
+/// asked of any word, e.g. a word in a stack frame that may or may not be a reference: addresses
+/// outside the heap, or whose allocation bits are not mapped, are not objects.
+pub fn is_alloced_object(mmapper: &Mmapper, addr: Address) -> bool {
+    if addr < HEAP_START || addr >= HEAP_END || !addr.is_aligned_to(BYTES_IN_WORD) {
+        return false;
+    }
+    let meta = side_metadata::address_to_meta_address(ALLOC_BIT_SPEC, addr);
+    mmapper.is_mapped_address(meta) && is_alloced(unsafe { addr.to_object_reference() })
+}
+
+/// Find the allocated object with the highest reference at or below `addr`, searching at most
+/// `max_search_bytes` below it. The search stops at memory whose allocation bits are not mapped.
+pub fn find_alloced_object_below(
+    mmapper: &Mmapper,
+    addr: Address,
+    max_search_bytes: usize,
+) -> Option<ObjectReference> {
+    if addr < HEAP_START || addr >= HEAP_END {
+        return None;
+    }
+    let limit = if addr - HEAP_START > max_search_bytes {
+        addr - max_search_bytes
+    } else {
+        HEAP_START
+    };
+    let mut cursor = addr.align_down(BYTES_IN_WORD);
+    // The metadata is mapped in whole chunks, so it is checked once for each chunk.
+    let mut mapped_chunk = None;
+    while cursor >= limit {
+        let meta = side_metadata::address_to_meta_address(ALLOC_BIT_SPEC, cursor);
+        let chunk = meta.align_down(MMAP_CHUNK_BYTES);
+        if mapped_chunk != Some(chunk) {
+            if !mmapper.is_mapped_address(meta) {
+                return None;
+            }
+            mapped_chunk = Some(chunk);
+        }
+        let object = unsafe { cursor.to_object_reference() };
+        if is_alloced(object) {
+            return Some(object);
+        }
+        if cursor == HEAP_START {
+            break;
+        }
+        cursor -= BYTES_IN_WORD;
+    }
+    None
+}
+
+/// Visit the allocated objects whose references are in `[start, end)`, in address order. The
+/// allocation bits of the range must be mapped.
+pub fn scan_objects<VM: VMBinding, S: LinearScan>(start: Address, end: Address, scanner: &mut S) {
+    debug_assert!(start.is_aligned_to(1 << LOG_BYTES_IN_WORD));
+    let mut cursor = start;
+    while cursor < end {
+        let object = unsafe { cursor.to_object_reference() };
+        if is_alloced(object) {
+            scanner.scan::<VM>(object);
+        }
+        cursor += 1usize << LOG_BYTES_IN_WORD;
+    }
+}
+
+/// Adapts a closure to the `LinearScan` of `scan_objects()`.
+pub struct ObjectVisitor<'a>(pub &'a mut dyn FnMut(ObjectReference));
+
+impl LinearScan for ObjectVisitor<'_> {
+    fn scan<VM: VMBinding>(&mut self, object: ObjectReference) {
+        (self.0)(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+    use crate::util::test_util::serial_test;
+
+    // Far from the start of the heap, so the metadata does not overlap with any other test.
+    const DATA_START: Address = HEAP_START.add(0x4000_0000);
+
+    #[test]
+    fn set_and_unset() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            side_metadata::ensure_metadata_mapped(
+                ALLOC_BIT_SPEC,
+                &mmapper,
+                DATA_START,
+                BYTES_IN_PAGE,
+            );
+            let a = unsafe { DATA_START.to_object_reference() };
+            let b = unsafe { (DATA_START + 8usize).to_object_reference() };
+            set_alloc_bit(a);
+            set_alloc_bit(b);
+            unset_alloc_bit(a);
+            assert!(!is_alloced(a));
+            assert!(is_alloced(b));
+            side_metadata::bzero_metadata(ALLOC_BIT_SPEC, DATA_START, BYTES_IN_PAGE);
+            assert!(!is_alloced(b));
+        })
+    }
+
+    #[test]
+    fn find_objects_from_any_word() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            side_metadata::ensure_metadata_mapped(
+                ALLOC_BIT_SPEC,
+                &mmapper,
+                DATA_START,
+                BYTES_IN_PAGE,
+            );
+            let object = DATA_START + 16usize;
+            set_alloc_bit(unsafe { object.to_object_reference() });
+            assert!(is_alloced_object(&mmapper, object));
+            assert!(!is_alloced_object(&mmapper, object + 1usize));
+            assert!(!is_alloced_object(&mmapper, object + 8usize));
+            assert!(!is_alloced_object(&mmapper, HEAP_END));
+            // The allocation bits of this address are not mapped.
+            assert!(!is_alloced_object(&mmapper, DATA_START + (1usize << 30)));
+
+            let internal = object + 27usize;
+            assert_eq!(
+                find_alloced_object_below(&mmapper, internal, 64),
+                Some(unsafe { object.to_object_reference() })
+            );
+            assert_eq!(find_alloced_object_below(&mmapper, internal, 8), None);
+            side_metadata::bzero_metadata(ALLOC_BIT_SPEC, DATA_START, BYTES_IN_PAGE);
+        })
+    }
+}
@@ -0,0 +1,95 @@
+//! The card table: one byte of global side metadata for each card of `2^LOG_CARD_BYTES` bytes in
+//! the heap, which is dirty if an object whose reference is on the card may have been modified
+//! since the card was last scanned.
+//!
+//! A card table is a remembered set for generational plans that needs no buffer, so the barrier
+//! is a single unconditional store. A JIT can inline it as
+//!
+//! ```text
+//! *(CARD_TABLE_BASE + ((object - HEAP_START) >> LOG_CARD_BYTES)) = CARD_DIRTY
+//! ```
+//!
+//! where `CARD_TABLE_BASE` is `card_address(HEAP_START)`.
+
+use crate::util::alloc_bit::ALLOC_BIT_SPEC;
+use crate::util::constants::LOG_CARD_BYTES;
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
+use crate::util::{Address, ObjectReference};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The card table, laid out after the allocation bits.
+pub const CARD_TABLE_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: ALLOC_BIT_SPEC.next_offset(),
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_CARD_BYTES,
+};
+
+/// The bytes of memory that each card covers.
+pub const BYTES_IN_CARD: usize = 1 << LOG_CARD_BYTES;
+
+/// The value of a clean card. Cards are clean when their memory is acquired.
+pub const CARD_CLEAN: u8 = 0;
+/// The value of a dirty card.
+pub const CARD_DIRTY: u8 = 1;
+
+/// The address of the card table entry of the card that holds `addr`.
+#[inline(always)]
+pub fn card_address(addr: Address) -> Address {
+    side_metadata::address_to_meta_address(CARD_TABLE_SPEC, addr)
+}
+
+/// Mark the card of `object` as dirty. Racing marks all store the same value, so this is a plain
+/// store rather than a read-modify-write.
+#[inline(always)]
+pub fn mark_card(object: ObjectReference) {
+    unsafe { &*card_address(object.to_address()).to_ptr::<AtomicU8>() }
+        .store(CARD_DIRTY, Ordering::Relaxed);
+}
+
+/// Whether the card that holds `addr` is dirty.
+#[inline]
+pub fn is_dirty(addr: Address) -> bool {
+    side_metadata::load_atomic(CARD_TABLE_SPEC, addr) == CARD_DIRTY
+}
+
+/// Clean the card that holds `addr`. Returns `true` if the card was dirty.
+#[inline]
+pub fn attempt_clean(addr: Address) -> bool {
+    side_metadata::compare_exchange_atomic(CARD_TABLE_SPEC, addr, CARD_DIRTY, CARD_CLEAN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+    use crate::util::heap::layout::heap_layout::Mmapper;
+    use crate::util::heap::layout::vm_layout_constants::HEAP_START;
+    use crate::util::test_util::serial_test;
+
+    // Far from the start of the heap, so the metadata does not overlap with any other test.
+    const DATA_START: Address = HEAP_START.add(0x4000_0000);
+
+    #[test]
+    fn mark_and_clean() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            side_metadata::ensure_metadata_mapped(
+                CARD_TABLE_SPEC,
+                &mmapper,
+                DATA_START,
+                BYTES_IN_PAGE,
+            );
+            let card = BYTES_IN_CARD;
+            // Objects on the same card share its entry.
+            mark_card(unsafe { (DATA_START + card + 8usize).to_object_reference() });
+            assert!(!is_dirty(DATA_START));
+            assert!(is_dirty(DATA_START + card));
+            assert!(is_dirty(DATA_START + card + (card - 1)));
+            assert!(!is_dirty(DATA_START + 2 * card));
+            assert!(attempt_clean(DATA_START + card));
+            assert!(!attempt_clean(DATA_START + card));
+            assert!(!is_dirty(DATA_START + card));
+        })
+    }
+}
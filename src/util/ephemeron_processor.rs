@@ -0,0 +1,165 @@
+use std::mem;
+use std::sync::Mutex;
+
+use crate::scheduler::gc_works::ProcessEdgesWork;
+use crate::util::{Address, ObjectReference, OpaquePointer};
+use crate::vm::ReferenceGlue;
+use crate::vm::VMBinding;
+
+/// Processes ephemerons: references whose value is retained only while their key is reachable.
+/// The key of an ephemeron is its referent.
+///
+/// During the closure, `process()` is called whenever the closure is drained. It traces the
+/// values of the ephemerons whose keys have been found reachable, which may make more keys
+/// reachable, until no more ephemerons are resolved. The ephemerons whose keys are still not
+/// reachable are then cleared by `clear_unresolved()`.
+///
+/// All the ephemerons are processed in every collection, including nursery collections: a value
+/// in the nursery may be stored into an ephemeron outside the nursery.
+pub struct EphemeronProcessor {
+    sync: Mutex<EphemeronProcessorSync>,
+}
+
+struct EphemeronProcessorSync {
+    /// The ephemerons whose keys are not yet found reachable in this collection.
+    pending: Vec<ObjectReference>,
+    /// The ephemerons whose keys are found reachable in this collection.
+    resolved: Vec<ObjectReference>,
+    /// The ephemerons cleared by this collection, to be handed to the VM.
+    enqueued: Vec<ObjectReference>,
+}
+
+impl EphemeronProcessor {
+    pub fn new() -> Self {
+        EphemeronProcessor {
+            sync: Mutex::new(EphemeronProcessorSync {
+                pending: vec![],
+                resolved: vec![],
+                enqueued: vec![],
+            }),
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.pending.clear();
+        sync.resolved.clear();
+        sync.enqueued.clear();
+    }
+
+    pub fn add_candidate<VM: VMBinding>(
+        &self,
+        ephemeron: ObjectReference,
+        key: ObjectReference,
+        value: ObjectReference,
+    ) {
+        let mut sync = self.sync.lock().unwrap();
+        VM::VMReferenceGlue::set_referent(ephemeron, key);
+        VM::VMReferenceGlue::set_ephemeron_value(ephemeron, value);
+        sync.pending.push(ephemeron);
+    }
+
+    /// Trace the values of the reachable ephemerons whose keys are reachable. Returns true if
+    /// any ephemeron is resolved, in which case the closure of the traced values may resolve
+    /// more ephemerons.
+    pub fn process<E: ProcessEdgesWork>(&self, trace: &mut E) -> bool {
+        let mut sync = self.sync.lock().unwrap();
+        let sync = &mut *sync;
+        let mut resolved_any = false;
+        for ephemeron in mem::take(&mut sync.pending) {
+            // Neither the ephemeron nor its key may be reachable yet, but the closure of the
+            // values traced in this round may reach them.
+            if !ephemeron.is_live() {
+                sync.pending.push(ephemeron);
+                continue;
+            }
+            let key = <E::VM as VMBinding>::VMReferenceGlue::get_referent(ephemeron);
+            if key.is_null() {
+                // Cleared by the application. Drop the ephemeron.
+                continue;
+            }
+            if !key.is_live() {
+                sync.pending.push(ephemeron);
+                continue;
+            }
+            let new_ephemeron = trace.trace_object(ephemeron);
+            <E::VM as VMBinding>::VMReferenceGlue::set_referent(
+                new_ephemeron,
+                trace.trace_object(key),
+            );
+            let value = <E::VM as VMBinding>::VMReferenceGlue::get_ephemeron_value(new_ephemeron);
+            if !value.is_null() {
+                <E::VM as VMBinding>::VMReferenceGlue::set_ephemeron_value(
+                    new_ephemeron,
+                    trace.trace_object(value),
+                );
+            }
+            sync.resolved.push(new_ephemeron);
+            resolved_any = true;
+        }
+        trace!(
+            "ephemerons: {} resolved, {} pending",
+            sync.resolved.len(),
+            sync.pending.len()
+        );
+        resolved_any
+    }
+
+    /// Clear the reachable ephemerons whose keys are unreachable, and keep the resolved
+    /// ephemerons for the next collection.
+    pub fn clear_unresolved<E: ProcessEdgesWork>(&self, trace: &mut E) {
+        let mut sync = self.sync.lock().unwrap();
+        let sync = &mut *sync;
+        for ephemeron in mem::take(&mut sync.pending) {
+            if !ephemeron.is_live() {
+                continue;
+            }
+            let new_ephemeron = trace.trace_object(ephemeron);
+            <E::VM as VMBinding>::VMReferenceGlue::clear_referent(new_ephemeron);
+            <E::VM as VMBinding>::VMReferenceGlue::set_ephemeron_value(new_ephemeron, unsafe {
+                Address::zero().to_object_reference()
+            });
+            sync.enqueued.push(new_ephemeron);
+        }
+        sync.pending = mem::take(&mut sync.resolved);
+    }
+
+    /// Update the ephemerons, their keys and their values with the addresses the objects are
+    /// moved to. This is only needed if objects move after the ephemerons were processed.
+    pub fn forward<E: ProcessEdgesWork>(&self, trace: &mut E) {
+        let mut sync = self.sync.lock().unwrap();
+        let sync = &mut *sync;
+        for ephemeron in sync.pending.iter_mut().chain(sync.enqueued.iter_mut()) {
+            let key = <E::VM as VMBinding>::VMReferenceGlue::get_referent(*ephemeron);
+            if !key.is_null() {
+                <E::VM as VMBinding>::VMReferenceGlue::set_referent(
+                    *ephemeron,
+                    trace.trace_object(key),
+                );
+            }
+            let value = <E::VM as VMBinding>::VMReferenceGlue::get_ephemeron_value(*ephemeron);
+            if !value.is_null() {
+                <E::VM as VMBinding>::VMReferenceGlue::set_ephemeron_value(
+                    *ephemeron,
+                    trace.trace_object(value),
+                );
+            }
+            *ephemeron = trace.trace_object(*ephemeron);
+        }
+    }
+
+    /// Hand the ephemerons cleared by this collection to the VM.
+    pub fn enqueue<VM: VMBinding>(&self, tls: OpaquePointer) {
+        let mut sync = self.sync.lock().unwrap();
+        if !sync.enqueued.is_empty() {
+            VM::VMReferenceGlue::enqueue_references(&sync.enqueued, tls);
+            sync.enqueued.clear();
+        }
+    }
+}
+
+impl Default for EphemeronProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,82 @@
+use crate::scheduler::gc_works::ProcessEdgesWork;
+use crate::util::ObjectReference;
+
+/// The objects with finalizers, and the objects that are ready to be finalized.
+///
+/// A candidate that is found unreachable after the closure is moved to the ready queue and
+/// traced, so that it (and everything it points to) survives the collection. The VM then pulls
+/// it from the queue with `get_ready_object()` and runs its finalizer. Objects in the ready queue
+/// stay alive until the VM pulls them.
+#[derive(Default)]
+pub struct FinalizableProcessor {
+    /// Objects that have finalizers, and are not yet found unreachable.
+    candidates: Vec<ObjectReference>,
+    /// Index into `candidates` for the start of the candidates added since the last collection.
+    nursery_index: usize,
+    /// Objects that were found unreachable, and whose finalizers are to be run by the VM.
+    ready_for_finalize: Vec<ObjectReference>,
+}
+
+impl FinalizableProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, object: ObjectReference) {
+        self.candidates.push(object);
+    }
+
+    /// Find the candidates that are unreachable, retain them, and move them to the ready queue.
+    /// In a nursery collection, only the candidates added since the last collection are
+    /// scanned: the older candidates were not in the nursery.
+    pub fn scan<E: ProcessEdgesWork>(&mut self, trace: &mut E, nursery: bool) {
+        // The objects in the ready queue must outlive this collection too.
+        for object in self.ready_for_finalize.iter_mut() {
+            *object = trace.trace_object(*object);
+        }
+
+        let start = if nursery { self.nursery_index } else { 0 };
+        let mut to_index = start;
+        for i in start..self.candidates.len() {
+            let object = self.candidates[i];
+            if object.is_live() {
+                self.candidates[to_index] = trace.trace_object(object);
+                to_index += 1;
+            } else {
+                self.ready_for_finalize.push(trace.trace_object(object));
+            }
+        }
+        self.candidates.truncate(to_index);
+        self.nursery_index = to_index;
+        trace!(
+            "{} finalizable candidates, {} ready for finalization",
+            self.candidates.len(),
+            self.ready_for_finalize.len()
+        );
+    }
+
+    /// Update the candidates and the ready objects to their new addresses, for plans that
+    /// move objects after the closure.
+    pub fn forward<E: ProcessEdgesWork>(&mut self, trace: &mut E) {
+        for object in self
+            .candidates
+            .iter_mut()
+            .chain(self.ready_for_finalize.iter_mut())
+        {
+            *object = trace.trace_object(*object);
+        }
+    }
+
+    pub fn get_ready_object(&mut self) -> Option<ObjectReference> {
+        self.ready_for_finalize.pop()
+    }
+
+    /// Remove all the candidates, regardless of their reachability, and return them together
+    /// with the objects that are ready for finalization.
+    pub fn get_all_finalizers(&mut self) -> Vec<ObjectReference> {
+        let mut objects = std::mem::take(&mut self.ready_for_finalize);
+        objects.append(&mut self.candidates);
+        self.nursery_index = 0;
+        objects
+    }
+}
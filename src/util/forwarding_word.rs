@@ -14,7 +14,7 @@ const BEING_FORWARDED: u8 = 2;
 // ...11
 const FORWARDED: u8 = 3;
 // ...11
-const FORWARDING_MASK: u8 = 3;
+pub const FORWARDING_MASK: u8 = 3;
 #[allow(unused)]
 const FORWARDING_BITS: usize = 2;
 
@@ -80,8 +80,9 @@ pub fn forward_object<VM: VMBinding, CC: CopyContext>(
             );
         }
         None => {
-            gc_byte::write_gc_byte::<VM>(object, FORWARDED);
+            // The forwarding word must be written before other threads can see the object as forwarded.
             write_forwarding_word::<VM>(object, new_object.to_address().as_usize());
+            gc_byte::write_gc_byte::<VM>(object, FORWARDED);
         }
     };
     new_object
@@ -97,8 +98,8 @@ pub fn set_forwarding_pointer<VM: VMBinding>(object: ObjectReference, ptr: Objec
             );
         }
         None => {
-            gc_byte::write_gc_byte::<VM>(object, FORWARDED);
             write_forwarding_word::<VM>(object, ptr.to_address().as_usize());
+            gc_byte::write_gc_byte::<VM>(object, FORWARDED);
         }
     }
 }
@@ -1,3 +1,5 @@
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
 use crate::util::ObjectReference;
 use crate::vm::ObjectModel;
 use crate::vm::VMBinding;
@@ -6,6 +8,15 @@ use std::sync::atomic::{AtomicU8, Ordering};
 /// This struct encapsulates operations on the per-object GC byte (metadata)
 pub struct GCByte {}
 
+/// The GC byte of VMs that have no room for it in their object headers: one byte of global
+/// side metadata for each word of the heap, so object references must be word aligned.
+pub const SIDE_GC_BYTE_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: 0,
+    log_num_of_bits: 3,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
 // TODO: we probably need to add non-atomic versions of the read and write methods
 /// Return the GC byte of an object as an atomic.
 ///
@@ -14,13 +25,16 @@ pub struct GCByte {}
 /// If the client VM provides the one byte in its object headers
 /// (see [trait ObjectModel](crate::vm::ObjectModel)),
 /// MMTk uses that byte as the per-object metadata.
-/// Otherwise, MMTk provides the metadata on its side.
+/// Otherwise, MMTk provides the metadata on its side (see `SIDE_GC_BYTE_SPEC`).
 ///
 fn get_gc_byte<VM: VMBinding>(object: ObjectReference) -> &'static AtomicU8 {
     if VM::VMObjectModel::HAS_GC_BYTE {
         unsafe { &*(object.to_address() + VM::VMObjectModel::GC_BYTE_OFFSET).to_ptr::<AtomicU8>() }
     } else {
-        todo!("\"HAS_GC_BYTE == false\" is not supported yet")
+        // The side GC byte is a whole metadata byte, so it can be used like a header byte.
+        debug_assert!(object.to_address().is_aligned_to(1 << LOG_BYTES_IN_WORD));
+        let addr = side_metadata::address_to_meta_address(SIDE_GC_BYTE_SPEC, object.to_address());
+        unsafe { &*addr.to_ptr::<AtomicU8>() }
     }
 }
 
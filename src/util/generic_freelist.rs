@@ -1,3 +1,7 @@
+use crate::util::heap::snapshot::{unsupported, SpaceSnapshot};
+use std::io;
+use std::slice::Iter;
+
 pub const FAILURE: i32 = -1;
 
 pub const MAX_HEADS: i32 = 128; // somewhat arbitrary
@@ -22,6 +26,16 @@ pub trait GenericFreeList: Sized {
     fn get_entry(&self, index: i32) -> i32;
     fn set_entry(&mut self, index: i32, value: i32);
 
+    /// Save the memory that holds this list and the values of its state in a heap snapshot.
+    fn snapshot(&self, _snapshot: &mut SpaceSnapshot) -> io::Result<()> {
+        Err(unsupported("The free list"))
+    }
+
+    /// Restore the state saved by `snapshot()`, after the memory of the snapshot is mapped.
+    fn restore(&mut self, _state: &mut Iter<usize>) -> io::Result<()> {
+        Err(unsupported("The free list"))
+    }
+
     fn alloc(&mut self, size: i32) -> i32 {
         let mut unit = self.head();
         let mut s = 0;
@@ -1,22 +1,45 @@
-use crate::plan::SelectedConstraints;
 use crate::util::gc_byte;
 use crate::util::ObjectReference;
 use crate::vm::VMBinding;
 
+// The header layout cannot depend on the plan, which is only known at run time. The unlogged bit
+// is the highest bit of the GC byte, above the header bits of all the plans, so it is reserved
+// whether the plan logs objects or not.
 pub const TOTAL_BITS: usize = 8;
-pub const NEEDS_UNLOGGED_BIT: bool = SelectedConstraints::NEEDS_LOG_BIT_IN_HEADER;
-pub const UNLOGGED_BIT_NUMBER: usize = SelectedConstraints::NEEDS_LOG_BIT_IN_HEADER_NUM;
+pub const NEEDS_UNLOGGED_BIT: bool = true;
+pub const UNLOGGED_BIT_NUMBER: usize = TOTAL_BITS - 1;
 pub const UNLOGGED_BIT: u8 = 1 << UNLOGGED_BIT_NUMBER;
 pub const USED_GLOBAL_BITS: usize = TOTAL_BITS - UNLOGGED_BIT_NUMBER;
 
+/// Set the unlogged bit. This is atomic, as GC workers may update the other bits at the same time.
 pub fn mark_as_unlogged<VM: VMBinding>(object: ObjectReference) {
-    gc_byte::write_gc_byte::<VM>(object, gc_byte::read_gc_byte::<VM>(object) | UNLOGGED_BIT);
+    loop {
+        let old_value = gc_byte::read_gc_byte::<VM>(object);
+        if gc_byte::compare_exchange_gc_byte::<VM>(object, old_value, old_value | UNLOGGED_BIT) {
+            return;
+        }
+    }
 }
 
 pub fn mark_as_logged<VM: VMBinding>(object: ObjectReference) {
-    gc_byte::write_gc_byte::<VM>(object, gc_byte::read_gc_byte::<VM>(object) & !UNLOGGED_BIT);
+    attempt_log::<VM>(object);
 }
 
+/// Clear the unlogged bit. Returns true if it was set, i.e. the caller is the one thread that
+/// logs the object.
+pub fn attempt_log<VM: VMBinding>(object: ObjectReference) -> bool {
+    loop {
+        let old_value = gc_byte::read_gc_byte::<VM>(object);
+        if old_value & UNLOGGED_BIT == 0 {
+            return false;
+        }
+        if gc_byte::compare_exchange_gc_byte::<VM>(object, old_value, old_value & !UNLOGGED_BIT) {
+            return true;
+        }
+    }
+}
+
+#[inline(always)]
 pub fn is_unlogged<VM: VMBinding>(object: ObjectReference) -> bool {
     (gc_byte::read_gc_byte::<VM>(object) & UNLOGGED_BIT) == UNLOGGED_BIT
 }
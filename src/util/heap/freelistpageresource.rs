@@ -1,4 +1,6 @@
+use std::io;
 use std::ops::{Deref, DerefMut};
+use std::slice::Iter;
 use std::sync::{Mutex, MutexGuard};
 
 use super::layout::map::Map;
@@ -14,6 +16,7 @@ use crate::util::generic_freelist::GenericFreeList;
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::util::heap::layout::vm_layout_constants::*;
 use crate::util::heap::pageresource::CommonPageResource;
+use crate::util::heap::snapshot::{next_value, unsupported, SpaceSnapshot};
 use crate::util::OpaquePointer;
 use crate::util::{generic_freelist, memory};
 use crate::vm::VMBinding;
@@ -113,16 +116,30 @@ impl<VM: VMBinding> PageResource<VM> for FreeListPageResource<VM> {
         let bytes = conversions::pages_to_bytes(required_pages);
         // The meta-data portion of reserved Pages was committed above.
         self.commit_pages(reserved_pages, required_pages, tls);
-        self.common()
-            .space
-            .unwrap()
-            .grow_space(rtn, bytes, new_chunk);
+        self.reuse_pages(rtn, required_pages);
+        if new_chunk {
+            // Pages above the high water mark may start in the middle of a chunk, but the space
+            // grows by whole chunks.
+            let chunk_start = rtn.align_down(BYTES_IN_CHUNK);
+            self.common()
+                .space
+                .unwrap()
+                .grow_space(chunk_start, bytes + (rtn - chunk_start), true);
+        } else {
+            self.common().space.unwrap().grow_space(rtn, bytes, false);
+        }
         self.common()
             .space
             .unwrap()
             .common()
             .mmapper
             .ensure_mapped(rtn, required_pages);
+        let space_common = self.common().space.unwrap().common();
+        space_common
+            .metadata
+            .ensure_mapped(space_common.mmapper, rtn, bytes);
+        // Pages that were used before may still have the metadata of their old objects.
+        space_common.metadata.bzero(rtn, bytes);
         if zeroed {
             memory::zero(rtn, bytes);
         }
@@ -132,6 +149,47 @@ impl<VM: VMBinding> PageResource<VM> for FreeListPageResource<VM> {
     fn adjust_for_metadata(&self, pages: usize) -> usize {
         pages
     }
+
+    fn snapshot(&self, snapshot: &mut SpaceSnapshot) -> io::Result<()> {
+        let sync = self.sync.lock().unwrap();
+        let space = self.common().space.unwrap();
+        let high_water = self
+            .vm_map()
+            .get_high_water(space.common().descriptor)
+            .ok_or_else(|| unsupported("The map of the chunks of the heap"))?;
+        for chunk in space.mapped_chunks() {
+            snapshot.add_mapped_extents(space.common().mmapper, chunk, BYTES_IN_CHUNK);
+        }
+        self.free_list.snapshot(snapshot)?;
+        snapshot.state.extend_from_slice(&[
+            sync.pages_currently_on_freelist,
+            sync.highwater_mark as usize,
+            self.common().get_reserved(),
+            self.common().get_committed(),
+            high_water.as_usize(),
+        ]);
+        Ok(())
+    }
+
+    fn restore(&self, state: &mut Iter<usize>) -> io::Result<()> {
+        // FIXME: We need a safe implementation
+        #[allow(clippy::cast_ref_to_mut)]
+        let self_mut: &mut Self = unsafe { &mut *(self as *const _ as *mut _) };
+        let mut sync = self.sync.lock().unwrap();
+        self_mut.free_list.restore(state)?;
+        sync.pages_currently_on_freelist = next_value(state);
+        sync.highwater_mark = next_value(state) as i32;
+        self.common().reset_reserved();
+        self.common().reserve(next_value(state));
+        self.common().reset_committed();
+        self.common().commit(next_value(state));
+        let descriptor = self.common().space.unwrap().common().descriptor;
+        let high_water = unsafe { Address::from_usize(next_value(state)) };
+        if !self.vm_map().set_high_water(descriptor, high_water) {
+            return Err(unsupported("The map of the chunks of the heap"));
+        }
+        Ok(())
+    }
 }
 
 impl<VM: VMBinding> FreeListPageResource<VM> {
@@ -310,11 +368,18 @@ impl<VM: VMBinding> FreeListPageResource<VM> {
         // if (VM.config.ZERO_PAGES_ON_RELEASE)
         //     VM.memory.zero(false, first, Conversions.pagesToBytes(pages));
         debug_assert!(pages as usize <= self.common.get_committed());
+        self.common
+            .space
+            .unwrap()
+            .common()
+            .metadata
+            .bzero(first, conversions::pages_to_bytes(pages as _));
         let me = unsafe { &mut *(self as *mut Self) };
         let freed = {
             let mut sync = self.sync.lock().unwrap();
             self.common.release_reserved(pages as _);
             self.common.release_committed(pages as _);
+            self.uncommit_pages(first, pages as _);
             let freed = me.free_list.free(page_offset as _, true);
             sync.pages_currently_on_freelist += pages as usize;
             freed
@@ -0,0 +1,296 @@
+use crate::util::options::{GCTriggerSelector, Options};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A policy that decides when a GC is triggered, and how large the heap is. The policy is
+/// selected with the `gc_trigger` option, or set by the binding with
+/// `memory_manager::set_gc_trigger()`.
+pub trait GCTriggerPolicy: Sync + Send {
+    /// Called when a space acquires `pages` pages for allocation.
+    fn on_alloc(&self, _pages: usize) {}
+    /// Called when a GC is scheduled.
+    fn on_gc_start(&self) {}
+    /// Called at the end of a GC, with the pages that the plan still reserves.
+    fn on_gc_end(&self, _pages_reserved: usize) {}
+    /// Is a GC required? `space_full` is true if a space failed to acquire new pages.
+    fn is_gc_required(&self, space_full: bool, pages_reserved: usize) -> bool {
+        space_full || self.is_heap_full(pages_reserved)
+    }
+    /// Is the heap full with the pages that the plan reserves?
+    fn is_heap_full(&self, pages_reserved: usize) -> bool {
+        pages_reserved > self.get_heap_size_in_pages()
+    }
+    /// The current size of the heap in pages.
+    fn get_heap_size_in_pages(&self) -> usize;
+}
+
+/// Create the policy selected by the options, with the heap size passed to `gc_init()`.
+pub fn create_gc_trigger(options: &Options, heap_pages: usize) -> Box<dyn GCTriggerPolicy> {
+    match options.gc_trigger {
+        GCTriggerSelector::TimeRatio if options.variable_size_heap => {
+            let pages_or_default = |bytes| match bytes {
+                0 => heap_pages,
+                bytes => crate::util::conversions::bytes_to_pages(bytes),
+            };
+            let (min_pages, max_pages) = clamp_heap_bounds(
+                pages_or_default(options.min_heap),
+                heap_pages,
+                pages_or_default(options.max_heap),
+            );
+            box TimeRatioTrigger::new(min_pages, heap_pages, max_pages)
+        }
+        _ => box FixedHeapSizeTrigger::new(heap_pages),
+    }
+}
+
+/// Clamp the min and max heap sizes set by the options, so that the initial heap size is between
+/// them. The heap size passed to `gc_init()` takes precedence over the options.
+fn clamp_heap_bounds(min_pages: usize, initial_pages: usize, max_pages: usize) -> (usize, usize) {
+    if min_pages > initial_pages {
+        warn!(
+            "min_heap ({} pages) is larger than the heap size ({} pages). Using the heap size instead.",
+            min_pages, initial_pages
+        );
+    }
+    if max_pages < initial_pages {
+        warn!(
+            "max_heap ({} pages) is smaller than the heap size ({} pages). Using the heap size instead.",
+            max_pages, initial_pages
+        );
+    }
+    (
+        usize::min(min_pages, initial_pages),
+        usize::max(max_pages, initial_pages),
+    )
+}
+
+/// Triggers a GC when the heap of a fixed size is full.
+pub struct FixedHeapSizeTrigger {
+    total_pages: usize,
+}
+
+impl FixedHeapSizeTrigger {
+    pub fn new(total_pages: usize) -> Self {
+        Self { total_pages }
+    }
+}
+
+impl GCTriggerPolicy for FixedHeapSizeTrigger {
+    fn get_heap_size_in_pages(&self) -> usize {
+        self.total_pages
+    }
+}
+
+/// The heap size change factors, indexed by GC load (the fraction of time spent in GC since the
+/// last resize, in the first column) and by live ratio (the fraction of the heap that is still
+/// reserved after a GC, in the first row). Factors between the sample points are interpolated.
+/// The heap shrinks when GC is cheap and the heap is mostly free, and grows when GC is expensive
+/// and much of the heap is live. This is the function used by Java MMTk.
+const FUNCTION: [[f64; 7]; 8] = [
+    [0.00, 0.00, 0.10, 0.30, 0.60, 0.80, 1.00],
+    [0.01, 0.90, 0.90, 0.95, 1.00, 1.00, 1.00],
+    [0.02, 0.90, 0.90, 0.95, 1.00, 1.00, 1.00],
+    [0.05, 0.95, 0.95, 1.00, 1.00, 1.00, 1.00],
+    [0.07, 1.00, 1.00, 1.10, 1.15, 1.20, 1.20],
+    [0.15, 1.00, 1.00, 1.20, 1.25, 1.35, 1.30],
+    [0.40, 1.00, 1.00, 1.25, 1.30, 1.50, 1.50],
+    [1.00, 1.00, 1.00, 1.25, 1.30, 1.50, 1.50],
+];
+
+/// The factor to grow the heap by when a GC leaves the heap full, however cheap the GC was.
+const FULL_HEAP_GROWTH_FACTOR: f64 = 1.5;
+
+/// Triggers a GC when the heap is full, and resizes the heap between a minimum and a maximum
+/// size after each GC, based on the ratio of time spent in GC and on the live size.
+pub struct TimeRatioTrigger {
+    min_pages: usize,
+    max_pages: usize,
+    total_pages: AtomicUsize,
+    sync: Mutex<TimeRatioTriggerSync>,
+}
+
+struct TimeRatioTriggerSync {
+    /// The start of the current GC.
+    gc_start: Option<Instant>,
+    /// The time spent in GC since the last resize.
+    gc_time: Duration,
+    /// The time of the last resize.
+    last_resize: Instant,
+}
+
+impl TimeRatioTrigger {
+    pub fn new(min_pages: usize, initial_pages: usize, max_pages: usize) -> Self {
+        assert!(
+            min_pages <= initial_pages && initial_pages <= max_pages,
+            "Initial heap size ({} pages) is not between min heap size ({} pages) and max heap size ({} pages)",
+            initial_pages,
+            min_pages,
+            max_pages
+        );
+        TimeRatioTrigger {
+            min_pages,
+            max_pages,
+            total_pages: AtomicUsize::new(initial_pages),
+            sync: Mutex::new(TimeRatioTriggerSync {
+                gc_start: None,
+                gc_time: Duration::from_secs(0),
+                last_resize: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn get_min_pages(&self) -> usize {
+        self.min_pages
+    }
+
+    pub fn get_max_pages(&self) -> usize {
+        self.max_pages
+    }
+}
+
+impl GCTriggerPolicy for TimeRatioTrigger {
+    fn on_gc_start(&self) {
+        self.sync.lock().unwrap().gc_start = Some(Instant::now());
+    }
+
+    /// Resize the heap at the end of a GC, based on the time spent in GC since the last resize,
+    /// and on the pages still reserved after this GC.
+    fn on_gc_end(&self, pages_reserved: usize) {
+        let mut sync = self.sync.lock().unwrap();
+        let now = Instant::now();
+        if let Some(gc_start) = sync.gc_start.take() {
+            sync.gc_time += now - gc_start;
+        }
+        if self.min_pages == self.max_pages {
+            return;
+        }
+
+        let total_pages = self.get_heap_size_in_pages();
+        let elapsed = now - sync.last_resize;
+        let gc_load = if elapsed.as_nanos() == 0 {
+            0.0
+        } else {
+            sync.gc_time.as_secs_f64() / elapsed.as_secs_f64()
+        };
+        let live_ratio = pages_reserved as f64 / total_pages as f64;
+        let mut factor = growth_factor(gc_load, live_ratio);
+        if pages_reserved >= total_pages {
+            factor = factor.max(FULL_HEAP_GROWTH_FACTOR);
+        }
+        let new_pages = ((total_pages as f64 * factor) as usize)
+            .max(self.min_pages)
+            .min(self.max_pages);
+        if new_pages != total_pages {
+            debug!(
+                "Resize heap: {} -> {} pages (gc load = {:.3}, live ratio = {:.3})",
+                total_pages, new_pages, gc_load, live_ratio
+            );
+            self.total_pages.store(new_pages, Ordering::Relaxed);
+        }
+        sync.gc_time = Duration::from_secs(0);
+        sync.last_resize = now;
+    }
+
+    fn get_heap_size_in_pages(&self) -> usize {
+        self.total_pages.load(Ordering::Relaxed)
+    }
+}
+
+/// Interpolate the heap size change factor from `FUNCTION`.
+fn growth_factor(gc_load: f64, live_ratio: f64) -> f64 {
+    // Values out of the range of the function are clamped to its bounds.
+    let gc_load = gc_load.max(0.0).min(1.0);
+    let live_ratio = live_ratio.max(0.0).min(1.0);
+
+    let mut load_index = 1;
+    while load_index < FUNCTION.len() - 1 && FUNCTION[load_index + 1][0] < gc_load {
+        load_index += 1;
+    }
+    let mut ratio_index = 1;
+    while ratio_index < FUNCTION[0].len() - 1 && FUNCTION[0][ratio_index + 1] < live_ratio {
+        ratio_index += 1;
+    }
+    if gc_load <= FUNCTION[1][0] {
+        return interpolate_ratio(1, ratio_index, live_ratio);
+    }
+
+    // Interpolate along the live ratio in the two rows around the GC load, then between the rows.
+    let low = interpolate_ratio(load_index, ratio_index, live_ratio);
+    let high = interpolate_ratio(load_index + 1, ratio_index, live_ratio);
+    let low_load = FUNCTION[load_index][0];
+    let high_load = FUNCTION[load_index + 1][0];
+    low + (high - low) * (gc_load - low_load) / (high_load - low_load)
+}
+
+fn interpolate_ratio(row: usize, ratio_index: usize, live_ratio: f64) -> f64 {
+    let low_ratio = FUNCTION[0][ratio_index];
+    let high_ratio = FUNCTION[0][ratio_index + 1];
+    let low = FUNCTION[row][ratio_index];
+    let high = FUNCTION[row][ratio_index + 1];
+    low + (high - low) * (live_ratio - low_ratio) / (high_ratio - low_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn factor_at_sample_points() {
+        assert_close(growth_factor(0.01, 0.0), 0.90);
+        assert_close(growth_factor(0.05, 0.30), 1.00);
+        assert_close(growth_factor(0.15, 0.80), 1.35);
+        assert_close(growth_factor(1.00, 1.00), 1.50);
+    }
+
+    #[test]
+    fn factor_between_sample_points() {
+        // Halfway between live ratio 0.10 and 0.30 in the row for GC load 0.07
+        assert_close(growth_factor(0.07, 0.20), 1.05);
+        // Halfway between GC load 0.40 and 1.00 at live ratio 0.60
+        assert_close(growth_factor(0.70, 0.60), 1.30);
+    }
+
+    #[test]
+    fn factor_out_of_range() {
+        assert_close(growth_factor(0.0, 0.0), 0.90);
+        assert_close(growth_factor(2.0, 2.0), 1.50);
+    }
+
+    #[test]
+    fn fixed_heap_is_full_when_reserved_exceeds_size() {
+        let trigger = FixedHeapSizeTrigger::new(100);
+        assert!(!trigger.is_heap_full(100));
+        assert!(trigger.is_heap_full(101));
+        assert!(trigger.is_gc_required(true, 0));
+    }
+
+    #[test]
+    fn time_ratio_stays_within_bounds() {
+        let trigger = TimeRatioTrigger::new(50, 100, 120);
+        // A full heap grows, but not beyond the max heap size.
+        trigger.on_gc_start();
+        trigger.on_gc_end(100);
+        assert_eq!(trigger.get_heap_size_in_pages(), 120);
+        trigger.on_gc_start();
+        trigger.on_gc_end(120);
+        assert_eq!(trigger.get_heap_size_in_pages(), 120);
+    }
+
+    #[test]
+    fn heap_bounds_are_clamped_to_heap_size() {
+        assert_eq!(clamp_heap_bounds(50, 100, 120), (50, 120));
+        assert_eq!(clamp_heap_bounds(150, 100, 120), (100, 120));
+        assert_eq!(clamp_heap_bounds(50, 100, 80), (50, 100));
+    }
+
+    #[test]
+    fn shrink_when_mostly_free_and_grow_when_busy() {
+        assert!(growth_factor(0.01, 0.05) < 1.0);
+        assert!(growth_factor(0.50, 0.90) > 1.0);
+    }
+}
@@ -1,11 +1,8 @@
 use crate::util::Address;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering;
 
 pub struct HeapMeta {
     pub heap_cursor: Address,
     pub heap_limit: Address,
-    pub total_pages: AtomicUsize,
 }
 
 impl HeapMeta {
@@ -13,7 +10,6 @@ impl HeapMeta {
         HeapMeta {
             heap_cursor: start,
             heap_limit: end,
-            total_pages: AtomicUsize::new(0),
         }
     }
 
@@ -46,8 +42,4 @@ impl HeapMeta {
     pub fn get_discontig_end(&self) -> Address {
         self.heap_limit - 1
     }
-
-    pub fn get_total_pages(&self) -> usize {
-        self.total_pages.load(Ordering::Relaxed)
-    }
 }
@@ -57,5 +57,18 @@ pub trait Map: Sized {
 
     fn add_to_cumulative_committed_pages(&self, pages: usize);
 
+    /// The end of the chunks handed out to the space of `descriptor`, for a heap snapshot. Only
+    /// the 64-bit map hands out the chunks of each space in address order, so the other maps
+    /// return `None`.
+    fn get_high_water(&self, _descriptor: SpaceDescriptor) -> Option<Address> {
+        None
+    }
+
+    /// Restore the end saved from `get_high_water()`. Returns `false` if this map does not hand
+    /// out chunks in address order.
+    fn set_high_water(&self, _descriptor: SpaceDescriptor, _high_water: Address) -> bool {
+        false
+    }
+
     fn get_cumulative_committed_pages(&self) -> usize;
 }
@@ -203,6 +203,16 @@ impl Map for Map64 {
     fn get_cumulative_committed_pages(&self) -> usize {
         self.cumulative_committed_pages.load(Ordering::Relaxed)
     }
+
+    fn get_high_water(&self, descriptor: SpaceDescriptor) -> Option<Address> {
+        Some(self.high_water[descriptor.get_index()])
+    }
+
+    fn set_high_water(&self, descriptor: SpaceDescriptor, high_water: Address) -> bool {
+        let self_mut = unsafe { self.mut_self() };
+        self_mut.high_water[descriptor.get_index()] = high_water;
+        true
+    }
 }
 
 impl Map64 {
@@ -1,10 +1,13 @@
 #[macro_use]
 pub mod layout;
 pub mod freelistpageresource;
+pub mod gc_trigger;
 mod heap_meta;
 pub mod monotonepageresource;
 pub mod pageresource;
+pub mod snapshot;
 pub mod space_descriptor;
+pub mod uncommit;
 mod vmrequest;
 
 pub use self::freelistpageresource::FreeListPageResource;
@@ -2,13 +2,22 @@ use super::layout::vm_layout_constants::BYTES_IN_CHUNK;
 use super::vmrequest::HEAP_LAYOUT_64BIT;
 use crate::policy::space::required_chunks;
 use crate::util::address::Address;
+use crate::util::constants::BYTES_IN_PAGE;
 use crate::util::conversions::*;
-use std::sync::{Mutex, MutexGuard};
+use std::collections::VecDeque;
+use std::io;
+use std::slice::Iter;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use crate::util::alloc::embedded_meta_data::*;
 use crate::util::heap::layout::vm_layout_constants::LOG_BYTES_IN_CHUNK;
 use crate::util::heap::pageresource::CommonPageResource;
-use crate::util::OpaquePointer;
+use crate::util::heap::snapshot::{next_value, unsupported, SpaceSnapshot};
+use crate::util::{memory, OpaquePointer};
+use crate::vm::ActivePlan;
 
 use super::layout::map::Map;
 use super::layout::Mmapper;
@@ -17,7 +26,10 @@ use super::PageResource;
 
 use crate::util::heap::layout::heap_layout::VMMap;
 use crate::vm::VMBinding;
-use libc::{c_void, memset};
+
+/// The size of the blocks zeroed by the concurrent zeroing thread, after each of which the
+/// allocating threads may use the zeroed pages.
+const CONCURRENT_ZEROING_BLOCK_BYTES: usize = 64 << 10;
 
 pub struct MonotonePageResource<VM: VMBinding> {
     common: CommonPageResource<VM>,
@@ -25,6 +37,48 @@ pub struct MonotonePageResource<VM: VMBinding> {
     /** Number of pages to reserve at the start of every allocation */
     meta_data_pages_per_region: usize,
     sync: Mutex<MonotonePageResourceSync>,
+
+    /** Zero pages with non-temporal stores */
+    zero_nontemporal: AtomicBool,
+    /** Zero pages in a separate zeroing thread, if it is triggered after a release */
+    zero_concurrent: AtomicBool,
+    /** Whether the zeroing thread zeroes the pages released by the last release */
+    zeroing_triggered: AtomicBool,
+    /** Current frontier of zeroing, in a separate zeroing thread */
+    zeroing_cursor: AtomicUsize,
+    /** Current limit of zeroing. Pages beyond it have never been allocated. */
+    zeroing_sentinel: AtomicUsize,
+    /** The zeroing thread, which is started by the first trigger and waits for the next one */
+    zeroing_thread: Mutex<Option<JoinHandle<()>>>,
+    zeroing_state: Mutex<ZeroingState>,
+    /** Signalled when the zeroing state changes, and when the zeroing cursor advances */
+    zeroing_progress: Condvar,
+    /** Time allocating threads spent waiting for the zeroing thread, in nanoseconds */
+    zeroing_wait_nanos: AtomicU64,
+    zeroing_rates: Mutex<ZeroingRates>,
+}
+
+/// What the zeroing thread is doing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ZeroingState {
+    /// Waiting to be triggered.
+    Idle,
+    /// Triggered, but not yet zeroing.
+    Requested,
+    /// Zeroing the pages released by the last release.
+    Zeroing,
+}
+
+/// The measurements that decide whether zeroing concurrently pays off.
+#[derive(Default)]
+struct ZeroingRates {
+    /// When the allocation after the last release started.
+    mutator_start: Option<Instant>,
+    /// Bytes allocated per second by the mutators between the last two releases, excluding the
+    /// time spent waiting for the zeroing thread.
+    allocation: Option<f64>,
+    /// Bytes zeroed per second by the zeroing thread.
+    zeroing: Option<f64>,
 }
 
 struct MonotonePageResourceSync {
@@ -35,16 +89,13 @@ struct MonotonePageResourceSync {
     /** Base address of the current chunk of addresses */
     current_chunk: Address,
     conditional: MonotonePageResourceConditional,
+    /** The ranges of pages after the cursor that the last release kept, in address order. They
+     * are skipped by the allocation. */
+    kept: VecDeque<(Address, Address)>,
 }
 
 pub enum MonotonePageResourceConditional {
-    Contiguous {
-        start: Address,
-        /** Current frontier of zeroing, in a separate zeroing thread */
-        zeroing_cursor: Address,
-        /** Current limit of zeroing.  If zeroingCursor < zeroingSentinel, zeroing is still happening. */
-        zeroing_sentinel: Address,
-    },
+    Contiguous { start: Address },
     Discontiguous,
 }
 impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
@@ -71,10 +122,10 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
             "In MonotonePageResource, reserved_pages = {}, required_pages = {}",
             reserved_pages, immut_required_pages
         );
-        let mut required_pages = immut_required_pages;
+        let mut required_pages;
         let mut new_chunk = false;
         let mut sync = self.sync.lock().unwrap();
-        let mut rtn = sync.cursor;
+        let mut rtn;
         debug!(
             "cursor = {}, sentinel = {}, current_chunk = {}",
             sync.cursor, sync.sentinel, sync.current_chunk
@@ -100,15 +151,30 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
             );
         }
 
-        if self.meta_data_pages_per_region != 0 {
-            /* adjust allocation for metadata */
-            let region_start = Self::get_region_start(sync.cursor + pages_to_bytes(required_pages));
-            let region_delta = region_start.get_offset(sync.cursor);
-            if region_delta >= 0 {
-                /* start new region, so adjust pages and return address accordingly */
-                required_pages +=
-                    bytes_to_pages(region_delta as usize) + self.meta_data_pages_per_region;
-                rtn = region_start + pages_to_bytes(self.meta_data_pages_per_region);
+        loop {
+            rtn = sync.cursor;
+            required_pages = immut_required_pages;
+            if self.meta_data_pages_per_region != 0 {
+                /* adjust allocation for metadata */
+                let region_start =
+                    Self::get_region_start(sync.cursor + pages_to_bytes(required_pages));
+                let region_delta = region_start.get_offset(sync.cursor);
+                if region_delta >= 0 {
+                    /* start new region, so adjust pages and return address accordingly */
+                    required_pages +=
+                        bytes_to_pages(region_delta as usize) + self.meta_data_pages_per_region;
+                    rtn = region_start + pages_to_bytes(self.meta_data_pages_per_region);
+                }
+            }
+            // Skip the kept pages that the allocation would overlap. The pages before them are
+            // left unused until the next release.
+            match sync.kept.front() {
+                Some(&(kept_start, _))
+                    if kept_start < sync.cursor + pages_to_bytes(required_pages) =>
+                {
+                    self.skip_kept_pages(&mut sync)
+                }
+                _ => break,
             }
         }
         let bytes = pages_to_bytes(required_pages);
@@ -153,6 +219,13 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
                 sync.current_chunk = chunk_align_down(sync.cursor);
             }
             self.commit_pages(reserved_pages, required_pages, tls);
+            // The zeroing thread skips the pages that are no longer released, so the pages must
+            // be zeroed before they are taken back from the uncommitter.
+            let zeroing_triggered = zeroed && self.zeroing_triggered.load(Ordering::Acquire);
+            if zeroing_triggered {
+                self.wait_for_zeroing(sync.cursor);
+            }
+            self.reuse_pages(old, required_pages);
             self.common()
                 .space
                 .unwrap()
@@ -164,23 +237,20 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
                 .common()
                 .mmapper
                 .ensure_mapped(old, required_pages);
-
-            // FIXME: concurrent zeroing
-            if zeroed {
-                unsafe {
-                    memset(old.to_mut_ptr() as *mut c_void, 0, bytes);
-                }
-            }
-            /*
-            if zeroed {
-                if !self.zero_concurrent {
-                    VM.memory.zero(zeroNT, old, bytes);
+            let space_common = self.common().space.unwrap().common();
+            space_common
+                .metadata
+                .ensure_mapped(space_common.mmapper, old, bytes);
+            // Pages that were used before may still have the metadata of their old objects.
+            space_common.metadata.bzero(old, bytes);
+
+            if zeroed && !zeroing_triggered {
+                if self.zero_nontemporal.load(Ordering::Relaxed) {
+                    memory::zero_nt(old, bytes);
                 } else {
-                    while (cursor.GT(zeroingCursor));
+                    memory::zero(old, bytes);
                 }
             }
-            VM.events.tracePageAcquired(space, rtn, requiredPages);
-            */
             rtn
         }
     }
@@ -190,6 +260,124 @@ impl<VM: VMBinding> PageResource<VM> for MonotonePageResource<VM> {
             + ((pages + PAGES_IN_REGION - 1) >> LOG_PAGES_IN_REGION)
                 * self.meta_data_pages_per_region
     }
+
+    fn update_zeroing_approach(&self, nontemporal: bool, concurrent: bool) {
+        // A discontiguous resource has no single range for the zeroing thread to zero.
+        if concurrent && !self.common().contiguous {
+            warn!("Concurrent zeroing needs a contiguous space. The pages are zeroed when they are acquired instead.");
+        }
+        self.zero_nontemporal.store(nontemporal, Ordering::Relaxed);
+        self.zero_concurrent
+            .store(concurrent && self.common().contiguous, Ordering::Relaxed);
+    }
+
+    fn skip_concurrent_zeroing(&self) {
+        // The pages released by the last release are zeroed when they are acquired.
+        self.zeroing_triggered.store(false, Ordering::Release);
+        self.start_mutator_phase();
+    }
+
+    fn trigger_concurrent_zeroing(&self) {
+        // The zeroing thread zeroes the whole released range, which must not have kept pages.
+        if !self.zero_concurrent.load(Ordering::Relaxed)
+            || !self.sync.lock().unwrap().kept.is_empty()
+        {
+            return self.skip_concurrent_zeroing();
+        }
+        self.zeroing_triggered.store(true, Ordering::Release);
+        self.start_mutator_phase();
+        let mut thread = self.zeroing_thread.lock().unwrap();
+        if thread.is_none() {
+            // The page resource lives as long as its space, which is never dropped. The zeroing
+            // thread must not take the lock, as an allocating thread waits for it with the lock
+            // held.
+            let pr = self as *const Self as usize;
+            *thread = Some(
+                std::thread::Builder::new()
+                    .name("MMTk zeroing".to_string())
+                    .spawn(move || unsafe { &*(pr as *const Self) }.concurrent_zeroing())
+                    .unwrap(),
+            );
+        }
+        let mut state = self.zeroing_state.lock().unwrap();
+        debug_assert_eq!(*state, ZeroingState::Idle);
+        *state = ZeroingState::Requested;
+        self.zeroing_progress.notify_all();
+    }
+
+    fn concurrent_zeroing(&self) {
+        let mut state = self.zeroing_state.lock().unwrap();
+        loop {
+            while *state != ZeroingState::Requested {
+                state = self.zeroing_progress.wait(state).unwrap();
+            }
+            *state = ZeroingState::Zeroing;
+            drop(state);
+            self.zero_released_pages();
+            state = self.zeroing_state.lock().unwrap();
+            *state = ZeroingState::Idle;
+            self.zeroing_progress.notify_all();
+        }
+    }
+
+    fn concurrent_zeroing_keeps_up(&self) -> bool {
+        let rates = self.zeroing_rates.lock().unwrap();
+        match (rates.allocation, rates.zeroing) {
+            (Some(allocation), Some(zeroing)) => allocation <= zeroing,
+            // Try zeroing concurrently until we know the rates.
+            _ => true,
+        }
+    }
+
+    fn snapshot(&self, snapshot: &mut SpaceSnapshot) -> io::Result<()> {
+        let sync = self.sync.lock().unwrap();
+        let start = match sync.conditional {
+            MonotonePageResourceConditional::Contiguous { start } => start,
+            MonotonePageResourceConditional::Discontiguous => {
+                return Err(unsupported("A discontiguous monotone page resource"))
+            }
+        };
+        // The memory after the cursor and the kept pages is not in use.
+        let end = sync.kept.back().map_or(sync.cursor, |&(_, end)| end);
+        let mmapper = self.common().space.unwrap().common().mmapper;
+        snapshot.add_mapped_extents(mmapper, start, chunk_align_up(end) - start);
+        snapshot.state.extend_from_slice(&[
+            sync.cursor.as_usize(),
+            sync.current_chunk.as_usize(),
+            self.zeroing_sentinel.load(Ordering::Relaxed),
+            self.common().get_reserved(),
+            self.common().get_committed(),
+            sync.kept.len(),
+        ]);
+        for &(kept_start, kept_end) in &sync.kept {
+            snapshot
+                .state
+                .extend_from_slice(&[kept_start.as_usize(), kept_end.as_usize()]);
+        }
+        Ok(())
+    }
+
+    fn restore(&self, state: &mut Iter<usize>) -> io::Result<()> {
+        let mut sync = self.sync.lock().unwrap();
+        sync.cursor = unsafe { Address::from_usize(next_value(state)) };
+        sync.current_chunk = unsafe { Address::from_usize(next_value(state)) };
+        self.zeroing_sentinel
+            .store(next_value(state), Ordering::Relaxed);
+        self.common().reset_reserved();
+        self.common().reserve(next_value(state));
+        self.common().reset_committed();
+        self.common().commit(next_value(state));
+        let kept = next_value(state);
+        sync.kept = (0..kept)
+            .map(|_| unsafe {
+                (
+                    Address::from_usize(next_value(state)),
+                    Address::from_usize(next_value(state)),
+                )
+            })
+            .collect();
+        Ok(())
+    }
 }
 
 impl<VM: VMBinding> MonotonePageResource<VM> {
@@ -209,12 +397,19 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
                 cursor: start,
                 current_chunk: chunk_align_down(start),
                 sentinel,
-                conditional: MonotonePageResourceConditional::Contiguous {
-                    start,
-                    zeroing_cursor: sentinel,
-                    zeroing_sentinel: start,
-                },
+                conditional: MonotonePageResourceConditional::Contiguous { start },
+                kept: VecDeque::new(),
             }),
+            zero_nontemporal: AtomicBool::new(false),
+            zero_concurrent: AtomicBool::new(false),
+            zeroing_triggered: AtomicBool::new(false),
+            zeroing_cursor: AtomicUsize::new(sentinel.as_usize()),
+            zeroing_sentinel: AtomicUsize::new(start.as_usize()),
+            zeroing_thread: Mutex::new(None),
+            zeroing_state: Mutex::new(ZeroingState::Idle),
+            zeroing_progress: Condvar::new(),
+            zeroing_wait_nanos: AtomicU64::new(0),
+            zeroing_rates: Mutex::new(ZeroingRates::default()),
         }
     }
 
@@ -228,7 +423,18 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
                 current_chunk: unsafe { Address::zero() },
                 sentinel: unsafe { Address::zero() },
                 conditional: MonotonePageResourceConditional::Discontiguous,
+                kept: VecDeque::new(),
             }),
+            zero_nontemporal: AtomicBool::new(false),
+            zero_concurrent: AtomicBool::new(false),
+            zeroing_triggered: AtomicBool::new(false),
+            zeroing_cursor: AtomicUsize::new(0),
+            zeroing_sentinel: AtomicUsize::new(0),
+            zeroing_thread: Mutex::new(None),
+            zeroing_state: Mutex::new(ZeroingState::Idle),
+            zeroing_progress: Condvar::new(),
+            zeroing_wait_nanos: AtomicU64::new(0),
+            zeroing_rates: Mutex::new(ZeroingRates::default()),
         }
     }
 
@@ -254,10 +460,37 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
         let mut guard = self.sync.lock().unwrap();
         self.common().reset_reserved();
         self.common().reset_committed();
-        self.release_pages(&mut guard);
+        self.release_pages(&mut guard, VecDeque::new());
         drop(guard);
     }
 
+    /// Like `reset()`, but keep the given ranges of pages, which hold objects that must not move.
+    /// The ranges are page aligned, in address order and in use. Allocation resumes at
+    /// the start of the page resource, and skips the kept pages. Only contiguous page resources
+    /// can keep pages.
+    ///
+    /// # Safety
+    /// As `reset()`.
+    pub unsafe fn reset_keeping(&self, kept: VecDeque<(Address, Address)>) {
+        let mut guard = self.sync.lock().unwrap();
+        if let MonotonePageResourceConditional::Discontiguous = guard.conditional {
+            panic!("A discontiguous page resource cannot keep pages")
+        }
+        let kept_pages = kept
+            .iter()
+            .map(|&(start, end)| {
+                debug_assert!(start.is_aligned_to(BYTES_IN_PAGE));
+                debug_assert!(end.is_aligned_to(BYTES_IN_PAGE));
+                bytes_to_pages(end - start)
+            })
+            .sum();
+        self.common().reset_reserved();
+        self.common().reserve(kept_pages);
+        self.common().reset_committed();
+        self.common().commit(kept_pages);
+        self.release_pages(&mut guard, kept);
+    }
+
     /*/**
     * Release all pages associated with this page resource, optionally
     * zeroing on release and optionally memory protecting on release.
@@ -293,14 +526,49 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
      }*/
 
     #[inline]
-    unsafe fn release_pages(&self, guard: &mut MutexGuard<MonotonePageResourceSync>) {
-        // TODO: concurrent zeroing
+    unsafe fn release_pages(
+        &self,
+        guard: &mut MutexGuard<MonotonePageResourceSync>,
+        kept: VecDeque<(Address, Address)>,
+    ) {
         if self.common().contiguous {
-            guard.cursor = match guard.conditional {
+            let start = match guard.conditional {
                 MonotonePageResourceConditional::Contiguous { start: _start, .. } => _start,
                 _ => unreachable!(),
             };
+            if self.zero_concurrent.load(Ordering::Relaxed) {
+                // Wait for current zeroing to finish.
+                let mut state = self.zeroing_state.lock().unwrap();
+                while *state != ZeroingState::Idle {
+                    state = self.zeroing_progress.wait(state).unwrap();
+                }
+                drop(state);
+                self.zeroing_triggered.store(false, Ordering::Release);
+                self.end_mutator_phase(guard.cursor - start);
+            }
+            // The pages that the last release kept may still be after the cursor.
+            while !guard.kept.is_empty() {
+                self.skip_kept_pages(guard);
+            }
+            // Reset zeroing region. TODO: We will perform unnecessary zeroing if the nursery size
+            // has decreased.
+            if guard.cursor.as_usize() > self.zeroing_sentinel.load(Ordering::Relaxed) {
+                self.zeroing_sentinel
+                    .store(guard.cursor.as_usize(), Ordering::Relaxed);
+            }
+            self.zeroing_cursor
+                .store(start.as_usize(), Ordering::Relaxed);
+            // Release the pages between the kept ones.
+            let mut first = start;
+            for &(kept_start, kept_end) in &kept {
+                self.release_pages_extent(first, kept_start - first);
+                first = kept_end;
+            }
+            self.release_pages_extent(first, guard.cursor - first);
+            guard.cursor = start;
+            guard.kept = kept;
         } else if !guard.cursor.is_zero() {
+            debug_assert!(kept.is_empty());
             let bytes = guard.cursor - guard.current_chunk;
             self.release_pages_extent(guard.current_chunk, bytes);
             while self.move_to_next_chunk(guard) {
@@ -315,9 +583,108 @@ impl<VM: VMBinding> MonotonePageResource<VM> {
         }
     }
 
-    fn release_pages_extent(&self, _first: Address, bytes: usize) {
+    /// Move the cursor past the next kept pages. The pages before them were released, and are
+    /// taken back from the uncommitter, so that all the pages below the cursor are released
+    /// again by the next release.
+    fn skip_kept_pages(&self, sync: &mut MonotonePageResourceSync) {
+        let (kept_start, kept_end) = sync.kept.pop_front().unwrap();
+        self.reuse_pages(sync.cursor, bytes_to_pages(kept_start - sync.cursor));
+        sync.cursor = kept_end;
+    }
+
+    /// Zero the pages between the zeroing cursor and the zeroing sentinel in blocks, and let
+    /// the allocating threads that wait for them know after each block. The pages that the
+    /// uncommitter has returned to the OS are already zero, and are skipped.
+    fn zero_released_pages(&self) {
+        let nontemporal = self.zero_nontemporal.load(Ordering::Relaxed);
+        let sentinel =
+            unsafe { Address::from_usize(self.zeroing_sentinel.load(Ordering::Relaxed)) };
+        let mut first = unsafe { Address::from_usize(self.zeroing_cursor.load(Ordering::Relaxed)) };
+        let uncommitter = &VM::VMActivePlan::global().base().uncommitter;
+        let zeroing_start = Instant::now();
+        let mut bytes = 0;
+        while first < sentinel {
+            let last = (first + CONCURRENT_ZEROING_BLOCK_BYTES).min(sentinel);
+            let ranges = if memory::UNCOMMITTED_PAGES_ARE_ZERO {
+                uncommitter.get_committed_ranges(first, last)
+            } else {
+                vec![(first, last)]
+            };
+            for (start, end) in ranges {
+                if nontemporal {
+                    memory::zero_nt(start, end - start);
+                } else {
+                    memory::zero(start, end - start);
+                }
+                bytes += end - start;
+            }
+            self.advance_zeroing_cursor(last);
+            first = last;
+        }
+        // The pages beyond the sentinel have never been allocated, so they are zero.
+        self.advance_zeroing_cursor(Address::MAX);
+        let elapsed = zeroing_start.elapsed().as_secs_f64();
+        if bytes != 0 && elapsed > 0f64 {
+            self.zeroing_rates.lock().unwrap().zeroing = Some(bytes as f64 / elapsed);
+        }
+    }
+
+    fn advance_zeroing_cursor(&self, cursor: Address) {
+        self.zeroing_cursor
+            .store(cursor.as_usize(), Ordering::Release);
+        // Take the lock, so that a thread that has just found the old cursor is already waiting.
+        let _state = self.zeroing_state.lock().unwrap();
+        self.zeroing_progress.notify_all();
+    }
+
+    /// Wait until the zeroing thread has zeroed the pages below `cursor`.
+    fn wait_for_zeroing(&self, cursor: Address) {
+        if cursor.as_usize() <= self.zeroing_cursor.load(Ordering::Acquire) {
+            return;
+        }
+        let wait_start = Instant::now();
+        let mut state = self.zeroing_state.lock().unwrap();
+        while cursor.as_usize() > self.zeroing_cursor.load(Ordering::Acquire) {
+            state = self.zeroing_progress.wait(state).unwrap();
+        }
+        drop(state);
+        self.zeroing_wait_nanos
+            .fetch_add(wait_start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn start_mutator_phase(&self) {
+        self.zeroing_wait_nanos.store(0, Ordering::Relaxed);
+        self.zeroing_rates.lock().unwrap().mutator_start = Some(Instant::now());
+    }
+
+    /// Measure the allocation rate since the zeroing approach was last chosen.
+    fn end_mutator_phase(&self, allocated_bytes: usize) {
+        let mut rates = self.zeroing_rates.lock().unwrap();
+        if let Some(mutator_start) = rates.mutator_start.take() {
+            let wait = Duration::from_nanos(self.zeroing_wait_nanos.load(Ordering::Relaxed));
+            let elapsed = mutator_start
+                .elapsed()
+                .checked_sub(wait)
+                .unwrap_or_default();
+            if allocated_bytes != 0 && elapsed > Duration::default() {
+                rates.allocation = Some(allocated_bytes as f64 / elapsed.as_secs_f64());
+            }
+        }
+    }
+
+    fn release_pages_extent(&self, first: Address, bytes: usize) {
         let pages = crate::util::conversions::bytes_to_pages(bytes);
         debug_assert!(bytes == crate::util::conversions::pages_to_bytes(pages));
+        if bytes != 0 {
+            // Stale metadata, e.g. allocation bits, must not describe the released memory.
+            self.common()
+                .space
+                .unwrap()
+                .common()
+                .metadata
+                .bzero(first, bytes);
+        }
+        self.uncommit_pages(first, pages);
         // FIXME ZERO_PAGES_ON_RELEASE
         // FIXME Options.protectOnRelease
         // FIXME VM.events.tracePageReleased
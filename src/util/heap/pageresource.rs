@@ -3,10 +3,14 @@ use crate::util::address::Address;
 use crate::util::OpaquePointer;
 use crate::vm::ActivePlan;
 
+use std::io;
+use std::slice::Iter;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use super::layout::map::Map;
 use crate::util::heap::layout::heap_layout::VMMap;
+use crate::util::heap::snapshot::{unsupported, SpaceSnapshot};
 use crate::vm::VMBinding;
 
 pub trait PageResource<VM: VMBinding>: 'static {
@@ -39,23 +43,35 @@ pub trait PageResource<VM: VMBinding>: 'static {
             .fetch_sub(reserved_pages, Ordering::Relaxed);
     }
 
+    /// Set how the pages of a zeroed resource are zeroed: with non-temporal stores, and/or by a
+    /// zeroing thread after each release. Without concurrent zeroing, the pages are zeroed when
+    /// they are acquired.
     fn update_zeroing_approach(&self, _nontemporal: bool, concurrent: bool) {
         debug_assert!(!concurrent || self.common().contiguous);
         unimplemented!()
     }
 
-    fn skip_concurrent_zeroing(&self) {
-        unimplemented!()
-    }
+    /// Zero the pages when they are acquired until the next release, instead of concurrently.
+    fn skip_concurrent_zeroing(&self) {}
 
+    /// Start zeroing the released pages in a zeroing thread, ahead of the allocation cursor.
+    /// This must be called after a release, before any page is acquired again. A resource
+    /// without concurrent zeroing zeroes the pages when they are acquired instead.
     fn trigger_concurrent_zeroing(&self) {
-        unimplemented!()
+        self.skip_concurrent_zeroing()
     }
 
+    /// The body of the zeroing thread.
     fn concurrent_zeroing(&self) {
         panic!("This PageResource does not implement concurrent zeroing")
     }
 
+    /// Can the zeroing thread keep ahead of the allocation, as measured so far? A resource
+    /// without concurrent zeroing never keeps up, so its pages are zeroed when they are acquired.
+    fn concurrent_zeroing_keeps_up(&self) -> bool {
+        false
+    }
+
     fn alloc_pages(
         &self,
         reserved_pages: usize,
@@ -92,8 +108,41 @@ pub trait PageResource<VM: VMBinding>: 'static {
         self.common().reserved.load(Ordering::Relaxed)
     }
 
+    /// The pages that are committed for this resource: the pages in use, and the pages released
+    /// by this resource that are not yet returned to the OS.
     fn committed_pages(&self) -> usize {
         self.common().committed.load(Ordering::Relaxed)
+            + self.common().released.load(Ordering::Relaxed)
+    }
+
+    /// Hand the released pages to the uncommitter, which returns them to the OS as the
+    /// `uncommit_policy` option decides. The caller must hold the lock.
+    fn uncommit_pages(&self, start: Address, pages: usize) {
+        VM::VMActivePlan::global().base().uncommitter.release(
+            start,
+            pages,
+            &self.common().released,
+        );
+    }
+
+    /// Take back the pages that are about to be used again from the uncommitter. The caller
+    /// must hold the lock.
+    fn reuse_pages(&self, start: Address, pages: usize) {
+        VM::VMActivePlan::global()
+            .base()
+            .uncommitter
+            .reuse(start, pages);
+    }
+
+    /// Save the memory this resource hands out and the values of its state in the heap snapshot
+    /// of its space.
+    fn snapshot(&self, _snapshot: &mut SpaceSnapshot) -> io::Result<()> {
+        Err(unsupported("The page resource"))
+    }
+
+    /// Restore the state saved by `snapshot()`, after the memory of the snapshot is mapped.
+    fn restore(&self, _state: &mut Iter<usize>) -> io::Result<()> {
+        Err(unsupported("The page resource"))
     }
 
     fn bind_space(&mut self, space: &'static dyn Space<VM>) {
@@ -110,6 +159,8 @@ pub trait PageResource<VM: VMBinding>: 'static {
 pub struct CommonPageResource<VM: VMBinding> {
     reserved: AtomicUsize,
     committed: AtomicUsize,
+    /// The pages released by this resource that are still committed.
+    released: Arc<AtomicUsize>,
 
     pub contiguous: bool,
     pub growable: bool,
@@ -121,6 +172,7 @@ impl<VM: VMBinding> CommonPageResource<VM> {
         CommonPageResource {
             reserved: AtomicUsize::new(0),
             committed: AtomicUsize::new(0),
+            released: Arc::new(AtomicUsize::new(0)),
 
             contiguous,
             growable,
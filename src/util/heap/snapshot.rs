@@ -0,0 +1,354 @@
+//! Heap snapshots: the contents of a quiescent heap written to a file, from which a new process
+//! can restore the heap during `gc_init()`. The heap is at fixed addresses, so the memory of each
+//! space is mapped back where it was, with copy-on-write from the file, and only the state that
+//! is not kept in that memory (e.g. the cursors of the page resources) is written as values.
+//!
+//! A snapshot holds, for each space in the order of `Plan::for_each_space()`:
+//! * its extents: the mapped memory that holds its objects, with their side metadata,
+//! * its regions: other memory that holds state of the space, e.g. a free list,
+//! * its state: the values of the state of the space and its page resource.
+//!
+//! Snapshots need the 64-bit heap layout, in which every space is contiguous. Plans whose spaces
+//! keep tables of their blocks, i.e. Immix, MarkSweep, ConcMarkSweep and MarkCompact, do not
+//! support them. A snapshot is only taken after a full heap GC, so that it holds no garbage,
+//! except with NoGC, which never collects. The objects allocated since that GC are in the
+//! snapshot too. The VM space is not kept: a boot image has to be mapped again with
+//! `map_boot_image()`.
+
+use crate::mmtk::{MMTK, SFT_MAP};
+use crate::plan::CollectionKind;
+use crate::policy::space::{Space, SFT};
+use crate::util::constants::BYTES_IN_PAGE;
+use crate::util::conversions;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_START, MMAP_CHUNK_BYTES};
+use crate::util::heap::layout::Mmapper as _;
+use crate::util::heap::vmrequest::HEAP_LAYOUT_64BIT;
+use crate::util::memory;
+use crate::util::options::PlanSelector;
+use crate::util::Address;
+use crate::vm::VMBinding;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::slice::{self, Iter};
+
+const MAGIC: &[u8; 8] = b"MMTKHEAP";
+const VERSION: usize = 1;
+
+/// What a heap snapshot keeps of a space.
+#[derive(Default)]
+pub struct SpaceSnapshot {
+    /// The memory that holds the objects of the space. Their side metadata is kept as well.
+    pub extents: Vec<(Address, usize)>,
+    /// Other memory that holds the state of the space, e.g. the free list of its page resource.
+    pub regions: Vec<(Address, usize)>,
+    /// The state of the space that is not kept in its memory.
+    pub state: Vec<usize>,
+}
+
+impl SpaceSnapshot {
+    /// Add the memory in `[start, start + bytes)` that `mmapper` has mapped to the extents.
+    pub fn add_mapped_extents(&mut self, mmapper: &Mmapper, start: Address, bytes: usize) {
+        let mut cursor = start;
+        while cursor < start + bytes {
+            if mmapper.is_mapped_address(cursor) {
+                match self.extents.last_mut() {
+                    Some((last, len)) if *last + *len == cursor => *len += MMAP_CHUNK_BYTES,
+                    _ => self.extents.push((cursor, MMAP_CHUNK_BYTES)),
+                }
+            }
+            cursor += MMAP_CHUNK_BYTES;
+        }
+    }
+}
+
+/// Take the next value of the state saved in `SpaceSnapshot::state`.
+pub fn next_value(state: &mut Iter<usize>) -> usize {
+    *state
+        .next()
+        .expect("The state in the heap snapshot is too short")
+}
+
+/// The error for a part of the heap that cannot be saved in or restored from a snapshot.
+pub fn unsupported(what: &str) -> Error {
+    Error::new(
+        ErrorKind::Other,
+        format!("{} cannot be restored from a heap snapshot", what),
+    )
+}
+
+fn invalid(what: String) -> Error {
+    Error::new(ErrorKind::InvalidData, what)
+}
+
+/// The name of the plan, which a snapshot can only be restored with.
+fn plan_name<VM: VMBinding>(mmtk: &MMTK<VM>) -> String {
+    format!("{:?}", mmtk.options.plan)
+}
+
+/// Write the heap to the file at `path`. No GC may be in progress, and the mutators must be
+/// stopped.
+pub fn snapshot_heap<VM: VMBinding>(mmtk: &MMTK<VM>, path: &str) -> io::Result<()> {
+    if !HEAP_LAYOUT_64BIT {
+        return Err(unsupported("A heap with the 32-bit layout"));
+    }
+    // Without a full heap GC right before, the snapshot would keep the garbage of the heap. A
+    // plan that does not collect has no garbage to drop.
+    let info = mmtk.plan.base().gc_info();
+    if mmtk.options.plan != PlanSelector::NoGC
+        && (info.gc == 0 || info.kind != CollectionKind::Full)
+    {
+        return Err(Error::new(
+            ErrorKind::Other,
+            "The last GC before a heap snapshot must be a full heap GC",
+        ));
+    }
+    let mut spaces = vec![];
+    let mut error = None;
+    mmtk.plan.for_each_space(&mut |space| {
+        if error.is_none() {
+            match space.snapshot() {
+                Ok(snapshot) => {
+                    spaces.push((space.get_name(), space.common().metadata.clone(), snapshot))
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+    });
+    if let Some(e) = error {
+        return Err(e);
+    }
+
+    let mut out = SnapshotWriter {
+        out: BufWriter::new(File::create(path)?),
+        offset: 0,
+    };
+    out.bytes(MAGIC)?;
+    out.word(VERSION)?;
+    out.word(HEAP_START.as_usize())?;
+    out.string(&plan_name(mmtk))?;
+    out.words(&mmtk.plan.snapshot_state())?;
+    out.word(spaces.len())?;
+    for (name, metadata, snapshot) in spaces {
+        out.string(name)?;
+        out.word(snapshot.extents.len())?;
+        for &(start, bytes) in &snapshot.extents {
+            out.word(start.as_usize())?;
+            out.word(bytes)?;
+            out.memory(start, bytes)?;
+            for (meta_start, meta_bytes) in metadata.regions(start, bytes) {
+                out.raw(meta_start, meta_bytes)?;
+            }
+        }
+        out.word(snapshot.regions.len())?;
+        for &(start, bytes) in &snapshot.regions {
+            out.word(start.as_usize())?;
+            out.word(bytes)?;
+            out.memory(start, bytes)?;
+        }
+        out.words(&snapshot.state)?;
+    }
+    out.out.flush()
+}
+
+/// Restore the heap from the snapshot at `path`. This must be called in `gc_init()`, after the
+/// spaces are initialized and before anything is allocated.
+pub fn restore_heap<VM: VMBinding>(mmtk: &MMTK<VM>, path: &str) -> io::Result<()> {
+    if !HEAP_LAYOUT_64BIT {
+        return Err(unsupported("A heap with the 32-bit layout"));
+    }
+    let mut input = SnapshotReader {
+        input: BufReader::new(File::open(path)?),
+        offset: 0,
+    };
+    let mut magic = [0u8; 8];
+    input.bytes(&mut magic)?;
+    if &magic != MAGIC || input.word()? != VERSION {
+        return Err(invalid(format!("{} is not a heap snapshot", path)));
+    }
+    if input.word()? != HEAP_START.as_usize() {
+        return Err(invalid(
+            "The snapshot is of a heap at another address".to_owned(),
+        ));
+    }
+    let plan = input.string()?;
+    if plan != plan_name(mmtk) {
+        return Err(invalid(format!("The snapshot is of a {} heap", plan)));
+    }
+    let plan_state = input.words()?;
+
+    let mut spaces: Vec<&dyn Space<VM>> = vec![];
+    mmtk.plan.for_each_space(&mut |space| {
+        // The spaces live as long as the plan, which lives as long as MMTk.
+        spaces.push(unsafe { &*(space as *const dyn Space<VM>) })
+    });
+    if input.word()? != spaces.len() {
+        return Err(invalid("The snapshot has other spaces".to_owned()));
+    }
+    for space in spaces {
+        let name = input.string()?;
+        if name != space.get_name() {
+            return Err(invalid(format!(
+                "The snapshot has {} instead of {}",
+                name,
+                space.get_name()
+            )));
+        }
+        let common = space.common();
+        for _ in 0..input.word()? {
+            let (start, bytes) = (input.address()?, input.word()?);
+            common
+                .mmapper
+                .ensure_mapped(start, conversions::bytes_to_pages(bytes));
+            input.map(start, bytes)?;
+            common.metadata.ensure_mapped(common.mmapper, start, bytes);
+            for (meta_start, meta_bytes) in common.metadata.regions(start, bytes) {
+                input.raw(meta_start, meta_bytes)?;
+            }
+            let first_chunk = conversions::chunk_align_down(start);
+            SFT_MAP.update(
+                space.as_sft() as *const (dyn SFT + Sync),
+                first_chunk,
+                conversions::bytes_to_chunks_up(start + bytes - first_chunk),
+            );
+        }
+        for _ in 0..input.word()? {
+            let (start, bytes) = (input.address()?, input.word()?);
+            input.map(start, bytes)?;
+        }
+        space.restore(&mut input.words()?.iter())?;
+    }
+    mmtk.plan.restore_state(&plan_state);
+    Ok(())
+}
+
+struct SnapshotWriter {
+    out: BufWriter<File>,
+    offset: u64,
+}
+
+impl SnapshotWriter {
+    fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn word(&mut self, value: usize) -> io::Result<()> {
+        self.bytes(&(value as u64).to_le_bytes())
+    }
+
+    fn words(&mut self, values: &[usize]) -> io::Result<()> {
+        self.word(values.len())?;
+        values.iter().try_for_each(|&v| self.word(v))
+    }
+
+    fn string(&mut self, s: &str) -> io::Result<()> {
+        self.word(s.len())?;
+        self.bytes(s.as_bytes())
+    }
+
+    fn raw(&mut self, start: Address, bytes: usize) -> io::Result<()> {
+        self.bytes(unsafe { slice::from_raw_parts(start.to_ptr::<u8>(), bytes) })
+    }
+
+    /// Write memory that is mapped back from the file, so it starts at a page in the file.
+    fn memory(&mut self, start: Address, bytes: usize) -> io::Result<()> {
+        let padding =
+            conversions::raw_align_up(self.offset as usize, BYTES_IN_PAGE) - self.offset as usize;
+        self.bytes(&vec![0u8; padding])?;
+        self.raw(start, bytes)
+    }
+}
+
+struct SnapshotReader {
+    input: BufReader<File>,
+    offset: u64,
+}
+
+impl SnapshotReader {
+    fn bytes(&mut self, bytes: &mut [u8]) -> io::Result<()> {
+        self.input.read_exact(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn word(&mut self) -> io::Result<usize> {
+        let mut bytes = [0u8; 8];
+        self.bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes) as usize)
+    }
+
+    fn address(&mut self) -> io::Result<Address> {
+        Ok(unsafe { Address::from_usize(self.word()?) })
+    }
+
+    fn words(&mut self) -> io::Result<Vec<usize>> {
+        (0..self.word()?).map(|_| self.word()).collect()
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let mut bytes = vec![0u8; self.word()?];
+        self.bytes(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|e| invalid(e.to_string()))
+    }
+
+    fn raw(&mut self, start: Address, bytes: usize) -> io::Result<()> {
+        self.bytes(unsafe { slice::from_raw_parts_mut(start.to_mut_ptr::<u8>(), bytes) })
+    }
+
+    /// Map the memory written by `SnapshotWriter::memory()` at `start`, with copy-on-write.
+    fn map(&mut self, start: Address, bytes: usize) -> io::Result<()> {
+        let offset = conversions::raw_align_up(self.offset as usize, BYTES_IN_PAGE) as u64;
+        memory::mmap_file(start, bytes, self.input.get_ref(), offset)?;
+        self.offset = offset + bytes as u64;
+        self.input.seek(SeekFrom::Start(self.offset))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_is_mapped_back_from_the_file() {
+        let path = std::env::temp_dir().join(format!("mmtk-snapshot-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut page = vec![0u8; 2 * BYTES_IN_PAGE];
+        let start = Address::from_mut_ptr(page.as_mut_ptr()).align_up(BYTES_IN_PAGE);
+        unsafe { start.store(42usize) };
+
+        let mut out = SnapshotWriter {
+            out: BufWriter::new(File::create(path).unwrap()),
+            offset: 0,
+        };
+        out.string("space").unwrap();
+        out.memory(start, BYTES_IN_PAGE).unwrap();
+        out.words(&[1, 2]).unwrap();
+        out.out.flush().unwrap();
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                BYTES_IN_PAGE,
+                libc::PROT_NONE,
+                libc::MAP_ANON | libc::MAP_PRIVATE,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        let to = Address::from_mut_ptr(ptr);
+        let mut input = SnapshotReader {
+            input: BufReader::new(File::open(path).unwrap()),
+            offset: 0,
+        };
+        assert_eq!(input.string().unwrap(), "space");
+        input.map(to, BYTES_IN_PAGE).unwrap();
+        assert_eq!(unsafe { to.load::<usize>() }, 42);
+        assert_eq!(input.words().unwrap(), vec![1, 2]);
+        unsafe { libc::munmap(ptr, BYTES_IN_PAGE) };
+        std::fs::remove_file(path).unwrap();
+    }
+}
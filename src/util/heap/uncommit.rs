@@ -0,0 +1,301 @@
+use crate::util::conversions::{bytes_to_pages, pages_to_bytes};
+use crate::util::memory;
+use crate::util::options::{UncommitPolicy, UnsafeOptionsWrapper};
+use crate::util::Address;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Returns the pages released by page resources to the OS, as the `uncommit_policy` option
+/// decides.
+///
+/// A released page stays committed until it is either uncommitted, or acquired again by a page
+/// resource. Until then, it is counted in the `released` pages of the page resource that released
+/// it, so that `PageResource::committed_pages()` follows the resident memory. Released chunks
+/// return to the global chunk pool and may be acquired by any space, so all the page resources
+/// share one uncommitter.
+pub struct Uncommitter {
+    options: Arc<UnsafeOptionsWrapper>,
+    sync: Mutex<UncommitterSync>,
+}
+
+struct UncommitterSync {
+    /// The released pages that are still committed, by their start address.
+    released: BTreeMap<Address, ReleasedPages>,
+    /// The number of GCs finished so far.
+    gcs: usize,
+}
+
+struct ReleasedPages {
+    pages: usize,
+    /// The counter of released pages of the page resource that released these pages.
+    owner: Arc<AtomicUsize>,
+    /// The number of GCs that had finished when these pages were released.
+    released_at: usize,
+}
+
+impl Uncommitter {
+    pub fn new(options: Arc<UnsafeOptionsWrapper>) -> Self {
+        Uncommitter {
+            options,
+            sync: Mutex::new(UncommitterSync {
+                released: BTreeMap::new(),
+                gcs: 0,
+            }),
+        }
+    }
+
+    /// Take the pages released by a page resource. `owner` counts the released pages of that
+    /// page resource.
+    pub fn release(&self, start: Address, pages: usize, owner: &Arc<AtomicUsize>) {
+        if pages == 0 {
+            return;
+        }
+        let mut sync = self.sync.lock().unwrap();
+        owner.fetch_add(pages, Ordering::Relaxed);
+        let released_at = sync.gcs;
+        let old = sync.released.insert(
+            start,
+            ReleasedPages {
+                pages,
+                owner: owner.clone(),
+                released_at,
+            },
+        );
+        debug_assert!(old.is_none(), "pages at {} are released twice", start);
+        if self.options.uncommit_policy == UncommitPolicy::Eager {
+            self.uncommit_released(&mut sync);
+        }
+    }
+
+    /// Forget the released pages in the given range, as a page resource acquires them again.
+    /// This must be called before the pages are used.
+    pub fn reuse(&self, start: Address, pages: usize) {
+        let end = start + pages_to_bytes(pages);
+        let mut sync = self.sync.lock().unwrap();
+        let overlapping: Vec<Address> = sync
+            .released
+            .range(..end)
+            .rev()
+            .take_while(|(s, r)| **s + pages_to_bytes(r.pages) > start)
+            .map(|(s, _)| *s)
+            .collect();
+        for s in overlapping {
+            let r = sync.released.remove(&s).unwrap();
+            let e = s + pages_to_bytes(r.pages);
+            // Keep the parts outside the reused range.
+            if s < start {
+                Self::split(&mut sync, s, start, &r);
+            }
+            if e > end {
+                Self::split(&mut sync, end, e, &r);
+            }
+            let reused = bytes_to_pages(e.min(end) - s.max(start));
+            r.owner.fetch_sub(reused, Ordering::Relaxed);
+        }
+    }
+
+    /// Uncommit the released pages that the policy no longer keeps, once a GC is finished.
+    pub fn on_gc_end(&self) {
+        let mut sync = self.sync.lock().unwrap();
+        sync.gcs += 1;
+        self.uncommit_released(&mut sync);
+    }
+
+    /// The parts of `[start, end)` that are released and still committed, in address order. The
+    /// other released pages in the range have been uncommitted.
+    pub fn get_committed_ranges(&self, start: Address, end: Address) -> Vec<(Address, Address)> {
+        let sync = self.sync.lock().unwrap();
+        let mut ranges: Vec<(Address, Address)> = sync
+            .released
+            .range(..end)
+            .rev()
+            .take_while(|(s, r)| **s + pages_to_bytes(r.pages) > start)
+            .map(|(s, r)| ((*s).max(start), (*s + pages_to_bytes(r.pages)).min(end)))
+            .collect();
+        ranges.reverse();
+        ranges
+    }
+
+    /// The pages that are released and still committed.
+    pub fn get_released_pages(&self) -> usize {
+        let sync = self.sync.lock().unwrap();
+        sync.released.values().map(|r| r.pages).sum()
+    }
+
+    fn split(sync: &mut UncommitterSync, start: Address, end: Address, r: &ReleasedPages) {
+        sync.released.insert(
+            start,
+            ReleasedPages {
+                pages: bytes_to_pages(end - start),
+                owner: r.owner.clone(),
+                released_at: r.released_at,
+            },
+        );
+    }
+
+    fn uncommit_released(&self, sync: &mut UncommitterSync) {
+        let expired: Vec<Address> = match self.options.uncommit_policy {
+            UncommitPolicy::Never => vec![],
+            UncommitPolicy::Eager => sync.released.keys().cloned().collect(),
+            UncommitPolicy::Lazy => {
+                let after_gcs = self.options.uncommit_after_gcs;
+                let gcs = sync.gcs;
+                sync.released
+                    .iter()
+                    .filter(|(_, r)| r.released_at + after_gcs <= gcs)
+                    .map(|(s, _)| *s)
+                    .collect()
+            }
+            UncommitPolicy::RetainFloor => {
+                // Uncommit the oldest pages first.
+                let mut oldest: Vec<(Address, usize, usize)> = sync
+                    .released
+                    .iter()
+                    .map(|(s, r)| (*s, r.pages, r.released_at))
+                    .collect();
+                oldest.sort_by_key(|&(_, _, released_at)| released_at);
+                let mut retained: usize = oldest.iter().map(|&(_, pages, _)| pages).sum();
+                let floor = bytes_to_pages(self.options.uncommit_floor);
+                oldest
+                    .into_iter()
+                    .take_while(|&(_, pages, _)| {
+                        let over = retained > floor;
+                        retained -= pages;
+                        over
+                    })
+                    .map(|(s, _, _)| s)
+                    .collect()
+            }
+        };
+        for start in expired {
+            let pages = sync.released[&start].pages;
+            // If the pages cannot be uncommitted, they are still committed, so keep them, and
+            // try again later.
+            if let Err(e) = memory::uncommit(start, pages_to_bytes(pages)) {
+                warn!("Failed to uncommit {} pages at {}: {}", pages, start, e);
+                continue;
+            }
+            let r = sync.released.remove(&start).unwrap();
+            r.owner.fetch_sub(r.pages, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+    use crate::util::options::Options;
+
+    const PAGES: usize = 8;
+
+    fn with_pages<F: FnOnce(Address)>(f: F) {
+        let size = PAGES * BYTES_IN_PAGE;
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_ANON | libc::MAP_PRIVATE,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        f(Address::from_mut_ptr(ptr));
+        unsafe { libc::munmap(ptr, size) };
+    }
+
+    fn uncommitter(policy: &str) -> Uncommitter {
+        let options = Arc::new(UnsafeOptionsWrapper::new(Options::default()));
+        assert!(unsafe { options.process("uncommit_policy", policy) });
+        Uncommitter::new(options)
+    }
+
+    #[test]
+    fn eager() {
+        with_pages(|start| {
+            let u = uncommitter("Eager");
+            let owner = Arc::new(AtomicUsize::new(0));
+            u.release(start, PAGES, &owner);
+            assert_eq!(u.get_released_pages(), 0);
+            assert_eq!(owner.load(Ordering::Relaxed), 0);
+        })
+    }
+
+    #[test]
+    fn failed_uncommit_keeps_pages() {
+        with_pages(|start| {
+            let u = uncommitter("Eager");
+            let owner = Arc::new(AtomicUsize::new(0));
+            // The OS rejects a range that does not start at a page.
+            u.release(start + 1usize, 1, &owner);
+            assert_eq!(u.get_released_pages(), 1);
+            assert_eq!(owner.load(Ordering::Relaxed), 1);
+        })
+    }
+
+    #[test]
+    fn lazy() {
+        with_pages(|start| {
+            let u = uncommitter("Lazy");
+            let owner = Arc::new(AtomicUsize::new(0));
+            u.release(start, PAGES, &owner);
+            assert_eq!(owner.load(Ordering::Relaxed), PAGES);
+            u.on_gc_end();
+            assert_eq!(u.get_released_pages(), PAGES);
+            u.on_gc_end();
+            assert_eq!(u.get_released_pages(), 0);
+            assert_eq!(owner.load(Ordering::Relaxed), 0);
+        })
+    }
+
+    #[test]
+    fn retain_floor() {
+        with_pages(|start| {
+            let u = uncommitter("RetainFloor");
+            let floor = 4 * BYTES_IN_PAGE;
+            assert!(unsafe { u.options.process("uncommit_floor", &floor.to_string()) });
+            let owner = Arc::new(AtomicUsize::new(0));
+            u.release(start, 2, &owner);
+            u.on_gc_end();
+            u.release(start + 2 * BYTES_IN_PAGE, 4, &owner);
+            u.on_gc_end();
+            // The older pages go first, until no more than the floor is retained.
+            assert_eq!(u.get_released_pages(), 4);
+            assert_eq!(owner.load(Ordering::Relaxed), 4);
+        })
+    }
+
+    #[test]
+    fn committed_ranges_are_clipped() {
+        with_pages(|start| {
+            let u = uncommitter("Never");
+            let owner = Arc::new(AtomicUsize::new(0));
+            let page = |i: usize| start + i * BYTES_IN_PAGE;
+            u.release(page(0), 2, &owner);
+            u.release(page(4), 3, &owner);
+            assert_eq!(
+                u.get_committed_ranges(page(1), page(5)),
+                vec![(page(1), page(2)), (page(4), page(5))]
+            );
+            assert_eq!(u.get_committed_ranges(page(2), page(4)), vec![]);
+        })
+    }
+
+    #[test]
+    fn reuse_splits_released_pages() {
+        with_pages(|start| {
+            let u = uncommitter("Never");
+            let owner = Arc::new(AtomicUsize::new(0));
+            u.release(start, PAGES, &owner);
+            u.reuse(start + 2 * BYTES_IN_PAGE, 3);
+            assert_eq!(u.get_released_pages(), PAGES - 3);
+            assert_eq!(owner.load(Ordering::Relaxed), PAGES - 3);
+            u.reuse(start, PAGES);
+            assert_eq!(u.get_released_pages(), 0);
+            assert_eq!(owner.load(Ordering::Relaxed), 0);
+        })
+    }
+}
@@ -1,6 +1,8 @@
 use crate::util::Address;
 use libc::{c_void, PROT_EXEC, PROT_NONE, PROT_READ, PROT_WRITE};
+use std::fs::File;
 use std::io::{Error, Result};
+use std::os::unix::io::AsRawFd;
 
 pub fn zero(start: Address, len: usize) {
     unsafe {
@@ -8,6 +10,31 @@ pub fn zero(start: Address, len: usize) {
     }
 }
 
+/// Zero memory with non-temporal stores, which do not bring the memory into the caches. This is
+/// faster for memory that will not be accessed soon. Falls back to `zero()` where non-temporal
+/// stores are not supported, or the memory is not 16-byte aligned.
+pub fn zero_nt(start: Address, len: usize) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use std::arch::x86_64::{__m128i, _mm_setzero_si128, _mm_sfence, _mm_stream_si128};
+        if start.is_aligned_to(16) && len % 16 == 0 {
+            unsafe {
+                let zero = _mm_setzero_si128();
+                let end = (start + len).to_mut_ptr::<__m128i>();
+                let mut cursor = start.to_mut_ptr::<__m128i>();
+                while cursor < end {
+                    _mm_stream_si128(cursor, zero);
+                    cursor = cursor.add(1);
+                }
+                // Make the stores visible before the memory is handed out.
+                _mm_sfence();
+            }
+            return;
+        }
+    }
+    zero(start, len)
+}
+
 /// Demand-zero mmap:
 /// This function guarantees to zero all mapped memory.
 pub fn dzmmap(start: Address, size: usize) -> Result<Address> {
@@ -31,6 +58,55 @@ pub fn dzmmap(start: Address, size: usize) -> Result<Address> {
     }
 }
 
+/// Map `size` bytes of `file`, from `offset`, at `start` with copy-on-write: the memory is
+/// initialized from the file, and writes to it are private to this process and never reach the
+/// file. Any memory mapped at `start` before is replaced. `offset` must be page aligned, and
+/// accessing the pages after the end of the file raises `SIGBUS`.
+pub fn mmap_file(start: Address, size: usize, file: &File, offset: u64) -> Result<Address> {
+    let prot = libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC;
+    let flags = libc::MAP_PRIVATE | libc::MAP_FIXED;
+    let result: *mut c_void = unsafe {
+        libc::mmap(
+            start.to_mut_ptr(),
+            size,
+            prot,
+            flags,
+            file.as_raw_fd(),
+            offset as libc::off_t,
+        )
+    };
+    if result == libc::MAP_FAILED {
+        Err(Error::last_os_error())
+    } else {
+        assert_eq!(
+            Address::from_mut_ptr(result),
+            start,
+            "mmap with MAP_FIXED returned another address"
+        );
+        Ok(start)
+    }
+}
+
+/// Do the pages read as zero once they are uncommitted?
+pub const UNCOMMITTED_PAGES_ARE_ZERO: bool = cfg!(target_os = "linux");
+
+/// Return the physical memory of the pages to the OS, while keeping the pages mapped.
+/// On Linux, we use `MADV_DONTNEED`: the pages stop counting towards RSS at once, and read as
+/// zero when they are accessed again. Elsewhere, we use `MADV_FREE`, with which the OS reclaims
+/// the pages when it needs the memory, and the pages may keep their contents until then.
+pub fn uncommit(start: Address, size: usize) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    let advice = libc::MADV_DONTNEED;
+    #[cfg(not(target_os = "linux"))]
+    let advice = libc::MADV_FREE;
+    let result = unsafe { libc::madvise(start.to_mut_ptr(), size, advice) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
 pub fn munprotect(start: Address, size: usize) -> Result<()> {
     let result =
         unsafe { libc::mprotect(start.to_mut_ptr(), size, PROT_READ | PROT_WRITE | PROT_EXEC) };
@@ -49,3 +125,84 @@ pub fn mprotect(start: Address, size: usize) -> Result<()> {
         Err(Error::from_raw_os_error(result))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+
+    #[test]
+    fn zero_nt_zeroes_memory() {
+        let mut buf = vec![0xffu8; 4 * BYTES_IN_PAGE + 16];
+        let start = Address::from_mut_ptr(buf.as_mut_ptr()).align_up(16);
+        zero_nt(start, 4 * BYTES_IN_PAGE);
+        let offset = start - Address::from_mut_ptr(buf.as_mut_ptr());
+        assert!(buf[offset..offset + 4 * BYTES_IN_PAGE]
+            .iter()
+            .all(|b| *b == 0));
+        assert_eq!(buf[offset + 4 * BYTES_IN_PAGE], 0xff);
+    }
+
+    #[test]
+    fn mmap_file_is_copy_on_write() {
+        use std::io::{Read, Seek, SeekFrom, Write};
+        let path = std::env::temp_dir().join(format!("mmtk-mmap-file-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(&[0x2au8; BYTES_IN_PAGE]).unwrap();
+        // Reserve an address to map the file at.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                BYTES_IN_PAGE,
+                libc::PROT_NONE,
+                libc::MAP_ANON | libc::MAP_PRIVATE,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        let start = Address::from_mut_ptr(ptr);
+        assert_eq!(mmap_file(start, BYTES_IN_PAGE, &file, 0).unwrap(), start);
+        assert_eq!(unsafe { start.load::<u8>() }, 0x2a);
+        unsafe { start.store(1u8) };
+        assert_eq!(unsafe { start.load::<u8>() }, 1);
+        // The write is not written back to the file.
+        let mut first = [0u8];
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_exact(&mut first).unwrap();
+        assert_eq!(first[0], 0x2a);
+        unsafe { libc::munmap(ptr, BYTES_IN_PAGE) };
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn uncommit_keeps_pages_mapped() {
+        let size = 4 * BYTES_IN_PAGE;
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_ANON | libc::MAP_PRIVATE,
+                -1,
+                0,
+            )
+        };
+        assert_ne!(ptr, libc::MAP_FAILED);
+        let start = Address::from_mut_ptr(ptr);
+        unsafe { libc::memset(ptr, 0xff, size) };
+        assert!(uncommit(start, size).is_ok());
+        // The pages are still mapped, and can be used again.
+        unsafe { start.store(42usize) };
+        assert_eq!(unsafe { start.load::<usize>() }, 42);
+        #[cfg(target_os = "linux")]
+        assert_eq!(unsafe { (start + BYTES_IN_PAGE).load::<usize>() }, 0);
+        unsafe { libc::munmap(ptr, size) };
+    }
+}
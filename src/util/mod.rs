@@ -6,7 +6,11 @@ pub mod macros;
 pub mod conversions;
 pub mod address;
 pub mod alloc;
+pub mod alloc_bit;
+pub mod card_table;
 pub mod constants;
+pub mod ephemeron_processor;
+pub mod finalizable_processor;
 pub mod forwarding_word;
 pub mod gc_byte;
 pub mod generic_freelist;
@@ -17,11 +21,13 @@ pub mod logger;
 pub mod memory;
 pub mod opaque_pointer;
 pub mod options;
+pub mod pin_bit;
 pub mod queue;
 pub mod raw_memory_freelist;
 pub mod reference_processor;
 #[cfg(feature = "sanity")]
 pub mod sanity;
+pub mod side_metadata;
 pub mod statistics;
 mod synchronized_counter;
 pub mod treadmill;
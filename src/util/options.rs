@@ -2,17 +2,108 @@ use crate::util::constants::LOG_BYTES_IN_PAGE;
 use std::cell::UnsafeCell;
 use std::default::Default;
 use std::ops::Deref;
+use std::str::FromStr;
 
 custom_derive! {
+    /// How the nursery is zeroed, selected with the `nursery_zeroing` option.
     #[derive(Copy, Clone, EnumFromStr)]
     pub enum NurseryZeroingOptions {
+        /// Zero the pages with memset when they are acquired.
         Temporal,
+        /// Zero the pages with non-temporal stores when they are acquired.
         Nontemporal,
+        /// Zero the nursery in a zeroing thread after each GC, ahead of the allocation.
         Concurrent,
+        /// Zero the nursery concurrently, unless the mutators were measured to allocate faster
+        /// than the zeroing thread zeroes.
         Adaptive,
     }
 }
 
+custom_derive! {
+    /// The GC trigger policies that can be selected with the `gc_trigger` option.
+    #[derive(Copy, Clone, Debug, PartialEq, EnumFromStr)]
+    pub enum GCTriggerSelector {
+        FixedHeapSize,
+        TimeRatio,
+    }
+}
+
+custom_derive! {
+    /// When the pages released by a GC are returned to the OS, selected with the
+    /// `uncommit_policy` option.
+    #[derive(Copy, Clone, Debug, PartialEq, EnumFromStr)]
+    pub enum UncommitPolicy {
+        /// Keep the released pages committed.
+        Never,
+        /// Return the pages as soon as they are released.
+        Eager,
+        /// Return the pages that stay free for `uncommit_after_gcs` GCs.
+        Lazy,
+        /// Keep at most `uncommit_floor` bytes of free pages committed, and return the rest at
+        /// the end of each GC.
+        RetainFloor,
+    }
+}
+
+custom_derive! {
+    /// How generational plans remember the mature objects that are modified by the mutators,
+    /// selected with the `remembered_set` option.
+    #[derive(Copy, Clone, Debug, PartialEq, EnumFromStr)]
+    pub enum RememberedSet {
+        /// Log each modified object once in a buffer, with the unlogged bit in its header.
+        ModBuffer,
+        /// Dirty the card of each modified object, and scan the objects on dirty cards.
+        CardTable,
+    }
+}
+
+custom_derive! {
+    /// How the statistics are written at the end of the harness, selected with the
+    /// `stats_format` option.
+    #[derive(Copy, Clone, Debug, PartialEq, EnumFromStr)]
+    pub enum StatsFormat {
+        /// A tab-separated table between banner lines.
+        Text,
+        /// A JSON object with the values of each counter in each phase.
+        Json,
+        /// A row of column names and a row of values.
+        Csv,
+    }
+}
+
+/// The GC plans that can be selected at boot time with the `plan` option.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlanSelector {
+    NoGC,
+    SemiSpace,
+    GenCopy,
+    MarkSweep,
+    ConcMarkSweep,
+    Immix,
+    MarkCompact,
+    MyGC,
+}
+
+impl FromStr for PlanSelector {
+    type Err = String;
+
+    /// Plan names are case insensitive, e.g. `MMTK_PLAN=gencopy` and `MMTK_PLAN=GenCopy` both select `GenCopy`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nogc" => Ok(PlanSelector::NoGC),
+            "semispace" => Ok(PlanSelector::SemiSpace),
+            "gencopy" => Ok(PlanSelector::GenCopy),
+            "marksweep" => Ok(PlanSelector::MarkSweep),
+            "concmarksweep" => Ok(PlanSelector::ConcMarkSweep),
+            "immix" => Ok(PlanSelector::Immix),
+            "markcompact" => Ok(PlanSelector::MarkCompact),
+            "mygc" => Ok(PlanSelector::MyGC),
+            _ => Err(format!("Unknown plan: {}", s)),
+        }
+    }
+}
+
 pub struct UnsafeOptionsWrapper(UnsafeCell<Options>);
 unsafe impl Sync for UnsafeOptionsWrapper {}
 
@@ -23,7 +114,18 @@ impl UnsafeOptionsWrapper {
     /// # Safety
     /// This method is not thread safe, as internally it acquires a mutable reference to self.
     /// It is supposed to be used by one thread during boot time.
+    ///
+    /// The plan is built along with the MMTk instance that holds these options, so the `plan`
+    /// option is rejected here. It can only be selected with the `MMTK_PLAN` environment
+    /// variable.
     pub unsafe fn process(&self, name: &str, value: &str) -> bool {
+        if name == "plan" {
+            warn!(
+                "The plan cannot be changed to {} once MMTk is created. Set MMTK_PLAN instead.",
+                value
+            );
+            return false;
+        }
         (&mut *self.0.get()).set_from_camelcase_str(name, value)
     }
 }
@@ -49,9 +151,10 @@ macro_rules! options {
             pub fn set_from_str(&mut self, s: &str, val: &str)->bool {
                 match s {
                     $(stringify!($name) => if let Ok(val) = val.parse() {
-                        self.$name = val;
                         let validate_fn = $validator;
-                        validate_fn(val)
+                        let valid = validate_fn(&val);
+                        self.$name = val;
+                        valid
                     } else {
                         false
                     })*
@@ -84,16 +187,46 @@ macro_rules! options {
     ]
 }
 options! {
-    threads:               usize                [|v| v > 0]    = num_cpus::get(),
+    // The GC plan to use. This is only read when MMTK is created, so it can only be set with MMTK_PLAN.
+    plan:                  PlanSelector         [always_valid] = PlanSelector::NoGC,
+    threads:               usize                [|v: &usize| *v > 0] = num_cpus::get(),
     use_short_stack_scans: bool                 [always_valid] = false,
     use_return_barrier:    bool                 [always_valid] = false,
     eager_complete_sweep:  bool                 [always_valid] = false,
     ignore_system_g_c:     bool                 [always_valid] = false,
-    // Note: Not used. To workaround cmd args passed by the running script
+    // Whether the GCs that the VM requests collect the full heap in generational plans, rather
+    // than only the nursery.
+    full_heap_system_gc:   bool                 [always_valid] = false,
+    // The policy that triggers GCs and sizes the heap. This is only read by gc_init().
+    gc_trigger:            GCTriggerSelector    [always_valid] = GCTriggerSelector::TimeRatio,
+    // Whether the heap may be resized between min_heap and max_heap after a GC.
+    // If not, the heap has a fixed size, whatever gc_trigger is.
     variable_size_heap:    bool                 [always_valid] = true,
+    // The min and max heap sizes in bytes. 0 means the heap size passed to gc_init(). A min heap
+    // size above, or a max heap size below, the heap size passed to gc_init() is clamped to it.
+    min_heap:              usize                [always_valid] = 0,
+    max_heap:              usize                [always_valid] = 0,
     no_finalizer:          bool                 [always_valid] = false,
     no_reference_types:    bool                 [always_valid] = false,
     nursery_zeroing:       NurseryZeroingOptions[always_valid] = NurseryZeroingOptions::Temporal,
+    // The remembered set of generational plans. It must not change once mutators are bound.
+    remembered_set:        RememberedSet        [always_valid] = RememberedSet::ModBuffer,
+    // When the pages released by GCs are returned to the OS. By default, they are kept committed.
+    uncommit_policy:       UncommitPolicy       [always_valid] = UncommitPolicy::Never,
+    // The number of GCs that released pages have to stay free for with the lazy uncommit policy.
+    uncommit_after_gcs:    usize                [always_valid] = 2,
+    // The bytes of free pages that are kept committed with the retain-floor uncommit policy.
+    uncommit_floor:        usize                [always_valid] = 0,
+    // The file that the timeline of the work packets is written to as Chrome trace-event JSON
+    // when the harness ends. Empty means the timeline is not recorded.
+    work_trace:            String               [always_valid] = String::new(),
+    // The format and the file that the statistics are written to when the harness ends. Empty
+    // means stdout.
+    stats_format:          StatsFormat          [always_valid] = StatsFormat::Text,
+    stats_output:          String               [always_valid] = String::new(),
+    // The heap snapshot, written by `memory_manager::snapshot_heap()`, that `gc_init()` restores
+    // the heap from. Empty means the heap starts empty.
+    restore_heap:          String               [always_valid] = String::new(),
     // Note: This gets ignored. Use RUST_LOG to specify log level.
     // TODO: Delete this option.
     verbose:               usize                [always_valid] = 0,
@@ -102,7 +235,10 @@ options! {
     // FIXME: These options are set for JikesRVM. We need a proper way to set options.
     //   We need to set these values programmatically in VM specific code.
     vm_space:              bool                 [always_valid] = true,
-    vm_space_size:         usize                [|v| v > 0]    = 0x7cc_cccc,
+    // The size of the VM space with the 32-bit heap layout. The VM space is reserved when MMTk is
+    // created, so a boot image larger than the default needs MMTK_VM_SPACE_SIZE to be set in the
+    // environment.
+    vm_space_size:         usize                [|v: &usize| *v > 0] = 0x7cc_cccc,
 }
 
 impl Options {
@@ -137,7 +273,7 @@ impl Options {
 #[cfg(test)]
 mod tests {
     use crate::util::constants::LOG_BYTES_IN_PAGE;
-    use crate::util::options::Options;
+    use crate::util::options::{Options, PlanSelector, UnsafeOptionsWrapper};
     use crate::util::test_util::serial_test;
 
     const DEFAULT_STRESS_FACTOR: usize = usize::max_value() >> LOG_BYTES_IN_PAGE;
@@ -199,6 +335,63 @@ mod tests {
         })
     }
 
+    #[test]
+    fn with_plan_env_var() {
+        serial_test(|| {
+            std::env::set_var("MMTK_PLAN", "gencopy");
+
+            let res = std::panic::catch_unwind(|| {
+                let options = Options::default();
+                assert_eq!(options.plan, PlanSelector::GenCopy);
+            });
+            assert!(res.is_ok());
+
+            std::env::set_var("MMTK_PLAN", "MarkSweep");
+
+            let res = std::panic::catch_unwind(|| {
+                let options = Options::default();
+                assert_eq!(options.plan, PlanSelector::MarkSweep);
+            });
+            assert!(res.is_ok());
+
+            std::env::remove_var("MMTK_PLAN");
+        })
+    }
+
+    #[test]
+    fn process_rejects_plan() {
+        serial_test(|| {
+            let options = UnsafeOptionsWrapper::new(Options::default());
+            let plan = options.plan;
+            let new_plan = if plan == PlanSelector::GenCopy {
+                "MarkSweep"
+            } else {
+                "GenCopy"
+            };
+            assert!(!unsafe { options.process("plan", new_plan) });
+            assert_eq!(options.plan, plan);
+            // Other options can still be set.
+            assert!(unsafe { options.process("threads", "3") });
+            assert_eq!(options.threads, 3);
+        })
+    }
+
+    #[test]
+    fn with_invalid_plan_env_var() {
+        serial_test(|| {
+            // unknown plan, so use the default plan
+            std::env::set_var("MMTK_PLAN", "abc");
+
+            let res = std::panic::catch_unwind(|| {
+                let options = Options::default();
+                assert_eq!(options.plan, PlanSelector::NoGC);
+            });
+            assert!(res.is_ok());
+
+            std::env::remove_var("MMTK_PLAN");
+        })
+    }
+
     #[test]
     fn with_invalid_env_var_key() {
         serial_test(|| {
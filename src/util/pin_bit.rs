@@ -0,0 +1,72 @@
+//! The pin bits: one bit of global side metadata for each word of the heap, which is set for the
+//! objects that GCs must not move, e.g. buffers that native code accesses through raw pointers.
+//!
+//! Spaces that never move objects ignore the bits. Copy spaces keep pinned objects in place, and
+//! keep the pages they are on when the rest of the space is released.
+
+use crate::util::card_table::CARD_TABLE_SPEC;
+use crate::util::constants::LOG_BYTES_IN_WORD;
+use crate::util::side_metadata::{self, SideMetadataScope, SideMetadataSpec};
+use crate::util::ObjectReference;
+
+/// The pin bits, laid out after the card table.
+pub const PIN_BIT_SPEC: SideMetadataSpec = SideMetadataSpec {
+    scope: SideMetadataScope::Global,
+    offset: CARD_TABLE_SPEC.next_offset(),
+    log_num_of_bits: 0,
+    log_min_obj_size: LOG_BYTES_IN_WORD as usize,
+};
+
+/// Pin `object`. Returns `false` if it was already pinned.
+#[inline]
+pub fn pin_object(object: ObjectReference) -> bool {
+    side_metadata::compare_exchange_atomic(PIN_BIT_SPEC, object.to_address(), 0, 1)
+}
+
+/// Unpin `object`. Returns `false` if it was not pinned.
+#[inline]
+pub fn unpin_object(object: ObjectReference) -> bool {
+    side_metadata::compare_exchange_atomic(PIN_BIT_SPEC, object.to_address(), 1, 0)
+}
+
+/// Whether `object` is pinned.
+#[inline]
+pub fn is_pinned(object: ObjectReference) -> bool {
+    side_metadata::load_atomic(PIN_BIT_SPEC, object.to_address()) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::constants::BYTES_IN_PAGE;
+    use crate::util::heap::layout::heap_layout::Mmapper;
+    use crate::util::heap::layout::vm_layout_constants::HEAP_START;
+    use crate::util::test_util::serial_test;
+    use crate::util::Address;
+
+    // Far from the start of the heap, so the metadata does not overlap with any other test.
+    const DATA_START: Address = HEAP_START.add(0x4000_0000);
+
+    #[test]
+    fn pin_and_unpin() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            side_metadata::ensure_metadata_mapped(
+                PIN_BIT_SPEC,
+                &mmapper,
+                DATA_START,
+                BYTES_IN_PAGE,
+            );
+            let a = unsafe { DATA_START.to_object_reference() };
+            let b = unsafe { (DATA_START + 8usize).to_object_reference() };
+            assert!(pin_object(a));
+            assert!(!pin_object(a));
+            assert!(is_pinned(a));
+            assert!(!is_pinned(b));
+            assert!(unpin_object(a));
+            assert!(!unpin_object(a));
+            assert!(!is_pinned(a));
+            side_metadata::bzero_metadata(PIN_BIT_SPEC, DATA_START, BYTES_IN_PAGE);
+        })
+    }
+}
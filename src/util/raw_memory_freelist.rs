@@ -2,6 +2,9 @@ use super::generic_freelist::*;
 use crate::util::address::Address;
 use crate::util::constants::*;
 use crate::util::conversions;
+use crate::util::heap::snapshot::{next_value, SpaceSnapshot};
+use std::io;
+use std::slice::Iter;
 
 /** log2 of the number of bits used by a free list entry (two entries per unit) */
 const LOG_ENTRY_BITS: usize = LOG_BITS_IN_INT as _;
@@ -68,6 +71,22 @@ impl GenericFreeList for RawMemoryFreeList {
             self.__alloc(size, unit, s)
         }
     }
+
+    fn snapshot(&self, snapshot: &mut SpaceSnapshot) -> io::Result<()> {
+        snapshot
+            .regions
+            .push((self.base, self.high_water - self.base));
+        snapshot
+            .state
+            .extend_from_slice(&[self.high_water.as_usize(), self.current_units as usize]);
+        Ok(())
+    }
+
+    fn restore(&mut self, state: &mut Iter<usize>) -> io::Result<()> {
+        self.high_water = unsafe { Address::from_usize(next_value(state)) };
+        self.current_units = next_value(state) as i32;
+        Ok(())
+    }
 }
 
 impl RawMemoryFreeList {
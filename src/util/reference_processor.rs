@@ -1,17 +1,32 @@
-use std::cell::UnsafeCell;
 use std::sync::Mutex;
 use std::vec::Vec;
 
-use crate::plan::{MutatorContext, TraceLocal};
+use crate::scheduler::gc_works::ProcessEdgesWork;
+use crate::util::ephemeron_processor::EphemeronProcessor;
+use crate::util::ObjectReference;
 use crate::util::OpaquePointer;
-use crate::util::{Address, ObjectReference};
+use crate::vm::ReferenceGlue;
 use crate::vm::VMBinding;
-use crate::vm::{ActivePlan, ReferenceGlue};
 
+/// The reference processors of all the reference semantics, and the ephemeron processor.
+///
+/// A collection processes the references in this order, each step after the transitive
+/// closure of the step before it is complete:
+/// 1. `process_ephemerons()` traces the values of ephemerons with reachable keys, whenever the
+///    closure is drained, until no more ephemerons are resolved.
+/// 2. `retain_soft_refs()` keeps the referents of reachable soft references alive, unless
+///    this is an emergency collection.
+/// 3. `scan_soft_refs()` and `scan_weak_refs()` clear the references whose referents are dead,
+///    and `clear_ephemerons()` clears the ephemerons whose keys are dead.
+/// 4. `scan_phantom_refs()` does the same for phantom references.
+///
+/// Plans whose objects only move after the closure call `forward_refs()` once the new
+/// addresses are known. The cleared references are handed to the VM by `enqueue_refs()`.
 pub struct ReferenceProcessors {
     soft: ReferenceProcessor,
     weak: ReferenceProcessor,
     phantom: ReferenceProcessor,
+    ephemeron: EphemeronProcessor,
 }
 
 impl ReferenceProcessors {
@@ -20,6 +35,7 @@ impl ReferenceProcessors {
             soft: ReferenceProcessor::new(Semantics::SOFT),
             weak: ReferenceProcessor::new(Semantics::WEAK),
             phantom: ReferenceProcessor::new(Semantics::PHANTOM),
+            ephemeron: EphemeronProcessor::new(),
         }
     }
 
@@ -35,6 +51,7 @@ impl ReferenceProcessors {
         self.soft.clear();
         self.weak.clear();
         self.phantom.clear();
+        self.ephemeron.clear();
     }
 
     pub fn add_soft_candidate<VM: VMBinding>(
@@ -61,27 +78,51 @@ impl ReferenceProcessors {
         self.phantom.add_candidate::<VM>(reff, referent);
     }
 
-    pub fn forward_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T) {
-        self.soft.forward::<VM, T>(trace, false);
-        self.weak.forward::<VM, T>(trace, false);
-        self.phantom.forward::<VM, T>(trace, false);
+    pub fn add_ephemeron_candidate<VM: VMBinding>(
+        &self,
+        ephemeron: ObjectReference,
+        key: ObjectReference,
+        value: ObjectReference,
+    ) {
+        self.ephemeron.add_candidate::<VM>(ephemeron, key, value);
     }
 
-    pub fn scan_weak_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, tls: OpaquePointer) {
-        self.soft.scan::<VM, T>(trace, false, false, tls);
-        self.weak.scan::<VM, T>(trace, false, false, tls);
+    pub fn process_ephemerons<E: ProcessEdgesWork>(&self, trace: &mut E) -> bool {
+        self.ephemeron.process::<E>(trace)
     }
 
-    pub fn scan_soft_refs<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, tls: OpaquePointer) {
-        self.soft.scan::<VM, T>(trace, false, false, tls);
+    pub fn clear_ephemerons<E: ProcessEdgesWork>(&self, trace: &mut E) {
+        self.ephemeron.clear_unresolved::<E>(trace);
     }
 
-    pub fn scan_phantom_refs<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        tls: OpaquePointer,
-    ) {
-        self.phantom.scan::<VM, T>(trace, false, false, tls);
+    pub fn retain_soft_refs<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
+        self.soft.retain::<E>(trace, nursery);
+    }
+
+    pub fn scan_soft_refs<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
+        self.soft.scan::<E>(trace, nursery);
+    }
+
+    pub fn scan_weak_refs<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
+        self.weak.scan::<E>(trace, nursery);
+    }
+
+    pub fn scan_phantom_refs<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
+        self.phantom.scan::<E>(trace, nursery);
+    }
+
+    pub fn forward_refs<E: ProcessEdgesWork>(&self, trace: &mut E) {
+        self.soft.forward::<E>(trace);
+        self.weak.forward::<E>(trace);
+        self.phantom.forward::<E>(trace);
+        self.ephemeron.forward::<E>(trace);
+    }
+
+    pub fn enqueue_refs<VM: VMBinding>(&self, tls: OpaquePointer) {
+        self.soft.enqueue::<VM>(tls);
+        self.weak.enqueue::<VM>(tls);
+        self.phantom.enqueue::<VM>(tls);
+        self.ephemeron.enqueue::<VM>(tls);
     }
 }
 
@@ -93,9 +134,7 @@ impl Default for ReferenceProcessors {
 
 // Debug flags
 pub const TRACE: bool = false;
-pub const TRACE_UNREACHABLE: bool = false;
 pub const TRACE_DETAIL: bool = false;
-pub const TRACE_FORWARD: bool = false;
 
 // XXX: We differ from the original implementation
 //      by ignoring "stress," i.e. where the array
@@ -107,10 +146,7 @@ pub const TRACE_FORWARD: bool = false;
 const INITIAL_SIZE: usize = 256;
 
 pub struct ReferenceProcessor {
-    // XXX: To support the possibility of the collector working
-    //      on the reference in parallel, we wrap the structure
-    //      in an UnsafeCell.
-    sync: UnsafeCell<Mutex<ReferenceProcessorSync>>,
+    sync: Mutex<ReferenceProcessorSync>,
 
     /**
      * Semantics
@@ -118,8 +154,6 @@ pub struct ReferenceProcessor {
     semantics: Semantics,
 }
 
-unsafe impl Sync for ReferenceProcessor {}
-
 #[derive(Debug, PartialEq)]
 pub enum Semantics {
     SOFT,
@@ -128,20 +162,17 @@ pub enum Semantics {
 }
 
 struct ReferenceProcessorSync {
-    // XXX: A data race on any of these fields is UB. If
-    //      parallelizing this code, change the types to
-    //      have the correct semantics.
     /**
      * The table of reference objects for the current semantics
      */
-    references: Vec<Address>,
+    references: Vec<ObjectReference>,
 
     /**
-     * In a MarkCompact (or similar) collector, we need to update the {@code references}
-     * field, and then update its contents.  We implement this by saving the pointer in
-     * this untraced field for use during the {@code forward} pass.
+     * The references whose referents were cleared by the current collection.
+     * They are handed to the VM at the end of the collection, when their
+     * addresses are final.
      */
-    unforwarded_references: Option<Vec<Address>>,
+    enqueued_references: Vec<ObjectReference>,
 
     /**
      * Index into the <code>references</code> table for the start of
@@ -153,91 +184,70 @@ struct ReferenceProcessorSync {
 impl ReferenceProcessor {
     pub fn new(semantics: Semantics) -> Self {
         ReferenceProcessor {
-            sync: UnsafeCell::new(Mutex::new(ReferenceProcessorSync {
+            sync: Mutex::new(ReferenceProcessorSync {
                 references: Vec::with_capacity(INITIAL_SIZE),
-                unforwarded_references: None,
+                enqueued_references: vec![],
                 nursery_index: 0,
-            })),
+            }),
             semantics,
         }
     }
 
-    fn sync(&self) -> &Mutex<ReferenceProcessorSync> {
-        unsafe { &*self.sync.get() }
-    }
-
-    // UNSAFE: Bypasses mutex
-    // It is designed to allow getting mut ref from UnsafeCell.
-    // TODO: We may need to rework on this to remove the unsafety.
-    #[allow(clippy::mut_from_ref)]
-    unsafe fn sync_mut(&self) -> &mut ReferenceProcessorSync {
-        (&mut *self.sync.get()).get_mut().unwrap()
-    }
-
     pub fn clear(&self) {
-        let mut sync = self.sync().lock().unwrap();
+        let mut sync = self.sync.lock().unwrap();
         sync.references.clear();
-        sync.unforwarded_references = None;
+        sync.enqueued_references.clear();
         sync.nursery_index = 0;
     }
 
     pub fn add_candidate<VM: VMBinding>(&self, reff: ObjectReference, referent: ObjectReference) {
-        let mut sync = self.sync().lock().unwrap();
+        let mut sync = self.sync.lock().unwrap();
         VM::VMReferenceGlue::set_referent(reff, referent);
-        sync.references.push(reff.to_address());
+        sync.references.push(reff);
     }
 
-    pub fn forward<VM: VMBinding, T: TraceLocal>(&self, trace: &mut T, _nursery: bool) {
-        let mut sync = unsafe { self.sync_mut() };
-        let references: &mut Vec<Address> = &mut sync.references;
-        // XXX: Copies `unforwarded_references` out. Should be fine since it's not accessed
-        //      concurrently & it's set to `None` at the end anyway..
-        let mut unforwarded_references: Vec<Address> = sync.unforwarded_references.clone().unwrap();
+    /**
+     * Update the references and their referents with the addresses the objects are
+     * moved to. This is only needed if objects move after the references were scanned.
+     */
+    pub fn forward<E: ProcessEdgesWork>(&self, trace: &mut E) {
+        let mut sync = self.sync.lock().unwrap();
+        let sync = &mut *sync;
         if TRACE {
             trace!("Starting ReferenceProcessor.forward({:?})", self.semantics);
         }
-        if TRACE_DETAIL {
-            trace!("{:?} Reference table is {:?}", self.semantics, references);
-            trace!(
-                "{:?} unforwardedReferences is {:?}",
-                self.semantics,
-                unforwarded_references
-            );
-        }
-
-        for (i, unforwarded_ref) in unforwarded_references
+        for reference in sync
+            .references
             .iter_mut()
-            .enumerate()
-            .take(references.len())
+            .chain(sync.enqueued_references.iter_mut())
         {
-            let reference = unsafe { unforwarded_ref.to_object_reference() };
+            let referent = <E::VM as VMBinding>::VMReferenceGlue::get_referent(*reference);
+            if !referent.is_null() {
+                <E::VM as VMBinding>::VMReferenceGlue::set_referent(
+                    *reference,
+                    trace.trace_object(referent),
+                );
+            }
+            let new_reference = trace.trace_object(*reference);
             if TRACE_DETAIL {
-                trace!("slot {:?}: forwarding {:?}", i, reference);
+                trace!("forwarding {:?} -> {:?}", reference, new_reference);
             }
-            VM::VMReferenceGlue::set_referent(
-                reference,
-                trace.get_forwarded_referent(VM::VMReferenceGlue::get_referent(reference)),
-            );
-            let new_reference = trace.get_forwarded_reference(reference);
-            *unforwarded_ref = new_reference.to_address();
+            *reference = new_reference;
         }
-
         if TRACE {
             trace!("Ending ReferenceProcessor.forward({:?})", self.semantics)
         }
-        sync.unforwarded_references = None;
     }
 
-    fn scan<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        nursery: bool,
-        retain: bool,
-        tls: OpaquePointer,
-    ) {
-        let sync = unsafe { self.sync_mut() };
-        sync.unforwarded_references = Some(sync.references.clone());
-        let references: &mut Vec<Address> = &mut sync.references;
+    /**
+     * Scan the references (only the ones added since the last collection in a
+     * nursery collection), and drop the ones that are dead or whose referents
+     * are dead. Referents that are dead are cleared, and their references are
+     * enqueued.
+     */
+    fn scan<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
+        let mut sync = self.sync.lock().unwrap();
+        let sync = &mut *sync;
 
         if TRACE {
             trace!("Starting ReferenceProcessor.scan({:?})", self.semantics);
@@ -246,88 +256,133 @@ impl ReferenceProcessor {
         let from_index = to_index;
 
         if TRACE_DETAIL {
-            trace!("{:?} Reference table is {:?}", self.semantics, references);
-        }
-        if retain {
-            for addr in references.iter().skip(from_index) {
-                let reference = unsafe { addr.to_object_reference() };
-                self.retain_referent::<VM, T>(trace, reference);
-            }
-        } else {
-            for i in from_index..references.len() {
-                let reference = unsafe { references[i].to_object_reference() };
-
-                /* Determine liveness (and forward if necessary) the reference */
-                let new_reference = VM::VMReferenceGlue::process_reference(trace, reference, tls);
-                if !new_reference.is_null() {
-                    references[to_index] = new_reference.to_address();
-                    to_index += 1;
-                    if TRACE_DETAIL {
-                        let index = to_index - 1;
-                        trace!(
-                            "SCANNED {} {:?} -> {:?}",
-                            index,
-                            references[index],
-                            unsafe { references[index].to_object_reference() }
-                        );
-                    }
-                }
-            }
             trace!(
-                "{:?} references: {} -> {}",
+                "{:?} Reference table is {:?}",
                 self.semantics,
-                references.len(),
-                to_index
+                sync.references
             );
-            sync.nursery_index = to_index;
-            references.truncate(to_index);
         }
-
-        /* flush out any remset entries generated during the above activities */
-        unsafe {
-            VM::VMActivePlan::mutator(tls).flush_remembered_sets();
+        for i in from_index..sync.references.len() {
+            let reference = sync.references[i];
+
+            /* Determine liveness (and forward if necessary) the reference */
+            if let Some(new_reference) =
+                self.process_reference(trace, reference, &mut sync.enqueued_references)
+            {
+                sync.references[to_index] = new_reference;
+                to_index += 1;
+                if TRACE_DETAIL {
+                    trace!("SCANNED {} {:?} -> {:?}", i, reference, new_reference);
+                }
+            }
         }
+        trace!(
+            "{:?} references: {} -> {}",
+            self.semantics,
+            sync.references.len(),
+            to_index
+        );
+        sync.nursery_index = to_index;
+        sync.references.truncate(to_index);
+
         if TRACE {
             trace!("Ending ReferenceProcessor.scan({:?})", self.semantics);
         }
     }
 
     /**
-     * This method deals only with soft references. It retains the referent
-     * if the reference is definitely reachable.
-     * @param reference the address of the reference. This may or may not
-     * be the address of a heap object, depending on the VM.
-     * @param trace the thread local trace element.
+     * This method deals only with soft references. It retains the referents
+     * of the references that are definitely reachable.
      */
-    fn retain_referent<VM: VMBinding, T: TraceLocal>(
-        &self,
-        trace: &mut T,
-        reference: ObjectReference,
-    ) {
-        debug_assert!(!reference.is_null());
+    fn retain<E: ProcessEdgesWork>(&self, trace: &mut E, nursery: bool) {
         debug_assert!(self.semantics == Semantics::SOFT);
+        let sync = self.sync.lock().unwrap();
+        let from_index = if nursery { sync.nursery_index } else { 0 };
 
-        if TRACE_DETAIL {
-            trace!("Processing reference: {:?}", reference);
+        for &reference in &sync.references[from_index..] {
+            if TRACE_DETAIL {
+                trace!("Processing reference: {:?}", reference);
+            }
+            if !reference.is_live() {
+                /*
+                 * Reference is currently unreachable but may get reachable by the
+                 * following trace. We postpone the decision.
+                 */
+                continue;
+            }
+            /*
+             * Reference is definitely reachable.  Retain the referent.
+             */
+            let reference = trace.trace_object(reference);
+            let referent = <E::VM as VMBinding>::VMReferenceGlue::get_referent(reference);
+            if !referent.is_null() {
+                trace.trace_object(referent);
+            }
+            if TRACE_DETAIL {
+                trace!(" ~> {:?} (retained)", referent);
+            }
         }
+    }
 
+    /**
+     * Process a reference with the current semantics. Returns the reference
+     * (at its new address, if it moved) if it should stay in the table.
+     */
+    fn process_reference<E: ProcessEdgesWork>(
+        &self,
+        trace: &mut E,
+        reference: ObjectReference,
+        enqueued_references: &mut Vec<ObjectReference>,
+    ) -> Option<ObjectReference> {
+        debug_assert!(!reference.is_null());
+
+        /*
+         * If the reference is dead, we're done with it. Let it (and
+         * possibly its referent) be garbage-collected.
+         */
         if !reference.is_live() {
-            /*
-             * Reference is currently unreachable but may get reachable by the
-             * following trace. We postpone the decision.
-             */
-            return;
+            return None;
         }
 
+        /* The reference object is live */
+        let new_reference = trace.trace_object(reference);
+        let old_referent = <E::VM as VMBinding>::VMReferenceGlue::get_referent(new_reference);
+
         /*
-         * Reference is definitely reachable.  Retain the referent.
+         * If the application has cleared the referent the Java spec says
+         * this does not cause the Reference object to be enqueued. We
+         * simply allow the Reference object to fall out of our
+         * waiting list.
          */
-        let referent = VM::VMReferenceGlue::get_referent(reference);
-        if !referent.is_null() {
-            trace.retain_referent(referent);
+        if old_referent.is_null() {
+            return None;
         }
-        if TRACE_DETAIL {
-            trace!(" ~> {:?} (retained)", referent.to_address());
+
+        if old_referent.is_live() {
+            /*
+             * Referent is still reachable in a way that is as strong as
+             * or stronger than the current reference level. Update the
+             * referent in case it was moved.
+             */
+            let new_referent = trace.trace_object(old_referent);
+            <E::VM as VMBinding>::VMReferenceGlue::set_referent(new_reference, new_referent);
+            Some(new_reference)
+        } else {
+            /* Referent is unreachable. Clear the referent and enqueue the reference object. */
+            <E::VM as VMBinding>::VMReferenceGlue::clear_referent(new_reference);
+            enqueued_references.push(new_reference);
+            None
+        }
+    }
+
+    /**
+     * Hand the references cleared by this collection to the VM.
+     */
+    fn enqueue<VM: VMBinding>(&self, tls: OpaquePointer) {
+        let mut sync = self.sync.lock().unwrap();
+        if !sync.enqueued_references.is_empty() {
+            VM::VMReferenceGlue::enqueue_references(&sync.enqueued_references, tls);
+            sync.enqueued_references.clear();
         }
     }
 }
@@ -0,0 +1,28 @@
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START};
+use crate::util::Address;
+
+// Side metadata lives in two address ranges outside the heap: one for global metadata and one
+// for local metadata. Metadata addresses are computed from the offset of the data into the heap,
+// so each range must be able to hold the metadata for the whole heap.
+
+/// The start of the range that holds global metadata.
+#[cfg(all(target_pointer_width = "64", not(feature = "force_32bit_heap_layout")))]
+pub const GLOBAL_SIDE_METADATA_BASE_ADDRESS: Address =
+    unsafe { Address::from_usize(0x0000_6000_0000_0000usize) };
+#[cfg(any(target_pointer_width = "32", feature = "force_32bit_heap_layout"))]
+pub const GLOBAL_SIDE_METADATA_BASE_ADDRESS: Address = unsafe { Address::from_usize(0x1000_0000) };
+
+/// The start of the range that holds local metadata. Spaces never overlap, so all spaces
+/// share this range, and each space lays out its local metadata from the start of it.
+#[cfg(all(target_pointer_width = "64", not(feature = "force_32bit_heap_layout")))]
+pub const LOCAL_SIDE_METADATA_BASE_ADDRESS: Address =
+    unsafe { Address::from_usize(0x0000_7000_0000_0000usize) };
+#[cfg(any(target_pointer_width = "32", feature = "force_32bit_heap_layout"))]
+pub const LOCAL_SIDE_METADATA_BASE_ADDRESS: Address = unsafe { Address::from_usize(0x3800_0000) };
+
+/// The size of each of the global and local metadata ranges.
+pub const SIDE_METADATA_RANGE_BYTES: usize =
+    LOCAL_SIDE_METADATA_BASE_ADDRESS.get_extent(GLOBAL_SIDE_METADATA_BASE_ADDRESS);
+
+/// The size of the heap that side metadata describes.
+pub(super) const HEAP_BYTES: usize = HEAP_END.get_extent(HEAP_START);
@@ -0,0 +1,383 @@
+use super::constants::*;
+use crate::util::alloc_bit;
+use crate::util::card_table;
+use crate::util::constants::{BITS_IN_BYTE, BYTES_IN_PAGE, LOG_BITS_IN_BYTE};
+use crate::util::conversions;
+use crate::util::gc_byte;
+use crate::util::heap::layout::heap_layout::Mmapper;
+use crate::util::heap::layout::mmapper::Mmapper as _;
+use crate::util::heap::layout::vm_layout_constants::{HEAP_END, HEAP_START, MMAP_CHUNK_BYTES};
+use crate::util::memory;
+use crate::util::pin_bit;
+use crate::util::Address;
+use crate::vm::{ObjectModel, VMBinding};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Which spaces have a kind of side metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SideMetadataScope {
+    /// Every space has the metadata, e.g. the side GC byte.
+    Global,
+    /// Only the space that declares the metadata has it.
+    Local,
+}
+
+/// A kind of side metadata: `2^log_num_of_bits` bits for every `2^log_min_obj_size` bytes of data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SideMetadataSpec {
+    pub scope: SideMetadataScope,
+    /// The offset of this metadata from the start of the range of its scope. Specs of the same
+    /// scope are laid out one after another with `next_offset()`.
+    pub offset: usize,
+    /// log2 of the number of bits in each metadata value. A value never spans two bytes, so
+    /// this is at most 3.
+    pub log_num_of_bits: usize,
+    /// log2 of the number of data bytes that each metadata value describes.
+    pub log_min_obj_size: usize,
+}
+
+impl SideMetadataSpec {
+    /// log2 of the number of data bytes described by each metadata byte.
+    const fn log_data_bytes_per_meta_byte(&self) -> usize {
+        self.log_min_obj_size + LOG_BITS_IN_BYTE as usize - self.log_num_of_bits
+    }
+
+    /// The bytes of metadata needed to describe the whole heap.
+    pub const fn extent(&self) -> usize {
+        conversions::raw_align_up(
+            HEAP_BYTES >> self.log_data_bytes_per_meta_byte(),
+            MMAP_CHUNK_BYTES,
+        )
+    }
+
+    /// The offset of the next spec of the same scope.
+    pub const fn next_offset(&self) -> usize {
+        self.offset + self.extent()
+    }
+
+    fn base_address(&self) -> Address {
+        match self.scope {
+            SideMetadataScope::Global => GLOBAL_SIDE_METADATA_BASE_ADDRESS,
+            SideMetadataScope::Local => LOCAL_SIDE_METADATA_BASE_ADDRESS,
+        }
+    }
+
+    /// The mask of a metadata value, before it is shifted into place.
+    fn value_mask(&self) -> u8 {
+        ((1u16 << (1 << self.log_num_of_bits)) - 1) as u8
+    }
+}
+
+/// Returns the address of the metadata byte that holds the metadata value of `data_addr`.
+#[inline]
+pub fn address_to_meta_address(spec: SideMetadataSpec, data_addr: Address) -> Address {
+    debug_assert!(
+        data_addr >= HEAP_START && data_addr <= HEAP_END,
+        "{} is not in the heap, so it has no side metadata",
+        data_addr
+    );
+    spec.base_address()
+        + spec.offset
+        + ((data_addr - HEAP_START) >> spec.log_data_bytes_per_meta_byte())
+}
+
+/// Returns the shift of the metadata value of `data_addr` within its metadata byte.
+#[inline]
+fn meta_bit_shift(spec: SideMetadataSpec, data_addr: Address) -> usize {
+    (((data_addr - HEAP_START) >> spec.log_min_obj_size) << spec.log_num_of_bits)
+        & (BITS_IN_BYTE - 1)
+}
+
+#[inline]
+fn meta_byte(spec: SideMetadataSpec, data_addr: Address) -> &'static AtomicU8 {
+    unsafe { &*address_to_meta_address(spec, data_addr).to_ptr::<AtomicU8>() }
+}
+
+/// Atomically reads the metadata value of `data_addr`.
+#[inline]
+pub fn load_atomic(spec: SideMetadataSpec, data_addr: Address) -> u8 {
+    let byte = meta_byte(spec, data_addr).load(Ordering::SeqCst);
+    (byte >> meta_bit_shift(spec, data_addr)) & spec.value_mask()
+}
+
+/// Atomically writes the metadata value of `data_addr`. The values of other data in the same
+/// metadata byte are left unchanged.
+#[inline]
+pub fn store_atomic(spec: SideMetadataSpec, data_addr: Address, value: u8) {
+    let shift = meta_bit_shift(spec, data_addr);
+    let mask = spec.value_mask() << shift;
+    debug_assert!(value & !spec.value_mask() == 0);
+    let _ = meta_byte(spec, data_addr).fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+        Some((old & !mask) | (value << shift))
+    });
+}
+
+/// Atomically replaces the metadata value of `data_addr` with `new_value` if it is `old_value`.
+///
+/// Returns `true` if the operation succeeds.
+#[inline]
+pub fn compare_exchange_atomic(
+    spec: SideMetadataSpec,
+    data_addr: Address,
+    old_value: u8,
+    new_value: u8,
+) -> bool {
+    let shift = meta_bit_shift(spec, data_addr);
+    let mask = spec.value_mask() << shift;
+    debug_assert!(old_value & !spec.value_mask() == 0 && new_value & !spec.value_mask() == 0);
+    // Only fails if the value differs, not if other values in the same byte change concurrently.
+    meta_byte(spec, data_addr)
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+            if (old & mask) >> shift == old_value {
+                Some((old & !mask) | (new_value << shift))
+            } else {
+                None
+            }
+        })
+        .is_ok()
+}
+
+/// Clears the metadata values of the data in `[start, start + bytes)`.
+pub fn bzero_metadata(spec: SideMetadataSpec, start: Address, bytes: usize) {
+    let end = start + bytes;
+    let meta_start = address_to_meta_address(spec, start);
+    let meta_end = address_to_meta_address(spec, end);
+    let start_shift = meta_bit_shift(spec, start);
+    let end_shift = meta_bit_shift(spec, end);
+    // Bytes shared with data outside the range are only cleared in part.
+    let clear_bits = |meta: Address, from: usize, to: usize| {
+        let mask = ((1u16 << to) - (1u16 << from)) as u8;
+        unsafe { &*meta.to_ptr::<AtomicU8>() }.fetch_and(!mask, Ordering::SeqCst);
+    };
+    if meta_start == meta_end {
+        clear_bits(meta_start, start_shift, end_shift);
+        return;
+    }
+    let mut cursor = meta_start;
+    if start_shift != 0 {
+        clear_bits(meta_start, start_shift, BITS_IN_BYTE);
+        cursor += 1usize;
+    }
+    memory::zero(cursor, meta_end - cursor);
+    if end_shift != 0 {
+        clear_bits(meta_end, 0, end_shift);
+    }
+}
+
+/// Memory maps the metadata of the data in `[start, start + bytes)`, unless it is mapped already.
+pub fn ensure_metadata_mapped(
+    spec: SideMetadataSpec,
+    mmapper: &Mmapper,
+    start: Address,
+    bytes: usize,
+) {
+    let meta_start = conversions::page_align_down(address_to_meta_address(spec, start));
+    let meta_end =
+        (address_to_meta_address(spec, start + bytes - 1usize) + 1usize).align_up(BYTES_IN_PAGE);
+    mmapper.ensure_mapped(
+        meta_start,
+        conversions::bytes_to_pages_up(meta_end - meta_start),
+    );
+}
+
+/// The side metadata of a space.
+#[derive(Clone, Debug, Default)]
+pub struct SideMetadataContext {
+    /// The metadata that every space has.
+    pub global: Vec<SideMetadataSpec>,
+    /// The metadata of this space only.
+    pub local: Vec<SideMetadataSpec>,
+}
+
+impl SideMetadataContext {
+    /// Create the context of a space with the given local metadata. The global metadata is
+    /// whatever MMTk has to keep on the side for the object model of the VM, the allocation bits,
+    /// the card table and the pin bits.
+    pub fn new<VM: VMBinding>(local: Vec<SideMetadataSpec>) -> Self {
+        let mut global = vec![];
+        if !VM::VMObjectModel::HAS_GC_BYTE {
+            global.push(gc_byte::SIDE_GC_BYTE_SPEC);
+        }
+        global.push(alloc_bit::ALLOC_BIT_SPEC);
+        global.push(card_table::CARD_TABLE_SPEC);
+        global.push(pin_bit::PIN_BIT_SPEC);
+        verify_specs(&global, SideMetadataScope::Global);
+        verify_specs(&local, SideMetadataScope::Local);
+        SideMetadataContext { global, local }
+    }
+
+    fn specs(&self) -> impl Iterator<Item = &SideMetadataSpec> {
+        self.global.iter().chain(self.local.iter())
+    }
+
+    /// Memory maps all the metadata of the data in `[start, start + bytes)`. Spaces call this
+    /// whenever they map memory for data.
+    pub fn ensure_mapped(&self, mmapper: &Mmapper, start: Address, bytes: usize) {
+        for &spec in self.specs() {
+            ensure_metadata_mapped(spec, mmapper, start, bytes);
+        }
+    }
+
+    /// The memory that holds the metadata of the data in `[start, start + bytes)`, as `(start,
+    /// bytes)` pairs. The range must be aligned so that no metadata byte also describes data
+    /// outside of it.
+    pub fn regions(&self, start: Address, bytes: usize) -> Vec<(Address, usize)> {
+        self.specs()
+            .map(|&spec| {
+                let meta_start = address_to_meta_address(spec, start);
+                (
+                    meta_start,
+                    address_to_meta_address(spec, start + bytes) - meta_start,
+                )
+            })
+            .collect()
+    }
+
+    /// Clears all the metadata of the data in `[start, start + bytes)`. Memory that is reused
+    /// for new data must not keep the metadata of the data it held before.
+    pub fn bzero(&self, start: Address, bytes: usize) {
+        for &spec in self.specs() {
+            bzero_metadata(spec, start, bytes);
+        }
+    }
+}
+
+/// Checks that the specs have the given scope, and that they neither overlap nor leave their range.
+fn verify_specs(specs: &[SideMetadataSpec], scope: SideMetadataScope) {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for spec in specs {
+        assert_eq!(spec.scope, scope, "{:?} is in the wrong scope", spec);
+        assert!(
+            spec.log_num_of_bits <= LOG_BITS_IN_BYTE as usize,
+            "{:?} has values larger than a byte",
+            spec
+        );
+        assert!(
+            spec.next_offset() <= SIDE_METADATA_RANGE_BYTES,
+            "{:?} does not fit in the side metadata range",
+            spec
+        );
+        for &(offset, next_offset) in &ranges {
+            assert!(
+                spec.next_offset() <= offset || spec.offset >= next_offset,
+                "{:?} overlaps with another spec",
+                spec
+            );
+        }
+        ranges.push((spec.offset, spec.next_offset()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test_util::serial_test;
+
+    const MARK_BIT_SPEC: SideMetadataSpec = SideMetadataSpec {
+        scope: SideMetadataScope::Local,
+        offset: 0,
+        log_num_of_bits: 0,
+        log_min_obj_size: 3,
+    };
+
+    const TWO_BIT_SPEC: SideMetadataSpec = SideMetadataSpec {
+        scope: SideMetadataScope::Local,
+        offset: MARK_BIT_SPEC.next_offset(),
+        log_num_of_bits: 1,
+        log_min_obj_size: 3,
+    };
+
+    // Far from the start of the heap, so the metadata does not overlap with any other test.
+    const DATA_START: Address = HEAP_START.add(0x4000_0000);
+
+    #[test]
+    fn meta_addresses() {
+        assert_eq!(
+            address_to_meta_address(MARK_BIT_SPEC, HEAP_START),
+            LOCAL_SIDE_METADATA_BASE_ADDRESS
+        );
+        // 8 values in a byte, each for 8 bytes of data
+        assert_eq!(
+            address_to_meta_address(MARK_BIT_SPEC, HEAP_START + 63usize),
+            LOCAL_SIDE_METADATA_BASE_ADDRESS
+        );
+        assert_eq!(
+            address_to_meta_address(MARK_BIT_SPEC, HEAP_START + 64usize),
+            LOCAL_SIDE_METADATA_BASE_ADDRESS + 1usize
+        );
+        assert_eq!(meta_bit_shift(MARK_BIT_SPEC, HEAP_START + 24usize), 3);
+        assert_eq!(meta_bit_shift(TWO_BIT_SPEC, HEAP_START + 24usize), 6);
+        assert_eq!(
+            address_to_meta_address(TWO_BIT_SPEC, HEAP_START + 32usize),
+            LOCAL_SIDE_METADATA_BASE_ADDRESS + MARK_BIT_SPEC.extent() + 1usize
+        );
+    }
+
+    #[test]
+    fn specs_fit() {
+        verify_specs(
+            &[
+                gc_byte::SIDE_GC_BYTE_SPEC,
+                alloc_bit::ALLOC_BIT_SPEC,
+                card_table::CARD_TABLE_SPEC,
+                pin_bit::PIN_BIT_SPEC,
+            ],
+            SideMetadataScope::Global,
+        );
+        verify_specs(&[MARK_BIT_SPEC, TWO_BIT_SPEC], SideMetadataScope::Local);
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlapping_specs() {
+        let overlapping = SideMetadataSpec {
+            offset: MARK_BIT_SPEC.extent() / 2,
+            ..MARK_BIT_SPEC
+        };
+        verify_specs(&[MARK_BIT_SPEC, overlapping], SideMetadataScope::Local);
+    }
+
+    #[test]
+    fn load_store_compare_exchange() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            ensure_metadata_mapped(TWO_BIT_SPEC, &mmapper, DATA_START, BYTES_IN_PAGE);
+            let a = DATA_START;
+            let b = DATA_START + 8usize;
+            assert_eq!(load_atomic(TWO_BIT_SPEC, a), 0);
+            store_atomic(TWO_BIT_SPEC, a, 3);
+            store_atomic(TWO_BIT_SPEC, b, 2);
+            assert_eq!(load_atomic(TWO_BIT_SPEC, a), 3);
+            assert_eq!(load_atomic(TWO_BIT_SPEC, b), 2);
+            assert!(!compare_exchange_atomic(TWO_BIT_SPEC, b, 1, 0));
+            assert!(compare_exchange_atomic(TWO_BIT_SPEC, b, 2, 1));
+            assert_eq!(load_atomic(TWO_BIT_SPEC, a), 3);
+            assert_eq!(load_atomic(TWO_BIT_SPEC, b), 1);
+            bzero_metadata(TWO_BIT_SPEC, a, BYTES_IN_PAGE);
+        })
+    }
+
+    #[test]
+    fn bzero_partial_bytes() {
+        serial_test(|| {
+            let mmapper = Mmapper::new();
+            ensure_metadata_mapped(MARK_BIT_SPEC, &mmapper, DATA_START, BYTES_IN_PAGE);
+            let words = BYTES_IN_PAGE >> 3;
+            for i in 0..words {
+                store_atomic(MARK_BIT_SPEC, DATA_START + (i << 3), 1);
+            }
+            // Clear from the 3rd value of the first byte to the 5th value of the 4th byte.
+            bzero_metadata(MARK_BIT_SPEC, DATA_START + 16usize, 200);
+            for i in 0..words {
+                let cleared = (2..27).contains(&i);
+                assert_eq!(
+                    load_atomic(MARK_BIT_SPEC, DATA_START + (i << 3)),
+                    if cleared { 0 } else { 1 },
+                    "value {}",
+                    i
+                );
+            }
+            bzero_metadata(MARK_BIT_SPEC, DATA_START, BYTES_IN_PAGE);
+        })
+    }
+}
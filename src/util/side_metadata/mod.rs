@@ -0,0 +1,14 @@
+//! Side metadata: metadata that is kept in its own address range instead of in the objects or
+//! memory it describes.
+//!
+//! Each kind of metadata is described by a [`SideMetadataSpec`](struct.SideMetadataSpec.html),
+//! which maps every address in the heap to a metadata value of up to 8 bits. Global metadata is
+//! kept for all spaces, and local metadata only for the space that declares it. A space keeps its
+//! specs in a [`SideMetadataContext`](struct.SideMetadataContext.html), and maps the metadata
+//! of its memory on demand, whenever it maps the memory itself.
+
+mod constants;
+mod global;
+
+pub use self::constants::*;
+pub use self::global::*;
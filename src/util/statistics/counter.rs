@@ -15,13 +15,22 @@ pub trait Counter {
     fn merge_phases(&self) -> bool;
     fn implicitly_start(&self) -> bool;
     fn name(&self) -> &String;
+    /// The raw value counted in a phase.
+    fn phase_value(&self, phase: usize) -> u64;
+    /// The raw value counted in all phases (`None`), or in the mutator or the GC phases.
+    fn total_value(&self, mutator: Option<bool>) -> u64;
+    /// Format a raw value in the unit that the counter is reported in.
+    fn format_value(&self, val: u64) -> String;
 }
 
 pub trait Diffable {
     type Val;
     fn current_value() -> Self::Val;
     fn diff(current: &Self::Val, earlier: &Self::Val) -> u64;
-    fn print_diff(val: u64);
+    fn format_diff(val: u64) -> String;
+    fn print_diff(val: u64) {
+        print!("{}", Self::format_diff(val));
+    }
 }
 
 pub struct MonotoneNanoTime;
@@ -38,8 +47,8 @@ impl Diffable for MonotoneNanoTime {
         delta.as_secs() * 1_000_000_000 + u64::from(delta.subsec_nanos())
     }
 
-    fn print_diff(val: u64) {
-        print!("{}", format!("{:.*}", 2, val as f64 / 1e6f64));
+    fn format_diff(val: u64) -> String {
+        format!("{:.*}", 2, val as f64 / 1e6f64)
     }
 }
 
@@ -101,18 +110,7 @@ impl<T: Diffable> Counter for LongCounter<T> {
     }
 
     fn print_total(&self, mutator: Option<bool>) {
-        match mutator {
-            None => self.print_value(self.total_count),
-            Some(m) => {
-                let mut total = 0;
-                let mut p = if m { 0 } else { 1 };
-                while p <= self.stats.get_phase() {
-                    total += self.count[p];
-                    p += 2;
-                }
-                self.print_value(total);
-            }
-        };
+        self.print_value(self.total_value(mutator));
     }
 
     fn print_min(&self, mutator: bool) {
@@ -157,6 +155,29 @@ impl<T: Diffable> Counter for LongCounter<T> {
     fn name(&self) -> &String {
         &self.name
     }
+
+    fn phase_value(&self, phase: usize) -> u64 {
+        self.count[phase]
+    }
+
+    fn total_value(&self, mutator: Option<bool>) -> u64 {
+        match mutator {
+            None => self.total_count,
+            Some(m) => {
+                let mut total = 0;
+                let mut p = if m { 0 } else { 1 };
+                while p <= self.stats.get_phase() {
+                    total += self.count[p];
+                    p += 2;
+                }
+                total
+            }
+        }
+    }
+
+    fn format_value(&self, val: u64) -> String {
+        T::format_diff(val)
+    }
 }
 
 impl<T: Diffable> LongCounter<T> {
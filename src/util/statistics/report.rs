@@ -0,0 +1,203 @@
+//! The statistics gathered during the harness, in a structured form that can be written as a
+//! text table, as JSON or as CSV. The columns are always in the same order: the GC count, the
+//! counters in the order they were created, then the work-packet statistics sorted by name.
+
+use crate::util::options::StatsFormat;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// The values of one counter. The values are formatted in the unit of the counter, e.g.
+/// milliseconds for timers, so they are written as they are.
+pub struct CounterStats {
+    pub name: String,
+    /// Whether the counter is reported as a single column rather than as mutator and GC columns.
+    pub merge_phases: bool,
+    pub total: String,
+    pub mutator: String,
+    pub gc: String,
+    /// The value in each mutator phase, i.e. before the first GC, between the first two GCs, etc.
+    pub mutator_phases: Vec<String>,
+    /// The value in each GC phase.
+    pub gc_phases: Vec<String>,
+}
+
+pub struct StatsReport {
+    /// The number of GCs during the harness.
+    pub gc: usize,
+    /// The total time of the harness in milliseconds.
+    pub total_time: String,
+    pub counters: Vec<CounterStats>,
+    /// The statistics of the work packets.
+    pub scheduler: BTreeMap<String, String>,
+}
+
+impl StatsReport {
+    /// The columns of the table, as pairs of names and values.
+    pub fn columns(&self) -> Vec<(String, &str)> {
+        let mut columns = vec![("GC".to_owned(), "")];
+        for c in &self.counters {
+            if c.merge_phases {
+                columns.push((c.name.clone(), &c.total));
+            } else {
+                columns.push((format!("{}.mu", c.name), &c.mutator));
+                columns.push((format!("{}.gc", c.name), &c.gc));
+            }
+        }
+        for (name, value) in &self.scheduler {
+            columns.push((name.clone(), value));
+        }
+        columns
+    }
+
+    pub fn write<W: Write>(&self, format: StatsFormat, out: &mut W) -> io::Result<()> {
+        match format {
+            StatsFormat::Text => self.write_text(out),
+            StatsFormat::Json => self.write_json(out),
+            StatsFormat::Csv => self.write_csv(out),
+        }
+    }
+
+    /// Write the table between the banner lines that `harness_end` has always printed.
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "============================ MMTk Statistics Totals ============================"
+        )?;
+        let gc = self.gc.to_string();
+        let columns = self.columns();
+        for (name, _) in &columns {
+            write!(out, "{}\t", name)?;
+        }
+        writeln!(out)?;
+        for (i, (_, value)) in columns.iter().enumerate() {
+            write!(out, "{}\t", if i == 0 { &gc } else { *value })?;
+        }
+        writeln!(out)?;
+        writeln!(out, "Total time: {} ms", self.total_time)?;
+        writeln!(
+            out,
+            "------------------------------ End MMTk Statistics -----------------------------"
+        )
+    }
+
+    pub fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let gc = self.gc.to_string();
+        let columns = self.columns();
+        let names: Vec<_> = columns.iter().map(|(name, _)| csv_field(name)).collect();
+        writeln!(out, "{}", names.join(","))?;
+        let values: Vec<_> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, (_, value))| csv_field(if i == 0 { &gc } else { value }))
+            .collect();
+        writeln!(out, "{}", values.join(","))
+    }
+
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"gc\": {},", self.gc)?;
+        writeln!(out, "  \"total_time\": {},", json_value(&self.total_time))?;
+        writeln!(out, "  \"counters\": [")?;
+        for (i, c) in self.counters.iter().enumerate() {
+            let phases = |values: &[String]| {
+                let values: Vec<_> = values.iter().map(|v| json_value(v)).collect();
+                format!("[{}]", values.join(", "))
+            };
+            write!(
+                out,
+                "    {{\"name\": {}, \"merge_phases\": {}, \"total\": {}, \"mutator\": {}, \"gc\": {}, \"mutator_phases\": {}, \"gc_phases\": {}}}",
+                json_string(&c.name),
+                c.merge_phases,
+                json_value(&c.total),
+                json_value(&c.mutator),
+                json_value(&c.gc),
+                phases(&c.mutator_phases),
+                phases(&c.gc_phases),
+            )?;
+            writeln!(out, "{}", separator(i, self.counters.len()))?;
+        }
+        writeln!(out, "  ],")?;
+        writeln!(out, "  \"scheduler\": {{")?;
+        for (i, (name, value)) in self.scheduler.iter().enumerate() {
+            write!(out, "    {}: {}", json_string(name), json_value(value))?;
+            writeln!(out, "{}", separator(i, self.scheduler.len()))?;
+        }
+        writeln!(out, "  }}")?;
+        writeln!(out, "}}")
+    }
+}
+
+/// The comma after the `i`th of `len` elements of a JSON array or object.
+fn separator(i: usize, len: usize) -> &'static str {
+    if i + 1 < len {
+        ","
+    } else {
+        ""
+    }
+}
+
+fn json_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The values are numbers, but are quoted if they are not, so that the output is always valid.
+fn json_value(s: &str) -> String {
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() => s.to_owned(),
+        _ => json_string(s),
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> StatsReport {
+        let mut scheduler = BTreeMap::new();
+        scheduler.insert("works.Prepare.count".to_owned(), "2".to_owned());
+        scheduler.insert("total-works.count".to_owned(), "10".to_owned());
+        StatsReport {
+            gc: 2,
+            total_time: "3.50".to_owned(),
+            counters: vec![CounterStats {
+                name: "time".to_owned(),
+                merge_phases: false,
+                total: "3.50".to_owned(),
+                mutator: "3.00".to_owned(),
+                gc: "0.50".to_owned(),
+                mutator_phases: vec!["1.00".to_owned(), "1.00".to_owned(), "1.00".to_owned()],
+                gc_phases: vec!["0.25".to_owned(), "0.25".to_owned()],
+            }],
+            scheduler,
+        }
+    }
+
+    #[test]
+    fn csv_columns_are_ordered() {
+        let mut out = vec![];
+        report().write_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "GC,time.mu,time.gc,total-works.count,works.Prepare.count\n2,3.00,0.50,10,2\n"
+        );
+    }
+
+    #[test]
+    fn json_has_phases() {
+        let mut out = vec![];
+        report().write_json(&mut out).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert!(
+            json.contains("\"mutator_phases\": [1.00, 1.00, 1.00], \"gc_phases\": [0.25, 0.25]")
+        );
+        assert!(json.contains("\"total-works.count\": 10,\n    \"works.Prepare.count\": 2\n"));
+    }
+}
@@ -1,8 +1,10 @@
 use crate::mmtk::MMTK;
 use crate::util::statistics::counter::{Counter, LongCounter};
+use crate::util::statistics::report::{CounterStats, StatsReport};
 use crate::util::statistics::Timer;
 use crate::vm::VMBinding;
-use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -115,51 +117,43 @@ impl Stats {
         }
     }
 
-    pub fn print_stats<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
-        println!(
-            "============================ MMTk Statistics Totals ============================"
-        );
-        let scheduler_stat = mmtk.scheduler.statistics();
-        self.print_column_names(&scheduler_stat);
-        print!("{}\t", self.get_phase() / 2);
+    /// The values of the counters and the work-packet statistics gathered so far.
+    pub fn report<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) -> StatsReport {
+        let phase = self.get_phase();
         let counter = self.counters.lock().unwrap();
-        for iter in &(*counter) {
-            let c = iter.lock().unwrap();
-            if c.merge_phases() {
-                c.print_total(None);
-                print!("\t");
-            } else {
-                c.print_total(Some(true));
-                print!("\t");
-                c.print_total(Some(false));
-                print!("\t");
-            }
-        }
-        for value in scheduler_stat.values() {
-            print!("{}\t", value);
+        let counters = counter
+            .iter()
+            .map(|iter| {
+                let c = iter.lock().unwrap();
+                let phases = |first: usize| {
+                    (first..=phase)
+                        .step_by(2)
+                        .map(|p| c.format_value(c.phase_value(p)))
+                        .collect()
+                };
+                CounterStats {
+                    name: c.name().clone(),
+                    merge_phases: c.merge_phases(),
+                    total: c.format_value(c.total_value(None)),
+                    mutator: c.format_value(c.total_value(Some(true))),
+                    gc: c.format_value(c.total_value(Some(false))),
+                    mutator_phases: phases(0),
+                    gc_phases: phases(1),
+                }
+            })
+            .collect();
+        let total_time = self.total_time.lock().unwrap();
+        StatsReport {
+            gc: phase / 2,
+            total_time: total_time.format_value(total_time.total_value(None)),
+            counters,
+            scheduler: mmtk.scheduler.statistics(),
         }
-        println!();
-        print!("Total time: ");
-        self.total_time.lock().unwrap().print_total(None);
-        println!(" ms");
-        println!("------------------------------ End MMTk Statistics -----------------------------")
     }
 
-    pub fn print_column_names(&self, scheduler_stat: &HashMap<String, String>) {
-        print!("GC\t");
-        let counter = self.counters.lock().unwrap();
-        for iter in &(*counter) {
-            let c = iter.lock().unwrap();
-            if c.merge_phases() {
-                print!("{}\t", c.name());
-            } else {
-                print!("{}.mu\t{}.gc\t", c.name(), c.name());
-            }
-        }
-        for name in scheduler_stat.keys() {
-            print!("{}\t", name);
-        }
-        println!();
+    pub fn print_stats<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
+        let stdout = io::stdout();
+        let _ = self.report(mmtk).write_text(&mut stdout.lock());
     }
 
     pub fn start_all(&self) {
@@ -179,7 +173,22 @@ impl Stats {
 
     pub fn stop_all<VM: VMBinding>(&self, mmtk: &'static MMTK<VM>) {
         self.stop_all_counters();
-        self.print_stats(mmtk);
+        let report = self.report(mmtk);
+        let format = mmtk.options.stats_format;
+        let result = if mmtk.options.stats_output.is_empty() {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            report.write(format, &mut out)
+        } else {
+            File::create(&mmtk.options.stats_output).and_then(|file| {
+                let mut out = BufWriter::new(file);
+                report.write(format, &mut out)?;
+                out.flush()
+            })
+        };
+        if let Err(e) = result {
+            warn!("Failed to write the statistics: {}", e);
+        }
     }
 
     fn stop_all_counters(&self) {
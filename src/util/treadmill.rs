@@ -2,14 +2,14 @@ use std::collections::HashSet;
 use std::mem::swap;
 use std::sync::Mutex;
 
-use crate::util::Address;
+use crate::util::ObjectReference;
 
 #[derive(Debug)]
 pub struct TreadMill {
-    from_space: Mutex<HashSet<Address>>,
-    to_space: Mutex<HashSet<Address>>,
-    collect_nursery: Mutex<HashSet<Address>>,
-    alloc_nursery: Mutex<HashSet<Address>>,
+    from_space: Mutex<HashSet<ObjectReference>>,
+    to_space: Mutex<HashSet<ObjectReference>>,
+    collect_nursery: Mutex<HashSet<ObjectReference>>,
+    alloc_nursery: Mutex<HashSet<ObjectReference>>,
 }
 
 impl TreadMill {
@@ -22,17 +22,17 @@ impl TreadMill {
         }
     }
 
-    pub fn add_to_treadmill(&self, cell: Address, nursery: bool) {
+    pub fn add_to_treadmill(&self, object: ObjectReference, nursery: bool) {
         if nursery {
-            // println!("+ an {}", cell);
-            self.alloc_nursery.lock().unwrap().insert(cell);
+            // println!("+ an {}", object);
+            self.alloc_nursery.lock().unwrap().insert(object);
         } else {
-            // println!("+ ts {}", cell);
-            self.to_space.lock().unwrap().insert(cell);
+            // println!("+ ts {}", object);
+            self.to_space.lock().unwrap().insert(object);
         }
     }
 
-    pub fn collect_nursery(&self) -> Vec<Address> {
+    pub fn collect_nursery(&self) -> Vec<ObjectReference> {
         let mut guard = self.collect_nursery.lock().unwrap();
         let vals = guard.iter().copied().collect();
         guard.clear();
@@ -40,7 +40,7 @@ impl TreadMill {
         vals
     }
 
-    pub fn collect(&self) -> Vec<Address> {
+    pub fn collect(&self) -> Vec<ObjectReference> {
         let mut guard = self.from_space.lock().unwrap();
         let vals = guard.iter().copied().collect();
         guard.clear();
@@ -48,19 +48,37 @@ impl TreadMill {
         vals
     }
 
-    pub fn copy(&self, cell: Address, is_in_nursery: bool) {
+    pub fn copy(&self, object: ObjectReference, is_in_nursery: bool) {
         if is_in_nursery {
             let mut guard = self.collect_nursery.lock().unwrap();
-            debug_assert!(guard.contains(&cell));
-            guard.remove(&cell);
-        // println!("cn -> ts {}", cell);
+            debug_assert!(guard.contains(&object));
+            guard.remove(&object);
+        // println!("cn -> ts {}", object);
         } else {
             let mut guard = self.from_space.lock().unwrap();
-            debug_assert!(guard.contains(&cell));
-            guard.remove(&cell);
-            // println!("fs -> ts {}", cell);
+            debug_assert!(guard.contains(&object));
+            guard.remove(&object);
+            // println!("fs -> ts {}", object);
         }
-        self.to_space.lock().unwrap().insert(cell);
+        self.to_space.lock().unwrap().insert(object);
+    }
+
+    fn lists(&self) -> [&Mutex<HashSet<ObjectReference>>; 4] {
+        [
+            &self.from_space,
+            &self.to_space,
+            &self.collect_nursery,
+            &self.alloc_nursery,
+        ]
+    }
+
+    /// All the objects on the treadmill, whichever list they are on.
+    pub fn objects(&self) -> Vec<ObjectReference> {
+        let mut objects = vec![];
+        for list in &self.lists() {
+            objects.extend(list.lock().unwrap().iter().copied());
+        }
+        objects
     }
 
     pub fn to_space_empty(&self) -> bool {
@@ -1,4 +1,4 @@
-use crate::plan::MutatorContext;
+use crate::plan::{GCInfo, MutatorContext};
 use crate::scheduler::gc_works::ProcessEdgesWork;
 use crate::scheduler::*;
 use crate::util::OpaquePointer;
@@ -57,4 +57,32 @@ pub trait Collection<VM: VMBinding> {
     fn out_of_memory(_tls: OpaquePointer) {
         panic!("Out of memory!");
     }
+
+    /// Inform the VM that a GC has started, before the mutators are stopped. This and the hooks
+    /// below do nothing by default. They are called by GC threads, and must not allocate in or
+    /// trigger a GC.
+    ///
+    /// Arguments:
+    /// * `info`: The number and the kind of the GC, and the bytes used by the heap before it.
+    fn gc_started(_info: &GCInfo) {}
+
+    /// Inform the VM that all the mutators have been stopped for a GC, i.e. that
+    /// `stop_all_mutators()` has returned.
+    ///
+    /// Arguments:
+    /// * `info`: The information about the current GC.
+    fn mutators_stopped(_info: &GCInfo) {}
+
+    /// Inform the VM that the transitive closure of a GC is done, before the spaces are released.
+    ///
+    /// Arguments:
+    /// * `info`: The information about the current GC.
+    fn closure_done(_info: &GCInfo) {}
+
+    /// Inform the VM that a GC has finished, after the mutators are resumed.
+    ///
+    /// Arguments:
+    /// * `info`: The information about the GC, with the bytes used by the heap after it and the
+    ///   time it took.
+    fn gc_finished(_info: &GCInfo) {}
 }
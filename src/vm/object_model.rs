@@ -6,15 +6,15 @@ use crate::AllocationSemantics;
 /// VM-specific methods for object model.
 ///
 /// MMTk requires *at least one byte* (or possibly a few bits in certain plans) for MMTk as per-object metadata.
-/// MMTk currently assumes the available byte/bits are in the *header words* --
-/// this is a strict requirement, and we are looking to revamp the design and support VMs that do not have header words for objects.
+/// The byte is either in the *header words* of objects, or, for VMs that have no room for it there,
+/// in side metadata that MMTk keeps outside the objects (see `HAS_GC_BYTE`).
 ///
 /// Note that depending on the selected GC plan, only a subset of the methods provided here will be used.
 pub trait ObjectModel<VM: VMBinding> {
     /// Whether an exclusive GC byte in each object's header word is available for MMTk.
-    /// If such a byte is not available in the VM, MMTk will handle it in its own memory.
-    ///
-    /// Note: Currently only the `true` value is supported.
+    /// If such a byte is not available in the VM, MMTk will handle it in its own memory,
+    /// as side metadata. In that case, object references must be word aligned, and the first
+    /// word of an object is overwritten with the forwarding pointer when the object is copied.
     const HAS_GC_BYTE: bool = true;
     /// The offset of the GC byte from the object reference, in number of bytes.
     ///
@@ -45,6 +45,9 @@ pub trait ObjectModel<VM: VMBinding> {
     /// determine what the object's reference will be post-copy. Return the address
     /// past the end of the copied object.
     ///
+    /// Sliding compaction moves an object to a lower address that may overlap its current
+    /// storage, so the copy must behave like `memmove`.
+    ///
     /// Arguments:
     /// * `from`: The address of the object to be copied.
     /// * `to`: The target location.
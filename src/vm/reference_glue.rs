@@ -17,7 +17,9 @@ pub trait ReferenceGlue<VM: VMBinding> {
         });
     }
 
-    /// Get the referent from a reference.
+    /// Get the referent from a reference. The referent is held weakly: `Scanning` must not
+    /// report the slot of the referent when it scans the reference, as MMTk traces, clears and
+    /// forwards the referents itself.
     ///
     /// Arguments:
     /// * `object`: The object reference.
@@ -30,16 +32,59 @@ pub trait ReferenceGlue<VM: VMBinding> {
     /// * `referent`: The referent object reference.
     fn set_referent(reff: ObjectReference, referent: ObjectReference);
 
-    /// Process a reference with the current semantics and return an updated reference (e.g. with a new address)
-    /// if the reference is still alive, otherwise return a null object reference.
+    /// Get the value of an ephemeron. The key of an ephemeron is its referent. Like the key, the
+    /// value is held weakly: `Scanning` must not report the slot of the value when it scans the
+    /// ephemeron, or the value would keep its key alive. MMTk traces the value itself once the
+    /// key is found reachable.
+    ///
+    /// VMs that do not add ephemerons with `memory_manager::add_ephemeron_candidate()` need not
+    /// implement this method.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The object reference for the ephemeron.
+    fn get_ephemeron_value(_ephemeron: ObjectReference) -> ObjectReference {
+        panic!("Ephemerons are added, but ReferenceGlue::get_ephemeron_value() is not implemented")
+    }
+
+    /// Set the value of an ephemeron.
+    ///
+    /// VMs that do not add ephemerons with `memory_manager::add_ephemeron_candidate()` need not
+    /// implement this method.
+    ///
+    /// Arguments:
+    /// * `ephemeron`: The object reference for the ephemeron.
+    /// * `value`: The value object reference.
+    fn set_ephemeron_value(_ephemeron: ObjectReference, _value: ObjectReference) {
+        panic!("Ephemerons are added, but ReferenceGlue::set_ephemeron_value() is not implemented")
+    }
+
+    /// Hand the references whose referents were cleared by a collection to the VM, e.g. to put
+    /// them on their reference queues. This is called near the end of the collection, once the
+    /// references are at their final addresses. The default implementation drops them, for VMs
+    /// that have no reference queues.
+    ///
+    /// Arguments:
+    /// * `references`: The references that were cleared.
+    /// * `tls`: The GC thread that is enqueueing the references.
+    fn enqueue_references(_references: &[ObjectReference], _tls: OpaquePointer) {}
+
+    /// MMTk no longer calls this method: it processes the references added with
+    /// `memory_manager::add_weak_candidate()` etc. itself, and clears their referents with
+    /// `set_referent()`. VMs that enqueued the cleared references here should implement
+    /// `enqueue_references()` instead.
     ///
     /// Arguments:
     /// * `trace`: A reference to a `TraceLocal` object for this reference.
     /// * `reference`: The address of the reference. This may or may not be the address of a heap object, depending on the VM.
     /// * `tls`: The GC thread that is processing this reference.
+    #[deprecated(
+        note = "MMTk processes references itself. Implement `enqueue_references()` instead."
+    )]
     fn process_reference<T: TraceLocal>(
-        trace: &mut T,
+        _trace: &mut T,
         reference: ObjectReference,
-        tls: OpaquePointer,
-    ) -> ObjectReference;
+        _tls: OpaquePointer,
+    ) -> ObjectReference {
+        reference
+    }
 }
@@ -1,15 +1,39 @@
-use mmtk::{Plan, SelectedPlan};
+use mmtk::{Mutator, Plan};
 use mmtk::vm::ActivePlan;
 use mmtk::util::OpaquePointer;
 use mmtk::scheduler::*;
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use DummyVM;
 use SINGLETON;
 
+lazy_static! {
+    /// The mutators bound with `api::bind_mutator()`.
+    static ref MUTATORS: Mutex<Vec<usize>> = Mutex::new(vec![]);
+}
+static NEXT_MUTATOR: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// Whether the current thread has bound a mutator. GC threads never do.
+    static IS_MUTATOR: Cell<bool> = Cell::new(false);
+}
+
+/// Make a mutator known to the GC, which prepares and releases it in each GC.
+pub fn register_mutator(mutator: *mut Mutator<DummyVM>) {
+    MUTATORS.lock().unwrap().push(mutator as usize);
+    IS_MUTATOR.with(|is_mutator| is_mutator.set(true));
+}
+
+pub fn unregister_mutator(mutator: *mut Mutator<DummyVM>) {
+    MUTATORS.lock().unwrap().retain(|&m| m != mutator as usize);
+}
+
 pub struct VMActivePlan<> {}
 
 impl ActivePlan<DummyVM> for VMActivePlan {
-    fn global() -> &'static SelectedPlan<DummyVM> {
-        &SINGLETON.plan
+    fn global() -> &'static dyn Plan<VM = DummyVM> {
+        &*SINGLETON.plan
     }
 
     fn worker(_tls: OpaquePointer) -> &'static mut GCWorker<DummyVM> {
@@ -17,15 +41,14 @@ impl ActivePlan<DummyVM> for VMActivePlan {
     }
 
     fn number_of_mutators() -> usize {
-        unimplemented!()
+        MUTATORS.lock().unwrap().len()
     }
 
     unsafe fn is_mutator(_tls: OpaquePointer) -> bool {
-        // FIXME
-        true
+        IS_MUTATOR.with(|is_mutator| is_mutator.get())
     }
 
-    unsafe fn mutator(_tls: OpaquePointer) -> &'static mut <SelectedPlan<DummyVM> as Plan>::Mutator {
+    unsafe fn mutator(_tls: OpaquePointer) -> &'static mut Mutator<DummyVM> {
         unimplemented!()
     }
 
@@ -34,10 +57,11 @@ impl ActivePlan<DummyVM> for VMActivePlan {
     }
 
     fn reset_mutator_iterator() {
-        unimplemented!()
+        NEXT_MUTATOR.store(0, Ordering::SeqCst);
     }
 
-    fn get_next_mutator() -> Option<&'static mut <SelectedPlan<DummyVM> as Plan>::Mutator> {
-        unimplemented!()
+    fn get_next_mutator() -> Option<&'static mut Mutator<DummyVM>> {
+        let next = NEXT_MUTATOR.fetch_add(1, Ordering::SeqCst);
+        MUTATORS.lock().unwrap().get(next).map(|&mutator| unsafe { &mut *(mutator as *mut Mutator<DummyVM>) })
     }
-}
\ No newline at end of file
+}
@@ -6,10 +6,10 @@ use std::ffi::CStr;
 use mmtk::memory_manager;
 use mmtk::AllocationSemantics;
 use mmtk::util::{ObjectReference, OpaquePointer, Address};
-use mmtk::SelectedPlan;
 use mmtk::scheduler::GCWorker;
 use mmtk::Mutator;
 use mmtk::MMTK;
+use active_plan;
 use DummyVM;
 use SINGLETON;
 
@@ -28,23 +28,26 @@ pub extern "C" fn start_control_collector(tls: OpaquePointer) {
 }
 
 #[no_mangle]
-pub extern "C" fn bind_mutator(tls: OpaquePointer) -> *mut Mutator<SelectedPlan<DummyVM>> {
-    Box::into_raw(memory_manager::bind_mutator(&SINGLETON, tls))
+pub extern "C" fn bind_mutator(tls: OpaquePointer) -> *mut Mutator<DummyVM> {
+    let mutator = Box::into_raw(memory_manager::bind_mutator(&SINGLETON, tls));
+    active_plan::register_mutator(mutator);
+    mutator
 }
 
 #[no_mangle]
-pub extern "C" fn destroy_mutator(mutator: *mut Mutator<SelectedPlan<DummyVM>>) {
+pub extern "C" fn destroy_mutator(mutator: *mut Mutator<DummyVM>) {
+    active_plan::unregister_mutator(mutator);
     memory_manager::destroy_mutator(unsafe { Box::from_raw(mutator) })
 }
 
 #[no_mangle]
-pub extern "C" fn alloc(mutator: *mut Mutator<SelectedPlan<DummyVM>>, size: usize,
+pub extern "C" fn alloc(mutator: *mut Mutator<DummyVM>, size: usize,
                     align: usize, offset: isize, semantics: AllocationSemantics) -> Address {
     memory_manager::alloc::<DummyVM>(unsafe { &mut *mutator }, size, align, offset, semantics)
 }
 
 #[no_mangle]
-pub extern "C" fn post_alloc(mutator: *mut Mutator<SelectedPlan<DummyVM>>, refer: ObjectReference, type_refer: ObjectReference,
+pub extern "C" fn post_alloc(mutator: *mut Mutator<DummyVM>, refer: ObjectReference, type_refer: ObjectReference,
                                         bytes: usize, semantics: AllocationSemantics) {
     memory_manager::post_alloc::<DummyVM>(unsafe { &mut *mutator }, refer, type_refer, bytes, semantics)
 }
@@ -125,6 +128,29 @@ pub extern "C" fn add_phantom_candidate(reff: ObjectReference, referent: ObjectR
     memory_manager::add_phantom_candidate(&SINGLETON, reff, referent)
 }
 
+#[no_mangle]
+pub extern "C" fn add_ephemeron_candidate(ephemeron: ObjectReference, key: ObjectReference, value: ObjectReference) {
+    memory_manager::add_ephemeron_candidate(&SINGLETON, ephemeron, key, value)
+}
+
+#[no_mangle]
+pub extern "C" fn add_finalizer(object: ObjectReference) {
+    memory_manager::add_finalizer(&SINGLETON, object)
+}
+
+/// Store an object that is ready to be finalized in `object`, and return true. Returns false,
+/// and leaves `object` untouched, if there is no such object.
+#[no_mangle]
+pub extern "C" fn get_finalizable_object(object: *mut ObjectReference) -> bool {
+    match memory_manager::get_finalizable_object(&SINGLETON) {
+        Some(ready) => {
+            unsafe { *object = ready };
+            true
+        }
+        None => false,
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn harness_begin(tls: OpaquePointer) {
     memory_manager::harness_begin(&SINGLETON, tls)
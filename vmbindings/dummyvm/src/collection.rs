@@ -1,31 +1,118 @@
 use mmtk::vm::Collection;
-use mmtk::MutatorContext;
+use mmtk::{memory_manager, GCInfo, MutatorContext};
 use mmtk::util::OpaquePointer;
 use mmtk::MMTK;
 use mmtk::scheduler::*;
 use mmtk::scheduler::gc_works::*;
+use mmtk::vm::ActivePlan;
+use std::mem;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use active_plan::VMActivePlan;
 use DummyVM;
+use SINGLETON;
+
+/// The mutators of the dummy VM are the threads of the tests. They only stop for a GC at
+/// safepoints: in `block_for_gc()`, or when they call `safepoint()`.
+struct World {
+    /// Whether a GC is waiting for the mutators to stop.
+    stop_requested: bool,
+    /// The number of mutators that are stopped.
+    stopped: usize,
+    /// The number of times that the mutators were resumed.
+    resumed: usize,
+}
+
+lazy_static! {
+    /// The GC hooks that were called, and the information they were given.
+    static ref GC_EVENTS: Mutex<Vec<(&'static str, GCInfo)>> = Mutex::new(vec![]);
+    static ref WORLD: Mutex<World> = Mutex::new(World { stop_requested: false, stopped: 0, resumed: 0 });
+    static ref WORLD_CHANGED: Condvar = Condvar::new();
+}
+
+/// Stop the current mutator until the GC resumes the mutators.
+fn stop_until_resumed() {
+    let mut world = WORLD.lock().unwrap();
+    let resumed = world.resumed;
+    world.stopped += 1;
+    WORLD_CHANGED.notify_all();
+    while world.resumed == resumed {
+        world = WORLD_CHANGED.wait(world).unwrap();
+    }
+    world.stopped -= 1;
+}
+
+/// Stop the current mutator if a GC is waiting for the mutators to stop. A mutator that runs
+/// while a GC is in progress, e.g. during concurrent marking, has to call this from time to time.
+pub fn safepoint() {
+    let stop_requested = WORLD.lock().unwrap().stop_requested;
+    if stop_requested {
+        stop_until_resumed();
+    }
+}
+
+/// Take the GC hooks that were called since the last call.
+pub fn take_gc_events() -> Vec<(&'static str, GCInfo)> {
+    mem::replace(&mut *GC_EVENTS.lock().unwrap(), vec![])
+}
+
+fn record_gc_event(hook: &'static str, info: &GCInfo) {
+    GC_EVENTS.lock().unwrap().push((hook, *info));
+}
+
+/// The number of times that the GCs resumed the mutators.
+pub fn mutators_resumed() -> usize {
+    WORLD.lock().unwrap().resumed
+}
 
 pub struct VMCollection {}
 
 impl Collection<DummyVM> for VMCollection {
     fn stop_all_mutators<E: ProcessEdgesWork<VM=DummyVM>>(_tls: OpaquePointer) {
-        unimplemented!()
+        let mut world = WORLD.lock().unwrap();
+        world.stop_requested = true;
+        while world.stopped < VMActivePlan::number_of_mutators() {
+            world = WORLD_CHANGED.wait(world).unwrap();
+        }
     }
 
     fn resume_mutators(_tls: OpaquePointer) {
-        unimplemented!()
+        let mut world = WORLD.lock().unwrap();
+        world.stop_requested = false;
+        world.resumed += 1;
+        WORLD_CHANGED.notify_all();
     }
 
     fn block_for_gc(_tls: OpaquePointer) {
-        unimplemented!();
+        stop_until_resumed();
+    }
+
+    fn spawn_worker_thread(_tls: OpaquePointer, ctx: Option<&Worker<MMTK<DummyVM>>>) {
+        let worker = ctx.map(|worker| worker as *const GCWorker<DummyVM> as usize);
+        thread::spawn(move || match worker {
+            None => memory_manager::start_control_collector(&SINGLETON, OpaquePointer::UNINITIALIZED),
+            Some(worker) => {
+                let worker = unsafe { &mut *(worker as *mut GCWorker<DummyVM>) };
+                memory_manager::start_worker(OpaquePointer::UNINITIALIZED, worker, &SINGLETON)
+            }
+        });
+    }
+
+    fn prepare_mutator<T: MutatorContext<DummyVM>>(_tls: OpaquePointer, _mutator: &T) {}
+
+    fn gc_started(info: &GCInfo) {
+        record_gc_event("gc_started", info);
+    }
+
+    fn mutators_stopped(info: &GCInfo) {
+        record_gc_event("mutators_stopped", info);
     }
 
-    fn spawn_worker_thread(_tls: OpaquePointer, _ctx: Option<&Worker<MMTK<DummyVM>>>) {
-        unimplemented!();
+    fn closure_done(info: &GCInfo) {
+        record_gc_event("closure_done", info);
     }
 
-    fn prepare_mutator<T: MutatorContext<DummyVM>>(_tls: OpaquePointer, _mutator: &T) {
-        unimplemented!()
+    fn gc_finished(info: &GCInfo) {
+        record_gc_event("gc_finished", info);
     }
-}
\ No newline at end of file
+}
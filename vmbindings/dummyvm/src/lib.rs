@@ -25,6 +25,9 @@ impl VMBinding for DummyVM {
     type VMCollection = collection::VMCollection;
     type VMActivePlan = active_plan::VMActivePlan;
     type VMReferenceGlue = reference_glue::VMReferenceGlue;
+
+    /// Allow alignments larger than a word, so that objects may be padded by whole words.
+    const MAX_ALIGNMENT_SHIFT: usize = 2;
 }
 
 //#[cfg(feature = "dummyvm")]
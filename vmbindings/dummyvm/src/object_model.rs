@@ -1,41 +1,113 @@
 use mmtk::vm::ObjectModel;
 use mmtk::util::{Address, ObjectReference};
-use mmtk::AllocationSemantics;
-use mmtk::CopyContext;
+use mmtk::util::constants::BYTES_IN_WORD;
+use mmtk::{memory_manager, AllocationSemantics, CopyContext, Mutator};
 use DummyVM;
 
+/// The objects of the dummy VM, so that tests can run GCs. An object starts with a word that
+/// holds the GC byte, and is overwritten with the forwarding pointer when the object is copied.
+/// The second word holds the size of the object in its low half and the number of reference
+/// fields in its high half. The reference fields follow, and then any other data.
+pub const HEADER_BYTES: usize = 2 * BYTES_IN_WORD;
+const SIZE_MASK: usize = (1 << 32) - 1;
+
+/// The size of an object with `refs` reference fields and `data_bytes` of other data.
+pub fn object_size(refs: usize, data_bytes: usize) -> usize {
+    HEADER_BYTES + refs * BYTES_IN_WORD + ((data_bytes + BYTES_IN_WORD - 1) & !(BYTES_IN_WORD - 1))
+}
+
+/// Allocate an object with `refs` null reference fields and `data_bytes` of zeroed data.
+pub fn alloc_object(mutator: &mut Mutator<DummyVM>, refs: usize, data_bytes: usize, semantics: AllocationSemantics) -> ObjectReference {
+    alloc_aligned_object(mutator, refs, data_bytes, BYTES_IN_WORD, semantics)
+}
+
+/// Allocate an object like `alloc_object()`, at an address aligned to `align`.
+pub fn alloc_aligned_object(mutator: &mut Mutator<DummyVM>, refs: usize, data_bytes: usize, align: usize, semantics: AllocationSemantics) -> ObjectReference {
+    let size = object_size(refs, data_bytes);
+    let addr = memory_manager::alloc(mutator, size, align, 0, semantics);
+    assert!(addr.is_aligned_to(align));
+    assert!(!addr.is_zero());
+    unsafe {
+        for offset in (0..size).step_by(BYTES_IN_WORD) {
+            (addr + offset).store(0usize);
+        }
+        (addr + BYTES_IN_WORD).store(size | refs << 32);
+    }
+    let object = unsafe { addr.to_object_reference() };
+    memory_manager::post_alloc(mutator, object, object, size, semantics);
+    object
+}
+
+/// The number of reference fields of an object.
+pub fn ref_count(object: ObjectReference) -> usize {
+    unsafe { (object.to_address() + BYTES_IN_WORD).load::<usize>() >> 32 }
+}
+
+/// The slot of the `i`th reference field of an object.
+pub fn ref_slot(object: ObjectReference, i: usize) -> Address {
+    debug_assert!(i < ref_count(object));
+    object.to_address() + HEADER_BYTES + i * BYTES_IN_WORD
+}
+
+/// The `i`th reference field of an object.
+pub fn get_ref(object: ObjectReference, i: usize) -> ObjectReference {
+    unsafe { ref_slot(object, i).load() }
+}
+
+/// Write the `i`th reference field of an object, with the write barrier of the plan.
+pub fn set_ref(mutator: &mut Mutator<DummyVM>, object: ObjectReference, i: usize, value: ObjectReference) {
+    let slot = ref_slot(object, i);
+    memory_manager::record_overwritten_edge(mutator, slot);
+    unsafe { slot.store(value) };
+    memory_manager::record_modified_node(mutator, object);
+}
+
+/// The start of the data after the reference fields of an object.
+pub fn data_start(object: ObjectReference) -> Address {
+    object.to_address() + HEADER_BYTES + ref_count(object) * BYTES_IN_WORD
+}
+
 pub struct VMObjectModel {}
 
 impl ObjectModel<DummyVM> for VMObjectModel {
-    fn copy(_from: ObjectReference, _semantics: AllocationSemantics, _copy_context: &mut impl CopyContext) -> ObjectReference {
-        unimplemented!()
+    fn copy(from: ObjectReference, semantics: AllocationSemantics, copy_context: &mut impl CopyContext) -> ObjectReference {
+        let bytes = Self::get_current_size(from);
+        let dst = copy_context.alloc_copy(from, bytes, BYTES_IN_WORD, 0, semantics);
+        Self::copy_to(from, unsafe { dst.to_object_reference() }, dst);
+        let to = unsafe { dst.to_object_reference() };
+        copy_context.post_copy(to, unsafe { Address::zero() }, bytes, semantics);
+        to
     }
 
-    fn copy_to(_from: ObjectReference, _to: ObjectReference, _region: Address) -> Address {
-        unimplemented!()
+    fn copy_to(from: ObjectReference, to: ObjectReference, _region: Address) -> Address {
+        let bytes = Self::get_current_size(from);
+        if from != to {
+            unsafe { std::ptr::copy(from.to_address().to_ptr::<u8>(), to.to_address().to_mut_ptr::<u8>(), bytes) };
+        }
+        to.to_address() + bytes
     }
 
-    fn get_current_size(_object: ObjectReference) -> usize {
-        unimplemented!()
+    fn get_current_size(object: ObjectReference) -> usize {
+        unsafe { (object.to_address() + BYTES_IN_WORD).load::<usize>() & SIZE_MASK }
     }
 
-    fn get_reference_when_copied_to(_from: ObjectReference, _to: Address) -> ObjectReference {
-        unimplemented!()
+    fn get_reference_when_copied_to(_from: ObjectReference, to: Address) -> ObjectReference {
+        unsafe { to.to_object_reference() }
     }
 
     fn get_type_descriptor(_reference: ObjectReference) -> &'static [i8] {
         unimplemented!()
     }
 
-    fn object_start_ref(_object: ObjectReference) -> Address {
-        unimplemented!()
+    fn object_start_ref(object: ObjectReference) -> Address {
+        object.to_address()
     }
 
-    fn ref_to_address(_object: ObjectReference) -> Address {
-        unimplemented!()
+    fn ref_to_address(object: ObjectReference) -> Address {
+        object.to_address()
     }
 
-    fn dump_object(_object: ObjectReference) {
-        unimplemented!()
+    fn dump_object(object: ObjectReference) {
+        println!("{} ({} bytes, {} references)", object, Self::get_current_size(object), ref_count(object));
     }
 }
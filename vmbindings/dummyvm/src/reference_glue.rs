@@ -1,19 +1,40 @@
 use mmtk::vm::ReferenceGlue;
 use mmtk::util::ObjectReference;
-use mmtk::TraceLocal;
 use mmtk::util::OpaquePointer;
+use mmtk::util::constants::BYTES_IN_WORD;
+use std::mem;
+use std::sync::Mutex;
+use object_model::data_start;
 use DummyVM;
 
+lazy_static! {
+    /// The references that the GCs cleared, in the order they were enqueued.
+    static ref ENQUEUED_REFERENCES: Mutex<Vec<ObjectReference>> = Mutex::new(vec![]);
+}
+
+/// Take the references that were cleared since the last call.
+pub fn take_enqueued_references() -> Vec<ObjectReference> {
+    mem::replace(&mut *ENQUEUED_REFERENCES.lock().unwrap(), vec![])
+}
+
 pub struct VMReferenceGlue {}
 
+/// A reference object of the dummy VM holds its referent in the first word of its data, and an
+/// ephemeron holds its value in the second. The data is not scanned, so both are held weakly.
 impl ReferenceGlue<DummyVM> for VMReferenceGlue {
-    fn set_referent(_reference: ObjectReference, _referent: ObjectReference) {
-        unimplemented!()
+    fn set_referent(reference: ObjectReference, referent: ObjectReference) {
+        unsafe { data_start(reference).store(referent) }
+    }
+    fn get_referent(object: ObjectReference) -> ObjectReference {
+        unsafe { data_start(object).load() }
+    }
+    fn get_ephemeron_value(ephemeron: ObjectReference) -> ObjectReference {
+        unsafe { (data_start(ephemeron) + BYTES_IN_WORD).load() }
     }
-    fn get_referent(_object: ObjectReference) -> ObjectReference {
-        unimplemented!()
+    fn set_ephemeron_value(ephemeron: ObjectReference, value: ObjectReference) {
+        unsafe { (data_start(ephemeron) + BYTES_IN_WORD).store(value) }
     }
-    fn process_reference<T: TraceLocal>(_trace: &mut T, _reference: ObjectReference, _tls: OpaquePointer) -> ObjectReference {
-        unimplemented!()
+    fn enqueue_references(references: &[ObjectReference], _tls: OpaquePointer) {
+        ENQUEUED_REFERENCES.lock().unwrap().extend_from_slice(references);
     }
-}
\ No newline at end of file
+}
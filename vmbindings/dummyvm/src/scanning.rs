@@ -1,29 +1,71 @@
 use mmtk::vm::Scanning;
-use mmtk::{TransitiveClosure, SelectedPlan, Mutator};
-use mmtk::util::{ObjectReference, SynchronizedCounter};
+use mmtk::{TransitiveClosure, Mutator};
+use mmtk::util::{Address, ObjectReference, SynchronizedCounter};
 use mmtk::util::OpaquePointer;
 use mmtk::scheduler::gc_works::*;
+use std::mem;
+use std::sync::Mutex;
+use object_model;
 use crate::DummyVM;
+use SINGLETON;
 
 static COUNTER: SynchronizedCounter = SynchronizedCounter::new(0);
 
+lazy_static! {
+    /// The global roots of the dummy VM. A GC updates the objects in place if it moves them.
+    static ref ROOTS: Mutex<Vec<ObjectReference>> = Mutex::new(vec![]);
+}
+
+/// Add a global root, and return its index.
+pub fn add_root(object: ObjectReference) -> usize {
+    let mut roots = ROOTS.lock().unwrap();
+    roots.push(object);
+    roots.len() - 1
+}
+
+/// The object that a global root refers to.
+pub fn get_root(index: usize) -> ObjectReference {
+    ROOTS.lock().unwrap()[index]
+}
+
+pub fn set_root(index: usize, object: ObjectReference) {
+    ROOTS.lock().unwrap()[index] = object;
+}
+
+fn ref_slots(object: ObjectReference) -> impl Iterator<Item = Address> {
+    (0..object_model::ref_count(object)).map(move |i| object_model::ref_slot(object, i))
+}
+
+/// Process the edges in packets of `W`, in the closure.
+fn process_edges<W: ProcessEdgesWork<VM=DummyVM>>(edges: Vec<Address>, roots: bool) {
+    for packet in edges.chunks(W::CAPACITY) {
+        SINGLETON.scheduler.closure_stage.add(W::new(packet.to_vec(), roots));
+    }
+}
+
 pub struct VMScanning {}
 
 impl Scanning<DummyVM> for VMScanning {
-    fn scan_objects<W: ProcessEdgesWork<VM=DummyVM>>(_objects: &[ObjectReference]) {
-        unimplemented!()
+    fn scan_objects<W: ProcessEdgesWork<VM=DummyVM>>(objects: &[ObjectReference]) {
+        let edges = objects.iter().flat_map(|&object| ref_slots(object)).collect();
+        process_edges::<W>(edges, false);
     }
     fn scan_thread_roots<W: ProcessEdgesWork<VM=DummyVM>>() {
-        unimplemented!()
+        // The mutators keep their references in the global roots.
     }
-    fn scan_thread_root<W: ProcessEdgesWork<VM=DummyVM>>(_mutator: &'static mut Mutator<SelectedPlan<DummyVM>>, _tls: OpaquePointer) {
-        unimplemented!()
+    fn scan_thread_root<W: ProcessEdgesWork<VM=DummyVM>>(_mutator: &'static mut Mutator<DummyVM>, _tls: OpaquePointer) {
     }
     fn scan_vm_specific_roots<W: ProcessEdgesWork<VM=DummyVM>>() {
-        unimplemented!()
+        // The mutators are stopped, so the roots do not move until the GC is over.
+        let mut roots = ROOTS.lock().unwrap();
+        let edges = roots.iter_mut().filter(|object| !object.is_null()).map(|object| Address::from_mut_ptr(object)).collect();
+        mem::drop(roots);
+        process_edges::<W>(edges, true);
     }
-    fn scan_object<T: TransitiveClosure>(_trace: &mut T, _object: ObjectReference, _tls: OpaquePointer) {
-        unimplemented!()
+    fn scan_object<T: TransitiveClosure>(trace: &mut T, object: ObjectReference, _tls: OpaquePointer) {
+        for slot in ref_slots(object) {
+            trace.process_edge(slot);
+        }
     }
 
     fn reset_thread_counter() {
@@ -31,10 +73,9 @@ impl Scanning<DummyVM> for VMScanning {
     }
 
     fn notify_initial_thread_scan_complete(_partial_scan: bool, _tls: OpaquePointer) {
-        unimplemented!()
     }
 
     fn supports_return_barrier() -> bool {
         unimplemented!()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,31 @@
+use super::fixtures::*;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+
+#[test]
+pub fn aligned_objects_survive_gc() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    // Some of the objects need to be padded past the start of their memory to be aligned.
+    let mut live = vec![];
+    for i in 0..1000usize {
+        let object = alloc_aligned_object(mutator, 0, 8, 16, AllocationSemantics::Default);
+        unsafe { data_start(object).store(i) };
+        live.push((add_root(object), i));
+    }
+    gc();
+
+    // The new objects do not take the memory of any of them.
+    for _ in 0..1000 {
+        let object = alloc_aligned_object(mutator, 0, 8, 16, AllocationSemantics::Default);
+        unsafe { data_start(object).store(usize::MAX) };
+    }
+    for &(root, i) in &live {
+        let object = get_root(root);
+        assert_eq!(unsafe { data_start(object).load::<usize>() }, i, "{} was freed", object);
+    }
+}
@@ -0,0 +1,32 @@
+use super::fixtures::*;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+
+#[test]
+pub fn barrier_remembers_mature_objects() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    // A GC promotes the object out of the nursery in the generational plans.
+    let mature = add_root(alloc_object(mutator, 1, 8, AllocationSemantics::Default));
+    gc();
+
+    for round in 0..3usize {
+        // The nursery object is only reachable from the mature object, which the write barrier
+        // remembers, as the next nursery GC does not trace the mature space.
+        let young = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+        unsafe { data_start(young).store(round) };
+        set_ref(mutator, get_root(mature), 0, young);
+        gc();
+        // Garbage that takes the place of dead nursery objects
+        for _ in 0..100 {
+            let garbage = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+            unsafe { data_start(garbage).store(usize::max_value()) };
+        }
+        let young = get_ref(get_root(mature), 0);
+        assert_eq!(unsafe { data_start(young).load::<usize>() }, round);
+    }
+}
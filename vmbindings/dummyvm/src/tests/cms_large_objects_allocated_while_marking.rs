@@ -0,0 +1,55 @@
+use super::fixtures::*;
+use collection::*;
+use mmtk::policy::space::Space;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+const LARGE_OBJECT_BYTES: usize = 64*1024;
+
+#[test]
+pub fn cms_large_objects_allocated_while_marking() {
+    if SINGLETON.options.plan != PlanSelector::ConcMarkSweep {
+        return;
+    }
+    let mutator = init_with_gc(20*1024*1024).unwrap();
+
+    // Fill the heap with garbage until a concurrent collection starts. The initial-mark pause
+    // stops this mutator at a safepoint.
+    loop {
+        alloc_object(mutator, 0, 1024, AllocationSemantics::Default);
+        let resumed = mutators_resumed();
+        safepoint();
+        if mutators_resumed() != resumed {
+            break;
+        }
+    }
+
+    // The collection is marking, and cannot finish before this mutator stops at a safepoint for
+    // the remark pause.
+    let mut roots = vec![];
+    for i in 0..4usize {
+        let object = alloc_object(mutator, 0, LARGE_OBJECT_BYTES, AllocationSemantics::Los);
+        unsafe { data_start(object).store(i) };
+        roots.push((add_root(object), i));
+    }
+    let resumed = mutators_resumed();
+    while mutators_resumed() == resumed {
+        safepoint();
+    }
+
+    // The large objects survive the remark pause, and the next collection.
+    for _ in 0..2 {
+        for &(root, i) in &roots {
+            let object = get_root(root);
+            assert!(SINGLETON.plan.common().get_los().in_space(object));
+            let mut found = false;
+            SINGLETON.plan.common().get_los().enumerate_objects(&mut |o| found |= o == object);
+            assert!(found, "{} was freed", object);
+            assert_eq!(unsafe { data_start(object).load::<usize>() }, i);
+        }
+        gc();
+    }
+}
@@ -0,0 +1,55 @@
+use super::fixtures::*;
+use collection::*;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+const CHILDREN: usize = 8192;
+
+#[test]
+pub fn cms_split_grey_objects() {
+    if SINGLETON.options.plan != PlanSelector::ConcMarkSweep {
+        return;
+    }
+    let mutator = init_with_gc(20*1024*1024).unwrap();
+
+    // Scanning the root greys all its children at once, more than a packet of concurrent marking
+    // holds, so half of them are split off into another packet. Only their children show whether
+    // they were scanned.
+    let root = alloc_object(mutator, CHILDREN, 0, AllocationSemantics::Los);
+    for i in 0..CHILDREN {
+        let child = alloc_object(mutator, 1, 0, AllocationSemantics::Default);
+        let grandchild = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+        unsafe { data_start(grandchild).store(i + 1) };
+        set_ref(mutator, child, 0, grandchild);
+        set_ref(mutator, root, i, child);
+    }
+    let root = add_root(root);
+
+    // Fill the heap with garbage until a concurrent collection starts, and wait for its remark
+    // pause.
+    loop {
+        alloc_object(mutator, 0, 1024, AllocationSemantics::Default);
+        let resumed = mutators_resumed();
+        safepoint();
+        if mutators_resumed() != resumed {
+            break;
+        }
+    }
+    let resumed = mutators_resumed();
+    while mutators_resumed() == resumed {
+        safepoint();
+    }
+
+    // The cells of grandchildren that were swept would be reused, and zeroed.
+    for _ in 0..CHILDREN {
+        alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+    }
+    let root = get_root(root);
+    for i in 0..CHILDREN {
+        let grandchild = get_ref(get_ref(root, i), 0);
+        assert_eq!(unsafe { data_start(grandchild).load::<usize>() }, i + 1, "grandchild {} was freed", i);
+    }
+}
@@ -0,0 +1,36 @@
+use super::fixtures::*;
+use mmtk::memory_manager;
+use mmtk::util::constants::BYTES_IN_WORD;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use SINGLETON;
+
+const OBJECT_BYTES: usize = 4096;
+
+#[test]
+pub fn concurrent_nursery_zeroing() {
+    // Only the nursery of GenCopy is zeroed concurrently.
+    if SINGLETON.options.plan != PlanSelector::GenCopy {
+        return;
+    }
+    assert!(memory_manager::process(&SINGLETON, "nursery_zeroing", "Concurrent"));
+    assert!(memory_manager::process(&SINGLETON, "uncommit_policy", "Lazy"));
+    let mutator = init_with_gc(64*1024*1024).unwrap();
+
+    // Dirty the nursery, and check that the memory allocated after each GC is zeroed again. The
+    // rounds allocate different amounts, so that some of the released pages are uncommitted before
+    // they are used again.
+    for round in 0..6 {
+        let objects = if round % 2 == 0 { 1024 } else { 256 };
+        for _ in 0..objects {
+            let start = memory_manager::alloc(mutator, OBJECT_BYTES, BYTES_IN_WORD, 0, AllocationSemantics::Default);
+            for offset in (0..OBJECT_BYTES).step_by(BYTES_IN_WORD) {
+                unsafe {
+                    assert_eq!((start + offset).load::<usize>(), 0, "{} is not zeroed in round {}", start + offset, round);
+                    (start + offset).store(usize::max_value());
+                }
+            }
+        }
+        gc();
+    }
+}
@@ -0,0 +1,31 @@
+use super::fixtures::*;
+use mmtk::memory_manager;
+use mmtk::util::Address;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+
+#[test]
+pub fn dead_objects_are_not_valid() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    // Some of the dead objects share a line of Immix with a live one.
+    let mut roots = vec![];
+    let mut dead = vec![];
+    for _ in 0..10 {
+        roots.push(add_root(alloc_object(mutator, 0, 48, AllocationSemantics::Default)));
+        dead.push(alloc_object(mutator, 0, 48, AllocationSemantics::Default));
+    }
+    gc();
+    let live: Vec<Address> = roots.iter().map(|&root| get_root(root).to_address()).collect();
+    for &addr in &live {
+        assert!(memory_manager::is_valid_object(addr));
+    }
+    // MarkCompact may have moved a live object to the address of a dead one.
+    for &object in &dead {
+        assert!(live.contains(&object.to_address()) || !memory_manager::is_valid_object(object.to_address()));
+    }
+}
@@ -0,0 +1,28 @@
+use crate::api::*;
+use mmtk::memory_manager;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+use SINGLETON;
+
+#[test]
+pub fn enumerate_objects_visits_allocated_objects() {
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+
+    let mut objects = vec![];
+    for &semantics in &[AllocationSemantics::Default, AllocationSemantics::Los, AllocationSemantics::Default] {
+        let addr = alloc(handle, 64, 8, 0, semantics);
+        let object = unsafe { addr.to_object_reference() };
+        post_alloc(handle, object, object, 64, semantics);
+        objects.push(object);
+    }
+    // Allocated but not yet initialized with post_alloc, so it is not an object yet.
+    let unfinished = unsafe { alloc(handle, 64, 8, 0, AllocationSemantics::Default).to_object_reference() };
+
+    let mut found = vec![];
+    memory_manager::enumerate_objects(&SINGLETON, |object| found.push(object));
+    for object in &objects {
+        assert!(found.contains(object));
+    }
+    assert!(!found.contains(&unfinished));
+}
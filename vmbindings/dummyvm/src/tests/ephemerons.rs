@@ -0,0 +1,74 @@
+use super::fixtures::*;
+use crate::api::*;
+use mmtk::{AllocationSemantics, Mutator};
+use mmtk::util::ObjectReference;
+use mmtk::vm::ReferenceGlue;
+use object_model::*;
+use reference_glue::*;
+use scanning::*;
+use DummyVM;
+
+/// An ephemeron holds its key and its value in its data.
+fn alloc_ephemeron(mutator: &mut Mutator<DummyVM>) -> ObjectReference {
+    alloc_object(mutator, 0, 16, AllocationSemantics::Default)
+}
+
+/// An object with a reference field, and a number to tell it apart
+fn alloc_numbered(mutator: &mut Mutator<DummyVM>, number: usize) -> ObjectReference {
+    let object = alloc_object(mutator, 1, 8, AllocationSemantics::Default);
+    unsafe { data_start(object).store(number) };
+    object
+}
+
+fn number(object: ObjectReference) -> usize {
+    unsafe { data_start(object).load() }
+}
+
+#[test]
+pub fn ephemerons() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    // The value of an ephemeron with a reachable key is kept alive.
+    let reachable_key = add_root(alloc_numbered(mutator, 1));
+    let resolved = add_root(alloc_ephemeron(mutator));
+    let value = alloc_numbered(mutator, 2);
+    add_ephemeron_candidate(get_root(resolved), get_root(reachable_key), value);
+    // The value may reach the key of another ephemeron.
+    let chained_key = alloc_numbered(mutator, 3);
+    set_ref(mutator, value, 0, chained_key);
+    let chained = add_root(alloc_ephemeron(mutator));
+    add_ephemeron_candidate(get_root(chained), chained_key, alloc_numbered(mutator, 4));
+    // A value kept alive only by its own key does not keep the key alive.
+    let cyclic = add_root(alloc_ephemeron(mutator));
+    let cyclic_key = alloc_numbered(mutator, 5);
+    let cyclic_value = alloc_numbered(mutator, 6);
+    set_ref(mutator, cyclic_value, 0, cyclic_key);
+    add_ephemeron_candidate(get_root(cyclic), cyclic_key, cyclic_value);
+    // An ephemeron whose key is unreachable is cleared.
+    let unresolved = add_root(alloc_ephemeron(mutator));
+    let unreachable_key = alloc_numbered(mutator, 7);
+    add_ephemeron_candidate(get_root(unresolved), unreachable_key, alloc_numbered(mutator, 8));
+
+    gc();
+
+    let resolved = get_root(resolved);
+    assert_eq!(VMReferenceGlue::get_referent(resolved), get_root(reachable_key));
+    let value = VMReferenceGlue::get_ephemeron_value(resolved);
+    assert_eq!(number(value), 2);
+    let chained = get_root(chained);
+    assert_eq!(VMReferenceGlue::get_referent(chained), get_ref(value, 0));
+    assert_eq!(number(VMReferenceGlue::get_referent(chained)), 3);
+    assert_eq!(number(VMReferenceGlue::get_ephemeron_value(chained)), 4);
+    for &cleared in &[get_root(cyclic), get_root(unresolved)] {
+        assert!(VMReferenceGlue::get_referent(cleared).is_null());
+        assert!(VMReferenceGlue::get_ephemeron_value(cleared).is_null());
+    }
+    let mut enqueued = take_enqueued_references();
+    enqueued.sort_by_key(|reference| reference.to_address());
+    let mut expected = vec![get_root(cyclic), get_root(unresolved)];
+    expected.sort_by_key(|reference| reference.to_address());
+    assert_eq!(enqueued, expected);
+}
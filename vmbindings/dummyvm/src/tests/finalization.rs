@@ -0,0 +1,50 @@
+use super::fixtures::*;
+use crate::api::*;
+use mmtk::AllocationSemantics;
+use mmtk::util::{Address, ObjectReference};
+use object_model::*;
+use scanning::*;
+
+/// The next object that is ready to be finalized, if any.
+fn finalizable_object() -> Option<ObjectReference> {
+    let mut object = unsafe { Address::zero().to_object_reference() };
+    if get_finalizable_object(&mut object) {
+        Some(object)
+    } else {
+        None
+    }
+}
+
+#[test]
+pub fn finalization() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    let reachable = add_root(alloc_object(mutator, 0, 8, AllocationSemantics::Default));
+    add_finalizer(get_root(reachable));
+    // An unreachable finalizable object, and an object that only it refers to
+    let unreachable = alloc_object(mutator, 1, 8, AllocationSemantics::Default);
+    let child = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+    unsafe {
+        data_start(unreachable).store(1usize);
+        data_start(child).store(2usize);
+    }
+    set_ref(mutator, unreachable, 0, child);
+    add_finalizer(unreachable);
+    assert!(finalizable_object().is_none());
+
+    gc();
+
+    // The unreachable object is resurrected with everything it refers to, and handed back once.
+    // The reachable object is not handed back.
+    let resurrected = finalizable_object().expect("the unreachable object is not ready to be finalized");
+    assert_eq!(unsafe { data_start(resurrected).load::<usize>() }, 1);
+    assert_eq!(unsafe { data_start(get_ref(resurrected, 0)).load::<usize>() }, 2);
+    assert!(finalizable_object().is_none());
+
+    // Once its finalizer has run, the object is no longer a candidate.
+    gc();
+    assert!(finalizable_object().is_none());
+}
@@ -0,0 +1,32 @@
+use crate::api::*;
+use mmtk::memory_manager;
+use mmtk::util::{Address, OpaquePointer};
+use mmtk::util::constants::BYTES_IN_PAGE;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use SINGLETON;
+
+#[test]
+pub fn find_base_object() {
+    gc_init(20*1024*1024);
+    let mutator = unsafe { &mut *bind_mutator(OpaquePointer::UNINITIALIZED) };
+
+    // The reference, an address in the data, and the last byte of an object all resolve to it.
+    for &(data_bytes, semantics, large) in &[(64, AllocationSemantics::Default, false), (3 * BYTES_IN_PAGE, AllocationSemantics::Los, true)] {
+        let object = alloc_object(mutator, 0, data_bytes, semantics);
+        let end = object.to_address() + object_size(0, data_bytes);
+        for &addr in &[object.to_address(), data_start(object) + 8usize, end - 1usize] {
+            assert_eq!(memory_manager::find_base_object(&SINGLETON, addr), Some(object));
+        }
+        // An address past the end does not resolve to the object, even in its last page.
+        assert_ne!(memory_manager::find_base_object(&SINGLETON, end), Some(object));
+        if large {
+            assert!(!end.is_aligned_to(BYTES_IN_PAGE));
+            assert_eq!(memory_manager::find_base_object(&SINGLETON, end), None);
+        }
+    }
+
+    let local = 0usize;
+    assert_eq!(memory_manager::find_base_object(&SINGLETON, Address::from_ref(&local)), None);
+    assert_eq!(memory_manager::find_base_object(&SINGLETON, Address::ZERO), None);
+}
@@ -0,0 +1,23 @@
+// Helpers for the tests that run GCs. This module has no tests of its own.
+use crate::api::*;
+use mmtk::util::OpaquePointer;
+use mmtk::util::options::PlanSelector;
+use mmtk::Mutator;
+use DummyVM;
+use SINGLETON;
+
+/// Initialize MMTk with GCs enabled, and bind a mutator for the current thread. Returns `None`
+/// for NoGC, which cannot collect.
+pub fn init_with_gc(heap_size: usize) -> Option<&'static mut Mutator<DummyVM>> {
+    gc_init(heap_size);
+    if SINGLETON.options.plan == PlanSelector::NoGC {
+        return None;
+    }
+    enable_collection(OpaquePointer::UNINITIALIZED);
+    Some(unsafe { &mut *bind_mutator(OpaquePointer::UNINITIALIZED) })
+}
+
+/// Run a full GC, and wait for it to finish.
+pub fn gc() {
+    handle_user_collection_request(OpaquePointer::UNINITIALIZED);
+}
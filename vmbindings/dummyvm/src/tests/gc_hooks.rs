@@ -0,0 +1,48 @@
+use super::fixtures::*;
+use collection::*;
+use mmtk::memory_manager;
+use mmtk::util::options::PlanSelector;
+use mmtk::{AllocationSemantics, CollectionKind, GCInfo};
+use object_model::*;
+use std::thread;
+use SINGLETON;
+
+/// Wait for the hooks of a GC that is called after the mutators are resumed.
+fn wait_for_gc_finished() -> Vec<(&'static str, GCInfo)> {
+    let mut events = vec![];
+    while events.last().map_or(true, |&(hook, _)| hook != "gc_finished") {
+        events.extend(take_gc_events());
+        thread::yield_now();
+    }
+    events
+}
+
+#[test]
+pub fn gc_hooks() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    for round in 1..=3 {
+        // Garbage that the GC frees
+        for _ in 0..16 {
+            alloc_object(mutator, 0, 64*1024, AllocationSemantics::Los);
+        }
+        gc();
+        let events = wait_for_gc_finished();
+        let hooks: Vec<&str> = events.iter().map(|&(hook, _)| hook).collect();
+        assert_eq!(hooks, ["gc_started", "mutators_stopped", "closure_done", "gc_finished"]);
+        for &(_, info) in &events {
+            assert_eq!(info.gc, round);
+            assert!(info.user_triggered);
+            assert!(!info.emergency);
+            // A user GC only collects the nursery in GenCopy.
+            let kind = if SINGLETON.options.plan == PlanSelector::GenCopy { CollectionKind::Nursery } else { CollectionKind::Full };
+            assert_eq!(info.kind, kind);
+        }
+        let (_, info) = events[3];
+        assert!(info.bytes_after < info.bytes_before, "{:?}", info);
+        assert!(info.bytes_after <= memory_manager::used_bytes(&SINGLETON));
+    }
+}
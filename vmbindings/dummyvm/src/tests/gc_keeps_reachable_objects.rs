@@ -0,0 +1,36 @@
+use super::fixtures::*;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+
+#[test]
+pub fn gc_keeps_reachable_objects() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    // A list of objects, half of them large, with a counter in each.
+    let mut head = alloc_object(mutator, 1, 8, AllocationSemantics::Default);
+    let root = add_root(head);
+    for i in 0..100usize {
+        let semantics = if i % 50 == 0 { AllocationSemantics::Los } else { AllocationSemantics::Default };
+        let object = alloc_object(mutator, 1, 8, semantics);
+        unsafe { data_start(object).store(i) };
+        set_ref(mutator, object, 0, head);
+        head = object;
+        set_root(root, head);
+        // Garbage
+        alloc_object(mutator, 0, 64, AllocationSemantics::Default);
+    }
+
+    for _ in 0..3 {
+        gc();
+        let mut object = get_root(root);
+        for i in (0..100usize).rev() {
+            assert_eq!(unsafe { data_start(object).load::<usize>() }, i);
+            object = get_ref(object, 0);
+        }
+        assert!(get_ref(object, 0).is_null());
+    }
+}
@@ -0,0 +1,43 @@
+use super::fixtures::*;
+use mmtk::policy::immixspace::{BYTES_IN_BLOCK, MAX_IMMIX_OBJECT_SIZE};
+use mmtk::policy::space::Space;
+use mmtk::util::options::PlanSelector;
+use mmtk::memory_manager;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+#[test]
+pub fn immix_large_objects() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+    if SINGLETON.options.plan != PlanSelector::Immix {
+        return;
+    }
+
+    // Objects of the largest size in the Immix space, and of sizes that go to the large object
+    // space.
+    let sizes = [MAX_IMMIX_OBJECT_SIZE - 8, MAX_IMMIX_OBJECT_SIZE, MAX_IMMIX_OBJECT_SIZE + 8, BYTES_IN_BLOCK, 2 * BYTES_IN_BLOCK];
+    let mut roots = vec![];
+    for (i, &size) in sizes.iter().enumerate() {
+        let object = alloc_object(mutator, 0, size - HEADER_BYTES, AllocationSemantics::Default);
+        assert_eq!(SINGLETON.plan.common().get_los().in_space(object), size > MAX_IMMIX_OBJECT_SIZE);
+        unsafe { data_start(object).store(i) };
+        roots.push((add_root(object), i));
+    }
+
+    // The largest objects still fit in a block when they need alignment padding.
+    for _ in 0..4 {
+        let addr = memory_manager::alloc(mutator, MAX_IMMIX_OBJECT_SIZE, 8, 4, AllocationSemantics::Default);
+        assert!((addr + 4usize).is_aligned_to(8));
+        assert_eq!(addr.align_down(BYTES_IN_BLOCK), (addr + MAX_IMMIX_OBJECT_SIZE - 1usize).align_down(BYTES_IN_BLOCK));
+    }
+
+    gc();
+    for &(root, i) in &roots {
+        assert_eq!(unsafe { data_start(get_root(root)).load::<usize>() }, i);
+    }
+}
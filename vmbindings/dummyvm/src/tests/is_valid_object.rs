@@ -0,0 +1,24 @@
+use crate::api::*;
+use mmtk::memory_manager;
+use mmtk::util::{Address, OpaquePointer};
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn is_valid_object_filters_words() {
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+
+    let addr = alloc(handle, 64, 8, 0, AllocationSemantics::Default);
+    let object = unsafe { addr.to_object_reference() };
+    // Only allocated objects are valid: post_alloc() sets the allocation bit.
+    assert!(!memory_manager::is_valid_object(addr));
+    post_alloc(handle, object, object, 64, AllocationSemantics::Default);
+    assert!(memory_manager::is_valid_object(addr));
+
+    // Words inside the object, misaligned words, and words outside the heap are not.
+    assert!(!memory_manager::is_valid_object(addr + 8usize));
+    assert!(!memory_manager::is_valid_object(addr + 1usize));
+    assert!(!memory_manager::is_valid_object(Address::ZERO));
+    let local = 0usize;
+    assert!(!memory_manager::is_valid_object(Address::from_ref(&local)));
+}
@@ -0,0 +1,53 @@
+use super::fixtures::*;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+#[test]
+pub fn markcompact_slides_objects() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+    if SINGLETON.options.plan != PlanSelector::MarkCompact {
+        return;
+    }
+
+    // Every third object is live, and each refers to the live object before it. Objects of
+    // different sizes make compaction skip the ends of some regions.
+    let mut roots = vec![];
+    let mut prev = unsafe { mmtk::util::Address::zero().to_object_reference() };
+    let mut high = prev;
+    for i in 0..3000usize {
+        let object = alloc_object(mutator, 1, 8 + (i % 7) * 200, AllocationSemantics::Default);
+        unsafe { data_start(object).store(i) };
+        if i % 3 == 0 {
+            set_ref(mutator, object, 0, prev);
+            roots.push((add_root(object), i));
+            prev = object;
+        }
+        high = object;
+    }
+
+    for _ in 0..2 {
+        gc();
+        let mut last = None;
+        for &(root, i) in &roots {
+            let object = get_root(root);
+            assert_eq!(unsafe { data_start(object).load::<usize>() }, i);
+            assert_eq!(get_ref(object, 0), last.unwrap_or(unsafe { mmtk::util::Address::zero().to_object_reference() }));
+            // Objects keep their allocation order, and never move up.
+            if let Some(last) = last {
+                assert!(object.to_address() > last.to_address());
+            }
+            last = Some(object);
+        }
+        assert!(last.unwrap().to_address() < high.to_address());
+    }
+
+    // The space freed by compaction is allocated again.
+    let reused = (0..1000).filter(|_| alloc_object(mutator, 0, 64, AllocationSemantics::Default).to_address() < high.to_address()).count();
+    assert!(reused > 0);
+}
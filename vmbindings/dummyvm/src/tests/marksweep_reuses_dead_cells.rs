@@ -0,0 +1,41 @@
+use super::fixtures::*;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+#[test]
+pub fn marksweep_reuses_dead_cells() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+    if SINGLETON.options.plan != PlanSelector::MarkSweep {
+        return;
+    }
+
+    // Every other object is live.
+    let mut live = vec![];
+    let mut dead = vec![];
+    for i in 0..1000usize {
+        let object = alloc_object(mutator, 0, 40, AllocationSemantics::Default);
+        unsafe { data_start(object).store(i) };
+        if i % 2 == 0 {
+            live.push((add_root(object), i));
+        } else {
+            dead.push(object);
+        }
+    }
+    gc();
+
+    // Objects are not moved, and the new objects take the cells of the dead objects.
+    for _ in 0..dead.len() {
+        let object = alloc_object(mutator, 0, 40, AllocationSemantics::Default);
+        assert!(dead.contains(&object), "{} is not a dead cell", object);
+        unsafe { data_start(object).store(usize::MAX) };
+    }
+    for &(root, i) in &live {
+        assert_eq!(unsafe { data_start(get_root(root)).load::<usize>() }, i);
+    }
+}
@@ -1,4 +1,29 @@
 // Each module should only contain one #[test] function.
 // We should run each module in a separate test process, as we do not have proper
 // setup/teardown procedure for MMTk instances.
-mod issue139;
\ No newline at end of file
+mod aligned_objects_survive_gc;
+mod barrier_remembers_mature_objects;
+mod cms_large_objects_allocated_while_marking;
+mod cms_split_grey_objects;
+mod concurrent_nursery_zeroing;
+#[cfg(feature = "global_alloc_bit")]
+mod dead_objects_are_not_valid;
+#[cfg(feature = "global_alloc_bit")]
+mod enumerate_objects;
+mod ephemerons;
+mod finalization;
+#[cfg(feature = "global_alloc_bit")]
+mod find_base_object;
+mod fixtures;
+mod gc_hooks;
+mod gc_keeps_reachable_objects;
+#[cfg(feature = "global_alloc_bit")]
+mod is_valid_object;
+mod immix_large_objects;
+mod issue139;
+mod markcompact_slides_objects;
+mod marksweep_reuses_dead_cells;
+mod pin_object;
+mod pinned_objects_stay_in_place;
+mod reference_processing;
+mod snapshot_heap;
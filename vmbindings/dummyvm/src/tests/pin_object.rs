@@ -0,0 +1,27 @@
+use crate::api::*;
+use mmtk::memory_manager;
+use mmtk::util::OpaquePointer;
+use mmtk::AllocationSemantics;
+
+#[test]
+pub fn pin_object_sets_pin_bit() {
+    gc_init(200*1024*1024);
+    let handle = bind_mutator(OpaquePointer::UNINITIALIZED);
+
+    for &semantics in &[AllocationSemantics::Default, AllocationSemantics::Los] {
+        let addr = alloc(handle, 64, 8, 0, semantics);
+        let object = unsafe { addr.to_object_reference() };
+        post_alloc(handle, object, object, 64, semantics);
+        let next = unsafe { (addr + 64usize).to_object_reference() };
+
+        assert!(!memory_manager::is_pinned(object));
+        assert!(memory_manager::pin_object(object));
+        assert!(!memory_manager::pin_object(object));
+        assert!(memory_manager::is_pinned(object));
+        // The pin bit is per object.
+        assert!(!memory_manager::is_pinned(next));
+        assert!(memory_manager::unpin_object(object));
+        assert!(!memory_manager::unpin_object(object));
+        assert!(!memory_manager::is_pinned(object));
+    }
+}
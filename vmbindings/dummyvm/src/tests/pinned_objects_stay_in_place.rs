@@ -0,0 +1,55 @@
+use super::fixtures::*;
+use mmtk::memory_manager;
+use mmtk::util::options::PlanSelector;
+use mmtk::AllocationSemantics;
+use mmtk::CollectionKind;
+use object_model::*;
+use scanning::*;
+use SINGLETON;
+
+#[test]
+pub fn pinned_objects_stay_in_place() {
+    let mutator = match init_with_gc(200*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+    let copying = match SINGLETON.options.plan {
+        PlanSelector::SemiSpace | PlanSelector::GenCopy | PlanSelector::MyGC => true,
+        // MarkCompact cannot pin objects.
+        PlanSelector::MarkCompact => return,
+        _ => false,
+    };
+
+    // The garbage before the pinned object is not kept with it.
+    for _ in 0..1000 {
+        alloc_object(mutator, 0, 1024, AllocationSemantics::Default);
+    }
+    let pinned = alloc_object(mutator, 1, 8, AllocationSemantics::Default);
+    unsafe { data_start(pinned).store(42usize) };
+    assert!(memory_manager::pin_object(pinned));
+    let root = add_root(pinned);
+
+    for i in 0..3usize {
+        // The allocation goes past the kept pages.
+        for _ in 0..1000 {
+            alloc_object(mutator, 0, 1024, AllocationSemantics::Default);
+        }
+        // Only the pinned object refers to its child, and the writes to the objects in the
+        // nursery are not remembered.
+        let child = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+        unsafe { data_start(child).store(i) };
+        set_ref(mutator, pinned, 0, child);
+        gc();
+        assert_eq!(get_root(root), pinned);
+        assert_eq!(unsafe { data_start(pinned).load::<usize>() }, 42);
+        assert_eq!(unsafe { data_start(get_ref(pinned, 0)).load::<usize>() }, i);
+        if copying {
+            assert!(memory_manager::used_bytes(&SINGLETON) < 512*1024);
+        }
+        // Keeping the pinned object in the nursery does not make the next GC a full heap GC.
+        if SINGLETON.options.plan == PlanSelector::GenCopy {
+            assert_eq!(SINGLETON.plan.base().gc_info().kind, CollectionKind::Nursery);
+        }
+    }
+    assert!(memory_manager::unpin_object(pinned));
+}
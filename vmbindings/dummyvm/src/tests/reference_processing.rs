@@ -0,0 +1,64 @@
+use super::fixtures::*;
+use crate::api::*;
+use mmtk::{AllocationSemantics, Mutator};
+use mmtk::util::ObjectReference;
+use object_model::*;
+use reference_glue::*;
+use scanning::*;
+use mmtk::vm::ReferenceGlue;
+use DummyVM;
+
+/// A reference object holds its referent in its data.
+fn alloc_reference(mutator: &mut Mutator<DummyVM>) -> ObjectReference {
+    alloc_object(mutator, 0, 8, AllocationSemantics::Default)
+}
+
+#[test]
+pub fn reference_processing() {
+    let mutator = match init_with_gc(20*1024*1024) {
+        Some(mutator) => mutator,
+        None => return,
+    };
+
+    let live = add_root(alloc_object(mutator, 0, 8, AllocationSemantics::Default));
+    let dead = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+    // A softly reachable object is retained, and so stays reachable for weak references.
+    let softly_reachable = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+    unsafe { data_start(softly_reachable).store(42usize) };
+    let weak_to_live = add_root(alloc_reference(mutator));
+    let weak_to_dead = add_root(alloc_reference(mutator));
+    let soft = add_root(alloc_reference(mutator));
+    let phantom_to_dead = add_root(alloc_reference(mutator));
+    add_weak_candidate(get_root(weak_to_live), get_root(live));
+    add_weak_candidate(get_root(weak_to_dead), dead);
+    add_soft_candidate(get_root(soft), softly_reachable);
+    add_phantom_candidate(get_root(phantom_to_dead), dead);
+    // A dead reference is dropped without being enqueued.
+    let dead_reference = alloc_reference(mutator);
+    add_weak_candidate(dead_reference, dead);
+
+    gc();
+
+    // The referent is updated if it moved.
+    assert_eq!(VMReferenceGlue::get_referent(get_root(weak_to_live)), get_root(live));
+    // Soft references are only cleared by emergency collections, and their referents are retained.
+    let softly_reachable = VMReferenceGlue::get_referent(get_root(soft));
+    assert!(!softly_reachable.is_null());
+    assert_eq!(unsafe { data_start(softly_reachable).load::<usize>() }, 42);
+    assert!(VMReferenceGlue::get_referent(get_root(weak_to_dead)).is_null());
+    assert!(VMReferenceGlue::get_referent(get_root(phantom_to_dead)).is_null());
+    let mut enqueued = take_enqueued_references();
+    enqueued.sort_by_key(|reference| reference.to_address());
+    let mut expected = vec![get_root(weak_to_dead), get_root(phantom_to_dead)];
+    expected.sort_by_key(|reference| reference.to_address());
+    assert_eq!(enqueued, expected);
+
+    // References added after a collection are processed by the next one, even if it is a
+    // nursery collection.
+    let young = alloc_object(mutator, 0, 8, AllocationSemantics::Default);
+    let weak_to_young = add_root(alloc_reference(mutator));
+    add_weak_candidate(get_root(weak_to_young), young);
+    gc();
+    assert!(VMReferenceGlue::get_referent(get_root(weak_to_young)).is_null());
+    assert_eq!(take_enqueued_references(), vec![get_root(weak_to_young)]);
+}
@@ -0,0 +1,64 @@
+use super::fixtures::*;
+use crate::api::*;
+use mmtk::memory_manager;
+use mmtk::util::options::PlanSelector;
+use mmtk::util::{Address, OpaquePointer};
+use mmtk::AllocationSemantics;
+use object_model::*;
+use scanning::*;
+use std::process::Command;
+use SINGLETON;
+
+// The test writes a snapshot, and runs itself again in a child process that restores the heap
+// from it. The child finds the addresses of the objects in the environment.
+#[test]
+pub fn snapshot_heap_restores_objects() {
+    // A user GC only collects the nursery of GenCopy without this.
+    assert!(memory_manager::process(&SINGLETON, "full_heap_system_gc", "true"));
+    let mutator = match init_with_gc(200*1024*1024) {
+        Some(mutator) => mutator,
+        None => unsafe { &mut *bind_mutator(OpaquePointer::UNINITIALIZED) },
+    };
+    let semantics = [AllocationSemantics::Default, AllocationSemantics::Los, AllocationSemantics::Default];
+
+    if let Ok(objects) = std::env::var("SNAPSHOT_HEAP_OBJECTS") {
+        let objects: Vec<Address> = objects.split(',').map(|a| unsafe { Address::from_usize(a.parse().unwrap()) }).collect();
+        for (i, object) in objects.iter().enumerate() {
+            assert_eq!(unsafe { data_start(object.to_object_reference()).load::<usize>() }, i + 1);
+        }
+        // The restored objects are not handed out again.
+        let size = object_size(0, 8);
+        let addr = alloc_object(mutator, 0, 8, AllocationSemantics::Default).to_address();
+        assert!(objects.iter().all(|&object| addr >= object + size || addr + size <= object));
+        return;
+    }
+
+    let roots: Vec<usize> = semantics.iter().enumerate().map(|(i, &semantics)| {
+        let object = alloc_object(mutator, 0, 8, semantics);
+        unsafe { data_start(object).store(i + 1) };
+        add_root(object)
+    }).collect();
+    let path = std::env::temp_dir().join(format!("mmtk-dummyvm-snapshot-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    if SINGLETON.options.plan != PlanSelector::NoGC {
+        // A snapshot is only written after a full heap GC, which drops the garbage.
+        assert!(memory_manager::snapshot_heap(&SINGLETON, path).is_err());
+        gc();
+    }
+    if let Err(e) = memory_manager::snapshot_heap(&SINGLETON, path) {
+        // Only the plans whose spaces keep tables of their blocks cannot write a snapshot.
+        match SINGLETON.options.plan {
+            PlanSelector::MarkSweep | PlanSelector::ConcMarkSweep | PlanSelector::Immix | PlanSelector::MarkCompact => return,
+            _ => panic!("{}", e),
+        }
+    }
+    let objects: Vec<String> = roots.iter().map(|&root| get_root(root).to_address().as_usize().to_string()).collect();
+    let status = Command::new(std::env::current_exe().unwrap())
+        .args(&["--exact", "tests::snapshot_heap::snapshot_heap_restores_objects"])
+        .env("MMTK_RESTORE_HEAP", path)
+        .env("SNAPSHOT_HEAP_OBJECTS", objects.join(","))
+        .status()
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(status.success());
+}